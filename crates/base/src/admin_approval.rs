@@ -0,0 +1,96 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::hex_utils::short_hex_string;
+use crate::karma_coin::karma_coin_core_types::{AccountId, AdminApproval, Signature};
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Keypair, PublicKey, Signer, Verifier};
+use prost::Message;
+
+/// Domain tag of params update proposals approval messages
+pub const PARAMS_UPDATE_APPROVAL_DOMAIN: &[u8] = b"karmacoin/params-update/v1";
+
+/// Domain tag of verifier update proposals approval messages
+pub const VERIFIER_UPDATE_APPROVAL_DOMAIN: &[u8] = b"karmacoin/verifier-update/v1";
+
+/// Returns the message signed by governance admins approving a proposal.
+/// Approvals are bound to the proposal type by its domain tag, as proposals of different types
+/// may have the same encoding, and to the chain by its genesis hash
+pub fn get_approval_message<T: Message>(
+    domain: &[u8],
+    genesis_hash: &[u8],
+    proposal: &T,
+) -> Vec<u8> {
+    let mut message = domain.to_vec();
+    message.extend_from_slice(genesis_hash);
+    message.append(&mut proposal.encode_to_vec());
+    message
+}
+
+impl AdminApproval {
+    /// Returns an admin approval of an approval message
+    pub fn new(message: &[u8], key_pair: &Keypair) -> Self {
+        AdminApproval {
+            account_id: Some(AccountId {
+                data: key_pair.public.to_bytes().to_vec(),
+            }),
+            signature: Some(Signature {
+                scheme: 0,
+                signature: key_pair.sign(message).as_ref().to_vec(),
+            }),
+        }
+    }
+}
+
+/// Verify all approvals of a message were signed by distinct governance admins and return
+/// the approvers
+pub fn get_approvers(
+    approvals: &[AdminApproval],
+    message: &[u8],
+    admins: &[AccountId],
+) -> Result<Vec<AccountId>> {
+    let mut approvers: Vec<AccountId> = vec![];
+
+    for approval in approvals.iter() {
+        let account_id = approval
+            .account_id
+            .as_ref()
+            .ok_or_else(|| anyhow!("approval without an account id"))?;
+
+        if !admins.contains(account_id) {
+            return Err(anyhow!(
+                "approver {} is not a governance admin",
+                short_hex_string(&account_id.data)
+            ));
+        }
+
+        if approvers.contains(account_id) {
+            return Err(anyhow!(
+                "admin {} approved more than once",
+                short_hex_string(&account_id.data)
+            ));
+        }
+
+        let signature = approval
+            .signature
+            .as_ref()
+            .ok_or_else(|| anyhow!("approval without a signature"))?;
+
+        PublicKey::from_bytes(&account_id.data)?
+            .verify(
+                message,
+                &ed25519_dalek::Signature::from_bytes(&signature.signature)?,
+            )
+            .map_err(|_| {
+                anyhow!(
+                    "invalid approval signature by admin {}",
+                    short_hex_string(&account_id.data)
+                )
+            })?;
+
+        approvers.push(account_id.clone());
+    }
+
+    Ok(approvers)
+}
@@ -4,6 +4,7 @@
 
 use crate::hasher::Hasher;
 use crate::karma_coin::karma_coin_core_types::Block;
+use crate::merkle::merkle_root;
 use crate::signed_trait::SignedTrait;
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
@@ -11,9 +12,12 @@ use ed25519_dalek::{PublicKey, Signature};
 use prost::Message;
 
 impl SignedTrait for Block {
+    /// The signed message excludes the signature and the digest, which is computed over the
+    /// signed block
     fn get_sign_message(&self) -> Result<Vec<u8>> {
         let mut cloned = self.clone();
         cloned.signature = None;
+        cloned.digest = vec![];
         let mut buf = Vec::with_capacity(cloned.encoded_len());
         cloned.encode(&mut buf)?;
         Ok(buf.to_vec())
@@ -49,4 +53,101 @@ impl Block {
         let hash = Hasher::hash(buf.as_ref())?;
         Ok(Bytes::from(hash))
     }
+
+    /// Verify the block's self-contained data: its author signature, its digest and its
+    /// transactions root when it has one
+    pub fn verify(&self) -> Result<()> {
+        if self.author.is_none() {
+            return Err(anyhow!("missing block author"));
+        }
+
+        self.verify_signature()?;
+
+        let mut unsealed = self.clone();
+        unsealed.digest = vec![];
+        if unsealed.get_hash()?.as_ref() != self.digest.as_slice() {
+            return Err(anyhow!("digest mismatch"));
+        }
+
+        // blocks created before transactions roots were introduced have no root
+        if !self.transactions_root.is_empty()
+            && merkle_root(&self.transactions_hashes)? != self.transactions_root
+        {
+            return Err(anyhow!("transactions root mismatch"));
+        }
+
+        Ok(())
+    }
+
+    /// Verify that this block follows the provided previous block in the chain
+    pub fn verify_parent(&self, prev_block: &Block) -> Result<()> {
+        if self.height != prev_block.height + 1 {
+            return Err(anyhow!(
+                "height {} doesn't follow previous block height {}",
+                self.height,
+                prev_block.height
+            ));
+        }
+
+        if self.prev_block_digest != prev_block.digest {
+            return Err(anyhow!(
+                "previous block digest doesn't match block {} digest",
+                prev_block.height
+            ));
+        }
+
+        // blocks created before genesis hashes were introduced have no genesis hash
+        if !prev_block.genesis_hash.is_empty() && self.genesis_hash != prev_block.genesis_hash {
+            return Err(anyhow!(
+                "genesis hash doesn't match block {} genesis hash",
+                prev_block.height
+            ));
+        }
+
+        if self.time < prev_block.time {
+            return Err(anyhow!(
+                "block time {} is before previous block time {}",
+                self.time,
+                prev_block.time
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Verify a sequence of consecutive blocks. Each block is verified and checked to follow the
+/// block before it. prev_block is the block preceding the first block, and should be None when
+/// the sequence starts at the genesis block.
+/// Uses only the blocks data so exported blocks can be audited without trusting a node.
+/// Returns a description of every verification failure found - an empty result for a valid chain.
+pub fn verify_chain(prev_block: Option<&Block>, blocks: &[Block]) -> Vec<String> {
+    let mut errors = vec![];
+    let mut prev_block = prev_block;
+
+    for block in blocks.iter() {
+        if let Err(e) = block.verify() {
+            errors.push(format!("block {}: {}", block.height, e));
+        }
+
+        match prev_block {
+            Some(prev) => {
+                if let Err(e) = block.verify_parent(prev) {
+                    errors.push(format!("block {}: {}", block.height, e));
+                }
+            }
+            None => {
+                if block.height != 1 || !block.prev_block_digest.is_empty() {
+                    errors.push(format!(
+                        "block {}: expected a genesis block at height 1 without a previous block digest",
+                        block.height
+                    ));
+                }
+            }
+        }
+
+        prev_block = Some(block);
+    }
+
+    errors
 }
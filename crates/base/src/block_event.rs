@@ -4,6 +4,8 @@
 
 use crate::hex_utils::short_hex_string;
 use crate::karma_coin::karma_coin_core_types::{BlockEvent, TransactionEvent};
+use crate::supply::checked_add;
+use anyhow::Result;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
@@ -24,19 +26,42 @@ impl BlockEvent {
             reward: 0,
             referral_rewards_count: 0,
             user_updates_count: 0,
+            burned_amount: 0,
+            karma_rewards_amount: 0,
+            karma_rewards_count: 0,
         }
     }
 
-    pub fn add_signup_reward(&mut self, value: u64) {
-        self.signup_rewards_amount += value;
+    pub fn add_signup_reward(&mut self, value: u64) -> Result<()> {
+        self.signup_rewards_amount =
+            checked_add(self.signup_rewards_amount, value, "signup rewards amount")?;
+        Ok(())
     }
 
-    pub fn add_referral_reward(&mut self, value: u64) {
-        self.referral_rewards_amount += value;
+    pub fn add_referral_reward(&mut self, value: u64) -> Result<()> {
+        self.referral_rewards_amount = checked_add(
+            self.referral_rewards_amount,
+            value,
+            "referral rewards amount",
+        )?;
+        Ok(())
+    }
+
+    pub fn add_fee(&mut self, value: u64) -> Result<()> {
+        self.fees_amount = checked_add(self.fees_amount, value, "fees amount")?;
+        Ok(())
+    }
+
+    pub fn add_burned(&mut self, value: u64) -> Result<()> {
+        self.burned_amount = checked_add(self.burned_amount, value, "burned amount")?;
+        Ok(())
     }
 
-    pub fn add_fee(&mut self, value: u64) {
-        self.fees_amount += value;
+    pub fn add_karma_reward(&mut self, value: u64) -> Result<()> {
+        self.karma_rewards_amount =
+            checked_add(self.karma_rewards_amount, value, "karma rewards amount")?;
+        self.karma_rewards_count += 1;
+        Ok(())
     }
 
     pub fn inc_total_payments(&mut self) {
@@ -30,6 +30,7 @@ impl BlockchainStats {
             appreciations_transactions_count: 0,
             karma_rewards_amount: 0,
             karma_rewards_count: 0,
+            burned_amount: 0,
         }
     }
 }
@@ -0,0 +1,414 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::genesis_config_service::{
+    GENESIS_TIMESTAMP_SECONDS_KEY, NET_ID_KEY, NET_NAME_KEY, ONE_KC_IN_KCENTS,
+    VERIFIERS_ACCOUNTS_IDS,
+};
+use crate::hex_utils::hex_from_string;
+use crate::karma_coin::karma_coin_core_types::{
+    AccountId, CharTrait, GenesisData, ParamChange, ParamsUpdate, PhoneVerifier,
+};
+use crate::supply::checked_add;
+use anyhow::{anyhow, Result};
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Typed genesis configuration.
+/// Deserialized from the genesis config layers - defaults, GENESIS_ env vars and an optional
+/// yaml or json genesis file. Field names are the genesis config keys and unknown keys are
+/// rejected so a typo in a genesis file fails on startup.
+/// Amounts are in KCents unless stated otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisConfig {
+    #[serde(rename = "net_id_key")]
+    pub net_id: u32,
+    #[serde(rename = "net_name_key")]
+    pub net_name: String,
+    #[serde(rename = "genesis_timestamp_key")]
+    pub genesis_time: u64,
+    #[serde(rename = "def_tx_fee_key")]
+    pub default_tx_fee: u64,
+
+    #[serde(rename = "signup_reward_p1_key")]
+    pub signup_reward_phase1_amount: u64,
+    #[serde(rename = "signup_reward_alloc_p1_key")]
+    pub signup_reward_phase1_alloc: u64,
+    #[serde(rename = "signup_reward_p2_key")]
+    pub signup_reward_phase2_amount: u64,
+    #[serde(rename = "signup_reward_alloc_p2")]
+    pub signup_reward_phase2_alloc: u64,
+    #[serde(rename = "signup_reward_p3")]
+    pub signup_reward_phase3_amount: u64,
+
+    #[serde(rename = "referral_reward_p1")]
+    pub referral_reward_phase1_amount: u64,
+    #[serde(rename = "referral_reward_alloc_p1")]
+    pub referral_reward_phase1_alloc: u64,
+    #[serde(rename = "referral_reward_p2")]
+    pub referral_reward_phase2_amount: u64,
+    #[serde(rename = "referral_reward_alloc_p2")]
+    pub referral_reward_phase2_alloc: u64,
+
+    #[serde(rename = "tx_fee_subsidy_max_amount_p1")]
+    pub tx_fee_subsidy_max_amount_phase1: u64,
+    #[serde(rename = "tx_fee_subsidy_allocation_p1")]
+    pub tx_fee_subsidies_alloc: u64,
+    #[serde(rename = "tx_fee_subsidy_max_txs_per_user")]
+    pub tx_fee_subsidy_max_per_user: u64,
+    #[serde(rename = "tx_fee_subsidy_max_amount")]
+    pub tx_fee_subsidy_max_amount: u64,
+
+    #[serde(rename = "causes_reward_amount_per_period", default)]
+    pub causes_reward_amount_per_period: u64,
+    #[serde(rename = "causes_reward_weeks_period")]
+    pub causes_reward_weeks_period: u64,
+    #[serde(rename = "causes_per_period")]
+    pub causes_per_period: u64,
+    #[serde(rename = "causes_rewards_allocation")]
+    pub causes_rewards_alloc: u64,
+
+    #[serde(rename = "block_rewards_last_block")]
+    pub block_reward_last_block: u64,
+    #[serde(rename = "block_rewards_amount")]
+    pub block_reward_amount: u64,
+
+    #[serde(rename = "karma_reward_amount")]
+    pub karma_reward_amount: u64,
+    #[serde(rename = "karma_reward_period_minutes")]
+    pub karma_reward_period_minutes: u64,
+    #[serde(rename = "karma_reward_top_n_users")]
+    pub karma_reward_top_n_users: u64,
+    /// Karma rewards allocation in KCs
+    #[serde(rename = "karma_rewards_allocation")]
+    pub karma_reward_alloc: u64,
+    #[serde(rename = "karma_rewards_min_appreciations")]
+    pub karma_rewards_eligibility: u64,
+
+    #[serde(rename = "backup_chain_task_period_minutes")]
+    pub backup_chain_task_period_minutes: u64,
+
+    /// Validators pool amount in KCs
+    #[serde(rename = "validates_pool_amount")]
+    pub validators_pool_amount: u64,
+    /// Hex encoded ed25519 public key
+    #[serde(rename = "validators_pool_account_id")]
+    pub validators_pool_account_id: String,
+    #[serde(rename = "Validators pool")]
+    pub validators_pool_account_name: String,
+
+    /// Verifiers hex encoded ed25519 public keys by verifier name
+    #[serde(rename = "verifiers_accounts_ids")]
+    pub verifiers: BTreeMap<String, String>,
+    /// Min number of distinct registered verifiers which must attest to a new or updated
+    /// mobile number
+    #[serde(rename = "verifiers_threshold")]
+    pub verifiers_threshold: u64,
+
+    #[serde(rename = "pending_payment_expiry_seconds")]
+    pub pending_payment_expiry_seconds: u64,
+
+    /// Hex encoded ed25519 public key of the account which receives deleted accounts balances.
+    /// Balances are burned when empty
+    #[serde(rename = "deleted_user_funds_account_id", default)]
+    pub deleted_user_funds_account_id: String,
+    /// Time after an account's deletion before its user name may be registered again
+    #[serde(rename = "user_name_cooldown_seconds")]
+    pub user_name_cooldown_seconds: u64,
+
+    /// Governance admins hex encoded ed25519 public keys by admin name.
+    /// Params updates are disabled when there are no admins
+    #[serde(rename = "governance_admins_accounts_ids")]
+    pub governance_admins: BTreeMap<String, String>,
+    /// Min number of admins approvals required to execute a params update
+    #[serde(rename = "governance_threshold")]
+    pub governance_threshold: u64,
+}
+
+/// Genesis config keys which can't be changed by a params update. These identify the chain,
+/// or, for the genesis verifiers, are only changed by verifier update txs after genesis
+pub const IMMUTABLE_PARAMS_KEYS: [&str; 4] = [
+    NET_ID_KEY,
+    NET_NAME_KEY,
+    GENESIS_TIMESTAMP_SECONDS_KEY,
+    VERIFIERS_ACCOUNTS_IDS,
+];
+
+// Decode a hex encoded ed25519 public key
+fn parse_public_key(name: &str, value: &str) -> Result<Vec<u8>> {
+    let data = hex_from_string(value.into())
+        .map_err(|e| anyhow!("{} is not a hex encoded key: {}", name, e))?;
+    PublicKey::from_bytes(&data)
+        .map_err(|e| anyhow!("{} is not an ed25519 public key: {}", name, e))?;
+    Ok(data)
+}
+
+impl GenesisConfig {
+    /// Validate the config. Returns all validation failures in one error
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+
+        if self.net_name.is_empty() {
+            errors.push("net name is empty".to_string());
+        }
+
+        let phases = [
+            (
+                "signup reward phase 1",
+                self.signup_reward_phase1_amount,
+                self.signup_reward_phase1_alloc,
+            ),
+            (
+                "signup reward phase 2",
+                self.signup_reward_phase2_amount,
+                self.signup_reward_phase2_alloc,
+            ),
+            (
+                "referral reward phase 1",
+                self.referral_reward_phase1_amount,
+                self.referral_reward_phase1_alloc,
+            ),
+            (
+                "referral reward phase 2",
+                self.referral_reward_phase2_amount,
+                self.referral_reward_phase2_alloc,
+            ),
+        ];
+        for (name, amount, alloc) in phases {
+            if amount > alloc {
+                errors.push(format!(
+                    "{} amount {} is larger than its allocation {}",
+                    name, amount, alloc
+                ));
+            }
+        }
+
+        // rewards don't increase in later phases
+        if self.signup_reward_phase2_amount > self.signup_reward_phase1_amount
+            || self.signup_reward_phase3_amount > self.signup_reward_phase2_amount
+        {
+            errors.push("signup reward amounts must not increase between phases".into());
+        }
+
+        if self.referral_reward_phase2_amount > self.referral_reward_phase1_amount {
+            errors.push("referral reward amounts must not increase between phases".into());
+        }
+
+        if self.tx_fee_subsidy_max_amount > self.tx_fee_subsidy_max_amount_phase1 {
+            errors.push("tx fee subsidy max amount must not increase after phase 1".into());
+        }
+
+        if let Err(e) = self.total_allocation() {
+            errors.push(e.to_string());
+        }
+
+        if self.karma_reward_period_minutes == 0 {
+            errors.push("karma rewards period must be positive".into());
+        }
+
+        if self.backup_chain_task_period_minutes == 0 {
+            errors.push("backup chain task period must be positive".into());
+        }
+
+        if let Err(e) = parse_public_key(
+            "validators pool account id",
+            &self.validators_pool_account_id,
+        ) {
+            errors.push(e.to_string());
+        }
+
+        if self.verifiers.is_empty() {
+            errors.push("no verifiers".into());
+        }
+
+        for (name, account_id) in self.verifiers.iter() {
+            if let Err(e) = parse_public_key(&format!("verifier {} account id", name), account_id) {
+                errors.push(e.to_string());
+            }
+        }
+
+        if self.verifiers_threshold == 0 {
+            errors.push("verifiers threshold must be positive".into());
+        }
+
+        if !self.deleted_user_funds_account_id.is_empty() {
+            if let Err(e) = parse_public_key(
+                "deleted user funds account id",
+                &self.deleted_user_funds_account_id,
+            ) {
+                errors.push(e.to_string());
+            }
+        }
+
+        for (name, account_id) in self.governance_admins.iter() {
+            if let Err(e) = parse_public_key(&format!("admin {} account id", name), account_id) {
+                errors.push(e.to_string());
+            }
+        }
+
+        if !self.governance_admins.is_empty()
+            && (self.governance_threshold == 0
+                || self.governance_threshold > self.governance_admins.len() as u64)
+        {
+            errors.push(format!(
+                "governance threshold {} must be between 1 and the number of admins {}",
+                self.governance_threshold,
+                self.governance_admins.len()
+            ));
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!("invalid genesis config: {}", errors.join(", ")));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the total amount in KCents allocated for rewards, subsidies and the validators pool
+    pub fn total_allocation(&self) -> Result<u64> {
+        let karma_reward_alloc = self
+            .karma_reward_alloc
+            .checked_mul(ONE_KC_IN_KCENTS)
+            .ok_or_else(|| anyhow!("karma rewards allocation overflow"))?;
+        let validators_pool_amount = self
+            .validators_pool_amount
+            .checked_mul(ONE_KC_IN_KCENTS)
+            .ok_or_else(|| anyhow!("validators pool amount overflow"))?;
+
+        [
+            self.signup_reward_phase1_alloc,
+            self.signup_reward_phase2_alloc,
+            self.referral_reward_phase1_alloc,
+            self.referral_reward_phase2_alloc,
+            self.tx_fee_subsidies_alloc,
+            self.causes_rewards_alloc,
+            karma_reward_alloc,
+            validators_pool_amount,
+        ]
+        .iter()
+        .try_fold(0, |total, alloc| {
+            checked_add(total, *alloc, "total genesis allocation")
+        })
+    }
+
+    /// Returns the genesis data provided to clients with its genesis hash.
+    /// The config should be validated first.
+    pub fn genesis_data(&self, char_traits: Vec<CharTrait>) -> Result<GenesisData> {
+        let verifiers = self
+            .verifiers
+            .iter()
+            .map(|(name, account_id)| {
+                Ok(PhoneVerifier {
+                    account_id: Some(AccountId {
+                        data: parse_public_key(name, account_id)?,
+                    }),
+                    name: name.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let governance_admins = self
+            .governance_admins
+            .iter()
+            .map(|(name, account_id)| {
+                Ok(AccountId {
+                    data: parse_public_key(name, account_id)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let deleted_user_funds_account_id = match self.deleted_user_funds_account_id.as_str() {
+            "" => None,
+            account_id => Some(AccountId {
+                data: parse_public_key("deleted user funds account id", account_id)?,
+            }),
+        };
+
+        let mut genesis_data = GenesisData {
+            net_id: self.net_id,
+            net_name: self.net_name.clone(),
+            genesis_time: self.genesis_time,
+            signup_reward_phase1_alloc: self.signup_reward_phase1_alloc,
+            signup_reward_phase2_alloc: self.signup_reward_phase2_alloc,
+            signup_reward_phase1_amount: self.signup_reward_phase1_amount,
+            signup_reward_phase2_amount: self.signup_reward_phase2_amount,
+            signup_reward_phase3_start: self.signup_reward_phase3_amount,
+            referral_reward_phase1_alloc: self.referral_reward_phase1_alloc,
+            referral_reward_phase2_alloc: self.referral_reward_phase2_alloc,
+            referral_reward_phase1_amount: self.referral_reward_phase1_amount,
+            referral_reward_phase2_amount: self.referral_reward_phase2_amount,
+            tx_fee_subsidy_max_per_user: self.tx_fee_subsidy_max_per_user,
+            tx_fee_subsidies_alloc: self.tx_fee_subsidies_alloc,
+            tx_fee_subsidy_max_amount: self.tx_fee_subsidy_max_amount,
+            tx_fee_subsidy_max_amount_phase1: self.tx_fee_subsidy_max_amount_phase1,
+            block_reward_amount: self.block_reward_amount,
+            block_reward_last_block: self.block_reward_last_block,
+            karma_reward_amount: self.karma_reward_amount,
+            karma_reward_alloc: self.karma_reward_alloc,
+            karma_reward_top_n_users: self.karma_reward_top_n_users,
+            karma_rewards_eligibility: self.karma_rewards_eligibility,
+            karma_rewards_period_hours: self.karma_reward_period_minutes,
+            validators_pool_amount: self.validators_pool_amount,
+            validators_pool_account_id: self.validators_pool_account_id.clone(),
+            validators_pool_account_name: self.validators_pool_account_name.clone(),
+            char_traits,
+            verifiers,
+            genesis_hash: vec![],
+            governance_admins,
+            governance_threshold: self.governance_threshold,
+            verifiers_threshold: self.verifiers_threshold,
+            deleted_user_funds_account_id,
+            user_name_cooldown_seconds: self.user_name_cooldown_seconds,
+        };
+
+        genesis_data.genesis_hash = genesis_data.compute_genesis_hash()?;
+        Ok(genesis_data)
+    }
+
+    /// Returns a validated copy of the config with the params changes applied.
+    /// Values are json encoded and keys are the genesis config keys
+    pub fn apply_changes(&self, changes: &[ParamChange]) -> Result<GenesisConfig> {
+        if changes.is_empty() {
+            return Err(anyhow!("no params changes"));
+        }
+
+        let mut value = serde_json::to_value(self)?;
+        let params = value
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("unexpected genesis config encoding"))?;
+
+        for change in changes.iter() {
+            if IMMUTABLE_PARAMS_KEYS.contains(&change.key.as_str()) {
+                return Err(anyhow!("param {} can't be updated", change.key));
+            }
+
+            let param = params
+                .get_mut(&change.key)
+                .ok_or_else(|| anyhow!("unknown param {}", change.key))?;
+
+            *param = serde_json::from_str(&change.value)
+                .map_err(|e| anyhow!("invalid {} value {}: {}", change.key, change.value, e))?;
+        }
+
+        let config: GenesisConfig =
+            serde_json::from_value(value).map_err(|e| anyhow!("invalid params update: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Returns the config with the params updates applied in order
+    pub fn apply_params_updates(&self, params_updates: &[ParamsUpdate]) -> Result<GenesisConfig> {
+        let mut config = self.clone();
+        for params_update in params_updates.iter() {
+            let proposal = params_update
+                .proposal
+                .as_ref()
+                .ok_or_else(|| anyhow!("params update without a proposal"))?;
+            config = config.apply_changes(&proposal.changes)?;
+        }
+        Ok(config)
+    }
+}
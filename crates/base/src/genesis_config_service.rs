@@ -2,10 +2,9 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::genesis_config::GenesisConfig;
 use crate::karma_coin::karma_coin_api::{GetGenesisDataRequest, GetGenesisDataResponse};
-use crate::karma_coin::karma_coin_core_types::{
-    AccountId, CharTrait, Community, GenesisData, PhoneVerifier,
-};
+use crate::karma_coin::karma_coin_core_types::{CharTrait, Community, GenesisData, ParamsUpdate};
 use anyhow::{anyhow, Result};
 use config::{Config, Environment, Map, Value};
 use log::*;
@@ -114,6 +113,24 @@ pub const VALIDATORS_POOL_COINS_AMOUNT_KEY: &str = "validates_pool_amount";
 /// A set of canonical mobile phone verifiers accounts ids
 pub const VERIFIERS_ACCOUNTS_IDS: &str = "verifiers_accounts_ids";
 
+/// Min number of distinct verifiers evidence required to register or update a mobile number
+pub const VERIFIERS_THRESHOLD_KEY: &str = "verifiers_threshold";
+
+/// Account which receives deleted accounts balances. Balances are burned when empty
+pub const DELETED_USER_FUNDS_ACCOUNT_ID_KEY: &str = "deleted_user_funds_account_id";
+
+/// Time after an account's deletion before its user name may be registered again
+pub const USER_NAME_COOLDOWN_SECONDS_KEY: &str = "user_name_cooldown_seconds";
+
+/// Time after which an escrowed payment to a number without an account is refunded to the payer
+pub const PENDING_PAYMENT_EXPIRY_SECONDS_KEY: &str = "pending_payment_expiry_seconds";
+
+/// Governance admins accounts ids by admin name. Admins approve genesis params updates
+pub const GOVERNANCE_ADMINS_ACCOUNTS_IDS: &str = "governance_admins_accounts_ids";
+
+/// Min number of governance admins approvals of a params update
+pub const GOVERNANCE_THRESHOLD_KEY: &str = "governance_threshold";
+
 /// This is the signup trait - user gets it for signing up
 pub const SIGNUP_CHAR_TRAIT_ID: u32 = 1;
 
@@ -136,7 +153,14 @@ pub const NO_CHAR_TRAIT_ID: u32 = 0;
 pub struct GenesisConfigService {
     config: Config,
     config_file: Option<String>,
-    pub(crate) genesis_data: Option<GenesisData>,
+    genesis_config: Option<GenesisConfig>,
+    genesis_data: Option<GenesisData>,
+    /// executed params updates ordered by target height
+    params_updates: Vec<ParamsUpdate>,
+    /// height of the next block. Params updates targeting it or a lower height are in effect
+    next_height: u64,
+    /// number of applied params updates and the resulting genesis config and data
+    effective_genesis: Option<(usize, GenesisConfig, GenesisData)>,
     char_traits: Option<Vec<CharTrait>>,
     communities: Option<Vec<Community>>,
 }
@@ -346,6 +370,26 @@ impl Actor for GenesisConfigService {
             .unwrap()
             .set_default(VALIDATORS_ACCOUNT_NAME_KEY, "Validator 1")
             .unwrap()
+            // escrowed payments to numbers without an account are refunded after 30 days
+            .set_default(PENDING_PAYMENT_EXPIRY_SECONDS_KEY, 30 * 24 * 60 * 60)
+            .unwrap()
+            // params updates are disabled until governance admins are configured
+            .set_default(
+                GOVERNANCE_ADMINS_ACCOUNTS_IDS,
+                HashMap::<String, String>::new(),
+            )
+            .unwrap()
+            .set_default(GOVERNANCE_THRESHOLD_KEY, 1)
+            .unwrap()
+            // a single verifier evidence is required by default
+            .set_default(VERIFIERS_THRESHOLD_KEY, 1)
+            .unwrap()
+            // deleted accounts balances are burned
+            .set_default(DELETED_USER_FUNDS_ACCOUNT_ID_KEY, "")
+            .unwrap()
+            // deleted accounts user names are released 30 days after their deletion
+            .set_default(USER_NAME_COOLDOWN_SECONDS_KEY, 30 * 24 * 60 * 60)
+            .unwrap()
             .add_source(
                 Environment::with_prefix("GENESIS")
                     .try_parsing(true)
@@ -373,19 +417,81 @@ impl Service for GenesisConfigService {}
 
 // helpers
 impl GenesisConfigService {
-    /// Returns all supported char traits from genesis data
-    async fn get_verifiers(&mut self) -> Result<Vec<PhoneVerifier>> {
-        let mut verifiers = vec![];
-        for (name, account_id) in self.config.get_table(VERIFIERS_ACCOUNTS_IDS).unwrap() {
-            verifiers.push(PhoneVerifier {
-                account_id: Some(AccountId {
-                    data: account_id.into_string()?.as_bytes().to_vec(),
-                }),
-                name,
-            })
+    /// Override a config value on top of the current config values
+    fn set_override<T: Into<Value>>(&mut self, key: &str, value: T) -> Result<()> {
+        self.config = Config::builder()
+            .add_source(self.config.clone())
+            .set_override(key, value)?
+            .build()?;
+        self.clear_genesis();
+        Ok(())
+    }
+
+    /// Clear the typed genesis config and data so they are loaded again from the config
+    fn clear_genesis(&mut self) {
+        self.genesis_config = None;
+        self.genesis_data = None;
+        self.effective_genesis = None;
+    }
+
+    /// Load and validate the typed genesis config and its genesis data from the config.
+    /// Both are cached until the config is changed.
+    fn load_genesis(&mut self) -> Result<(GenesisConfig, GenesisData)> {
+        if let (Some(config), Some(data)) = (&self.genesis_config, &self.genesis_data) {
+            return Ok((config.clone(), data.clone()));
         }
 
-        Ok(verifiers)
+        let genesis_config: GenesisConfig = self
+            .config
+            .clone()
+            .try_deserialize()
+            .map_err(|e| anyhow!("invalid genesis config: {}", e))?;
+        genesis_config.validate()?;
+
+        let genesis_data =
+            genesis_config.genesis_data(self.char_traits.as_ref().unwrap().clone())?;
+        genesis_data.validate()?;
+
+        self.genesis_config = Some(genesis_config.clone());
+        self.genesis_data = Some(genesis_data.clone());
+        Ok((genesis_config, genesis_data))
+    }
+
+    /// Returns the genesis config and data in effect at a block height, with all params
+    /// updates which target it or a lower height applied.
+    /// The genesis hash identifies the chain so it is not changed by params updates.
+    fn load_genesis_at(&mut self, height: u64) -> Result<(GenesisConfig, GenesisData)> {
+        let (genesis_config, genesis_data) = self.load_genesis()?;
+        let count = self
+            .params_updates
+            .iter()
+            .take_while(|u| u.target_height() <= height)
+            .count();
+
+        if count == 0 {
+            return Ok((genesis_config, genesis_data));
+        }
+
+        if let Some((applied, config, data)) = &self.effective_genesis {
+            if *applied == count {
+                return Ok((config.clone(), data.clone()));
+            }
+        }
+
+        let config = genesis_config.apply_params_updates(&self.params_updates[..count])?;
+        let mut data = config.genesis_data(self.char_traits.as_ref().unwrap().clone())?;
+        data.validate()?;
+        data.genesis_hash = genesis_data.genesis_hash;
+
+        self.effective_genesis = Some((count, config.clone(), data.clone()));
+        Ok((config, data))
+    }
+
+    /// Set the known params updates in target height order
+    fn set_params_updates(&mut self, mut params_updates: Vec<ParamsUpdate>) {
+        params_updates.sort_by_key(|u| u.sort_key());
+        self.params_updates = params_updates;
+        self.effective_genesis = None;
     }
 
     pub async fn get(key: String) -> Result<Option<String>> {
@@ -412,6 +518,42 @@ impl GenesisConfigService {
         Ok(res)
     }
 
+    /// Returns the validated genesis data snapshot in effect at the next block height
+    pub async fn get_genesis_data() -> Result<GenesisData> {
+        let config = GenesisConfigService::from_registry().await?;
+        let res = config
+            .call(GetGenesisData {
+                request: GetGenesisDataRequest {},
+            })
+            .await??;
+        res.genesis_data
+            .ok_or_else(|| anyhow!("missing genesis data"))
+    }
+
+    /// Returns the validated genesis data in effect at a block height
+    pub async fn get_genesis_data_at(height: u64) -> Result<GenesisData> {
+        let config = GenesisConfigService::from_registry().await?;
+        config.call(GetGenesisDataAt { height }).await?
+    }
+
+    /// Returns the validated typed genesis config in effect at the next block height
+    pub async fn get_genesis_config() -> Result<GenesisConfig> {
+        let config = GenesisConfigService::from_registry().await?;
+        config.call(GetGenesisConfig).await?
+    }
+
+    /// Returns the validated typed genesis config in effect at a block height
+    pub async fn get_genesis_config_at(height: u64) -> Result<GenesisConfig> {
+        let config = GenesisConfigService::from_registry().await?;
+        config.call(GetGenesisConfigAt { height }).await?
+    }
+
+    /// Returns all executed params updates ordered by target height
+    pub async fn get_params_updates() -> Result<Vec<ParamsUpdate>> {
+        let config = GenesisConfigService::from_registry().await?;
+        config.call(GetParamsUpdates).await
+    }
+
     pub async fn get_u64(key: String) -> Result<Option<u64>> {
         let config = GenesisConfigService::from_registry().await?;
         let res = config.call(GetU64(key)).await?;
@@ -432,6 +574,11 @@ impl GenesisConfigService {
         let config = GenesisConfigService::from_registry().await?;
         config.call(SetU64 { key, value }).await?
     }
+
+    pub async fn set_map(key: String, value: HashMap<String, String>) -> Result<()> {
+        let config = GenesisConfigService::from_registry().await?;
+        config.call(SetMap { key, value }).await?
+    }
 }
 
 #[message(result = "Result<()>")]
@@ -448,12 +595,14 @@ impl Handler<SetConfigFile> for GenesisConfigService {
             return Ok(());
         }
 
-        #[allow(deprecated)]
-        self.config
-            .merge(config::File::with_name(&msg.config_file))
-            .unwrap();
+        // layer the file over the current config so defaults and overrides are kept
+        self.config = Config::builder()
+            .add_source(self.config.clone())
+            .add_source(config::File::with_name(&msg.config_file))
+            .build()?;
 
         self.config_file = Some(msg.config_file.clone());
+        self.clear_genesis();
 
         info!(
             "Merging content of config file {:?}",
@@ -552,11 +701,7 @@ pub struct SetValue {
 #[async_trait::async_trait]
 impl Handler<SetValue> for GenesisConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetValue) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value)
     }
 }
 
@@ -569,11 +714,7 @@ pub struct SetU64 {
 #[async_trait::async_trait]
 impl Handler<SetU64> for GenesisConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetU64) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value.to_string()) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value.to_string())
     }
 }
 
@@ -589,72 +730,155 @@ impl Handler<GetGenesisData> for GenesisConfigService {
         _ctx: &mut Context<Self>,
         _msg: GetGenesisData,
     ) -> Result<GetGenesisDataResponse> {
-        if let Some(data) = self.genesis_data.as_ref() {
-            return Ok(GetGenesisDataResponse {
-                genesis_data: Some(data.clone()),
-            });
+        let (_, genesis_data) = self.load_genesis_at(self.next_height)?;
+        Ok(GetGenesisDataResponse {
+            genesis_data: Some(genesis_data),
+        })
+    }
+}
+
+#[message(result = "Result<GenesisConfig>")]
+pub struct GetGenesisConfig;
+
+#[async_trait::async_trait]
+impl Handler<GetGenesisConfig> for GenesisConfigService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _msg: GetGenesisConfig,
+    ) -> Result<GenesisConfig> {
+        let (genesis_config, _) = self.load_genesis_at(self.next_height)?;
+        Ok(genesis_config)
+    }
+}
+
+#[message(result = "Result<GenesisData>")]
+pub struct GetGenesisDataAt {
+    pub height: u64,
+}
+
+#[async_trait::async_trait]
+impl Handler<GetGenesisDataAt> for GenesisConfigService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetGenesisDataAt,
+    ) -> Result<GenesisData> {
+        let (_, genesis_data) = self.load_genesis_at(msg.height)?;
+        Ok(genesis_data)
+    }
+}
+
+#[message(result = "Result<GenesisConfig>")]
+pub struct GetGenesisConfigAt {
+    pub height: u64,
+}
+
+#[async_trait::async_trait]
+impl Handler<GetGenesisConfigAt> for GenesisConfigService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetGenesisConfigAt,
+    ) -> Result<GenesisConfig> {
+        let (genesis_config, _) = self.load_genesis_at(msg.height)?;
+        Ok(genesis_config)
+    }
+}
+
+/// Set all executed params updates and the next block height. Sent when the chain is loaded
+#[message]
+pub struct SetParamsUpdates {
+    pub params_updates: Vec<ParamsUpdate>,
+    pub next_height: u64,
+}
+
+#[async_trait::async_trait]
+impl Handler<SetParamsUpdates> for GenesisConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetParamsUpdates) {
+        self.set_params_updates(msg.params_updates);
+        self.next_height = msg.next_height;
+    }
+}
+
+/// Add params updates executed in a block and set the next block height.
+/// Sent once the block is committed
+#[message]
+pub struct AddParamsUpdates {
+    pub params_updates: Vec<ParamsUpdate>,
+    pub next_height: u64,
+}
+
+#[async_trait::async_trait]
+impl Handler<AddParamsUpdates> for GenesisConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: AddParamsUpdates) {
+        if !msg.params_updates.is_empty() {
+            let mut params_updates = self.params_updates.clone();
+            params_updates.extend(msg.params_updates);
+            self.set_params_updates(params_updates);
         }
+        self.next_height = msg.next_height;
+    }
+}
 
-        let genesis_data = GenesisData {
-            net_id: self.config.get_int(NET_ID_KEY).unwrap() as u32,
-            net_name: self.config.get_string(NET_NAME_KEY)?,
-
-            genesis_time: self.config.get_int(GENESIS_TIMESTAMP_SECONDS_KEY)? as u64,
-
-            signup_reward_phase1_alloc: self.config.get_int(SIGNUP_REWARD_ALLOCATION_PHASE1_KEY)?
-                as u64,
-            signup_reward_phase2_alloc: self.config.get_int(SIGNUP_REWARD_ALLOCATION_PHASE2_KEY)?
-                as u64,
-            signup_reward_phase1_amount: self.config.get_int(SIGNUP_REWARD_AMOUNT_PHASE1_KEY)?
-                as u64,
-            signup_reward_phase2_amount: self.config.get_int(SIGNUP_REWARD_AMOUNT_PHASE2_KEY)?
-                as u64,
-            signup_reward_phase3_start: self.config.get_int(SIGNUP_REWARD_AMOUNT_PHASE3_KEY)?
-                as u64,
-
-            referral_reward_phase1_alloc: self
-                .config
-                .get_int(REFERRAL_REWARD_ALLOCATION_PHASE1_KEY)?
-                as u64,
-            referral_reward_phase2_alloc: self
-                .config
-                .get_int(REFERRAL_REWARD_ALLOCATION_PHASE2_KEY)?
-                as u64,
-            referral_reward_phase1_amount: self.config.get_int(REFERRAL_REWARD_AMOUNT_PHASE1_KEY)?
-                as u64,
-            referral_reward_phase2_amount: self.config.get_int(REFERRAL_REWARD_AMOUNT_PHASE2_KEY)?
-                as u64,
-
-            tx_fee_subsidy_max_per_user: self.config.get_int(TX_FEE_SUBSIDY_MAX_TXS_PER_USER_KEY)?
-                as u64,
-            tx_fee_subsidies_alloc: self.config.get_int(TX_FEE_SUBSIDY_ALLOCATION_PHASE1_KEY)?
-                as u64,
-            tx_fee_subsidy_max_amount: self.config.get_int(TX_FEE_SUBSIDY_MAX_AMOUNT_KEY)? as u64,
-
-            block_reward_amount: self.config.get_int(BLOCK_REWARDS_AMOUNT)? as u64,
-            block_reward_last_block: self.config.get_int(BLOCK_REWARDS_LAST_BLOCK)? as u64,
-
-            karma_reward_amount: self.config.get_int(KARMA_REWARD_AMOUNT)? as u64,
-            karma_reward_alloc: self.config.get_int(KARAM_REWARDS_ALLOCATION_KEY)? as u64,
-            karma_reward_top_n_users: self.config.get_int(KARMA_REWARD_MAX_USERS_KEY)? as u64,
-            karma_rewards_eligibility: self.config.get_int(KARMA_REWARDS_ELIGIBILITY)? as u64,
-            karma_rewards_period_hours: self.config.get_int(KARMA_REWARD_PERIOD_MINUTES)? as u64,
-
-            validators_pool_amount: self.config.get_int(VALIDATORS_POOL_COINS_AMOUNT_KEY)? as u64,
-            validators_pool_account_id: self.config.get_string(VALIDATORS_POOL_ACCOUNT_ID_KEY)?,
-            validators_pool_account_name: self.config.get_string(VALIDATORS_ACCOUNT_NAME_KEY)?,
-
-            verifiers: self.get_verifiers().await?,
-
-            char_traits: self.char_traits.as_ref().unwrap().clone(),
-        };
+/// Validate that the genesis config stays valid when new params updates are applied with
+/// all executed params updates, in target height order
+#[message(result = "Result<()>")]
+pub struct ValidateParamsUpdates {
+    pub params_updates: Vec<ParamsUpdate>,
+}
 
-        // cache genesis data as it is read-only
-        self.genesis_data = Some(genesis_data.clone());
+#[async_trait::async_trait]
+impl Handler<ValidateParamsUpdates> for GenesisConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: ValidateParamsUpdates) -> Result<()> {
+        let (genesis_config, _) = self.load_genesis()?;
+        let mut params_updates: Vec<ParamsUpdate> = self
+            .params_updates
+            .iter()
+            .filter(|u| !msg.params_updates.iter().any(|n| n.tx_hash == u.tx_hash))
+            .cloned()
+            .collect();
+
+        for params_update in msg.params_updates.iter() {
+            if params_updates
+                .iter()
+                .any(|u| u.proposal == params_update.proposal)
+            {
+                return Err(anyhow!("params update proposal was already executed"));
+            }
+            params_updates.push(params_update.clone());
+        }
 
-        Ok(GetGenesisDataResponse {
-            genesis_data: Some(genesis_data.clone()),
-        })
+        params_updates.sort_by_key(|u| u.sort_key());
+        genesis_config.apply_params_updates(&params_updates)?;
+        Ok(())
+    }
+}
+
+#[message(result = "Vec<ParamsUpdate>")]
+pub struct GetParamsUpdates;
+
+#[async_trait::async_trait]
+impl Handler<GetParamsUpdates> for GenesisConfigService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _msg: GetParamsUpdates,
+    ) -> Vec<ParamsUpdate> {
+        self.params_updates.clone()
+    }
+}
+
+#[message(result = "Result<()>")]
+pub struct SetMap {
+    pub key: String,
+    pub value: HashMap<String, String>,
+}
+
+#[async_trait::async_trait]
+impl Handler<SetMap> for GenesisConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetMap) -> Result<()> {
+        self.set_override(msg.key.as_str(), msg.value)
     }
 }
 
@@ -667,10 +891,6 @@ pub struct SetBool {
 #[async_trait::async_trait]
 impl Handler<SetBool> for GenesisConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetBool) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value)
     }
 }
@@ -2,16 +2,70 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::genesis_config_service::ONE_KC_IN_KCENTS;
+use crate::hasher::Hasher;
+use crate::hex_utils::short_hex_string;
 use crate::karma_coin::karma_coin_core_types::GenesisData;
+use anyhow::{anyhow, Result};
+use prost::Message;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+impl GenesisData {
+    /// Validate genesis values which consensus rules depend on
+    pub fn validate(&self) -> Result<()> {
+        if self.net_name.is_empty() {
+            return Err(anyhow!("genesis net name is empty"));
+        }
+
+        if self
+            .karma_reward_alloc
+            .checked_mul(ONE_KC_IN_KCENTS)
+            .is_none()
+        {
+            return Err(anyhow!(
+                "genesis karma rewards allocation {} KC is too large",
+                self.karma_reward_alloc
+            ));
+        }
+
+        if self.karma_rewards_period_hours == 0 {
+            return Err(anyhow!("genesis karma rewards period must be positive"));
+        }
+
+        if self
+            .verifiers
+            .iter()
+            .any(|v| v.account_id.as_ref().is_none_or(|id| id.data.is_empty()))
+        {
+            return Err(anyhow!("genesis verifier without an account id"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the hash of the genesis data, excluding its genesis hash
+    pub fn compute_genesis_hash(&self) -> Result<Vec<u8>> {
+        let mut data = self.clone();
+        data.genesis_hash = vec![];
+        let mut buf = Vec::with_capacity(data.encoded_len());
+        data.encode(&mut buf)?;
+        Hasher::hash(buf.as_ref())
+    }
+
+    /// Returns the karma rewards allocation in KCents
+    pub fn karma_reward_alloc_kcents(&self) -> u64 {
+        self.karma_reward_alloc.saturating_mul(ONE_KC_IN_KCENTS)
+    }
+}
+
 impl Display for GenesisData {
     // This trait requires `fmt` with this exact signature.
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         writeln!(
             f,
-            "GenesisData {{ karma_reward_amount: {}, karma_reward_eligibility: {}, karma_reward_period: {} }}",
+            "GenesisData {{ genesis_hash: {}, karma_reward_amount: {}, karma_reward_eligibility: {}, karma_reward_period: {} }}",
+            short_hex_string(&self.genesis_hash),
             &self.karma_reward_amount,
             &self.karma_rewards_eligibility,
             &self.karma_rewards_period_hours,
@@ -114,6 +114,11 @@ pub struct SubmitTransactionRequest {
 pub struct SubmitTransactionResponse {
     #[prost(enumeration = "SubmitTransactionResult", tag = "1")]
     pub submit_transaction_result: i32,
+    /// set when the transaction was rejected
+    #[prost(enumeration = "SubmitTransactionRejectReason", tag = "2")]
+    pub reject_reason: i32,
+    #[prost(string, tag = "3")]
+    pub reject_message: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -150,6 +155,15 @@ pub struct GetGenesisDataResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetParamsUpdatesRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetParamsUpdatesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub params_updates: ::prost::alloc::vec::Vec<super::core_types::ParamsUpdate>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetBlockchainDataRequest {}
 /// Current blockchain data
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -158,11 +172,42 @@ pub struct GetBlockchainDataResponse {
     #[prost(message, optional, tag = "1")]
     pub stats: ::core::option::Option<super::core_types::BlockchainStats>,
 }
+/// Filters of an account's transactions. Unset fields match all transactions
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransactionsFilter {
+    /// empty for all types
+    #[prost(enumeration = "super::core_types::TransactionType", repeated, tag = "1")]
+    pub transaction_types: ::prost::alloc::vec::Vec<i32>,
+    #[prost(enumeration = "TransactionDirection", tag = "2")]
+    pub direction: i32,
+    /// payments with this char trait. 0 for any
+    #[prost(uint32, tag = "3")]
+    pub char_trait_id: u32,
+    /// payments in this community. 0 for any
+    #[prost(uint32, tag = "4")]
+    pub community_id: u32,
+    /// min tx timestamp (inclusive). 0 for no min
+    #[prost(uint64, tag = "5")]
+    pub from_time: u64,
+    /// max tx timestamp (inclusive). 0 for no max
+    #[prost(uint64, tag = "6")]
+    pub to_time: u64,
+}
+/// Returns an account's transactions, newest first, one page at a time
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetTransactionsRequest {
     #[prost(message, optional, tag = "1")]
     pub account_id: ::core::option::Option<super::core_types::AccountId>,
+    /// max number of transactions to return. 0 for the default page size
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+    /// next_cursor of the previous page. Empty for the first page
+    #[prost(bytes = "vec", tag = "3")]
+    pub cursor: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "4")]
+    pub filter: ::core::option::Option<TransactionsFilter>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -173,6 +218,42 @@ pub struct GetTransactionsResponse {
     >,
     #[prost(message, optional, tag = "2")]
     pub tx_events: ::core::option::Option<super::core_types::TransactionEvents>,
+    /// cursor of the next page. Empty when there are no more transactions
+    #[prost(bytes = "vec", tag = "3")]
+    pub next_cursor: ::prost::alloc::vec::Vec<u8>,
+}
+/// Returns an account's incoming transactions, newest first, one page at a time
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetIncomingTransactionsRequest {
+    #[prost(message, optional, tag = "1")]
+    pub account_id: ::core::option::Option<super::core_types::AccountId>,
+    /// max number of transactions to return. 0 for the default page size
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+    /// next_cursor of the previous page. Empty for the first page
+    #[prost(bytes = "vec", tag = "3")]
+    pub cursor: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetIncomingTransactionsResponse {
+    /// on chain txs
+    #[prost(message, repeated, tag = "1")]
+    pub transactions: ::prost::alloc::vec::Vec<
+        super::core_types::SignedTransactionWithStatus,
+    >,
+    #[prost(message, optional, tag = "2")]
+    pub tx_events: ::core::option::Option<super::core_types::TransactionEvents>,
+    /// cursor of the next page. Empty when there are no more transactions
+    #[prost(bytes = "vec", tag = "3")]
+    pub next_cursor: ::prost::alloc::vec::Vec<u8>,
+    /// pending payments to the account id or to the account's verified mobile number.
+    /// Only included in the first page
+    #[prost(message, repeated, tag = "4")]
+    pub pending_transactions: ::prost::alloc::vec::Vec<
+        super::core_types::SignedTransactionWithStatus,
+    >,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -192,6 +273,29 @@ pub struct GetTransactionResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WaitForTransactionRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub tx_hash: ::prost::alloc::vec::Vec<u8>,
+    /// max time to wait in milliseconds. Capped by the node
+    #[prost(uint64, tag = "2")]
+    pub timeout_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WaitForTransactionResponse {
+    /// the transaction and its status when the wait ended. Not set for an unknown transaction
+    #[prost(message, optional, tag = "1")]
+    pub transaction: ::core::option::Option<
+        super::core_types::SignedTransactionWithStatus,
+    >,
+    #[prost(message, optional, tag = "2")]
+    pub tx_events: ::core::option::Option<super::core_types::TransactionEvents>,
+    /// true when the transaction was not on chain or rejected before the timeout
+    #[prost(bool, tag = "3")]
+    pub timed_out: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetBlockchainEventsRequest {
     #[prost(uint64, tag = "1")]
     pub from_block_height: u64,
@@ -218,6 +322,78 @@ pub struct GetBlocksResponse {
     #[prost(message, repeated, tag = "1")]
     pub blocks: ::prost::alloc::vec::Vec<super::core_types::Block>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyChainRequest {
+    #[prost(uint64, tag = "1")]
+    pub from: u64,
+    #[prost(uint64, tag = "2")]
+    pub to: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyChainResponse {
+    #[prost(uint64, tag = "1")]
+    pub blocks_verified: u64,
+    /// description of every verification failure. Empty when the chain is valid
+    #[prost(string, repeated, tag = "2")]
+    pub errors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeBlockEventsRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeBlockEventsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub block_event: ::core::option::Option<super::core_types::BlockEvent>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeAccountEventsRequest {
+    #[prost(message, optional, tag = "1")]
+    pub account_id: ::core::option::Option<super::core_types::AccountId>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeAccountEventsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub transaction_event: ::core::option::Option<super::core_types::TransactionEvent>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTransactionProofRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub tx_hash: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTransactionProofResponse {
+    /// the block which includes the transaction
+    #[prost(message, optional, tag = "1")]
+    pub block: ::core::option::Option<super::core_types::Block>,
+    /// proof of the transaction hash in the block transactions root
+    #[prost(message, optional, tag = "2")]
+    pub proof: ::core::option::Option<super::core_types::MerkleProof>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetAccountProofRequest {
+    #[prost(message, optional, tag = "1")]
+    pub account_id: ::core::option::Option<super::core_types::AccountId>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetAccountProofResponse {
+    #[prost(message, optional, tag = "1")]
+    pub user: ::core::option::Option<super::core_types::User>,
+    /// the tip block the proof is provided against
+    #[prost(message, optional, tag = "2")]
+    pub block: ::core::option::Option<super::core_types::Block>,
+    /// proof of the serialized user in the block state root
+    #[prost(message, optional, tag = "3")]
+    pub proof: ::core::option::Option<super::core_types::SparseMerkleProof>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum SubmitTransactionResult {
@@ -244,6 +420,148 @@ impl SubmitTransactionResult {
         }
     }
 }
+/// Reason a submitted transaction was not admitted to the mem pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SubmitTransactionRejectReason {
+    Unspecified = 0,
+    /// malformed transaction or transaction data
+    InvalidData = 1,
+    InvalidSignature = 2,
+    InvalidNetId = 3,
+    InvalidTimestamp = 4,
+    FeeTooLow = 5,
+    InvalidNonce = 6,
+    InsufficientBalance = 7,
+    InvalidVerificationEvidence = 8,
+    /// signer has no on-chain account
+    SignerNotFound = 9,
+    /// already in the mem pool
+    Duplicate = 10,
+    AlreadyOnChain = 11,
+    /// pool is full and the tx fee is too low to replace a pending tx
+    MemPoolFull = 12,
+    /// signer has too many pending txs
+    AccountTxsLimit = 13,
+}
+impl SubmitTransactionRejectReason {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SubmitTransactionRejectReason::Unspecified => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_UNSPECIFIED"
+            }
+            SubmitTransactionRejectReason::InvalidData => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_DATA"
+            }
+            SubmitTransactionRejectReason::InvalidSignature => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_SIGNATURE"
+            }
+            SubmitTransactionRejectReason::InvalidNetId => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_NET_ID"
+            }
+            SubmitTransactionRejectReason::InvalidTimestamp => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_TIMESTAMP"
+            }
+            SubmitTransactionRejectReason::FeeTooLow => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_FEE_TOO_LOW"
+            }
+            SubmitTransactionRejectReason::InvalidNonce => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_NONCE"
+            }
+            SubmitTransactionRejectReason::InsufficientBalance => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INSUFFICIENT_BALANCE"
+            }
+            SubmitTransactionRejectReason::InvalidVerificationEvidence => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_VERIFICATION_EVIDENCE"
+            }
+            SubmitTransactionRejectReason::SignerNotFound => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_SIGNER_NOT_FOUND"
+            }
+            SubmitTransactionRejectReason::Duplicate => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_DUPLICATE"
+            }
+            SubmitTransactionRejectReason::AlreadyOnChain => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_ALREADY_ON_CHAIN"
+            }
+            SubmitTransactionRejectReason::MemPoolFull => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_MEM_POOL_FULL"
+            }
+            SubmitTransactionRejectReason::AccountTxsLimit => {
+                "SUBMIT_TRANSACTION_REJECT_REASON_ACCOUNT_TXS_LIMIT"
+            }
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SUBMIT_TRANSACTION_REJECT_REASON_UNSPECIFIED" => Some(Self::Unspecified),
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_DATA" => Some(Self::InvalidData),
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_SIGNATURE" => {
+                Some(Self::InvalidSignature)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_NET_ID" => Some(Self::InvalidNetId),
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_TIMESTAMP" => {
+                Some(Self::InvalidTimestamp)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_FEE_TOO_LOW" => Some(Self::FeeTooLow),
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_NONCE" => Some(Self::InvalidNonce),
+            "SUBMIT_TRANSACTION_REJECT_REASON_INSUFFICIENT_BALANCE" => {
+                Some(Self::InsufficientBalance)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_INVALID_VERIFICATION_EVIDENCE" => {
+                Some(Self::InvalidVerificationEvidence)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_SIGNER_NOT_FOUND" => {
+                Some(Self::SignerNotFound)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_DUPLICATE" => Some(Self::Duplicate),
+            "SUBMIT_TRANSACTION_REJECT_REASON_ALREADY_ON_CHAIN" => {
+                Some(Self::AlreadyOnChain)
+            }
+            "SUBMIT_TRANSACTION_REJECT_REASON_MEM_POOL_FULL" => Some(Self::MemPoolFull),
+            "SUBMIT_TRANSACTION_REJECT_REASON_ACCOUNT_TXS_LIMIT" => {
+                Some(Self::AccountTxsLimit)
+            }
+            _ => None,
+        }
+    }
+}
+/// Direction of an account's transactions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum TransactionDirection {
+    Any = 0,
+    /// signed by the account
+    Sent = 1,
+    /// signed by another account. e.g. a payment to the account
+    Received = 2,
+}
+impl TransactionDirection {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            TransactionDirection::Any => "TRANSACTION_DIRECTION_ANY",
+            TransactionDirection::Sent => "TRANSACTION_DIRECTION_SENT",
+            TransactionDirection::Received => "TRANSACTION_DIRECTION_RECEIVED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "TRANSACTION_DIRECTION_ANY" => Some(Self::Any),
+            "TRANSACTION_DIRECTION_SENT" => Some(Self::Sent),
+            "TRANSACTION_DIRECTION_RECEIVED" => Some(Self::Received),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod api_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -524,6 +842,26 @@ pub mod api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Returns all executed genesis params updates ordered by their target height
+        pub async fn get_params_updates(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetParamsUpdatesRequest>,
+        ) -> Result<tonic::Response<super::GetParamsUpdatesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/GetParamsUpdates",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Submit a signed transaction to the blockchain
         pub async fn submit_transaction(
             &mut self,
@@ -565,6 +903,30 @@ pub mod api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Get transactions received by an account, such as payments to its account id or to its
+        /// mobile number, and pending payments to the account which are waiting in the pool
+        pub async fn get_incoming_transactions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetIncomingTransactionsRequest>,
+        ) -> Result<
+            tonic::Response<super::GetIncomingTransactionsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/GetIncomingTransactions",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Get transaction data by its digest hash. Transaction may be in pool or on-chain
         /// Returns all events associated with the transaction
         pub async fn get_transaction(
@@ -586,6 +948,26 @@ pub mod api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Wait until a submitted transaction is on chain or rejected, or until a timeout
+        pub async fn wait_for_transaction(
+            &mut self,
+            request: impl tonic::IntoRequest<super::WaitForTransactionRequest>,
+        ) -> Result<tonic::Response<super::WaitForTransactionResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/WaitForTransaction",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Get blockchain events for a range of heights
         pub async fn get_blockchain_events(
             &mut self,
@@ -626,6 +1008,117 @@ pub mod api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Get an inclusion proof of an on-chain transaction in its block transactions root
+        pub async fn get_transaction_proof(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetTransactionProofRequest>,
+        ) -> Result<tonic::Response<super::GetTransactionProofResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/GetTransactionProof",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Get an inclusion proof of an account in the state root of the current tip block
+        pub async fn get_account_proof(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetAccountProofRequest>,
+        ) -> Result<tonic::Response<super::GetAccountProofResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/GetAccountProof",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Public api - verify blocks signatures, digests and chain linkage for a range of heights.
+        /// Heights beyond the chain tip are not verified and up to 1000 blocks are verified per request
+        pub async fn verify_chain(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VerifyChainRequest>,
+        ) -> Result<tonic::Response<super::VerifyChainResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/VerifyChain",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Stream the event of every new block once the block is committed
+        pub async fn subscribe_block_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeBlockEventsRequest>,
+        ) -> Result<
+            tonic::Response<
+                tonic::codec::Streaming<super::SubscribeBlockEventsResponse>,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/SubscribeBlockEvents",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// Stream the events of new transactions signed by or paying to an account
+        pub async fn subscribe_account_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeAccountEventsRequest>,
+        ) -> Result<
+            tonic::Response<
+                tonic::codec::Streaming<super::SubscribeAccountEventsResponse>,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.api.ApiService/SubscribeAccountEvents",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -692,6 +1185,11 @@ pub mod api_service_server {
             &self,
             request: tonic::Request<super::GetGenesisDataRequest>,
         ) -> Result<tonic::Response<super::GetGenesisDataResponse>, tonic::Status>;
+        /// Returns all executed genesis params updates ordered by their target height
+        async fn get_params_updates(
+            &self,
+            request: tonic::Request<super::GetParamsUpdatesRequest>,
+        ) -> Result<tonic::Response<super::GetParamsUpdatesResponse>, tonic::Status>;
         /// Submit a signed transaction to the blockchain
         async fn submit_transaction(
             &self,
@@ -703,12 +1201,26 @@ pub mod api_service_server {
             &self,
             request: tonic::Request<super::GetTransactionsRequest>,
         ) -> Result<tonic::Response<super::GetTransactionsResponse>, tonic::Status>;
+        /// Get transactions received by an account, such as payments to its account id or to its
+        /// mobile number, and pending payments to the account which are waiting in the pool
+        async fn get_incoming_transactions(
+            &self,
+            request: tonic::Request<super::GetIncomingTransactionsRequest>,
+        ) -> Result<
+            tonic::Response<super::GetIncomingTransactionsResponse>,
+            tonic::Status,
+        >;
         /// Get transaction data by its digest hash. Transaction may be in pool or on-chain
         /// Returns all events associated with the transaction
         async fn get_transaction(
             &self,
             request: tonic::Request<super::GetTransactionRequest>,
         ) -> Result<tonic::Response<super::GetTransactionResponse>, tonic::Status>;
+        /// Wait until a submitted transaction is on chain or rejected, or until a timeout
+        async fn wait_for_transaction(
+            &self,
+            request: tonic::Request<super::WaitForTransactionRequest>,
+        ) -> Result<tonic::Response<super::WaitForTransactionResponse>, tonic::Status>;
         /// Get blockchain events for a range of heights
         async fn get_blockchain_events(
             &self,
@@ -717,8 +1229,46 @@ pub mod api_service_server {
         /// Get blockchain events for a range of heights
         async fn get_blocks(
             &self,
-            request: tonic::Request<super::GetBlocksRequest>,
-        ) -> Result<tonic::Response<super::GetBlocksResponse>, tonic::Status>;
+            request: tonic::Request<super::GetBlocksRequest>,
+        ) -> Result<tonic::Response<super::GetBlocksResponse>, tonic::Status>;
+        /// Get an inclusion proof of an on-chain transaction in its block transactions root
+        async fn get_transaction_proof(
+            &self,
+            request: tonic::Request<super::GetTransactionProofRequest>,
+        ) -> Result<tonic::Response<super::GetTransactionProofResponse>, tonic::Status>;
+        /// Get an inclusion proof of an account in the state root of the current tip block
+        async fn get_account_proof(
+            &self,
+            request: tonic::Request<super::GetAccountProofRequest>,
+        ) -> Result<tonic::Response<super::GetAccountProofResponse>, tonic::Status>;
+        /// Public api - verify blocks signatures, digests and chain linkage for a range of heights.
+        /// Heights beyond the chain tip are not verified and up to 1000 blocks are verified per request
+        async fn verify_chain(
+            &self,
+            request: tonic::Request<super::VerifyChainRequest>,
+        ) -> Result<tonic::Response<super::VerifyChainResponse>, tonic::Status>;
+        /// Server streaming response type for the SubscribeBlockEvents method.
+        type SubscribeBlockEventsStream: futures_core::Stream<
+                Item = Result<super::SubscribeBlockEventsResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Stream the event of every new block once the block is committed
+        async fn subscribe_block_events(
+            &self,
+            request: tonic::Request<super::SubscribeBlockEventsRequest>,
+        ) -> Result<tonic::Response<Self::SubscribeBlockEventsStream>, tonic::Status>;
+        /// Server streaming response type for the SubscribeAccountEvents method.
+        type SubscribeAccountEventsStream: futures_core::Stream<
+                Item = Result<super::SubscribeAccountEventsResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Stream the events of new transactions signed by or paying to an account
+        async fn subscribe_account_events(
+            &self,
+            request: tonic::Request<super::SubscribeAccountEventsRequest>,
+        ) -> Result<tonic::Response<Self::SubscribeAccountEventsStream>, tonic::Status>;
     }
     /// Unified public API provided by blockchain nodes and verifiers
     #[derive(Debug)]
@@ -1183,6 +1733,46 @@ pub mod api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.api.ApiService/GetParamsUpdates" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetParamsUpdatesSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::GetParamsUpdatesRequest>
+                    for GetParamsUpdatesSvc<T> {
+                        type Response = super::GetParamsUpdatesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetParamsUpdatesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_params_updates(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetParamsUpdatesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/karma_coin.api.ApiService/SubmitTransaction" => {
                     #[allow(non_camel_case_types)]
                     struct SubmitTransactionSvc<T: ApiService>(pub Arc<T>);
@@ -1263,6 +1853,48 @@ pub mod api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.api.ApiService/GetIncomingTransactions" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetIncomingTransactionsSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::GetIncomingTransactionsRequest>
+                    for GetIncomingTransactionsSvc<T> {
+                        type Response = super::GetIncomingTransactionsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                super::GetIncomingTransactionsRequest,
+                            >,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_incoming_transactions(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetIncomingTransactionsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/karma_coin.api.ApiService/GetTransaction" => {
                     #[allow(non_camel_case_types)]
                     struct GetTransactionSvc<T: ApiService>(pub Arc<T>);
@@ -1303,6 +1935,46 @@ pub mod api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.api.ApiService/WaitForTransaction" => {
+                    #[allow(non_camel_case_types)]
+                    struct WaitForTransactionSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::WaitForTransactionRequest>
+                    for WaitForTransactionSvc<T> {
+                        type Response = super::WaitForTransactionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::WaitForTransactionRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).wait_for_transaction(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WaitForTransactionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/karma_coin.api.ApiService/GetBlockchainEvents" => {
                     #[allow(non_camel_case_types)]
                     struct GetBlockchainEventsSvc<T: ApiService>(pub Arc<T>);
@@ -1381,6 +2053,210 @@ pub mod api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.api.ApiService/GetTransactionProof" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetTransactionProofSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::GetTransactionProofRequest>
+                    for GetTransactionProofSvc<T> {
+                        type Response = super::GetTransactionProofResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetTransactionProofRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_transaction_proof(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetTransactionProofSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.api.ApiService/GetAccountProof" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetAccountProofSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::GetAccountProofRequest>
+                    for GetAccountProofSvc<T> {
+                        type Response = super::GetAccountProofResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetAccountProofRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_account_proof(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetAccountProofSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.api.ApiService/VerifyChain" => {
+                    #[allow(non_camel_case_types)]
+                    struct VerifyChainSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::UnaryService<super::VerifyChainRequest>
+                    for VerifyChainSvc<T> {
+                        type Response = super::VerifyChainResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VerifyChainRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).verify_chain(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = VerifyChainSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.api.ApiService/SubscribeBlockEvents" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeBlockEventsSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeBlockEventsRequest,
+                    > for SubscribeBlockEventsSvc<T> {
+                        type Response = super::SubscribeBlockEventsResponse;
+                        type ResponseStream = T::SubscribeBlockEventsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeBlockEventsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).subscribe_block_events(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribeBlockEventsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.api.ApiService/SubscribeAccountEvents" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeAccountEventsSvc<T: ApiService>(pub Arc<T>);
+                    impl<
+                        T: ApiService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeAccountEventsRequest,
+                    > for SubscribeAccountEventsSvc<T> {
+                        type Response = super::SubscribeAccountEventsResponse;
+                        type ResponseStream = T::SubscribeAccountEventsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeAccountEventsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).subscribe_account_events(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribeAccountEventsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
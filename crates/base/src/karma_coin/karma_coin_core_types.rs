@@ -10,12 +10,16 @@ pub struct AccountId {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Balance {
+    /// funds which can be used unless they are frozen
     #[prost(uint64, tag = "1")]
     pub free: u64,
+    /// funds which were set aside. e.g. for pending payments
     #[prost(uint64, tag = "2")]
     pub reserved: u64,
+    /// free funds which can't be transferred
     #[prost(uint64, tag = "3")]
     pub misc_frozen: u64,
+    /// free funds which can't be used to pay fees
     #[prost(uint64, tag = "4")]
     pub fee_frozen: u64,
 }
@@ -84,11 +88,12 @@ pub struct User {
     /// unique across the system
     #[prost(string, tag = "3")]
     pub user_name: ::prost::alloc::string::String,
-    /// verified current number
+    /// deprecated - not set. Numbers are only stored on chain by their hash
     #[prost(message, optional, tag = "4")]
     pub mobile_number: ::core::option::Option<MobileNumber>,
+    /// single amount balance. Migrated to balance.free on startup
     #[prost(uint64, tag = "5")]
-    pub balance: u64,
+    pub legacy_balance: u64,
     #[prost(message, repeated, tag = "6")]
     pub trait_scores: ::prost::alloc::vec::Vec<TraitScore>,
     /// one-time enc pre-keys for e2e messaging
@@ -100,6 +105,12 @@ pub struct User {
     /// all communities user is member of, admin rights and score in each
     #[prost(message, repeated, tag = "9")]
     pub community_memberships: ::prost::alloc::vec::Vec<CommunityMembership>,
+    /// user's free, reserved and frozen funds
+    #[prost(message, optional, tag = "10")]
+    pub balance: ::core::option::Option<Balance>,
+    /// sha256 hash of the verified current number which identifies it on chain
+    #[prost(bytes = "vec", tag = "11")]
+    pub mobile_number_hash: ::prost::alloc::vec::Vec<u8>,
 }
 /// Contact information for easy appreciation in the app of any contact
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -109,12 +120,16 @@ pub struct Contact {
     pub user_name: ::prost::alloc::string::String,
     #[prost(message, optional, tag = "2")]
     pub account_id: ::core::option::Option<AccountId>,
+    /// deprecated - not set. Contacts are identified by their number hash
     #[prost(message, optional, tag = "3")]
     pub mobile_number: ::core::option::Option<MobileNumber>,
     #[prost(message, repeated, tag = "4")]
     pub community_memberships: ::prost::alloc::vec::Vec<CommunityMembership>,
     #[prost(message, repeated, tag = "5")]
     pub trait_scores: ::prost::alloc::vec::Vec<TraitScore>,
+    /// sha256 hash of the contact's mobile number
+    #[prost(bytes = "vec", tag = "6")]
+    pub mobile_number_hash: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -152,6 +167,28 @@ pub struct PhoneVerifier {
     #[prost(string, tag = "2")]
     pub name: ::prost::alloc::string::String,
 }
+/// A phone verifier key in the on-chain verifiers registry
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegisteredVerifier {
+    /// verifier account id
+    #[prost(message, optional, tag = "1")]
+    pub account_id: ::core::option::Option<AccountId>,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// height of the first block which accepts its verification evidence
+    #[prost(uint64, tag = "3")]
+    pub valid_from: u64,
+    /// height of the last block which accepts its evidence. 0 if unlimited
+    #[prost(uint64, tag = "4")]
+    pub valid_until: u64,
+    /// height of the first block which rejects its evidence. 0 if not revoked
+    #[prost(uint64, tag = "5")]
+    pub revoked_at: u64,
+    /// the verifier's new key when this key was rotated
+    #[prost(message, optional, tag = "6")]
+    pub rotated_to: ::core::option::Option<AccountId>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Block {
@@ -161,7 +198,7 @@ pub struct Block {
     pub author: ::core::option::Option<AccountId>,
     #[prost(uint64, tag = "3")]
     pub height: u64,
-    /// of the signed transactions in this block
+    /// of the signed transactions in this block, in execution order
     #[prost(bytes = "vec", repeated, tag = "4")]
     pub transactions_hashes: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
     /// total fees paid in this block
@@ -177,9 +214,61 @@ pub struct Block {
     /// total coins minted in this block (rewards + tx fee subsidies)
     #[prost(uint64, tag = "9")]
     pub minted: u64,
-    /// block digest includes hash of all above data
+    /// block digest includes hash of all above data and below roots
     #[prost(bytes = "vec", tag = "10")]
     pub digest: ::prost::alloc::vec::Vec<u8>,
+    /// merkle root of transactions_hashes
+    #[prost(bytes = "vec", tag = "11")]
+    pub transactions_root: ::prost::alloc::vec::Vec<u8>,
+    /// sparse merkle root of all users accounts after this block was applied
+    #[prost(bytes = "vec", tag = "12")]
+    pub state_root: ::prost::alloc::vec::Vec<u8>,
+    /// genesis hash of the chain this block belongs to
+    #[prost(bytes = "vec", tag = "13")]
+    pub genesis_hash: ::prost::alloc::vec::Vec<u8>,
+    /// karma rewards round applied in this block, if any
+    #[prost(message, optional, tag = "14")]
+    pub karma_rewards: ::core::option::Option<KarmaRewards>,
+}
+/// A karma rewards round. Winners are randomly selected from the leader board by the block
+/// producer and are recorded in the block so replaying the block pays the same winners
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KarmaRewards {
+    /// paid to each winner. 0 when the rewards allocation is depleted
+    #[prost(uint64, tag = "1")]
+    pub reward_amount: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub winners: ::prost::alloc::vec::Vec<AccountId>,
+}
+/// A step in a merkle inclusion proof - a sibling hash on the path from a leaf to the root
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MerkleProofStep {
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: ::prost::alloc::vec::Vec<u8>,
+    /// true when the sibling is to the left of the path node
+    #[prost(bool, tag = "2")]
+    pub is_left: bool,
+}
+/// Merkle inclusion proof of a leaf in a merkle tree
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MerkleProof {
+    #[prost(uint64, tag = "1")]
+    pub leaf_index: u64,
+    #[prost(uint64, tag = "2")]
+    pub leaves_count: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub steps: ::prost::alloc::vec::Vec<MerkleProofStep>,
+}
+/// Inclusion proof of a leaf in a sparse merkle tree
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SparseMerkleProof {
+    /// siblings hashes on the path from the root down to the leaf
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub siblings: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -239,8 +328,25 @@ pub struct NewUserTransactionV1 {
     /// Evidence from a valid verifier about the new user
     #[prost(message, optional, tag = "1")]
     pub verify_number_response: ::core::option::Option<UserVerificationData>,
+    /// Evidence about the new user from other verifiers, required when the genesis
+    /// verifiers threshold is above 1
+    #[prost(message, repeated, tag = "2")]
+    pub additional_verify_number_responses: ::prost::alloc::vec::Vec<
+        UserVerificationData,
+    >,
 }
-/// new user transactions submitted by users
+/// UserVerificationDataEx evidence signed by a verifier
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SignedUserVerificationDataEx {
+    /// Serialized UserVerificationDataEx
+    #[prost(bytes = "vec", tag = "1")]
+    pub user_verification_data_ex: ::prost::alloc::vec::Vec<u8>,
+    /// Verifier signature of user_verification_data_ex. Public key is the data verifier account id
+    #[prost(message, optional, tag = "2")]
+    pub verifier_signature: ::core::option::Option<Signature>,
+}
+/// new user transactions submitted by users who only reveal their mobile number hash on chain
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NewUserTransactionV2 {
@@ -249,6 +355,10 @@ pub struct NewUserTransactionV2 {
     pub user_verification_data_ex: ::prost::alloc::vec::Vec<u8>,
     #[prost(message, optional, tag = "2")]
     pub verifier_signature: ::core::option::Option<Signature>,
+    /// Evidence about the new user from other verifiers, required when the genesis
+    /// verifiers threshold is above 1
+    #[prost(message, repeated, tag = "3")]
+    pub additional_evidence: ::prost::alloc::vec::Vec<SignedUserVerificationDataEx>,
 }
 /// Basic payment transaction with optional character appreciation
 /// Receiver must be identified by phone number or a karma coin account id
@@ -264,7 +374,7 @@ pub struct PaymentTransactionV1 {
     /// IMPORTANT: one of the next fields must be provided as the payee for the tx to be valid
     /// todo: use oneof proto feature here
     ///
-    /// pay to a mobile number
+    /// pay to a mobile number. deprecated - txs with raw numbers are rejected, use to_number_hash
     #[prost(message, optional, tag = "3")]
     pub to_number: ::core::option::Option<MobileNumber>,
     /// pay to a Karma Coin account id
@@ -275,6 +385,9 @@ pub struct PaymentTransactionV1 {
     pub char_trait_id: u32,
     #[prost(uint32, tag = "6")]
     pub community_id: u32,
+    /// pay to the sha256 hash of a mobile number
+    #[prost(bytes = "vec", tag = "7")]
+    pub to_number_hash: ::prost::alloc::vec::Vec<u8>,
 }
 /// Update user info
 /// User can update his nickname, mobile phone number or accountId in case
@@ -289,16 +402,119 @@ pub struct UpdateUserTransactionV1 {
     /// new requested nickname
     #[prost(string, tag = "1")]
     pub nickname: ::prost::alloc::string::String,
-    /// Updated mobile number or existing one
+    /// Updated mobile number or existing one. deprecated - txs with raw numbers are rejected
     #[prost(message, optional, tag = "2")]
     pub mobile_number: ::core::option::Option<MobileNumber>,
-    /// verifier attestation regarding the number and the account
+    /// verifier attestation regarding the number and the account. deprecated - txs with raw numbers are rejected
     #[prost(message, optional, tag = "3")]
     pub user_verification_data: ::core::option::Option<UserVerificationData>,
+    /// attestations by other verifiers. deprecated - txs with raw numbers are rejected
+    #[prost(message, repeated, tag = "4")]
+    pub additional_user_verification_data: ::prost::alloc::vec::Vec<
+        UserVerificationData,
+    >,
+    /// attestations by a quorum of verifiers regarding the updated mobile number hash and the account
+    #[prost(message, repeated, tag = "5")]
+    pub user_verification_data_ex: ::prost::alloc::vec::Vec<SignedUserVerificationDataEx>,
 }
+/// Deletes the signer's account. Its balance is redirected or burned per the genesis config and
+/// its user name may be registered again after the genesis user name cooldown
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteUserTransactionV1 {}
+/// A change of a single genesis config param
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParamChange {
+    /// genesis config key. e.g. block_rewards_amount
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// json encoded new value. e.g. 1000 or {"Verifier 1": "ec3d..."}
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
+/// A proposal to change genesis config params from a future block height
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParamsUpdateProposal {
+    /// height of the first block executed with the new params
+    #[prost(uint64, tag = "1")]
+    pub target_height: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub changes: ::prost::alloc::vec::Vec<ParamChange>,
+    #[prost(string, tag = "3")]
+    pub description: ::prost::alloc::string::String,
+}
+/// A governance admin approval of a params update proposal
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AdminApproval {
+    /// admin account id
+    #[prost(message, optional, tag = "1")]
+    pub account_id: ::core::option::Option<AccountId>,
+    /// admin signature on the binary proposal
+    #[prost(message, optional, tag = "2")]
+    pub signature: ::core::option::Option<Signature>,
+}
+/// Params update transactions may be submitted by any user but must be approved by at least
+/// the genesis governance threshold of governance admins
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParamsUpdateTransactionV1 {
+    #[prost(message, optional, tag = "1")]
+    pub proposal: ::core::option::Option<ParamsUpdateProposal>,
+    #[prost(message, repeated, tag = "2")]
+    pub approvals: ::prost::alloc::vec::Vec<AdminApproval>,
+}
+/// A proposal to change the on-chain verifiers registry
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifierUpdateProposal {
+    #[prost(enumeration = "VerifierAction", tag = "1")]
+    pub action: i32,
+    /// the added verifier key or the rotated or revoked key
+    #[prost(message, optional, tag = "2")]
+    pub account_id: ::core::option::Option<AccountId>,
+    /// added verifier name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// the new key of a rotated verifier
+    #[prost(message, optional, tag = "4")]
+    pub new_account_id: ::core::option::Option<AccountId>,
+    /// add: first valid height. rotate: first valid height of the new key. revoke: revocation height.
+    /// Must be after the tx block height. 0 for the block after the tx block
+    #[prost(uint64, tag = "5")]
+    pub height: u64,
+    /// last valid height of an added verifier or a new key. 0 if unlimited
+    #[prost(uint64, tag = "6")]
+    pub valid_until: u64,
+}
+/// Verifier update transactions may be submitted by any user but must be approved by at least
+/// the genesis governance threshold of governance admins
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifierUpdateTransactionV1 {
+    #[prost(message, optional, tag = "1")]
+    pub proposal: ::core::option::Option<VerifierUpdateProposal>,
+    #[prost(message, repeated, tag = "2")]
+    pub approvals: ::prost::alloc::vec::Vec<AdminApproval>,
+}
+/// An executed params update
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParamsUpdate {
+    /// params update transaction hash
+    #[prost(bytes = "vec", tag = "1")]
+    pub tx_hash: ::prost::alloc::vec::Vec<u8>,
+    /// height of the block the transaction was executed in
+    #[prost(uint64, tag = "2")]
+    pub height: u64,
+    #[prost(message, optional, tag = "3")]
+    pub proposal: ::core::option::Option<ParamsUpdateProposal>,
+    /// governance admins which approved the update
+    #[prost(message, repeated, tag = "4")]
+    pub approvers: ::prost::alloc::vec::Vec<AccountId>,
+}
 /// The generic transaction payload - unsigned
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -378,6 +594,7 @@ pub struct UserVerificationDataEx {
     pub verification_result: i32,
     #[prost(message, optional, tag = "4")]
     pub account_id: ::core::option::Option<AccountId>,
+    /// hex encoded sha256 hash of the verified mobile number
     #[prost(string, tag = "5")]
     pub mobile_number_hash: ::prost::alloc::string::String,
     #[prost(string, tag = "6")]
@@ -413,6 +630,52 @@ pub struct SignedTransactionWithStatus {
     /// receiver
     #[prost(message, optional, tag = "4")]
     pub to: ::core::option::Option<User>,
+    /// why the transaction was rejected when status is rejected
+    #[prost(string, tag = "5")]
+    pub rejection_reason: ::prost::alloc::string::String,
+}
+/// A submitted transaction which was rejected or dropped from the mem pool and will not be executed
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectedTransaction {
+    #[prost(message, optional, tag = "1")]
+    pub transaction: ::core::option::Option<SignedTransaction>,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+    /// rejection time
+    #[prost(uint64, tag = "3")]
+    pub timestamp: u64,
+}
+/// An on-chain payment to a mobile number without an account. The amount is reserved from the
+/// payer's balance until the payee signs up and claims it or until it expires and is refunded
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PendingPayment {
+    /// the escrowed payment transaction
+    #[prost(bytes = "vec", tag = "1")]
+    pub transaction_hash: ::prost::alloc::vec::Vec<u8>,
+    /// payer
+    #[prost(message, optional, tag = "2")]
+    pub from: ::core::option::Option<AccountId>,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(uint32, tag = "4")]
+    pub char_trait_id: u32,
+    #[prost(uint32, tag = "5")]
+    pub community_id: u32,
+    /// height of the block the payment was escrowed in
+    #[prost(uint64, tag = "6")]
+    pub height: u64,
+    /// time in millis after which the payment is refunded to the payer
+    #[prost(uint64, tag = "7")]
+    pub expires_at: u64,
+}
+/// All pending payments to a mobile number
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PendingPayments {
+    #[prost(message, repeated, tag = "1")]
+    pub payments: ::prost::alloc::vec::Vec<PendingPayment>,
 }
 /// Transaction added to ledger
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -446,6 +709,14 @@ pub struct TransactionEvent {
     pub appreciation_community_id: u32,
     #[prost(uint64, tag = "13")]
     pub fee: u64,
+    /// funds burned by the tx. e.g. a deleted account's balance
+    #[prost(uint64, tag = "14")]
+    pub burned_amount: u64,
+    /// a deleted account's balance paid to redirected_to
+    #[prost(uint64, tag = "15")]
+    pub redirected_amount: u64,
+    #[prost(message, optional, tag = "16")]
+    pub redirected_to: ::core::option::Option<AccountId>,
 }
 /// A collection of events for a transaction
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -484,7 +755,8 @@ pub struct BlockchainStats {
     /// total number of kCents minted by the protocol since genesis
     #[prost(uint64, tag = "8")]
     pub minted_amount: u64,
-    /// total number of kCents in circulation by minting. Not including pre-mint
+    /// total number of kCents held by users - minted and karma rewards funds minus burned funds.
+    /// Not including pre-mint
     #[prost(uint64, tag = "9")]
     pub circulation: u64,
     /// total tx fee subsidies issued by the protocol
@@ -519,6 +791,9 @@ pub struct BlockchainStats {
     /// karma rewards amount allocated
     #[prost(uint64, tag = "22")]
     pub karma_rewards_amount: u64,
+    /// total number of kCents burned. e.g. balances of deleted accounts
+    #[prost(uint64, tag = "23")]
+    pub burned_amount: u64,
 }
 /// Block events
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -550,6 +825,14 @@ pub struct BlockEvent {
     pub referral_rewards_count: u64,
     #[prost(uint64, tag = "13")]
     pub reward: u64,
+    /// funds removed from users balances in this block
+    #[prost(uint64, tag = "14")]
+    pub burned_amount: u64,
+    /// karma rewards paid in this block
+    #[prost(uint64, tag = "15")]
+    pub karma_rewards_amount: u64,
+    #[prost(uint64, tag = "16")]
+    pub karma_rewards_count: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -608,6 +891,26 @@ pub struct GenesisData {
     pub char_traits: ::prost::alloc::vec::Vec<CharTrait>,
     #[prost(message, repeated, tag = "27")]
     pub verifiers: ::prost::alloc::vec::Vec<PhoneVerifier>,
+    #[prost(uint64, tag = "28")]
+    pub tx_fee_subsidy_max_amount_phase1: u64,
+    /// hash of all other genesis data
+    #[prost(bytes = "vec", tag = "29")]
+    pub genesis_hash: ::prost::alloc::vec::Vec<u8>,
+    /// accounts which may approve params updates
+    #[prost(message, repeated, tag = "30")]
+    pub governance_admins: ::prost::alloc::vec::Vec<AccountId>,
+    /// min number of admins approvals of a params update
+    #[prost(uint64, tag = "31")]
+    pub governance_threshold: u64,
+    /// min number of distinct verifiers evidence of a mobile number
+    #[prost(uint64, tag = "32")]
+    pub verifiers_threshold: u64,
+    /// recipient of deleted accounts balances. Balances are burned when not set
+    #[prost(message, optional, tag = "33")]
+    pub deleted_user_funds_account_id: ::core::option::Option<AccountId>,
+    /// seconds from a deleted account's deletion tx time until its user name is released
+    #[prost(uint64, tag = "34")]
+    pub user_name_cooldown_seconds: u64,
 }
 /// Supported signature schemes
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -641,7 +944,9 @@ pub enum TransactionType {
     NewUserV1 = 1,
     UpdateUserV1 = 2,
     DeleteUserV1 = 3,
-    DeleteUserV = 4,
+    ParamsUpdateV1 = 5,
+    VerifierUpdateV1 = 6,
+    NewUserV2 = 7,
 }
 impl TransactionType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -654,7 +959,9 @@ impl TransactionType {
             TransactionType::NewUserV1 => "TRANSACTION_TYPE_NEW_USER_V1",
             TransactionType::UpdateUserV1 => "TRANSACTION_TYPE_UPDATE_USER_V1",
             TransactionType::DeleteUserV1 => "TRANSACTION_TYPE_DELETE_USER_V1",
-            TransactionType::DeleteUserV => "TRANSACTION_TYPE_DELETE_USER_V",
+            TransactionType::ParamsUpdateV1 => "TRANSACTION_TYPE_PARAMS_UPDATE_V1",
+            TransactionType::VerifierUpdateV1 => "TRANSACTION_TYPE_VERIFIER_UPDATE_V1",
+            TransactionType::NewUserV2 => "TRANSACTION_TYPE_NEW_USER_V2",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -664,7 +971,41 @@ impl TransactionType {
             "TRANSACTION_TYPE_NEW_USER_V1" => Some(Self::NewUserV1),
             "TRANSACTION_TYPE_UPDATE_USER_V1" => Some(Self::UpdateUserV1),
             "TRANSACTION_TYPE_DELETE_USER_V1" => Some(Self::DeleteUserV1),
-            "TRANSACTION_TYPE_DELETE_USER_V" => Some(Self::DeleteUserV),
+            "TRANSACTION_TYPE_PARAMS_UPDATE_V1" => Some(Self::ParamsUpdateV1),
+            "TRANSACTION_TYPE_VERIFIER_UPDATE_V1" => Some(Self::VerifierUpdateV1),
+            "TRANSACTION_TYPE_NEW_USER_V2" => Some(Self::NewUserV2),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum VerifierAction {
+    /// register a new verifier
+    Add = 0,
+    /// replace a verifier key with a new key
+    Rotate = 1,
+    /// stop accepting a verifier key evidence
+    Revoke = 2,
+}
+impl VerifierAction {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            VerifierAction::Add => "VERIFIER_ACTION_ADD",
+            VerifierAction::Rotate => "VERIFIER_ACTION_ROTATE",
+            VerifierAction::Revoke => "VERIFIER_ACTION_REVOKE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "VERIFIER_ACTION_ADD" => Some(Self::Add),
+            "VERIFIER_ACTION_ROTATE" => Some(Self::Rotate),
+            "VERIFIER_ACTION_REVOKE" => Some(Self::Revoke),
             _ => None,
         }
     }
@@ -822,6 +1163,16 @@ pub enum ExecutionInfo {
     AccountAlreadyExists = 8,
     TxFeeTooLow = 9,
     InternalNodeError = 10,
+    /// payment to a number without an account was escrowed
+    PaymentEscrowed = 11,
+    /// escrowed payment was claimed by the payee on signup
+    PaymentClaimed = 12,
+    /// expired escrowed payment was refunded to the payer
+    PaymentRefunded = 13,
+    /// account was deleted and its balance burned or redirected
+    AccountDeleted = 14,
+    /// escrowed payment was cancelled as its payer account was deleted
+    PaymentCancelled = 15,
 }
 impl ExecutionInfo {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -845,6 +1196,11 @@ impl ExecutionInfo {
             }
             ExecutionInfo::TxFeeTooLow => "EXECUTION_INFO_TX_FEE_TOO_LOW",
             ExecutionInfo::InternalNodeError => "EXECUTION_INFO_INTERNAL_NODE_ERROR",
+            ExecutionInfo::PaymentEscrowed => "EXECUTION_INFO_PAYMENT_ESCROWED",
+            ExecutionInfo::PaymentClaimed => "EXECUTION_INFO_PAYMENT_CLAIMED",
+            ExecutionInfo::PaymentRefunded => "EXECUTION_INFO_PAYMENT_REFUNDED",
+            ExecutionInfo::AccountDeleted => "EXECUTION_INFO_ACCOUNT_DELETED",
+            ExecutionInfo::PaymentCancelled => "EXECUTION_INFO_PAYMENT_CANCELLED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -861,6 +1217,11 @@ impl ExecutionInfo {
             "EXECUTION_INFO_ACCOUNT_ALREADY_EXISTS" => Some(Self::AccountAlreadyExists),
             "EXECUTION_INFO_TX_FEE_TOO_LOW" => Some(Self::TxFeeTooLow),
             "EXECUTION_INFO_INTERNAL_NODE_ERROR" => Some(Self::InternalNodeError),
+            "EXECUTION_INFO_PAYMENT_ESCROWED" => Some(Self::PaymentEscrowed),
+            "EXECUTION_INFO_PAYMENT_CLAIMED" => Some(Self::PaymentClaimed),
+            "EXECUTION_INFO_PAYMENT_REFUNDED" => Some(Self::PaymentRefunded),
+            "EXECUTION_INFO_ACCOUNT_DELETED" => Some(Self::AccountDeleted),
+            "EXECUTION_INFO_PAYMENT_CANCELLED" => Some(Self::PaymentCancelled),
             _ => None,
         }
     }
@@ -58,6 +58,14 @@ pub struct VerifyNumberResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyNumberResponseEx {
+    #[prost(message, optional, tag = "1")]
+    pub evidence: ::core::option::Option<
+        super::core_types::SignedUserVerificationDataEx,
+    >,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VerifyNumberRequestDataEx {
     #[prost(uint64, tag = "1")]
     pub timestamp: u64,
@@ -243,6 +251,27 @@ pub mod verifier_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Extended api - verifies number via Twilio whatsapp given user code
+        /// Returns evidence with the number hash instead of the number for NewUserTransactionV2
+        pub async fn verify_number_ex_v2(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VerifyNumberRequestEx>,
+        ) -> Result<tonic::Response<super::VerifyNumberResponseEx>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.verifier.VerifierService/VerifyNumberExV2",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -268,6 +297,12 @@ pub mod verifier_service_server {
             &self,
             request: tonic::Request<super::SendVerificationCodeRequest>,
         ) -> Result<tonic::Response<super::SendVerificationCodeResponse>, tonic::Status>;
+        /// Extended api - verifies number via Twilio whatsapp given user code
+        /// Returns evidence with the number hash instead of the number for NewUserTransactionV2
+        async fn verify_number_ex_v2(
+            &self,
+            request: tonic::Request<super::VerifyNumberRequestEx>,
+        ) -> Result<tonic::Response<super::VerifyNumberResponseEx>, tonic::Status>;
     }
     /// mobile phone numbers verifier api service
     #[derive(Debug)]
@@ -449,6 +484,46 @@ pub mod verifier_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.verifier.VerifierService/VerifyNumberExV2" => {
+                    #[allow(non_camel_case_types)]
+                    struct VerifyNumberExV2Svc<T: VerifierService>(pub Arc<T>);
+                    impl<
+                        T: VerifierService,
+                    > tonic::server::UnaryService<super::VerifyNumberRequestEx>
+                    for VerifyNumberExV2Svc<T> {
+                        type Response = super::VerifyNumberResponseEx;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VerifyNumberRequestEx>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).verify_number_ex_v2(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = VerifyNumberExV2Svc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -5,11 +5,13 @@
 extern crate core;
 extern crate serde;
 
+pub mod admin_approval;
 pub mod block;
 pub mod block_event;
 pub mod blockchain_stats;
 pub mod char_trait;
 pub mod client_config_service;
+pub mod genesis_config;
 pub mod genesis_config_service;
 pub mod genesis_data;
 pub mod hasher;
@@ -18,18 +20,25 @@ pub mod karma_coin;
 pub mod karma_coin_format;
 pub mod key_pair;
 pub mod logging_service;
+pub mod merkle;
+pub mod mobile_number;
 pub mod new_user_tx;
+pub mod params_update;
 pub mod payment_tx;
 pub mod server_config_service;
 pub mod signed_trait;
 pub mod signed_tx;
 pub mod sms_invite_data;
+pub mod sparse_merkle;
+pub mod supply;
 pub mod tests_helpers;
 pub mod transaction_event;
 pub mod tx_body;
 pub mod update_user_tx;
 pub mod user;
 pub mod user_verification_data;
+pub mod user_verification_data_ex;
+pub mod verifier;
 pub mod verify_number_request;
 
 pub const GRPC_DESCRIPTOR: &[u8] = include_bytes!("karma_coin/descriptor.bin");
@@ -0,0 +1,149 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::hasher::Hasher;
+use crate::karma_coin::karma_coin_core_types::{MerkleProof, MerkleProofStep};
+use anyhow::{anyhow, Result};
+
+/// Domain separation prefixes so a leaf can't be presented as an internal node
+pub(crate) const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Binary Sha256 merkle tree used for blocks transactions roots.
+/// Leaves are hashed as H(0 || leaf) and nodes as H(1 || left || right).
+/// A node without a sibling is promoted as is to the next level.
+/// The root of an empty tree is H() - the hash of no data.
+pub struct MerkleTree {
+    /// tree levels from the leaves hashes level up to the root level
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    /// Build a merkle tree from leaves data in the provided order
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Result<Self> {
+        let mut level = Vec::with_capacity(leaves.len());
+        for leaf in leaves.iter() {
+            level.push(hash_leaf(leaf.as_ref())?);
+        }
+
+        let mut levels = vec![level];
+        while levels.last().unwrap().len() > 1 {
+            let mut next = vec![];
+            for pair in levels.last().unwrap().chunks(2) {
+                match pair {
+                    [left, right] => next.push(hash_node(left, right)?),
+                    [single] => next.push(single.clone()),
+                    _ => unreachable!(),
+                }
+            }
+            levels.push(next);
+        }
+
+        Ok(MerkleTree { levels })
+    }
+
+    /// Returns the tree's root
+    pub fn root(&self) -> Result<Vec<u8>> {
+        match self.levels.last().unwrap().first() {
+            Some(root) => Ok(root.clone()),
+            None => Hasher::hash(&[]),
+        }
+    }
+
+    /// Returns an inclusion proof of the leaf at the provided index
+    pub fn proof(&self, leaf_index: usize) -> Result<MerkleProof> {
+        let leaves_count = self.levels[0].len();
+        if leaf_index >= leaves_count {
+            return Err(anyhow!(
+                "leaf index {} out of range of {} leaves",
+                leaf_index,
+                leaves_count
+            ));
+        }
+
+        let mut steps = vec![];
+        let mut index = leaf_index;
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            if index % 2 == 1 {
+                steps.push(MerkleProofStep {
+                    hash: level[index - 1].clone(),
+                    is_left: true,
+                });
+            } else if index + 1 < level.len() {
+                steps.push(MerkleProofStep {
+                    hash: level[index + 1].clone(),
+                    is_left: false,
+                });
+            }
+            index /= 2;
+        }
+
+        Ok(MerkleProof {
+            leaf_index: leaf_index as u64,
+            leaves_count: leaves_count as u64,
+            steps,
+        })
+    }
+}
+
+/// Returns the merkle root of the provided leaves
+pub fn merkle_root<T: AsRef<[u8]>>(leaves: &[T]) -> Result<Vec<u8>> {
+    MerkleTree::new(leaves)?.root()
+}
+
+/// Verify that leaf is included at the proof's leaf index in a tree with the provided root.
+/// Only uses the leaf, the proof and the root so it can be used offline by clients.
+pub fn verify_merkle_proof(root: &[u8], leaf: &[u8], proof: &MerkleProof) -> bool {
+    // the path implied by the leaf index and leaves count must match the proof steps
+    let mut index = proof.leaf_index;
+    let mut level_len = proof.leaves_count;
+    if index >= level_len {
+        return false;
+    }
+
+    let Ok(mut hash) = hash_leaf(leaf) else {
+        return false;
+    };
+
+    let mut steps = proof.steps.iter();
+    while level_len > 1 {
+        let has_sibling = index % 2 == 1 || index + 1 < level_len;
+        if has_sibling {
+            let Some(step) = steps.next() else {
+                return false;
+            };
+            if step.is_left != (index % 2 == 1) {
+                return false;
+            }
+            let res = if step.is_left {
+                hash_node(&step.hash, &hash)
+            } else {
+                hash_node(&hash, &step.hash)
+            };
+            let Ok(node) = res else {
+                return false;
+            };
+            hash = node;
+        }
+        index /= 2;
+        level_len = level_len.div_ceil(2);
+    }
+
+    steps.next().is_none() && hash == root
+}
+
+fn hash_leaf(leaf: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(leaf.len() + 1);
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(leaf);
+    Hasher::hash(&data)
+}
+
+pub(crate) fn hash_node(left: &[u8], right: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(left.len() + right.len() + 1);
+    data.push(NODE_PREFIX);
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    Hasher::hash(&data)
+}
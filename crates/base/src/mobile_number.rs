@@ -0,0 +1,32 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::hasher::Hasher;
+use crate::karma_coin::karma_coin_core_types::MobileNumber;
+use anyhow::{anyhow, Result};
+
+/// Length in bytes of a mobile number hash
+pub const MOBILE_NUMBER_HASH_LEN: usize = 32;
+
+/// Returns the sha256 hash of a mobile number which identifies it on chain
+pub fn mobile_number_hash(number: &str) -> Result<Vec<u8>> {
+    Hasher::hash(number.as_bytes())
+}
+
+/// Decode a hex encoded mobile number hash, as provided in verifiers evidence
+pub fn decode_mobile_number_hash(hash: &str) -> Result<Vec<u8>> {
+    let hash = hex::decode(hash.trim_start_matches("0x"))
+        .map_err(|_| anyhow!("invalid mobile number hash encoding"))?;
+    if hash.len() != MOBILE_NUMBER_HASH_LEN {
+        return Err(anyhow!("invalid mobile number hash length"));
+    }
+    Ok(hash)
+}
+
+impl MobileNumber {
+    /// Returns the hash of this mobile number
+    pub fn get_hash(&self) -> Result<Vec<u8>> {
+        mobile_number_hash(&self.number)
+    }
+}
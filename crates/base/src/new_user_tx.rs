@@ -2,12 +2,37 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use crate::karma_coin::karma_coin_core_types::NewUserTransactionV1;
-use anyhow::Result;
+use crate::karma_coin::karma_coin_core_types::{
+    NewUserTransactionV1, NewUserTransactionV2, SignedUserVerificationDataEx,
+};
+use anyhow::{anyhow, Result};
 
 impl NewUserTransactionV1 {
-    /// todo: verify all fields
+    /// V1 evidence attests to the mobile number, which must not be stored on chain, so v1 txs
+    /// are never valid. Use NewUserTransactionV2
     pub fn verify_syntax(&self) -> Result<()> {
+        Err(anyhow!(
+            "new user v1 txs are not accepted - use new user v2 txs"
+        ))
+    }
+}
+
+impl NewUserTransactionV2 {
+    /// Verify all fields
+    pub fn verify_syntax(&self) -> Result<()> {
+        if self.user_verification_data_ex.is_empty() || self.verifier_signature.is_none() {
+            return Err(anyhow!("verification evidence is required"));
+        }
         Ok(())
     }
+
+    /// Returns all signed verification evidence provided in the transaction
+    pub fn get_evidence(&self) -> Vec<SignedUserVerificationDataEx> {
+        let mut evidence = vec![SignedUserVerificationDataEx {
+            user_verification_data_ex: self.user_verification_data_ex.clone(),
+            verifier_signature: self.verifier_signature.clone(),
+        }];
+        evidence.extend(self.additional_evidence.iter().cloned());
+        evidence
+    }
 }
@@ -0,0 +1,91 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::admin_approval::{get_approval_message, get_approvers, PARAMS_UPDATE_APPROVAL_DOMAIN};
+use crate::hex_utils::short_hex_string;
+use crate::karma_coin::karma_coin_core_types::{
+    AccountId, AdminApproval, ParamsUpdate, ParamsUpdateProposal, ParamsUpdateTransactionV1,
+};
+use anyhow::{anyhow, Result};
+use ed25519_dalek::Keypair;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+impl ParamsUpdateProposal {
+    /// Verify all fields
+    pub fn verify_syntax(&self) -> Result<()> {
+        if self.target_height == 0 {
+            return Err(anyhow!("target height must be positive"));
+        }
+
+        if self.changes.is_empty() {
+            return Err(anyhow!("at least one param change is required"));
+        }
+
+        if self.changes.iter().any(|c| c.key.is_empty()) {
+            return Err(anyhow!("param change without a key"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an admin approval of the proposal on the chain with the provided genesis hash
+    pub fn approve(&self, genesis_hash: &[u8], key_pair: &Keypair) -> AdminApproval {
+        AdminApproval::new(
+            &get_approval_message(PARAMS_UPDATE_APPROVAL_DOMAIN, genesis_hash, self),
+            key_pair,
+        )
+    }
+}
+
+impl ParamsUpdateTransactionV1 {
+    /// Verify all approvals were signed by distinct governance admins and return the approvers
+    pub fn get_approvers(
+        &self,
+        admins: &[AccountId],
+        genesis_hash: &[u8],
+    ) -> Result<Vec<AccountId>> {
+        let proposal = self
+            .proposal
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing proposal"))?;
+        get_approvers(
+            &self.approvals,
+            &get_approval_message(PARAMS_UPDATE_APPROVAL_DOMAIN, genesis_hash, proposal),
+            admins,
+        )
+    }
+}
+
+impl ParamsUpdate {
+    /// Returns the height of the first block executed with the updated params
+    pub fn target_height(&self) -> u64 {
+        self.proposal.as_ref().map_or(0, |p| p.target_height)
+    }
+
+    /// Params updates are applied in target height order, and in execution order when they
+    /// target the same height
+    pub fn sort_key(&self) -> (u64, u64, Vec<u8>) {
+        (self.target_height(), self.height, self.tx_hash.clone())
+    }
+}
+
+impl Display for ParamsUpdate {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let changes = self.proposal.as_ref().map_or(vec![], |p| {
+            p.changes
+                .iter()
+                .map(|c| format!("{}: {}", c.key, c.value))
+                .collect()
+        });
+        write!(
+            f,
+            "ParamsUpdate {{ tx hash: {}, height: {}, target height: {}, changes: [{}] }}",
+            short_hex_string(&self.tx_hash),
+            self.height,
+            self.target_height(),
+            changes.join(", ")
+        )
+    }
+}
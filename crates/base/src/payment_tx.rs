@@ -4,6 +4,7 @@
 
 use crate::hex_utils::short_hex_string;
 use crate::karma_coin::karma_coin_core_types::PaymentTransactionV1;
+use crate::mobile_number::MOBILE_NUMBER_HASH_LEN;
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::fmt::{Display, Formatter};
@@ -15,43 +16,62 @@ impl PaymentTransactionV1 {
             return Err(anyhow!("sender's account id is required"));
         }
 
-        if self.to_number.is_none() && self.to_account_id.is_none() {
+        // txs are stored on chain so payees are identified by their mobile number hash
+        if self.to_number.is_some() {
             return Err(anyhow!(
-                "payee mobile number OR payee account id is required"
+                "payee mobile number is not allowed - use the mobile number hash"
             ));
         }
 
+        if self.to_number_hash.is_empty() && self.to_account_id.is_none() {
+            return Err(anyhow!(
+                "payee mobile number hash OR payee account id is required"
+            ));
+        }
+
+        if !self.to_number_hash.is_empty() && self.to_number_hash.len() != MOBILE_NUMBER_HASH_LEN {
+            return Err(anyhow!("invalid payee mobile number hash length"));
+        }
+
         if self.amount == 0 {
             return Err(anyhow!("payment amount must be greater than 0"));
         }
 
         Ok(())
     }
+
+    /// Returns the mobile number hash of the payee, if the payment is to a mobile number
+    pub fn get_payee_number_hash(&self) -> Result<Option<Vec<u8>>> {
+        if self.to_number_hash.is_empty() {
+            return Ok(None);
+        }
+
+        if self.to_number_hash.len() != MOBILE_NUMBER_HASH_LEN {
+            return Err(anyhow!("invalid payee mobile number hash length"));
+        }
+        Ok(Some(self.to_number_hash.clone()))
+    }
 }
 
 impl Display for PaymentTransactionV1 {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self.to_number {
-            Some(ref to_number) => {
-                write!(
-                    f,
-                    "PaymentTransactionV1 {{ from: {}, to: {}, amount: {}, char trait id: {} }}",
-                    short_hex_string(self.from.as_ref().unwrap().data.as_ref()),
-                    to_number.number,
-                    self.amount,
-                    self.char_trait_id
-                )
-            }
-            None => {
-                write!(
-                    f,
-                    "PaymentTransactionV1 {{ from: {}, to: {}, amount: {}, char trait id: {} }}",
-                    short_hex_string(self.from.as_ref().unwrap().data.as_ref()),
-                    short_hex_string(self.to_account_id.as_ref().unwrap().data.as_ref()),
-                    self.amount,
-                    self.char_trait_id
-                )
-            }
-        }
+        let to = if !self.to_number_hash.is_empty() {
+            short_hex_string(&self.to_number_hash)
+        } else if let Some(ref to_number) = self.to_number {
+            to_number.number.clone()
+        } else if let Some(ref to_account_id) = self.to_account_id {
+            short_hex_string(to_account_id.data.as_ref())
+        } else {
+            "none".into()
+        };
+
+        write!(
+            f,
+            "PaymentTransactionV1 {{ from: {}, to: {}, amount: {}, char trait id: {} }}",
+            short_hex_string(self.from.as_ref().unwrap().data.as_ref()),
+            to,
+            self.amount,
+            self.char_trait_id
+        )
     }
 }
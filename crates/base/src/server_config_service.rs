@@ -4,7 +4,7 @@
 
 use crate::hex_utils::hex_from_string;
 use crate::karma_coin::karma_coin_core_types::{KeyPair, PrivateKey, PublicKey};
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use config::builder::DefaultState;
 use config::{Config, ConfigBuilder, Environment};
 use log::*;
@@ -49,11 +49,33 @@ pub const START_API_SERVICE_CONFIG_KEY: &str = "start_api_service";
 
 pub const MEM_POOL_MAX_ITEMS_KEY: &str = "mem_pool_max_items_key";
 pub const MEM_POOL_MAX_TX_AGE_HOURS: &str = "mem_pool_max_tx_age_key";
+/// Min fee of transactions admitted to the mem pool
+pub const MEM_POOL_MIN_TX_FEE_KEY: &str = "mem_pool_min_tx_fee";
+/// Max number of pending transactions of a single signer in the mem pool
+pub const MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY: &str = "mem_pool_max_txs_per_account";
+/// Interval in seconds between removals of expired transactions from the mem pool
+pub const MEM_POOL_EXPIRY_INTERVAL_SECS_KEY: &str = "mem_pool_expiry_interval_secs";
+
+/// Server config file merged over the default config when no config file is provided
+pub const DEFAULT_CONFIG_FILE: &str = "./config.yaml";
+
+/// Yaml or json genesis config file merged over the default genesis config when it exists
+pub const GENESIS_CONFIG_FILE_CONFIG_KEY: &str = "genesis_config_file";
+
+/// When true, the ledger consistency is checked when the server starts.
+/// The check scans all blocks and users so it is off by default - use the server check command
+pub const CHECK_LEDGER_ON_STARTUP_CONFIG_KEY: &str = "check_ledger_on_startup";
+/// When true, derived indexes which are found inconsistent on startup are repaired
+pub const REPAIR_LEDGER_INDEXES_ON_STARTUP_CONFIG_KEY: &str = "repair_ledger_indexes_on_startup";
 
 // private identity key (ed25519)
 pub const BLOCK_PRODUCER_ID_PRIVATE_KEY: &str = "block_producer.private_key";
 pub const BLOCK_PRODUCER_ID_PUBLIC_KEY: &str = "block_producer.public_key";
 pub const BLOCK_PRODUCER_USER_NAME: &str = "block_producer.name";
+/// Interval in milliseconds between block production attempts
+pub const BLOCK_PRODUCTION_INTERVAL_MS_CONFIG_KEY: &str = "block_producer.block_interval_ms";
+/// Max number of transactions executed in a single block
+pub const MAX_TXS_PER_BLOCK_CONFIG_KEY: &str = "block_producer.max_txs_per_block";
 
 pub struct ServerConfigService {
     config: Config,
@@ -61,6 +83,15 @@ pub struct ServerConfigService {
 }
 
 impl ServerConfigService {
+    /// Override a config value on top of the current config values
+    fn set_override<T: Into<config::Value>>(&mut self, key: &str, value: T) -> Result<()> {
+        self.config = Config::builder()
+            .add_source(self.config.clone())
+            .set_override(key, value)?
+            .build()?;
+        Ok(())
+    }
+
     fn get_default_builder(&self) -> ConfigBuilder<DefaultState> {
         Config::builder()
             .set_default(DROP_DB_CONFIG_KEY, DEFAULT_DROP_DB_ON_EXIT)
@@ -88,6 +119,12 @@ impl ServerConfigService {
             // Keep txs in pool for 7 days only
             .set_default(MEM_POOL_MAX_TX_AGE_HOURS, 24 * 7)
             .unwrap()
+            .set_default(MEM_POOL_MIN_TX_FEE_KEY, 1)
+            .unwrap()
+            .set_default(MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY, 64)
+            .unwrap()
+            .set_default(MEM_POOL_EXPIRY_INTERVAL_SECS_KEY, 60)
+            .unwrap()
             // off by default to prevent charges - set to true to test sms messages
             .set_default(SEND_INVITE_SMS_MESSAGES_CONFIG_KEY, true)
             .unwrap()
@@ -108,6 +145,16 @@ impl ServerConfigService {
             .unwrap()
             .set_default(AUTH_SERVICE_PROTOCOL_KEY, "http")
             .unwrap()
+            .set_default(GENESIS_CONFIG_FILE_CONFIG_KEY, "./genesis.yaml")
+            .unwrap()
+            .set_default(CHECK_LEDGER_ON_STARTUP_CONFIG_KEY, false)
+            .unwrap()
+            .set_default(REPAIR_LEDGER_INDEXES_ON_STARTUP_CONFIG_KEY, false)
+            .unwrap()
+            .set_default(BLOCK_PRODUCTION_INTERVAL_MS_CONFIG_KEY, 1000)
+            .unwrap()
+            .set_default(MAX_TXS_PER_BLOCK_CONFIG_KEY, 1000)
+            .unwrap()
             .add_source(
                 Environment::with_prefix("KC")
                     .try_parsing(true)
@@ -360,11 +407,7 @@ pub struct SetValue {
 #[async_trait::async_trait]
 impl Handler<SetValue> for ServerConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetValue) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value)
     }
 }
 
@@ -377,11 +420,7 @@ pub struct SetU64 {
 #[async_trait::async_trait]
 impl Handler<SetU64> for ServerConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetU64) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value.to_string()) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value.to_string())
     }
 }
 
@@ -394,10 +433,6 @@ pub struct SetBool {
 #[async_trait::async_trait]
 impl Handler<SetBool> for ServerConfigService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: SetBool) -> Result<()> {
-        #[allow(deprecated)]
-        match self.config.set(msg.key.as_str(), msg.value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("{:?}", e)),
-        }
+        self.set_override(msg.key.as_str(), msg.value)
     }
 }
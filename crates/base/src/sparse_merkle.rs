@@ -0,0 +1,225 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::hasher::Hasher;
+use crate::karma_coin::karma_coin_core_types::SparseMerkleProof;
+use crate::merkle::{hash_node, LEAF_PREFIX};
+use anyhow::{anyhow, Result};
+
+/// Length in bytes of the tree keys and hashes
+pub const SPARSE_MERKLE_KEY_LEN: usize = 32;
+
+/// Max depth of a leaf in the tree - the number of bits of a key
+pub const SPARSE_MERKLE_MAX_DEPTH: usize = SPARSE_MERKLE_KEY_LEN * 8;
+
+/// Hash of an empty subtree and the root of an empty tree
+pub const EMPTY_SUBTREE_HASH: [u8; SPARSE_MERKLE_KEY_LEN] = [0; SPARSE_MERKLE_KEY_LEN];
+
+/// Node types tags in the node storage encoding
+const LEAF_NODE: u8 = 0;
+const INTERNAL_NODE: u8 = 1;
+
+/// A node of a sparse merkle tree used for the accounts state root.
+/// A leaf's path in the tree is given by the bits of its key, which is the hash of the account
+/// id, from the most significant bit. A subtree with a single leaf is represented by the leaf
+/// itself, so a leaf is stored at the depth of the shortest unique prefix of its key and the
+/// tree only has O(number of leaves) nodes.
+/// Leaves are hashed as H(0 || key || leaf) and internal nodes as H(1 || left || right) where
+/// an empty subtree hash is all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SparseMerkleNode {
+    Leaf { key: Vec<u8>, hash: Vec<u8> },
+    Internal { hash: Vec<u8> },
+}
+
+impl SparseMerkleNode {
+    /// Create a leaf node for a serialized leaf stored at key
+    pub fn leaf(key: &[u8], leaf: &[u8]) -> Result<Self> {
+        Ok(SparseMerkleNode::Leaf {
+            key: key.to_vec(),
+            hash: hash_sparse_leaf(key, leaf)?,
+        })
+    }
+
+    /// Create an internal node from its children. None for an empty child
+    pub fn internal(left: Option<&Self>, right: Option<&Self>) -> Result<Self> {
+        Ok(SparseMerkleNode::Internal {
+            hash: hash_node(
+                left.map_or(&EMPTY_SUBTREE_HASH[..], |n| n.hash()),
+                right.map_or(&EMPTY_SUBTREE_HASH[..], |n| n.hash()),
+            )?,
+        })
+    }
+
+    pub fn hash(&self) -> &[u8] {
+        match self {
+            SparseMerkleNode::Leaf { hash, .. } => hash,
+            SparseMerkleNode::Internal { hash } => hash,
+        }
+    }
+
+    /// Serialize the node for storage
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SparseMerkleNode::Leaf { key, hash } => [&[LEAF_NODE][..], key, hash].concat(),
+            SparseMerkleNode::Internal { hash } => [&[INTERNAL_NODE][..], hash].concat(),
+        }
+    }
+
+    /// Deserialize a stored node
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        match data.split_first() {
+            Some((&LEAF_NODE, rest)) if rest.len() == 2 * SPARSE_MERKLE_KEY_LEN => {
+                let (key, hash) = rest.split_at(SPARSE_MERKLE_KEY_LEN);
+                Ok(SparseMerkleNode::Leaf {
+                    key: key.to_vec(),
+                    hash: hash.to_vec(),
+                })
+            }
+            Some((&INTERNAL_NODE, hash)) if hash.len() == SPARSE_MERKLE_KEY_LEN => {
+                Ok(SparseMerkleNode::Internal {
+                    hash: hash.to_vec(),
+                })
+            }
+            _ => Err(anyhow!("invalid sparse merkle node data")),
+        }
+    }
+}
+
+/// Returns the tree key of an account - the hash of its id
+pub fn sparse_merkle_key(account_id: &[u8]) -> Result<Vec<u8>> {
+    Hasher::hash(account_id)
+}
+
+/// Returns true when the path of key goes right at the provided depth
+pub fn key_bit(key: &[u8], depth: usize) -> bool {
+    key[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Returns the storage key of the node at the provided depth on key's path: the big-endian
+/// depth followed by the key with all bits from depth on cleared
+pub fn node_storage_key(depth: usize, key: &[u8]) -> Vec<u8> {
+    let mut prefix = key.to_vec();
+    for (i, byte) in prefix.iter_mut().enumerate() {
+        let bits = depth.saturating_sub(i * 8).min(8);
+        *byte &= !(0xffu8.checked_shr(bits as u32).unwrap_or(0));
+    }
+    [&(depth as u16).to_be_bytes()[..], &prefix].concat()
+}
+
+/// Returns the key of the sibling path of key at the provided depth - key with the bit which
+/// selects the node at depth flipped
+pub fn sibling_key(key: &[u8], depth: usize) -> Vec<u8> {
+    let mut sibling = key.to_vec();
+    sibling[(depth - 1) / 8] ^= 0x80 >> ((depth - 1) % 8);
+    sibling
+}
+
+/// Returns all nodes of the sparse merkle tree of the provided (key, serialized leaf) pairs by
+/// storage key, and the tree root. Used to build the tree from scratch.
+pub fn sparse_merkle_nodes(
+    leaves: &[(Vec<u8>, Vec<u8>)],
+) -> Result<(Vec<(Vec<u8>, SparseMerkleNode)>, Vec<u8>)> {
+    let mut leaves = leaves
+        .iter()
+        .map(|(key, leaf)| SparseMerkleNode::leaf(key, leaf))
+        .collect::<Result<Vec<_>>>()?;
+    leaves.sort_by(|a, b| leaf_key(a).cmp(leaf_key(b)));
+
+    let mut nodes = vec![];
+    let root = build_subtree(&leaves, 0, &mut nodes)?;
+    let root_hash = root
+        .as_ref()
+        .map_or(EMPTY_SUBTREE_HASH.to_vec(), |n| n.hash().to_vec());
+    if let Some(root) = root {
+        nodes.push((node_storage_key(0, leaf_key(&leaves[0])), root));
+    }
+    Ok((nodes, root_hash))
+}
+
+/// Verify that leaf is the serialized leaf of account_id in a tree with the provided root.
+/// Only uses the leaf, the proof and the root so it can be used offline by clients.
+pub fn verify_sparse_merkle_proof(
+    root: &[u8],
+    account_id: &[u8],
+    leaf: &[u8],
+    proof: &SparseMerkleProof,
+) -> bool {
+    if proof.siblings.len() > SPARSE_MERKLE_MAX_DEPTH {
+        return false;
+    }
+
+    let Ok(key) = sparse_merkle_key(account_id) else {
+        return false;
+    };
+    let Ok(mut hash) = hash_sparse_leaf(&key, leaf) else {
+        return false;
+    };
+
+    for (depth, sibling) in proof.siblings.iter().enumerate().rev() {
+        let res = if key_bit(&key, depth) {
+            hash_node(sibling, &hash)
+        } else {
+            hash_node(&hash, sibling)
+        };
+        let Ok(node) = res else {
+            return false;
+        };
+        hash = node;
+    }
+
+    hash == root
+}
+
+/// Helper function - builds the subtree at depth of the provided leaves, which are sorted by
+/// key and share the path to depth. Nodes below depth are added to nodes.
+fn build_subtree(
+    leaves: &[SparseMerkleNode],
+    depth: usize,
+    nodes: &mut Vec<(Vec<u8>, SparseMerkleNode)>,
+) -> Result<Option<SparseMerkleNode>> {
+    match leaves {
+        [] => Ok(None),
+        [leaf] => Ok(Some(leaf.clone())),
+        _ => {
+            if depth == SPARSE_MERKLE_MAX_DEPTH {
+                return Err(anyhow!("duplicate sparse merkle tree key"));
+            }
+
+            let split = leaves.partition_point(|leaf| !key_bit(leaf_key(leaf), depth));
+            let (left, right) = leaves.split_at(split);
+            let left_node = build_subtree(left, depth + 1, nodes)?;
+            let right_node = build_subtree(right, depth + 1, nodes)?;
+            let node = SparseMerkleNode::internal(left_node.as_ref(), right_node.as_ref())?;
+
+            for (child, child_leaves) in [(left_node, left), (right_node, right)] {
+                if let Some(child) = child {
+                    let key = node_storage_key(depth + 1, leaf_key(&child_leaves[0]));
+                    nodes.push((key, child));
+                }
+            }
+            Ok(Some(node))
+        }
+    }
+}
+
+/// Helper function - the key of a leaf node
+fn leaf_key(node: &SparseMerkleNode) -> &[u8] {
+    match node {
+        SparseMerkleNode::Leaf { key, .. } => key,
+        SparseMerkleNode::Internal { .. } => &[],
+    }
+}
+
+fn hash_sparse_leaf(key: &[u8], leaf: &[u8]) -> Result<Vec<u8>> {
+    if key.len() != SPARSE_MERKLE_KEY_LEN {
+        return Err(anyhow!("invalid sparse merkle tree key length"));
+    }
+
+    let mut data = Vec::with_capacity(key.len() + leaf.len() + 1);
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(key);
+    data.extend_from_slice(leaf);
+    Hasher::hash(&data)
+}
@@ -0,0 +1,179 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Supply accounting.
+//! Funds are created by minting, moved between users by transfers and destroyed by burning.
+//! User balances only change via the operations in this module and all of them use overflow
+//! checked arithmetic. Minted and burned funds are accounted for in the blockchain stats so the
+//! sum of all users balances always equals the stats circulation.
+
+use crate::karma_coin::karma_coin_core_types::{Balance, BlockchainStats, User};
+use anyhow::{anyhow, Result};
+
+/// Returns a + b or an error when the named amount overflows
+pub fn checked_add(a: u64, b: u64, name: &str) -> Result<u64> {
+    a.checked_add(b)
+        .ok_or_else(|| anyhow!("{} overflow: {} + {}", name, a, b))
+}
+
+/// Returns a - b or an error when the named amount underflows
+pub fn checked_sub(a: u64, b: u64, name: &str) -> Result<u64> {
+    a.checked_sub(b)
+        .ok_or_else(|| anyhow!("{} underflow: {} - {}", name, a, b))
+}
+
+impl Balance {
+    /// Returns the free and reserved funds
+    pub fn total(&self) -> Result<u64> {
+        checked_add(self.free, self.reserved, "total balance")
+    }
+
+    /// Returns the free funds which are not frozen
+    pub fn spendable(&self) -> u64 {
+        self.free
+            .saturating_sub(self.misc_frozen.max(self.fee_frozen))
+    }
+
+    /// Add funds to the free balance
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        self.free = checked_add(self.free, amount, "free balance")?;
+        Ok(())
+    }
+
+    /// Remove spendable funds from the free balance
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        let spendable = self.spendable();
+        if amount > spendable {
+            return Err(anyhow!(
+                "insufficient spendable balance. spendable: {}, amount: {}",
+                spendable,
+                amount
+            ));
+        }
+        self.free -= amount;
+        Ok(())
+    }
+
+    /// Move spendable funds from the free balance to the reserved balance
+    pub fn reserve(&mut self, amount: u64) -> Result<()> {
+        let reserved = checked_add(self.reserved, amount, "reserved balance")?;
+        self.withdraw(amount)?;
+        self.reserved = reserved;
+        Ok(())
+    }
+
+    /// Move reserved funds back to the free balance
+    pub fn unreserve(&mut self, amount: u64) -> Result<()> {
+        let free = checked_add(self.free, amount, "free balance")?;
+        self.withdraw_reserved(amount)?;
+        self.free = free;
+        Ok(())
+    }
+
+    /// Remove funds from the reserved balance
+    pub fn withdraw_reserved(&mut self, amount: u64) -> Result<()> {
+        if amount > self.reserved {
+            return Err(anyhow!(
+                "insufficient reserved balance. reserved: {}, amount: {}",
+                self.reserved,
+                amount
+            ));
+        }
+        self.reserved -= amount;
+        Ok(())
+    }
+
+    /// Freeze free funds so they can't be transferred
+    pub fn freeze(&mut self, amount: u64) -> Result<()> {
+        let misc_frozen = checked_add(self.misc_frozen, amount, "frozen balance")?;
+        if misc_frozen > self.free {
+            return Err(anyhow!(
+                "insufficient free balance to freeze. free: {}, frozen: {}",
+                self.free,
+                misc_frozen
+            ));
+        }
+        self.misc_frozen = misc_frozen;
+        Ok(())
+    }
+
+    /// Unfreeze frozen funds
+    pub fn unfreeze(&mut self, amount: u64) -> Result<()> {
+        self.misc_frozen = checked_sub(self.misc_frozen, amount, "frozen balance")?;
+        Ok(())
+    }
+}
+
+/// Transfer spendable funds between users
+pub fn transfer(from: &mut User, to: &mut User, amount: u64) -> Result<()> {
+    // check the deposit first so a failed transfer doesn't change any balance
+    checked_add(to.free_balance(), amount, "free balance")?;
+    from.withdraw(amount)?;
+    to.deposit(amount)
+}
+
+/// Move all free funds and their frozen locks from one user to another.
+/// Reserved funds stay with the source user. Returns the moved amount.
+pub fn move_free_funds(from: &mut User, to: &mut User) -> Result<u64> {
+    let source = from.balance.get_or_insert_with(Balance::default);
+    let target = to.balance.get_or_insert_with(Balance::default);
+    let free = checked_add(target.free, source.free, "free balance")?;
+    let misc_frozen = checked_add(target.misc_frozen, source.misc_frozen, "frozen balance")?;
+    let fee_frozen = checked_add(target.fee_frozen, source.fee_frozen, "fee frozen balance")?;
+
+    let amount = source.free;
+    target.free = free;
+    target.misc_frozen = misc_frozen;
+    target.fee_frozen = fee_frozen;
+    source.free = 0;
+    source.misc_frozen = 0;
+    source.fee_frozen = 0;
+    Ok(amount)
+}
+
+/// Returns the sum of the provided users free and reserved funds
+pub fn total_balances<'a>(users: impl IntoIterator<Item = &'a User>) -> Result<u64> {
+    users.into_iter().try_fold(0, |total, user| {
+        checked_add(total, user.total_balance()?, "users balances")
+    })
+}
+
+/// Supply stats accounting
+impl BlockchainStats {
+    /// Account for funds minted by the protocol in a block and deposited to users
+    pub fn mint(&mut self, amount: u64) -> Result<()> {
+        self.minted_amount = checked_add(self.minted_amount, amount, "minted amount")?;
+        self.circulation = checked_add(self.circulation, amount, "circulation")?;
+        Ok(())
+    }
+
+    /// Account for karma rewards deposited to users in a block. Karma rewards are tracked
+    /// separately from the minted amount as they are limited by their own allocation
+    pub fn mint_karma_rewards(&mut self, amount: u64) -> Result<()> {
+        self.karma_rewards_amount =
+            checked_add(self.karma_rewards_amount, amount, "karma rewards amount")?;
+        self.circulation = checked_add(self.circulation, amount, "circulation")?;
+        Ok(())
+    }
+
+    /// Account for funds which were removed from users balances
+    pub fn burn(&mut self, amount: u64) -> Result<()> {
+        self.circulation = checked_sub(self.circulation, amount, "circulation")?;
+        self.burned_amount = checked_add(self.burned_amount, amount, "burned amount")?;
+        Ok(())
+    }
+
+    /// Verify the supply invariant - the sum of all users balances equals the circulation
+    pub fn verify_circulation(&self, users_balances: u64) -> Result<()> {
+        if users_balances != self.circulation {
+            return Err(anyhow!(
+                "supply invariant violated at height {}. users balances: {}, circulation: {}",
+                self.tip_height,
+                users_balances,
+                self.circulation
+            ));
+        }
+        Ok(())
+    }
+}
@@ -26,6 +26,9 @@ impl TransactionEvent {
             appreciation_char_trait_idx: 0,
             appreciation_community_id: 0,
             fee: 0,
+            burned_amount: 0,
+            redirected_amount: 0,
+            redirected_to: None,
         }
     }
 }
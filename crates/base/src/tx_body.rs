@@ -2,10 +2,11 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use crate::genesis_config_service::{GenesisConfigService, NET_ID_KEY};
+use crate::genesis_config_service::GenesisConfigService;
 use crate::karma_coin::karma_coin_core_types::{
-    DeleteUserTransactionV1, NewUserTransactionV1, PaymentTransactionV1, TransactionBody,
-    TransactionType, UpdateUserTransactionV1,
+    DeleteUserTransactionV1, NewUserTransactionV1, NewUserTransactionV2, ParamsUpdateTransactionV1,
+    PaymentTransactionV1, TransactionBody, TransactionType, UpdateUserTransactionV1,
+    VerifierUpdateTransactionV1,
 };
 use anyhow::{anyhow, Result};
 use chrono::{Duration, Utc};
@@ -27,12 +28,12 @@ impl Display for TransactionBody {
 
 impl TransactionBody {
     /// Validate transaction has valid syntax, fields has the correct net id and is properly
-    /// signed before processing it
-    pub async fn validate(&self, _user_nonce: u64) -> Result<()> {
-        // skip nonce validation for now until the design is fully sorted out
-        //self.validate_nonce(user_nonce)?;
+    /// signed before processing it.
+    /// Timestamp is verified when a tx is submitted and not here, so executing a tx doesn't
+    /// depend on the time it is executed at and blocks can be replayed.
+    pub async fn validate(&self, user_nonce: u64) -> Result<()> {
+        self.validate_nonce(user_nonce)?;
         self.verify_syntax().await?;
-        self.verify_timestamp()?;
         self.verify_tx_fee()
     }
 
@@ -64,11 +65,9 @@ impl TransactionBody {
             return Err(anyhow!("required transaction data is missing"));
         }
 
-        let net_id = GenesisConfigService::get_u64(NET_ID_KEY.into())
-            .await?
-            .unwrap();
+        let net_id = GenesisConfigService::get_genesis_config().await?.net_id;
 
-        if self.net_id as u64 != net_id {
+        if self.net_id != net_id {
             return Err(anyhow!(
                 "Transaction has wrong net id - expected: {}, got: {}",
                 net_id,
@@ -114,6 +113,20 @@ impl TransactionBody {
         )?)
     }
 
+    pub fn get_new_user_transaction_v2(&self) -> Result<NewUserTransactionV2> {
+        let data = self
+            .transaction_data
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing tx data"))?;
+        if data.transaction_type != TransactionType::NewUserV2 as i32 {
+            return Err(anyhow!("unexpected transaction type"));
+        }
+
+        Ok(NewUserTransactionV2::decode(
+            data.transaction_data.as_ref(),
+        )?)
+    }
+
     pub fn get_delete_user_transaction_v1(&self) -> Result<DeleteUserTransactionV1> {
         let data = self
             .transaction_data
@@ -128,6 +141,34 @@ impl TransactionBody {
         )?)
     }
 
+    pub fn get_params_update_transaction_v1(&self) -> Result<ParamsUpdateTransactionV1> {
+        let data = self
+            .transaction_data
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing tx data"))?;
+        if data.transaction_type != TransactionType::ParamsUpdateV1 as i32 {
+            return Err(anyhow!("unexpected transaction type"));
+        }
+
+        Ok(ParamsUpdateTransactionV1::decode(
+            data.transaction_data.as_ref(),
+        )?)
+    }
+
+    pub fn get_verifier_update_transaction_v1(&self) -> Result<VerifierUpdateTransactionV1> {
+        let data = self
+            .transaction_data
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing tx data"))?;
+        if data.transaction_type != TransactionType::VerifierUpdateV1 as i32 {
+            return Err(anyhow!("unexpected transaction type"));
+        }
+
+        Ok(VerifierUpdateTransactionV1::decode(
+            data.transaction_data.as_ref(),
+        )?)
+    }
+
     pub fn get_payment_transaction_v1(&self) -> Result<PaymentTransactionV1> {
         let data = self
             .transaction_data
@@ -8,11 +8,19 @@ use anyhow::{anyhow, Result};
 impl UpdateUserTransactionV1 {
     /// Verify all fields
     pub fn verify_syntax(&self) -> Result<()> {
-        if self.nickname.is_empty()
-            && (self.user_verification_data.is_none() || self.mobile_number.is_none())
+        // txs are stored on chain so a number update is attested by evidence on the number hash
+        if self.mobile_number.is_some()
+            || self.user_verification_data.is_some()
+            || !self.additional_user_verification_data.is_empty()
         {
             return Err(anyhow!(
-                "expected non-empty requested nickname or verify number response and mobile number"
+                "mobile number is not allowed - use mobile number hash evidence"
+            ));
+        }
+
+        if self.nickname.is_empty() && self.user_verification_data_ex.is_empty() {
+            return Err(anyhow!(
+                "expected non-empty requested nickname or mobile number hash evidence"
             ));
         }
 
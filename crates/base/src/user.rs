@@ -5,7 +5,7 @@
 use crate::genesis_config_service::KARMA_REWARD_TRAIT_ID;
 use crate::hex_utils::short_hex_string;
 use crate::karma_coin::karma_coin_core_types::{
-    AccountId, CommunityMembership, MobileNumber, TraitScore, User,
+    AccountId, Balance, CommunityMembership, MobileNumber, TraitScore, User,
 };
 use anyhow::{anyhow, Result};
 use log::info;
@@ -18,8 +18,8 @@ impl User {
         if self.account_id.is_none() {
             return Err(anyhow!("account id is required"));
         }
-        if self.mobile_number.is_none() {
-            return Err(anyhow!("mobile number is required"));
+        if self.mobile_number.is_none() && self.mobile_number_hash.is_empty() {
+            return Err(anyhow!("mobile number or mobile number hash is required"));
         }
         Ok(())
     }
@@ -31,12 +31,27 @@ impl User {
             account_id: Some(account_id),
             nonce: 0,
             user_name,
-            mobile_number: Some(mobile_number),
-            balance: 0,
+            // only the number hash is stored on chain
+            mobile_number_hash: mobile_number.get_hash().unwrap_or_default(),
+            mobile_number: None,
+            legacy_balance: 0,
             trait_scores: vec![],
             pre_keys: vec![],
             karma_score: 1, // new users have karma score of 1
             community_memberships: vec![],
+            balance: Some(Balance::default()),
+        }
+    }
+
+    /// Returns the hash of user's verified mobile number, if any. Users who signed up before
+    /// numbers were hashed on chain only have their number
+    pub fn get_mobile_number_hash(&self) -> Result<Option<Vec<u8>>> {
+        if !self.mobile_number_hash.is_empty() {
+            return Ok(Some(self.mobile_number_hash.clone()));
+        }
+        match self.mobile_number.as_ref() {
+            Some(mobile_number) => Ok(Some(mobile_number.get_hash()?)),
+            None => Ok(None),
         }
     }
 
@@ -104,21 +119,99 @@ impl User {
     }
 }
 
+/// Balance accounting. All amounts are in KCents and all changes are made by the overflow checked
+/// balance operations of the supply module
+impl User {
+    fn balance_mut(&mut self) -> &mut Balance {
+        self.balance.get_or_insert_with(Balance::default)
+    }
+
+    /// Returns user's free funds, including frozen funds
+    pub fn free_balance(&self) -> u64 {
+        self.balance.as_ref().map_or(0, |b| b.free)
+    }
+
+    /// Returns user's reserved funds
+    pub fn reserved_balance(&self) -> u64 {
+        self.balance.as_ref().map_or(0, |b| b.reserved)
+    }
+
+    /// Returns user's free and reserved funds
+    pub fn total_balance(&self) -> Result<u64> {
+        self.balance.as_ref().map_or(Ok(0), |b| b.total())
+    }
+
+    /// Returns the free funds which are not frozen and can be used for payments and fees
+    pub fn spendable_balance(&self) -> u64 {
+        self.balance.as_ref().map_or(0, |b| b.spendable())
+    }
+
+    /// Add funds to user's free balance
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().deposit(amount)
+    }
+
+    /// Remove spendable funds from user's free balance
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().withdraw(amount)
+    }
+
+    /// Move spendable funds from user's free balance to its reserved balance
+    pub fn reserve(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().reserve(amount)
+    }
+
+    /// Move reserved funds back to user's free balance
+    pub fn unreserve(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().unreserve(amount)
+    }
+
+    /// Remove funds from user's reserved balance. e.g. to pay them to another user
+    pub fn withdraw_reserved(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().withdraw_reserved(amount)
+    }
+
+    /// Freeze free funds so they can't be transferred
+    pub fn freeze(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().freeze(amount)
+    }
+
+    /// Unfreeze frozen funds
+    pub fn unfreeze(&mut self, amount: u64) -> Result<()> {
+        self.balance_mut().unfreeze(amount)
+    }
+
+    /// Move a balance stored before structured balances were introduced to the free balance.
+    /// Returns true if the user was changed.
+    pub fn migrate_legacy_balance(&mut self) -> Result<bool> {
+        if self.legacy_balance == 0 {
+            return Ok(false);
+        }
+        let amount = self.legacy_balance;
+        self.deposit(amount)?;
+        self.legacy_balance = 0;
+        Ok(true)
+    }
+}
+
 impl Display for User {
     // This trait requires `fmt` with this exact signature.
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
             f,
             "User {{ name: {}, number: {}, account_id: {}, nonce: {}, \
-            balance: {}, trait_scores: {:?}, pre_keys: {:?} }}",
+            balance: {}, reserved: {}, trait_scores: {:?}, pre_keys: {:?} }}",
             self.user_name,
             match self.mobile_number.as_ref() {
                 Some(mobile_number) => mobile_number.number.to_string(),
+                None if !self.mobile_number_hash.is_empty() =>
+                    short_hex_string(&self.mobile_number_hash),
                 None => "[n/a]".to_string(),
             },
             short_hex_string(self.account_id.as_ref().unwrap().data.as_slice()),
             self.nonce,
-            self.balance,
+            self.free_balance(),
+            self.reserved_balance(),
             self.trait_scores,
             self.pre_keys
         )
@@ -0,0 +1,129 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::hex_utils::short_hex_string;
+use crate::karma_coin::karma_coin_core_types::{
+    AccountId, SignedUserVerificationDataEx, UserVerificationDataEx, VerificationResult,
+};
+use crate::mobile_number::decode_mobile_number_hash;
+use crate::signed_trait::SignedTrait;
+use anyhow::{anyhow, Result};
+use ed25519_dalek::Keypair;
+use prost::Message;
+
+impl From<VerificationResult> for UserVerificationDataEx {
+    fn from(value: VerificationResult) -> Self {
+        UserVerificationDataEx {
+            verifier_account_id: None,
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            verification_result: value as i32,
+            account_id: None,
+            mobile_number_hash: "".into(),
+            requested_user_name: "".into(),
+        }
+    }
+}
+
+impl UserVerificationDataEx {
+    /// Returns the verified mobile number hash
+    pub fn get_mobile_number_hash(&self) -> Result<Vec<u8>> {
+        decode_mobile_number_hash(&self.mobile_number_hash)
+    }
+}
+
+impl SignedUserVerificationDataEx {
+    /// Returns the provided evidence signed by its verifier
+    pub fn new(data: &UserVerificationDataEx, key_pair: &Keypair) -> Result<Self> {
+        let mut signed = SignedUserVerificationDataEx {
+            user_verification_data_ex: data.encode_to_vec(),
+            verifier_signature: None,
+        };
+        signed.verifier_signature = Some(signed.sign(key_pair)?);
+        Ok(signed)
+    }
+
+    /// Returns the signed evidence
+    pub fn get_data(&self) -> Result<UserVerificationDataEx> {
+        Ok(UserVerificationDataEx::decode(
+            self.user_verification_data_ex.as_ref(),
+        )?)
+    }
+}
+
+impl SignedTrait for SignedUserVerificationDataEx {
+    fn get_sign_message(&self) -> Result<Vec<u8>> {
+        Ok(self.user_verification_data_ex.clone())
+    }
+
+    fn get_signature(&self) -> Result<ed25519_dalek::Signature> {
+        Ok(ed25519_dalek::Signature::from_bytes(
+            &self
+                .verifier_signature
+                .as_ref()
+                .ok_or_else(|| anyhow!("no signature found"))?
+                .signature
+                .clone(),
+        )?)
+    }
+
+    fn get_public_key(&self) -> Result<ed25519_dalek::PublicKey> {
+        Ok(ed25519_dalek::PublicKey::from_bytes(
+            &self
+                .get_data()?
+                .verifier_account_id
+                .as_ref()
+                .ok_or_else(|| anyhow!("no public found"))?
+                .data,
+        )?)
+    }
+}
+
+/// Verify that all evidence was signed by distinct verifiers and attests that the same account
+/// owns the same verified mobile number hash. Returns the verified data and the verifiers
+/// accounts ids. Callers should check that each verifier is registered.
+pub fn get_evidence_ex_verifiers(
+    evidence: &[&SignedUserVerificationDataEx],
+) -> Result<(UserVerificationDataEx, Vec<AccountId>)> {
+    let mut first: Option<UserVerificationDataEx> = None;
+    let mut verifiers: Vec<AccountId> = vec![];
+
+    for signed_data in evidence.iter() {
+        signed_data
+            .verify_signature()
+            .map_err(|_| anyhow!("invalid verification signature"))?;
+        let data = signed_data.get_data()?;
+
+        if data.verification_result != VerificationResult::Verified as i32 {
+            return Err(anyhow!("mobile number was not verified"));
+        }
+
+        if let Some(first) = first.as_ref() {
+            if data.account_id != first.account_id
+                || data.mobile_number_hash != first.mobile_number_hash
+                || data.requested_user_name != first.requested_user_name
+            {
+                return Err(anyhow!(
+                    "verification evidence attest to different user data"
+                ));
+            }
+        }
+
+        // verify_signature() checked the verifier account id is provided
+        let verifier = data.verifier_account_id.clone().unwrap();
+        if verifiers.contains(&verifier) {
+            return Err(anyhow!(
+                "verifier {} provided more than one evidence",
+                short_hex_string(&verifier.data)
+            ));
+        }
+        verifiers.push(verifier);
+
+        if first.is_none() {
+            first = Some(data);
+        }
+    }
+
+    let data = first.ok_or_else(|| anyhow!("missing verification evidence"))?;
+    Ok((data, verifiers))
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::admin_approval::{get_approval_message, get_approvers, VERIFIER_UPDATE_APPROVAL_DOMAIN};
+use crate::hex_utils::short_hex_string;
+use crate::karma_coin::karma_coin_core_types::{
+    AccountId, AdminApproval, RegisteredVerifier, VerifierAction, VerifierUpdateProposal,
+    VerifierUpdateTransactionV1,
+};
+use anyhow::{anyhow, Result};
+use ed25519_dalek::Keypair;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+impl RegisteredVerifier {
+    /// Returns true iff the verifier's evidence is accepted in a block at the provided height
+    pub fn is_valid_at(&self, height: u64) -> bool {
+        height >= self.valid_from
+            && (self.valid_until == 0 || height <= self.valid_until)
+            && (self.revoked_at == 0 || height < self.revoked_at)
+    }
+}
+
+impl Display for RegisteredVerifier {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RegisteredVerifier {{ name: {}, account id: {}, valid from: {}, valid until: {}, revoked at: {} }}",
+            self.name,
+            short_hex_string(&self.account_id.as_ref().map_or(vec![], |a| a.data.clone())),
+            self.valid_from,
+            self.valid_until,
+            self.revoked_at
+        )
+    }
+}
+
+impl VerifierUpdateProposal {
+    /// Verify all fields
+    pub fn verify_syntax(&self) -> Result<()> {
+        let action = VerifierAction::from_i32(self.action)
+            .ok_or_else(|| anyhow!("unknown verifier action"))?;
+
+        if self.account_id.as_ref().is_none_or(|a| a.data.len() != 32) {
+            return Err(anyhow!("verifier account id is required"));
+        }
+
+        match action {
+            VerifierAction::Add => {
+                if self.name.trim().is_empty() {
+                    return Err(anyhow!("added verifier name is required"));
+                }
+            }
+            VerifierAction::Rotate => {
+                if self
+                    .new_account_id
+                    .as_ref()
+                    .is_none_or(|a| a.data.len() != 32)
+                {
+                    return Err(anyhow!("rotated verifier new account id is required"));
+                }
+                if self.new_account_id == self.account_id {
+                    return Err(anyhow!("rotated verifier new key must be a different key"));
+                }
+            }
+            VerifierAction::Revoke => {}
+        }
+
+        if action != VerifierAction::Revoke
+            && self.valid_until != 0
+            && self.valid_until < self.height
+        {
+            return Err(anyhow!(
+                "verifier valid until height is before its first height"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an admin approval of the proposal on the chain with the provided genesis hash
+    pub fn approve(&self, genesis_hash: &[u8], key_pair: &Keypair) -> AdminApproval {
+        AdminApproval::new(
+            &get_approval_message(VERIFIER_UPDATE_APPROVAL_DOMAIN, genesis_hash, self),
+            key_pair,
+        )
+    }
+}
+
+impl VerifierUpdateTransactionV1 {
+    /// Verify all approvals were signed by distinct governance admins and return the approvers
+    pub fn get_approvers(
+        &self,
+        admins: &[AccountId],
+        genesis_hash: &[u8],
+    ) -> Result<Vec<AccountId>> {
+        let proposal = self
+            .proposal
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing proposal"))?;
+        get_approvers(
+            &self.approvals,
+            &get_approval_message(VERIFIER_UPDATE_APPROVAL_DOMAIN, genesis_hash, proposal),
+            admins,
+        )
+    }
+}
@@ -12,7 +12,7 @@ use self::rocksdb::{Direction, IteratorMode};
 use bytes::{BufMut, Bytes, BytesMut};
 use datetime::Instant;
 use rocksdb::DB as rocks;
-use rocksdb::{ColumnFamilyDescriptor, Options};
+use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch as RocksWriteBatch};
 use std::{env, fs};
 use xactor::*;
 
@@ -49,6 +49,34 @@ impl DatabaseService {
         db_service.call(item).await?
     }
 
+    /// Read all (key, value) pairs of a column family
+    pub async fn read_all(cf: &'static str) -> Result<Vec<(Bytes, Bytes)>> {
+        let data = DatabaseService::read_all_items(ReadAllItems {
+            from_key: None,
+            max_results: 0,
+            cf,
+        })
+        .await?;
+
+        Ok(data
+            .items
+            .into_iter()
+            .map(|(key, value)| (key, value.value))
+            .collect())
+    }
+
+    /// Read items with keys starting with a prefix from a column family, in keys order
+    pub async fn read_prefix_items(item: ReadPrefixItems) -> Result<Vec<(Bytes, DbValue)>> {
+        let db_service = DatabaseService::from_registry().await?;
+        db_service.call(item).await?
+    }
+
+    /// Atomically apply a batch of writes and deletes across column families
+    pub async fn write_batch(batch: WriteBatch) -> Result<()> {
+        let db_service = DatabaseService::from_registry().await?;
+        db_service.call(batch).await?
+    }
+
     /// Delete a value by key from a column family
     pub async fn delete(item: DeleteItem) -> Result<()> {
         let db_service = DatabaseService::from_registry().await?;
@@ -89,6 +117,15 @@ impl DatabaseService {
         Ok(())
     }
 
+    /// private helper method - prefix a value with its ttl time stamp for storage
+    fn encode_value(value: &Bytes, ttl: u64) -> Bytes {
+        let time_stamp = Instant::now().seconds() as u64 + ttl;
+        let mut buf = BytesMut::with_capacity(value.len() + 8);
+        buf.put_u64(time_stamp);
+        buf.put_slice(value.as_ref());
+        buf.freeze()
+    }
+
     /// private helper method - get the db options
     fn default_options() -> Options {
         let mut db_opts = Options::default();
@@ -181,16 +218,8 @@ pub struct WriteItem {
 #[async_trait::async_trait]
 impl Handler<WriteItem> for DatabaseService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: WriteItem) -> Result<()> {
-        let time_stamp = Instant::now().seconds() as u64 + msg.ttl;
-        debug!(
-            "PutItem: time_stamp for key {:?} is {:?}",
-            msg.data.key, time_stamp
-        );
-
-        let mut buf = BytesMut::with_capacity(msg.data.value.len() + 8);
-        buf.put_u64(time_stamp);
-        buf.put_slice(msg.data.value.to_vec().as_slice());
-        let data = buf.freeze();
+        debug!("PutItem: key {:?}, ttl {:?}", msg.data.key, msg.ttl);
+        let data = DatabaseService::encode_value(&msg.data.value, msg.ttl);
         let db_ref = self.db.as_ref().ok_or_else(|| anyhow!("db is nil"))?;
         let cf = db_ref
             .cf_handle(msg.cf)
@@ -202,6 +231,75 @@ impl Handler<WriteItem> for DatabaseService {
     }
 }
 
+///// Write batches
+
+/// A single operation in a write batch
+pub enum BatchOp {
+    Write(WriteItem),
+    Delete(DeleteItem),
+}
+
+/// A set of writes and deletes, possibly spanning multiple column families,
+/// which are applied to the db atomically - either all of them or none
+#[message(result = "Result<()>")]
+#[derive(Default)]
+pub struct WriteBatch {
+    pub ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    /// Add a write operation to the batch
+    pub fn write(&mut self, item: WriteItem) {
+        self.ops.push(BatchOp::Write(item));
+    }
+
+    /// Add a delete operation to the batch
+    pub fn delete(&mut self, item: DeleteItem) {
+        self.ops.push(BatchOp::Delete(item));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Apply a WriteBatch to the store in a single atomic db write
+#[async_trait::async_trait]
+impl Handler<WriteBatch> for DatabaseService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: WriteBatch) -> Result<()> {
+        let db_ref = self.db.as_ref().ok_or_else(|| anyhow!("db is nil"))?;
+        let mut batch = RocksWriteBatch::default();
+
+        for op in msg.ops.iter() {
+            match op {
+                BatchOp::Write(item) => {
+                    let cf = db_ref
+                        .cf_handle(item.cf)
+                        .ok_or_else(|| anyhow!("missing db column family: {}", item.cf))?;
+                    let data = DatabaseService::encode_value(&item.data.value, item.ttl);
+                    batch.put_cf(cf, item.data.key.as_ref(), data);
+                }
+                BatchOp::Delete(item) => {
+                    let cf = db_ref
+                        .cf_handle(item.cf)
+                        .ok_or_else(|| anyhow!("no matching cf: {:?}", item.cf))?;
+                    batch.delete_cf(cf, item.key.as_ref());
+                }
+            }
+        }
+
+        debug!("writing batch of {} ops", batch.len());
+
+        db_ref
+            .write(batch)
+            .map_err(|e| anyhow!("failed to write batch: {:?}", e))
+    }
+}
+
 ///// Compact db
 
 #[message(result = "Result<()>")]
@@ -286,6 +384,87 @@ impl Handler<ReadAllItems> for DatabaseService {
     }
 }
 
+/// Read (k,v) with keys starting with a prefix from a column family
+#[message(result = "Result<Vec<(Bytes, DbValue)>>")]
+#[derive(Clone)]
+pub struct ReadPrefixItems {
+    pub prefix: Bytes,
+    pub from_key: Option<Bytes>, // when provided - return items after key (excluding it)
+    pub reverse: bool,           // return items in descending keys order
+    pub max_results: u32,        // 0 for no limit, otherwise, return up to max_results
+    pub cf: &'static str,
+}
+
+/// Read items with a keys prefix in ascending or descending keys order
+#[async_trait::async_trait]
+impl Handler<ReadPrefixItems> for DatabaseService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: ReadPrefixItems,
+    ) -> Result<Vec<(Bytes, DbValue)>> {
+        let db_ref = self.db.as_ref().ok_or_else(|| anyhow!("db is nil"))?;
+        let cf = db_ref
+            .cf_handle(msg.cf)
+            .ok_or_else(|| anyhow!("no matching cf: {:?}", &msg.cf))?;
+
+        let prefix = msg.prefix.as_ref();
+        let prefix_end = prefix_successor(prefix);
+        let iter = match (msg.from_key.as_ref(), msg.reverse) {
+            (Some(from_key), true) => {
+                db_ref.iterator_cf(cf, IteratorMode::From(from_key, Direction::Reverse))
+            }
+            (Some(from_key), false) => {
+                db_ref.iterator_cf(cf, IteratorMode::From(from_key, Direction::Forward))
+            }
+            (None, true) => match prefix_end.as_ref() {
+                Some(end) => db_ref.iterator_cf(cf, IteratorMode::From(end, Direction::Reverse)),
+                None => db_ref.iterator_cf(cf, IteratorMode::End),
+            },
+            (None, false) => db_ref.iterator_cf(cf, IteratorMode::From(prefix, Direction::Forward)),
+        };
+
+        let mut res: Vec<(Bytes, DbValue)> = vec![];
+        for item in iter {
+            let kv_bytes = item?;
+            let key = kv_bytes.0.as_ref();
+            if msg.from_key.as_ref().is_some_and(|k| k.as_ref() == key) {
+                continue;
+            }
+
+            if !key.starts_with(prefix) {
+                // a reverse iteration may start right after the prefix range
+                if msg.reverse && key > prefix {
+                    continue;
+                }
+                break;
+            }
+
+            let (value, ttl) = parse_value(kv_bytes.1.as_ref())?;
+            res.push((Bytes::copy_from_slice(key), DbValue { value, ttl }));
+
+            if msg.max_results != 0 && res.len() >= msg.max_results as usize {
+                break;
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Helper function - returns the smallest key which is greater than all keys with a prefix,
+/// or None when there is no such key
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
 ///// Reading data
 
 #[message(result = "Result<Option<(Bytes, u64)>>")]
@@ -0,0 +1,131 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use db::db_service::{
+    DataItem, DatabaseService, DeleteItem, Destroy, ReadItem, WriteBatch, WriteItem,
+};
+
+use base::client_config_service::TESTS_COL_FAMILY;
+use base::tests_helpers::enable_logger;
+use bytes::Bytes;
+use db::db_service;
+use rocksdb::{ColumnFamilyDescriptor, Options};
+use xactor::*;
+
+const OTHER_TESTS_COL_FAMILY: &str = "other_tests_cf";
+
+#[tokio::test]
+async fn test_write_batch() {
+    enable_logger();
+
+    let addr = DatabaseService::from_registry().await.unwrap();
+
+    DatabaseService::config_db(db_service::Configure {
+        drop_on_exit: true,
+        db_name: "test_batch_db".to_string(),
+        col_descriptors: vec![
+            ColumnFamilyDescriptor::new(TESTS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(OTHER_TESTS_COL_FAMILY, Options::default()),
+        ],
+    })
+    .await
+    .unwrap();
+
+    DatabaseService::write(WriteItem {
+        data: DataItem {
+            key: Bytes::from("key_1"),
+            value: Bytes::from("value_1"),
+        },
+        cf: TESTS_COL_FAMILY,
+        ttl: 0,
+    })
+    .await
+    .unwrap();
+
+    // a batch spanning 2 column families which deletes an existing key
+    let mut batch = WriteBatch::default();
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from("key_2"),
+            value: Bytes::from("value_2"),
+        },
+        cf: TESTS_COL_FAMILY,
+        ttl: 0,
+    });
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from("key_3"),
+            value: Bytes::from("value_3"),
+        },
+        cf: OTHER_TESTS_COL_FAMILY,
+        ttl: 0,
+    });
+    batch.delete(DeleteItem {
+        key: Bytes::from("key_1"),
+        cf: TESTS_COL_FAMILY,
+    });
+    assert_eq!(batch.len(), 3);
+
+    DatabaseService::write_batch(batch).await.unwrap();
+
+    let data = DatabaseService::read(ReadItem {
+        key: Bytes::from("key_1"),
+        cf: TESTS_COL_FAMILY,
+    })
+    .await
+    .unwrap();
+    assert!(data.is_none(), "expected deleted key to be gone");
+
+    let data = DatabaseService::read(ReadItem {
+        key: Bytes::from("key_2"),
+        cf: TESTS_COL_FAMILY,
+    })
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(data.0, Bytes::from("value_2"));
+
+    let data = DatabaseService::read(ReadItem {
+        key: Bytes::from("key_3"),
+        cf: OTHER_TESTS_COL_FAMILY,
+    })
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(data.0, Bytes::from("value_3"));
+
+    // a batch with an unknown column family must not apply any of its ops
+    let mut batch = WriteBatch::default();
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from("key_4"),
+            value: Bytes::from("value_4"),
+        },
+        cf: TESTS_COL_FAMILY,
+        ttl: 0,
+    });
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from("key_5"),
+            value: Bytes::from("value_5"),
+        },
+        cf: "missing_cf",
+        ttl: 0,
+    });
+
+    assert!(DatabaseService::write_batch(batch).await.is_err());
+
+    let data = DatabaseService::read(ReadItem {
+        key: Bytes::from("key_4"),
+        cf: TESTS_COL_FAMILY,
+    })
+    .await
+    .unwrap();
+    assert!(data.is_none(), "expected failed batch to not be applied");
+
+    addr.call(Destroy)
+        .await
+        .expect("failed to delete the db")
+        .expect("");
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use db::db_service::{DataItem, DatabaseService, Destroy, ReadPrefixItems, WriteBatch, WriteItem};
+
+use base::client_config_service::TESTS_COL_FAMILY;
+use base::tests_helpers::enable_logger;
+use bytes::Bytes;
+use db::db_service;
+use rocksdb::{ColumnFamilyDescriptor, Options};
+use xactor::*;
+
+// helper function - read the keys of items with a prefix
+async fn read_keys(
+    prefix: &[u8],
+    from_key: Option<&[u8]>,
+    reverse: bool,
+    max_results: u32,
+) -> Vec<Bytes> {
+    DatabaseService::read_prefix_items(ReadPrefixItems {
+        prefix: Bytes::copy_from_slice(prefix),
+        from_key: from_key.map(Bytes::copy_from_slice),
+        reverse,
+        max_results,
+        cf: TESTS_COL_FAMILY,
+    })
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|(key, _)| key)
+    .collect()
+}
+
+#[tokio::test]
+async fn test_read_prefix_items() {
+    enable_logger();
+
+    let addr = DatabaseService::from_registry().await.unwrap();
+
+    DatabaseService::config_db(db_service::Configure {
+        drop_on_exit: true,
+        db_name: "test_prefix_db".to_string(),
+        col_descriptors: vec![ColumnFamilyDescriptor::new(
+            TESTS_COL_FAMILY,
+            Options::default(),
+        )],
+    })
+    .await
+    .unwrap();
+
+    let keys: Vec<&[u8]> = vec![
+        &[0, 9],
+        &[1],
+        &[1, 1],
+        &[1, 2],
+        &[1, 3],
+        &[2],
+        &[2, 0],
+        &[255, 1],
+        &[255, 2],
+    ];
+    let mut batch = WriteBatch::default();
+    for key in keys {
+        batch.write(WriteItem {
+            data: DataItem {
+                key: Bytes::copy_from_slice(key),
+                value: Bytes::from("value"),
+            },
+            cf: TESTS_COL_FAMILY,
+            ttl: 0,
+        });
+    }
+    DatabaseService::write_batch(batch).await.unwrap();
+
+    assert_eq!(
+        read_keys(&[1], None, false, 0).await,
+        vec![&[1][..], &[1, 1], &[1, 2], &[1, 3]]
+    );
+    assert_eq!(
+        read_keys(&[1], None, true, 0).await,
+        vec![&[1, 3][..], &[1, 2], &[1, 1], &[1]]
+    );
+
+    // pages continue after the provided key
+    assert_eq!(
+        read_keys(&[1], None, true, 2).await,
+        vec![&[1, 3][..], &[1, 2]]
+    );
+    assert_eq!(
+        read_keys(&[1], Some(&[1, 2]), true, 2).await,
+        vec![&[1, 1][..], &[1]]
+    );
+    assert_eq!(
+        read_keys(&[1], Some(&[1, 1]), false, 0).await,
+        vec![&[1, 2][..], &[1, 3]]
+    );
+
+    // a prefix without a successor key
+    assert_eq!(
+        read_keys(&[255], None, true, 0).await,
+        vec![&[255, 2][..], &[255, 1]]
+    );
+    assert!(read_keys(&[3], None, true, 0).await.is_empty());
+    assert!(read_keys(&[3], None, false, 0).await.is_empty());
+
+    let _ = addr.call(Destroy).await.expect("failed to delete the db");
+}
@@ -0,0 +1,155 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::{GenesisConfigService, SetConfigFile};
+use clap::{App, Arg, ArgMatches};
+use server::{StepActivity, TokenomicsSimulator};
+use std::fs::File;
+use std::io::Write;
+use xactor::*;
+
+// parse an optional u64 arg which has a default value
+fn u64_arg(matches: &ArgMatches, name: &str) -> Result<u64> {
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse::<u64>()
+        .map_err(|e| anyhow!("invalid {} value {}: {}", name, value, e))
+}
+
+/// An offline simulator which projects rewards and tx fee subsidies for a genesis config
+/// over a synthetic or a recorded stream of signups and payments
+#[tokio::main]
+async fn main() -> Result<()> {
+    let matches = App::new("KarmaCoin Tokenomics Simulator")
+        .version("0.1.0")
+        .author("AE  <a@karmaco.in>")
+        .about("Projects rewards allocations depletion, phase switches and minted supply")
+        .arg(
+            Arg::with_name("genesis")
+                .short("g")
+                .long("genesis")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Genesis config file to merge over the default genesis config"),
+        )
+        .arg(
+            Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Recorded activity csv with a signups,payments,referrals line per step"),
+        )
+        .arg(
+            Arg::with_name("steps")
+                .long("steps")
+                .takes_value(true)
+                .default_value("8760")
+                .help("Number of synthetic steps to simulate when no input is provided"),
+        )
+        .arg(
+            Arg::with_name("signups")
+                .long("signups")
+                .takes_value(true)
+                .default_value("10")
+                .help("Synthetic signups per step"),
+        )
+        .arg(
+            Arg::with_name("payments")
+                .long("payments")
+                .takes_value(true)
+                .default_value("20")
+                .help("Synthetic payments per step"),
+        )
+        .arg(
+            Arg::with_name("referrals")
+                .long("referrals")
+                .takes_value(true)
+                .default_value("5")
+                .help("Synthetic referral payments per step"),
+        )
+        .arg(
+            Arg::with_name("step_secs")
+                .long("step-secs")
+                .takes_value(true)
+                .default_value("3600")
+                .help("Simulated seconds per step"),
+        )
+        .arg(
+            Arg::with_name("tx_fee")
+                .long("tx-fee")
+                .takes_value(true)
+                .default_value("1")
+                .help("Fee in KCents paid by each simulated tx"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["csv", "json"])
+                .default_value("csv")
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Output file. Defaults to stdout"),
+        )
+        .get_matches();
+
+    let genesis_config = GenesisConfigService::from_registry().await?;
+    if let Some(genesis_file) = matches.value_of("genesis") {
+        genesis_config
+            .call(SetConfigFile {
+                config_file: genesis_file.into(),
+            })
+            .await??;
+    }
+    let genesis_data = GenesisConfigService::get_genesis_data().await?;
+
+    let steps = if let Some(input) = matches.value_of("input") {
+        StepActivity::parse_csv(&std::fs::read_to_string(input)?)?
+    } else {
+        let activity = StepActivity {
+            signups: u64_arg(&matches, "signups")?,
+            payments: u64_arg(&matches, "payments")?,
+            referrals: u64_arg(&matches, "referrals")?,
+        };
+        vec![activity; usize::try_from(u64_arg(&matches, "steps")?)?]
+    };
+
+    let simulator = TokenomicsSimulator::new(
+        genesis_data,
+        u64_arg(&matches, "step_secs")?,
+        u64_arg(&matches, "tx_fee")?,
+    )?;
+    let report = simulator.run(&steps)?;
+
+    let mut output: Box<dyn Write> = match matches.value_of("output") {
+        Some(file) => Box::new(File::create(file)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    match matches.value_of("format") {
+        Some("json") => report.write_json(&mut output)?,
+        _ => {
+            report.write_csv(&mut output)?;
+            // phase switches are included in the json output
+            for switch in report.phase_switches.iter() {
+                eprintln!(
+                    "step {} (height {}): {} {} -> {}",
+                    switch.step, switch.height, switch.name, switch.from, switch.to
+                );
+            }
+        }
+    }
+
+    output.flush()?;
+    Ok(())
+}
@@ -9,17 +9,18 @@ extern crate clap;
 extern crate db;
 
 use base::logging_service::{InitLogger, LoggingService};
+use base::server_config_service::DEFAULT_CONFIG_FILE;
 use server::server_service::{ServerService, Startup};
 use tokio::signal;
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use db::db_service::DatabaseService;
 
 use xactor::*;
 
 // Start a client app - good for testability / integration testing
 pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
-    let _matches = App::new("KarmaCoin Server")
+    let matches = App::new("KarmaCoin Server")
         .version("0.1.0")
         .author("AE  <a@karmaco.in>")
         .about("The coin for all of us")
@@ -32,6 +33,19 @@ pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 .help("Sets a custom config file")
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks the ledger consistency of the local db and exits")
+                .arg(
+                    Arg::with_name("repair")
+                        .short("r")
+                        .long("repair")
+                        .help("Repairs inconsistent derived indexes"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("replay").about(
+            "Rebuilds the ledger state from the local db blocks and compares it with the local db",
+        ))
         .get_matches();
 
     // Start app logger
@@ -44,6 +58,27 @@ pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
         .await
         .unwrap();
 
+    let config_file = matches.value_of("config").unwrap_or(DEFAULT_CONFIG_FILE);
+
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        let report =
+            ServerService::check_ledger(config_file, check_matches.is_present("repair")).await?;
+        println!("{}", report);
+        if !report.is_consistent() {
+            return Err("ledger is inconsistent".into());
+        }
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("replay").is_some() {
+        let report = ServerService::replay_ledger(config_file).await?;
+        println!("{}", report);
+        if !report.is_consistent() {
+            return Err("replayed ledger state differs from the local db".into());
+        }
+        return Ok(());
+    }
+
     /*
     let config = ServerConfigService::from_registry().await.unwrap();
 
@@ -9,6 +9,11 @@ extern crate core;
 extern crate db;
 
 // used by server-app to start the server
+pub use services::blockchain::ledger_checker::LedgerCheckReport;
+pub use services::blockchain::ledger_replay::LedgerReplayReport;
 pub use services::blockchain::tokenomics::Tokenomics;
+pub use services::blockchain::tokenomics_simulator::{
+    PhaseSwitch, SimulationRecord, SimulationReport, StepActivity, TokenomicsSimulator,
+};
 pub use services::server_service;
 mod services;
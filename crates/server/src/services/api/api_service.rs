@@ -4,7 +4,10 @@
 
 use crate::services::blockchain::block_event::GetBlocksEvents;
 use crate::services::blockchain::blockchain_service::BlockChainService;
-use crate::services::blockchain::blocks_store::GetBlocks;
+use crate::services::blockchain::blocks_store::{GetBlocks, VerifyChain, MAX_VERIFY_CHAIN_BLOCKS};
+use crate::services::blockchain::event_subscribers::{
+    start_subscriber, AccountEventsSubscriber, BlockEventsSubscriber, SUBSCRIBER_BUFFER_SIZE,
+};
 use crate::services::blockchain::get_all_users::GetAllUsers;
 use crate::services::blockchain::get_contacts::GetContacts;
 use crate::services::blockchain::get_leader_board::GetLeaderBoard;
@@ -12,18 +15,23 @@ use crate::services::blockchain::get_user_by_account_id::GetUserInfoByAccountId;
 use crate::services::blockchain::get_user_by_number::GetUserInfoByNumber;
 use crate::services::blockchain::get_user_by_user_name::GetUserInfoByUserName;
 use crate::services::blockchain::mem_pool_service::{AddTransaction, MemPoolService};
+use crate::services::blockchain::proofs::{GetAccountProof, GetTransactionProof};
 use crate::services::blockchain::set_community_admin::SetCommunityAdmin;
 use crate::services::blockchain::stats::GetStats;
 use crate::services::blockchain::tx_event::GetTransactionEvents;
-use crate::services::blockchain::txs_processor::ProcessTransactions;
+use crate::services::blockchain::tx_status::{wait_for_transaction, MAX_WAIT_FOR_TX_TIMEOUT_MS};
 use crate::services::blockchain::txs_store::{
-    GetTransactionByHash, GetTransactionsAndEventsByAccountId,
+    GetIncomingTransactions, GetTransactionByHash, GetTransactionsAndEventsByAccountId,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use base::genesis_config_service::{GenesisConfigService, GetGenesisData};
 use base::karma_coin::karma_coin_api::api_service_server::ApiService as ApiServiceTrait;
 use base::karma_coin::karma_coin_api::*;
+use base::karma_coin::karma_coin_core_types::AccountId;
 use bytes::Bytes;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use xactor::*;
 
@@ -222,6 +230,18 @@ impl ApiServiceTrait for ApiService {
         Ok(Response::new(resp))
     }
 
+    /// Returns all executed genesis params updates ordered by their target height
+    async fn get_params_updates(
+        &self,
+        _request: Request<GetParamsUpdatesRequest>,
+    ) -> Result<Response<GetParamsUpdatesResponse>, Status> {
+        let params_updates = GenesisConfigService::get_params_updates()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        Ok(Response::new(GetParamsUpdatesResponse { params_updates }))
+    }
+
     /// Submit a transaction for processing to the mem pool
     async fn submit_transaction(
         &self,
@@ -239,74 +259,100 @@ impl ApiServiceTrait for ApiService {
         })?;
 
         info!("adding tx to mempool...");
-        mem_pool
-            .call(AddTransaction(tx))
-            .await
-            .map_err(|e| {
-                info!("internal error: {}", e);
-                Status::internal(format!("internal error: {}", e))
-            })?
-            .map_err(|e| {
-                info!("internal error: {}", e);
-                Status::internal(format!("failed to add tx to pool: {}", e))
-            })?;
-
-        info!("tx added to mempool");
-
-        // start transaction processing to process all transactions in the mem pool
-        // in production this can be done on a timer every few seconds
-        // here we just trigger block production when a new transaction is submitted
-        let service = BlockChainService::from_registry().await.map_err(|e| {
+        let res = mem_pool.call(AddTransaction(tx)).await.map_err(|e| {
             info!("internal error: {}", e);
             Status::internal(format!("internal error: {}", e))
         })?;
 
-        info!("processing transactions...");
-        service
-            .call(ProcessTransactions {})
-            .await
-            .map_err(|e| {
+        if let Err(e) = res {
+            if e.reason == SubmitTransactionRejectReason::Unspecified {
                 info!("internal error: {}", e);
-                Status::internal(format!("internal error: {}", e))
-            })?
-            .map_err(|e| {
-                info!("internal error: {}", e);
-                Status::internal(format!("failed to call blockchain api: {}", e))
-            })?;
+                return Err(Status::internal(format!(
+                    "failed to add tx to pool: {}",
+                    e.error_message
+                )));
+            }
+
+            info!("tx rejected: {}", e);
+            return Ok(Response::new(SubmitTransactionResponse {
+                submit_transaction_result: SubmitTransactionResult::Rejected as i32,
+                reject_reason: e.reason as i32,
+                reject_message: e.error_message,
+            }));
+        }
 
+        info!("tx added to mempool");
+
+        // the tx is executed in a block by the block production timer
         info!("submit transaction returning response");
 
         Ok(Response::new(SubmitTransactionResponse {
             submit_transaction_result: SubmitTransactionResult::Submitted as i32,
+            reject_reason: SubmitTransactionRejectReason::Unspecified as i32,
+            reject_message: String::new(),
         }))
     }
 
-    /// Returns all transactions to, and or from an account
+    /// Returns a page of an account's transactions to, and or from it, newest first
     async fn get_transactions(
         &self,
         request: Request<GetTransactionsRequest>,
     ) -> Result<Response<GetTransactionsResponse>, Status> {
+        let request = request.into_inner();
         let account_id = request
-            .into_inner()
             .account_id
             .ok_or_else(|| Status::invalid_argument("account id is required"))?;
 
+        let cursor = parse_cursor(request.cursor, &account_id)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
         let service = BlockChainService::from_registry()
             .await
             .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
 
-        let (txs, events) = service
+        let resp = service
             .call(GetTransactionsAndEventsByAccountId {
                 account_id: Bytes::from(account_id.data),
+                limit: request.limit,
+                cursor,
+                filter: request.filter.unwrap_or_default(),
             })
             .await
             .map_err(|e| Status::internal(format!("internal error: {}", e)))?
             .map_err(|e| Status::internal(format!("failed to call blockchain api: {}", e)))?;
 
-        Ok(Response::new(GetTransactionsResponse {
-            transactions: txs,
-            tx_events: Some(events),
-        }))
+        Ok(Response::new(resp))
+    }
+
+    /// Returns a page of an account's incoming transactions, newest first, and pending
+    /// payments to the account
+    async fn get_incoming_transactions(
+        &self,
+        request: Request<GetIncomingTransactionsRequest>,
+    ) -> Result<Response<GetIncomingTransactionsResponse>, Status> {
+        let request = request.into_inner();
+        let account_id = request
+            .account_id
+            .ok_or_else(|| Status::invalid_argument("account id is required"))?;
+
+        let cursor = parse_cursor(request.cursor, &account_id)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let service = BlockChainService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        let resp = service
+            .call(GetIncomingTransactions {
+                account_id: Bytes::from(account_id.data),
+                limit: request.limit,
+                cursor,
+            })
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?
+            .map_err(|e| Status::internal(format!("failed to call blockchain api: {}", e)))?;
+
+        Ok(Response::new(resp))
     }
 
     /// Return transaction by its hash as well as stats (rejected, on-chain, mempool) and
@@ -343,6 +389,22 @@ impl ApiServiceTrait for ApiService {
         }))
     }
 
+    /// Wait until a submitted transaction is on chain or rejected, or until a timeout, and
+    /// return it with its status and events
+    async fn wait_for_transaction(
+        &self,
+        request: Request<WaitForTransactionRequest>,
+    ) -> Result<Response<WaitForTransactionResponse>, Status> {
+        let req = request.into_inner();
+        let timeout = Duration::from_millis(req.timeout_ms.min(MAX_WAIT_FOR_TX_TIMEOUT_MS));
+
+        let resp = wait_for_transaction(Bytes::from(req.tx_hash), timeout)
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        Ok(Response::new(resp))
+    }
+
     /// Returns blockchain events from a block height to a block height inclusive
     async fn get_blockchain_events(
         &self,
@@ -399,4 +461,146 @@ impl ApiServiceTrait for ApiService {
 
         Ok(Response::new(GetBlocksResponse { blocks }))
     }
+
+    /// Returns an inclusion proof of an on-chain transaction in its block transactions root
+    async fn get_transaction_proof(
+        &self,
+        request: Request<GetTransactionProofRequest>,
+    ) -> Result<Response<GetTransactionProofResponse>, Status> {
+        let service = BlockChainService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        let tx_hash = Bytes::from(request.into_inner().tx_hash);
+
+        let resp = service
+            .call(GetTransactionProof { tx_hash })
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?
+            .map_err(|e| Status::not_found(format!("failed to get proof: {}", e)))?;
+
+        Ok(Response::new(resp))
+    }
+
+    /// Returns an inclusion proof of an account in the state root of the current tip block
+    async fn get_account_proof(
+        &self,
+        request: Request<GetAccountProofRequest>,
+    ) -> Result<Response<GetAccountProofResponse>, Status> {
+        let service = BlockChainService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        let account_id = request
+            .into_inner()
+            .account_id
+            .ok_or_else(|| Status::invalid_argument("missing account id"))?;
+
+        let resp = service
+            .call(GetAccountProof { account_id })
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?
+            .map_err(|e| Status::failed_precondition(format!("failed to get proof: {}", e)))?;
+
+        Ok(Response::new(resp))
+    }
+
+    /// Verifies the stored blocks in a range of heights inclusive.
+    /// This is a public api - it only reads blocks and is limited to MAX_VERIFY_CHAIN_BLOCKS
+    /// blocks per request. Heights beyond the chain tip are not verified.
+    async fn verify_chain(
+        &self,
+        request: Request<VerifyChainRequest>,
+    ) -> Result<Response<VerifyChainResponse>, Status> {
+        let service = BlockChainService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        let req = request.into_inner();
+
+        if req.from > req.to {
+            return Err(Status::invalid_argument(
+                "from block height must be less than or equal to to block height",
+            ));
+        }
+
+        if req.to - req.from >= MAX_VERIFY_CHAIN_BLOCKS {
+            return Err(Status::invalid_argument(format!(
+                "at most {} blocks can be verified per request",
+                MAX_VERIFY_CHAIN_BLOCKS
+            )));
+        }
+
+        let resp = service
+            .call(VerifyChain {
+                from_height: req.from,
+                to_height: req.to,
+            })
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {}", e)))?
+            .map_err(|e| Status::internal(format!("failed to call blockchain api: {}", e)))?;
+
+        Ok(Response::new(resp))
+    }
+
+    type SubscribeBlockEventsStream = ReceiverStream<Result<SubscribeBlockEventsResponse, Status>>;
+
+    /// Streams the event of every new block once the block is committed
+    async fn subscribe_block_events(
+        &self,
+        _request: Request<SubscribeBlockEventsRequest>,
+    ) -> Result<Response<Self::SubscribeBlockEventsStream>, Status> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+
+        start_subscriber(
+            BlockEventsSubscriber {
+                sender: sender.clone(),
+            },
+            sender,
+        )
+        .await
+        .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    type SubscribeAccountEventsStream =
+        ReceiverStream<Result<SubscribeAccountEventsResponse, Status>>;
+
+    /// Streams the events of new transactions signed by or paying to an account
+    async fn subscribe_account_events(
+        &self,
+        request: Request<SubscribeAccountEventsRequest>,
+    ) -> Result<Response<Self::SubscribeAccountEventsStream>, Status> {
+        let account_id = request
+            .into_inner()
+            .account_id
+            .ok_or_else(|| Status::invalid_argument("missing account id"))?;
+
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+
+        start_subscriber(
+            AccountEventsSubscriber {
+                account_id,
+                sender: sender.clone(),
+            },
+            sender,
+        )
+        .await
+        .map_err(|e| Status::internal(format!("internal error: {}", e)))?;
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Helper function - returns a transactions page cursor of an account or an error when the
+/// cursor is not a cursor of the account's transactions
+fn parse_cursor(cursor: Vec<u8>, account_id: &AccountId) -> Result<Option<Bytes>> {
+    if cursor.is_empty() {
+        Ok(None)
+    } else if cursor.starts_with(&account_id.data) {
+        Ok(Some(Bytes::from(cursor)))
+    } else {
+        Err(anyhow!("invalid cursor"))
+    }
 }
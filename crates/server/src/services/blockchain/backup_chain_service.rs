@@ -4,7 +4,7 @@
 
 use crate::services::blockchain::backup_chain_task::BackupChain;
 use anyhow::Result;
-use base::genesis_config_service::{GenesisConfigService, BACKUP_CHAIN_TASK_PERIOD_MINUTES};
+use base::genesis_config_service::GenesisConfigService;
 use tokio::spawn;
 use tokio_schedule::{every, Job};
 use xactor::*;
@@ -21,9 +21,11 @@ impl Actor for BackupChainService {
     async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
         info!("started. Registering periodic chain backup task...");
 
-        let task_period_min = GenesisConfigService::get_u64(BACKUP_CHAIN_TASK_PERIOD_MINUTES.into())
-            .await?
-            .unwrap() as u32;
+        let task_period_min = u32::try_from(
+            GenesisConfigService::get_genesis_config()
+                .await?
+                .backup_chain_task_period_minutes,
+        )?;
 
         let task = every(task_period_min).minutes().perform(|| async {
             let service = BackupChainService::from_registry().await;
@@ -0,0 +1,98 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::Result;
+use bytes::Bytes;
+use chrono::Utc;
+use db::db_service::{BatchOp, DatabaseService, DeleteItem, ReadItem, WriteBatch, WriteItem};
+use std::collections::HashMap;
+
+/// All state changes made while producing a block.
+/// Changes are only written to the db when the batch is committed, in one atomic db write,
+/// so a block is either fully applied to the ledger or not at all.
+/// Reads via the batch return pending changes so txs in a block observe the changes made by
+/// previous txs in the same block.
+#[derive(Default)]
+pub(crate) struct BlockBatch {
+    batch: WriteBatch,
+    /// Pending values by (column family, key). None for a pending delete.
+    pending: HashMap<(&'static str, Bytes), Option<(Bytes, u64)>>,
+}
+
+impl BlockBatch {
+    /// Read an item by key from a column family, including pending changes
+    pub(crate) async fn read(&self, item: ReadItem) -> Result<Option<(Bytes, u64)>> {
+        if let Some(value) = self.pending.get(&(item.cf, item.key.clone())) {
+            return Ok(value.clone());
+        }
+
+        DatabaseService::read(item).await
+    }
+
+    /// Add a write to the batch
+    pub(crate) fn write(&mut self, item: WriteItem) {
+        self.set_pending(&item);
+        self.batch.write(item);
+    }
+
+    /// Add a delete to the batch
+    pub(crate) fn delete(&mut self, item: DeleteItem) {
+        self.pending.insert((item.cf, item.key.clone()), None);
+        self.batch.delete(item);
+    }
+
+    /// Returns all pending changes of a column family by key. None for a pending delete.
+    pub(crate) fn pending_items(&self, cf: &str) -> Vec<(Bytes, Option<Bytes>)> {
+        self.pending
+            .iter()
+            .filter(|((item_cf, _), _)| *item_cf == cf)
+            .map(|((_, key), value)| (key.clone(), value.as_ref().map(|v| v.0.clone())))
+            .collect()
+    }
+
+    /// Returns a save point which the batch can be rolled back to
+    pub(crate) fn save_point(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// Discard all changes made after the provided save point.
+    /// Used to drop the partial changes of a transaction which failed processing.
+    pub(crate) fn rollback_to(&mut self, save_point: usize) {
+        if save_point >= self.batch.len() {
+            return;
+        }
+
+        self.batch.ops.truncate(save_point);
+        self.pending.clear();
+
+        let ops = std::mem::take(&mut self.batch.ops);
+        for op in ops.iter() {
+            match op {
+                BatchOp::Write(item) => self.set_pending(item),
+                BatchOp::Delete(item) => {
+                    self.pending.insert((item.cf, item.key.clone()), None);
+                }
+            }
+        }
+        self.batch.ops = ops;
+    }
+
+    /// Atomically write all changes in this batch to the db
+    pub(crate) async fn commit(self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        info!("committing {} db changes", self.batch.len());
+        DatabaseService::write_batch(self.batch).await
+    }
+
+    fn set_pending(&mut self, item: &WriteItem) {
+        let ttl = Utc::now().timestamp() as u64 + item.ttl;
+        self.pending.insert(
+            (item.cf, item.data.key.clone()),
+            Some((item.data.value.clone(), ttl)),
+        );
+    }
+}
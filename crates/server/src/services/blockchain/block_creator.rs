@@ -2,7 +2,9 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::proofs::update_state_root;
 use crate::services::blockchain::tokenomics::Tokenomics;
 use crate::services::db_config_service::{
     BLOCKS_COL_FAMILY, USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY,
@@ -11,10 +13,11 @@ use anyhow::{anyhow, Result};
 use base::genesis_config_service::ONE_KC_IN_KCENTS;
 use base::hex_utils::short_hex_string;
 use base::karma_coin::karma_coin_core_types::*;
+use base::merkle::merkle_root;
 use base::server_config_service::{ServerConfigService, BLOCK_PRODUCER_USER_NAME};
 use base::signed_trait::SignedTrait;
+use base::supply::transfer;
 use bytes::Bytes;
-use chrono::Utc;
 use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
 use db::types::IntDbKey;
 use prost::Message;
@@ -24,9 +27,21 @@ impl BlockChainService {
     /// apply one-time patch on startup
     pub(crate) async fn _apply_patch(&self) -> Result<()> {
         info!("applying patch...");
+        let mut batch = BlockBatch::default();
+
         // get block producer user
         let mut block_producer: User = self
-            .get_block_producer_user_account(self.id_key_pair.as_ref().unwrap())
+            .get_block_producer_user_account(
+                &self
+                    .id_key_pair
+                    .as_ref()
+                    .unwrap()
+                    .public_key
+                    .as_ref()
+                    .unwrap()
+                    .key,
+                &batch,
+            )
             .await?;
 
         // spending account id (newdeal, +972549805384
@@ -35,11 +50,12 @@ impl BlockChainService {
             204, 80, 179, 111, 53, 25, 101, 50, 89, 140, 26, 197, 35,
         ];
 
-        let mut spending_user = match DatabaseService::read(ReadItem {
-            key: Bytes::from(spending_account_id.clone()),
-            cf: USERS_COL_FAMILY,
-        })
-        .await?
+        let mut spending_user = match batch
+            .read(ReadItem {
+                key: Bytes::from(spending_account_id.clone()),
+                cf: USERS_COL_FAMILY,
+            })
+            .await?
         {
             Some(data) => User::decode(data.0.as_ref())?,
             None => {
@@ -51,55 +67,55 @@ impl BlockChainService {
 
         info!("Transferring {} to {}...", amount, spending_user.user_name);
 
-        if block_producer.balance < amount {
-            return Err(anyhow!("insufficient producer balance"));
-        }
-
         // update balances
-
-        spending_user.balance += amount;
-        block_producer.balance -= amount;
+        transfer(&mut block_producer, &mut spending_user, amount)
+            .map_err(|e| anyhow!("insufficient producer balance: {}", e))?;
 
         // store users in db
 
         let mut buf = Vec::with_capacity(spending_user.encoded_len());
         spending_user.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(spending_account_id),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         let mut buf = Vec::with_capacity(block_producer.encoded_len());
         block_producer.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(block_producer.account_id.as_ref().unwrap().data.to_vec()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
+
+        batch.commit().await?;
 
         info!("Transferred {} to {}...", amount, spending_user.user_name);
 
         Ok(())
     }
 
-    /// Returns this block producer on-chain user account.
-    /// Attempts to create one if it doesn't exist using config data (account id and nickname)
-    async fn get_block_producer_user_account(&self, key_pair: &KeyPair) -> Result<User> {
+    /// Returns a block producer on-chain user account.
+    /// Attempts to create one if it doesn't exist using config data (nickname)
+    async fn get_block_producer_user_account(
+        &self,
+        account_id: &[u8],
+        batch: &BlockBatch,
+    ) -> Result<User> {
         // Get User from chain and reject tx if user doesn't exist
-        let block_producer = match DatabaseService::read(ReadItem {
-            key: Bytes::from(key_pair.public_key.as_ref().unwrap().key.clone()),
-            cf: USERS_COL_FAMILY,
-        })
-        .await?
+        let block_producer = match batch
+            .read(ReadItem {
+                key: Bytes::from(account_id.to_vec()),
+                cf: USERS_COL_FAMILY,
+            })
+            .await?
         {
             Some(data) => User::decode(data.0.as_ref())?,
             None => {
@@ -108,14 +124,15 @@ impl BlockChainService {
                 let user_name = ServerConfigService::get(BLOCK_PRODUCER_USER_NAME.into())
                     .await?
                     .unwrap();
-                let account_id = key_pair.public_key.as_ref().unwrap().key.clone();
+                let account_id = account_id.to_vec();
                 // verify the the requested user-name does not belong to a new user over
                 // todo: figure out block producers name stuff - maybe just use account id?
-                if (DatabaseService::read(ReadItem {
-                    key: Bytes::from(user_name.as_bytes().to_vec()),
-                    cf: USERS_NAMES_COL_FAMILY,
-                })
-                .await?)
+                if (batch
+                    .read(ReadItem {
+                        key: Bytes::from(user_name.as_bytes().to_vec()),
+                        cf: USERS_NAMES_COL_FAMILY,
+                    })
+                    .await?)
                     .is_some()
                 {
                     return Err(anyhow::anyhow!(
@@ -136,11 +153,13 @@ impl BlockChainService {
                     nonce: 0,
                     user_name,
                     mobile_number: None, // block producer account starts w/o a verified mobile number
-                    balance: 0,
+                    mobile_number_hash: vec![],
+                    legacy_balance: 0,
                     trait_scores: vec![],
                     pre_keys: vec![],
                     karma_score: 1,
                     community_memberships: vec![],
+                    balance: Some(Balance::default()),
                 }
             }
         };
@@ -148,22 +167,27 @@ impl BlockChainService {
         Ok(block_producer)
     }
 
-    /// Create a block with the provided txs hashes at a given height
+    /// Create a block with the provided txs hashes at a given height and time.
+    /// All block state changes are added to the provided batch which the caller commits.
     /// Internal help method
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn create_block(
         &self,
         transactions_hashes: &[Vec<u8>],
+        time: u64,
         stats: BlockchainStats,
         tokenomics: &Tokenomics,
-        mut block_event: BlockEvent,
+        block_event: BlockEvent,
+        karma_rewards: Option<KarmaRewards>,
         height: u64,
         key_pair: &KeyPair,
+        batch: &mut BlockBatch,
     ) -> Result<Block> {
-        let mut block_producer = self.get_block_producer_user_account(key_pair).await?;
-
         let mut block = Block {
-            time: Utc::now().timestamp_millis() as u64,
-            author: Some(block_producer.account_id.as_ref().unwrap().clone()),
+            time,
+            author: Some(AccountId {
+                data: key_pair.public_key.as_ref().unwrap().key.clone(),
+            }),
             height,
             transactions_hashes: transactions_hashes.to_vec(),
             fees: block_event.fees_amount,
@@ -172,14 +196,20 @@ impl BlockChainService {
             signature: None,
             prev_block_digest: vec![],
             digest: vec![],
+            transactions_root: vec![],
+            state_root: vec![],
+            genesis_hash: tokenomics.genesis_data.genesis_hash.clone(),
+            karma_rewards,
         };
 
         // Set previous block hash to the hash of the previous block unless genesis block
         if height != 1 {
             let Some(prev_block_data) = DatabaseService::read(ReadItem {
-                key: IntDbKey::from(height -1).0,
-                cf: BLOCKS_COL_FAMILY
-            }).await? else {
+                key: IntDbKey::from(height - 1).0,
+                cf: BLOCKS_COL_FAMILY,
+            })
+            .await?
+            else {
                 return Err(anyhow::anyhow!("Failed to read previous block"));
             };
 
@@ -190,56 +220,84 @@ impl BlockChainService {
         };
 
         // set block reward
-        block.reward = tokenomics.get_block_reward_amount(height).await?;
+        block.reward = tokenomics.get_block_reward_amount(height);
         info!("block reward: {}", block.reward);
 
+        // the state root commits to the state after the block producer was rewarded
+        self.reward_block_producer(&block, batch).await?;
+        block.transactions_root = merkle_root(transactions_hashes)?;
+        block.state_root = update_state_root(batch).await?;
+
         // sign the block
         block.signature = Some(block.sign(&key_pair.to_ed2559_keypair())?);
 
         // compute block hash (including the signature) and set it
         block.digest = block.get_hash()?.to_vec();
 
+        self.apply_block(&block, stats, block_event, batch).await?;
+
+        Ok(block)
+    }
+
+    /// Credit the block author with the block fees and reward in the provided batch
+    pub(crate) async fn reward_block_producer(
+        &self,
+        block: &Block,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
+        let mut block_producer = self
+            .get_block_producer_user_account(&block.author.as_ref().unwrap().data, batch)
+            .await?;
+
+        block_producer.deposit(block.fees)?;
+        block_producer.deposit(block.reward)?;
+
+        let mut buf = Vec::with_capacity(block_producer.encoded_len());
+        block_producer.encode(&mut buf)?;
+        batch.write(WriteItem {
+            data: DataItem {
+                key: Bytes::from(block_producer.account_id.as_ref().unwrap().data.to_vec()),
+                value: Bytes::from(buf),
+            },
+            cf: USERS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Add a block, its event and the updated blockchain stats to the provided batch.
+    /// Used for new blocks and for blocks which are replayed, after the block producer was
+    /// rewarded.
+    pub(crate) async fn apply_block(
+        &self,
+        block: &Block,
+        stats: BlockchainStats,
+        mut block_event: BlockEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
         // insert the block to the db
         let mut buf = Vec::with_capacity(block.encoded_len());
         info!("binary block size: {}", block.encoded_len());
         block.encode(&mut buf)?;
 
         // Write the block to the db
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
-                key: IntDbKey::from(height).0,
+                key: IntDbKey::from(block.height).0,
                 value: Bytes::from(buf),
             },
             cf: BLOCKS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         // Update and persist block event
         block_event.block_hash = block.digest.clone();
         block_event.reward = block.reward;
-        self.emit_block_event(&block_event).await?;
-
-        // Update block producer balance with block reward and with fees and persist
-
-        block_producer.balance += block_event.fees_amount + block.reward;
-
-        let mut buf = Vec::with_capacity(block_producer.encoded_len());
-        block_producer.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
-            data: DataItem {
-                key: Bytes::from(block_producer.account_id.as_ref().unwrap().data.to_vec()),
-                value: Bytes::from(buf),
-            },
-            cf: USERS_COL_FAMILY,
-            ttl: 0,
-        })
-        .await?;
+        self.emit_block_event(&block_event, batch)?;
 
         // Update blockchain global stats and persist
-        self.update_blockchain_stats(stats, &block_event, &block)
-            .await?;
-
-        Ok(block)
+        self.update_blockchain_stats(stats, &block_event, block, batch)
+            .await
     }
 }
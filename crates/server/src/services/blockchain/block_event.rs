@@ -2,6 +2,7 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::db_config_service::BLOCK_EVENTS_COL_FAMILY;
 use anyhow::Result;
@@ -61,18 +62,21 @@ impl BlockChainService {
     }
 
     /// emit a new block event
-    pub(crate) async fn emit_block_event(&self, event: &BlockEvent) -> Result<()> {
+    pub(crate) fn emit_block_event(
+        &self,
+        event: &BlockEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
         let mut buf = Vec::with_capacity(event.encoded_len());
         event.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: IntDbKey::from(event.height).0,
                 value: Bytes::from(buf),
             },
             cf: BLOCK_EVENTS_COL_FAMILY,
             ttl: 0, // todo: set ttl based on this node being archive or full
-        })
-        .await?;
+        });
 
         info!("Block event emitted: {}", event);
 
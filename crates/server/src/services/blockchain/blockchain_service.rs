@@ -2,11 +2,26 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::stats::{get_stats, stats_write_item};
+use crate::services::blockchain::txs_processor::ProcessTransactions;
+use crate::services::db_config_service::{
+    MOBILE_NUMBERS_COL_FAMILY, STATE_TREE_COL_FAMILY, USERS_COL_FAMILY,
+};
 use anyhow::Result;
-use base::karma_coin::karma_coin_core_types::KeyPair;
+use base::karma_coin::karma_coin_core_types::{KeyPair, TransactionEvent, User};
+use base::mobile_number::{mobile_number_hash, MOBILE_NUMBER_HASH_LEN};
 use base::server_config_service::{
-    GetBlockProducerIdKeyPair, GetVerifierIdKeyPair, ServerConfigService,
+    GetBlockProducerIdKeyPair, ServerConfigService, BLOCK_PRODUCTION_INTERVAL_MS_CONFIG_KEY,
+    MAX_TXS_PER_BLOCK_CONFIG_KEY,
+};
+use base::sparse_merkle::{
+    node_storage_key, sparse_merkle_key, sparse_merkle_nodes, EMPTY_SUBTREE_HASH,
 };
+use base::supply::total_balances;
+use bytes::Bytes;
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, WriteBatch, WriteItem};
+use prost::Message;
+use std::time::Duration;
 use xactor::*;
 
 /// Blockchain service mocks a blockchain node
@@ -16,12 +31,23 @@ use xactor::*;
 pub(crate) struct BlockChainService {
     /// block producer id pair
     pub(crate) id_key_pair: Option<KeyPair>,
-    pub(crate) verifier_key_pair: Option<KeyPair>,
+    /// true while blocks are replayed to re-derive the ledger state.
+    /// Side effects of txs execution such as push notes are skipped when replaying.
+    pub(crate) replaying: bool,
+    /// max number of transactions executed in a block
+    pub(crate) max_txs_per_block: u64,
+    /// events of txs processed for the current block, published to subscribers once committed
+    pub(crate) unpublished_tx_events: Vec<TransactionEvent>,
+    /// true when the service only inspects the local db, e.g. for the server check command.
+    /// Offline services don't migrate the db and don't produce blocks
+    pub(crate) offline: bool,
+    /// true when a karma rewards round should be applied in the next block
+    pub(crate) karma_rewards_due: bool,
 }
 
 #[async_trait::async_trait]
 impl Actor for BlockChainService {
-    async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
+    async fn started(&mut self, ctx: &mut Context<Self>) -> Result<()> {
         info!("Started");
 
         // block producer id key pair
@@ -34,19 +60,202 @@ impl Actor for BlockChainService {
 
         // todo: set block producer unique name
 
-        // verifier key pair for validation
-        self.verifier_key_pair = Some(
-            ServerConfigService::from_registry()
-                .await?
-                .call(GetVerifierIdKeyPair)
-                .await??,
-        );
+        if self.offline {
+            self.load_params_updates().await?;
+            return Ok(());
+        }
 
         // apply patch
         // self.apply_patch().await?;
 
+        self.migrate_account_txs_index().await?;
+        self.migrate_users_balances().await?;
+        self.migrate_mobile_numbers_index().await?;
+        self.migrate_users_mobile_numbers().await?;
+        self.init_state_tree().await?;
+        self.init_circulation().await?;
+        self.load_params_updates().await?;
+
+        // produce blocks from the mem pool transactions on a fixed interval
+        self.max_txs_per_block = ServerConfigService::get_u64(MAX_TXS_PER_BLOCK_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        let block_interval_ms =
+            ServerConfigService::get_u64(BLOCK_PRODUCTION_INTERVAL_MS_CONFIG_KEY.into())
+                .await?
+                .unwrap();
+
+        info!(
+            "producing blocks every {} ms with up to {} txs",
+            block_interval_ms, self.max_txs_per_block
+        );
+        ctx.send_interval(
+            ProcessTransactions,
+            Duration::from_millis(block_interval_ms),
+        );
+
         Ok(())
     }
 }
 
 impl Service for BlockChainService {}
+
+impl BlockChainService {
+    /// A service which is not registered and doesn't modify the local db on start.
+    /// Start it with Actor::start() to inspect the ledger without producing blocks
+    pub(crate) fn offline() -> Self {
+        BlockChainService {
+            offline: true,
+            ..Default::default()
+        }
+    }
+
+    /// Move users balances stored as a single amount to their structured balance free funds
+    pub(crate) async fn migrate_users_balances(&self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for (account_id, value) in DatabaseService::read_all(USERS_COL_FAMILY).await? {
+            let mut user = User::decode(value.as_ref())?;
+            if !user.migrate_legacy_balance()? {
+                continue;
+            }
+
+            info!("migrated balance of user {}", user.user_name);
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: account_id,
+                    value: Bytes::from(user.encode_to_vec()),
+                },
+                cf: USERS_COL_FAMILY,
+                ttl: 0,
+            });
+        }
+
+        if !batch.is_empty() {
+            DatabaseService::write_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-key mobile numbers index entries which were indexed by the number to the number hash.
+    /// Numbers are shorter than their hash so entries keyed by a hash are left as is.
+    pub(crate) async fn migrate_mobile_numbers_index(&self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for (number, account_id) in DatabaseService::read_all(MOBILE_NUMBERS_COL_FAMILY).await? {
+            if number.len() == MOBILE_NUMBER_HASH_LEN {
+                continue;
+            }
+
+            let number_hash = mobile_number_hash(&String::from_utf8_lossy(&number))?;
+            batch.delete(DeleteItem {
+                key: number,
+                cf: MOBILE_NUMBERS_COL_FAMILY,
+            });
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: Bytes::from(number_hash),
+                    value: account_id,
+                },
+                cf: MOBILE_NUMBERS_COL_FAMILY,
+                ttl: 0,
+            });
+        }
+
+        if !batch.is_empty() {
+            info!("migrated {} mobile numbers index entries", batch.len() / 2);
+            DatabaseService::write_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove mobile numbers stored on users accounts and keep only their hash
+    pub(crate) async fn migrate_users_mobile_numbers(&self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for (account_id, value) in DatabaseService::read_all(USERS_COL_FAMILY).await? {
+            let mut user = User::decode(value.as_ref())?;
+            if user.mobile_number.is_none() {
+                continue;
+            }
+
+            if let Some(number_hash) = user.get_mobile_number_hash()? {
+                user.mobile_number_hash = number_hash;
+            }
+            user.mobile_number = None;
+
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: account_id,
+                    value: Bytes::from(user.encode_to_vec()),
+                },
+                cf: USERS_COL_FAMILY,
+                ttl: 0,
+            });
+        }
+
+        if !batch.is_empty() {
+            info!("removed mobile numbers of {} users", batch.len());
+            DatabaseService::write_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Build the accounts state tree from all users accounts of a chain created before the tree
+    /// was introduced. Blocks update the tree from the accounts they change.
+    pub(crate) async fn init_state_tree(&self) -> Result<()> {
+        let root_item = ReadItem {
+            key: Bytes::from(node_storage_key(0, &EMPTY_SUBTREE_HASH)),
+            cf: STATE_TREE_COL_FAMILY,
+        };
+        if DatabaseService::read(root_item).await?.is_some() {
+            return Ok(());
+        }
+
+        let mut leaves = vec![];
+        for (account_id, value) in DatabaseService::read_all(USERS_COL_FAMILY).await? {
+            leaves.push((sparse_merkle_key(&account_id)?, value.to_vec()));
+        }
+        if leaves.is_empty() {
+            return Ok(());
+        }
+
+        let (nodes, _) = sparse_merkle_nodes(&leaves)?;
+        let mut batch = WriteBatch::default();
+        for (key, node) in nodes {
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: Bytes::from(key),
+                    value: Bytes::from(node.to_bytes()),
+                },
+                cf: STATE_TREE_COL_FAMILY,
+                ttl: 0,
+            });
+        }
+
+        info!("built accounts state tree of {} users", leaves.len());
+        DatabaseService::write_batch(batch).await
+    }
+
+    /// Verify that the sum of all users balances equals the circulation. Blocks only verify
+    /// the balances of the accounts they change, so all balances are verified on startup and
+    /// the service fails to start when they differ.
+    /// The circulation of a chain created before supply accounting is set to the users balances.
+    pub(crate) async fn init_circulation(&self) -> Result<()> {
+        let mut users = vec![];
+        for (_, value) in DatabaseService::read_all(USERS_COL_FAMILY).await? {
+            users.push(User::decode(value.as_ref())?);
+        }
+        let users_balances = total_balances(users.iter())?;
+
+        let mut stats = get_stats().await?;
+        if stats.circulation != 0 || users_balances == 0 {
+            return stats.verify_circulation(users_balances).map_err(|e| {
+                error!("{}", e);
+                e
+            });
+        }
+
+        info!("setting circulation to users balances: {}", users_balances);
+        stats.circulation = users_balances;
+        DatabaseService::write(stats_write_item(&stats)?).await
+    }
+}
@@ -3,8 +3,11 @@
 //
 
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::stats::get_stats;
 use crate::services::db_config_service::BLOCKS_COL_FAMILY;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use base::block::verify_chain;
+use base::karma_coin::karma_coin_api::VerifyChainResponse;
 use base::karma_coin::karma_coin_core_types::*;
 use db::db_service::{DatabaseService, ReadItem};
 use db::types::IntDbKey;
@@ -31,8 +34,74 @@ impl Handler<GetBlocks> for BlockChainService {
     }
 }
 
+/// Max number of blocks verified by one chain verification request
+pub(crate) const MAX_VERIFY_CHAIN_BLOCKS: u64 = 1000;
+
+/// Verify the stored blocks in a range of heights inclusive.
+/// The range is clamped to the chain tip and to MAX_VERIFY_CHAIN_BLOCKS blocks.
+#[message(result = "Result<VerifyChainResponse>")]
+pub(crate) struct VerifyChain {
+    pub(crate) from_height: u64,
+    pub(crate) to_height: u64,
+}
+
+#[async_trait::async_trait]
+impl Handler<VerifyChain> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: VerifyChain,
+    ) -> Result<VerifyChainResponse> {
+        let from_height = msg.from_height.max(1);
+        let to_height = msg
+            .to_height
+            .min(get_stats().await?.tip_height)
+            .min(from_height.saturating_add(MAX_VERIFY_CHAIN_BLOCKS - 1));
+
+        if from_height > to_height {
+            // nothing to verify beyond the tip
+            return Ok(VerifyChainResponse {
+                blocks_verified: 0,
+                errors: vec![],
+            });
+        }
+
+        // the block before the range anchors the first block in the range
+        let prev_block = if from_height > 1 {
+            self.get_block_by_height(from_height - 1).await?
+        } else {
+            None
+        };
+
+        if from_height > 1 && prev_block.is_none() {
+            return Err(anyhow!("missing block at height {}", from_height - 1));
+        }
+
+        let mut blocks = vec![];
+        let mut errors = vec![];
+        for h in from_height..=to_height {
+            match self.get_block_by_height(h).await? {
+                Some(block) => blocks.push(block),
+                None => {
+                    // the chain can't be verified past a missing block
+                    errors.push(format!("missing block at height {}", h));
+                    break;
+                }
+            }
+        }
+
+        let mut chain_errors = verify_chain(prev_block.as_ref(), &blocks);
+        chain_errors.append(&mut errors);
+
+        Ok(VerifyChainResponse {
+            blocks_verified: blocks.len() as u64,
+            errors: chain_errors,
+        })
+    }
+}
+
 impl BlockChainService {
-    async fn get_block_by_height(&self, height: u64) -> Result<Option<Block>> {
+    pub(crate) async fn get_block_by_height(&self, height: u64) -> Result<Option<Block>> {
         if let Some(data) = DatabaseService::read(ReadItem {
             key: IntDbKey::from(height).0,
             cf: BLOCKS_COL_FAMILY,
@@ -2,22 +2,31 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::pending_payments::{read_user, write_user};
 use crate::services::db_config_service::{
-    LEADER_BOARD_COL_FAMILY, MOBILE_NUMBERS_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY,
-    USERS_NAMES_COL_FAMILY,
+    DELETED_USERS_NAMES_COL_FAMILY, LEADER_BOARD_COL_FAMILY, MOBILE_NUMBERS_COL_FAMILY,
+    TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::GenesisConfigService;
+use base::hex_utils::short_hex_string;
 use base::karma_coin::karma_coin_core_types::{
-    ExecutionInfo, ExecutionResult, FeeType, SignedTransaction, TransactionBody, TransactionEvent,
-    User,
+    AccountId, ExecutionInfo, ExecutionResult, FeeType, SignedTransaction, TransactionBody,
+    TransactionEvent, User,
 };
 use bytes::Bytes;
-use db::db_service::{DataItem, DatabaseService, DeleteItem, WriteItem};
+use db::db_service::{DataItem, DeleteItem, ReadItem, WriteItem};
 use prost::Message;
 
 #[derive(Debug, Clone)]
-pub(crate) struct DeleteUserProcessingResponse {}
+pub(crate) struct DeleteUserProcessingResponse {
+    /// the deleted account funds which were burned. 0 when they were redirected
+    pub(crate) burned_amount: u64,
+    /// tx events of the account's pending payments which were cancelled
+    pub(crate) cancelled_payments_events: Vec<TransactionEvent>,
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct DeleteUserProcessingError {
@@ -25,14 +34,62 @@ pub(crate) struct DeleteUserProcessingError {
     pub(crate) error_message: String,
 }
 
+/// Helper function - returns true iff an index entry refers to the provided account
+async fn is_indexed_to(
+    key: Bytes,
+    cf: &'static str,
+    account_id: &AccountId,
+    batch: &BlockBatch,
+) -> Result<bool> {
+    Ok(batch
+        .read(ReadItem { key, cf })
+        .await?
+        .is_some_and(|(value, _)| value.as_ref() == account_id.data.as_slice()))
+}
+
+/// Validate that a user name is not a deleted account's user name in its cooldown period at the
+/// provided tx time. Tx times are used so the cooldown is the same when blocks are replayed
+pub(crate) async fn validate_user_name_released(
+    user_name: &str,
+    timestamp: u64,
+    batch: &BlockBatch,
+) -> Result<()> {
+    let Some((value, _)) = batch
+        .read(ReadItem {
+            key: Bytes::from(user_name.as_bytes().to_vec()),
+            cf: DELETED_USERS_NAMES_COL_FAMILY,
+        })
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let released_at = u64::from_be_bytes(
+        value
+            .as_ref()
+            .try_into()
+            .map_err(|_| anyhow!("invalid deleted user name release time"))?,
+    );
+    if timestamp < released_at {
+        return Err(anyhow!(
+            "user name {} of a deleted account is released at time {}",
+            user_name,
+            released_at
+        ));
+    }
+    Ok(())
+}
+
 impl BlockChainService {
-    /// Process a new user transaction - update ledger state, emit tx event
+    /// Process a delete user transaction - delete the account and its indexes, redirect or burn
+    /// its balance per the genesis config and emit tx event.
     /// This method will not add the tx to a block and is used as part of block creation flow
     pub(crate) async fn process_delete_user_transaction(
         &mut self,
         signed_transaction: &SignedTransaction,
         user: &User,
         event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
     ) -> Result<DeleteUserProcessingResponse, DeleteUserProcessingError> {
         let account_id =
             signed_transaction
@@ -40,18 +97,17 @@ impl BlockChainService {
                 .as_ref()
                 .ok_or_else(|| DeleteUserProcessingError {
                     execution_info: ExecutionInfo::InvalidData,
-                    error_message: "Missing verification evidence".into(),
+                    error_message: "missing signer account id".into(),
                 })?;
 
         let tx_hash = signed_transaction
             .get_hash()
             .map_err(|_| DeleteUserProcessingError {
                 execution_info: ExecutionInfo::InvalidData,
-                error_message: "Missing verification evidence".into(),
+                error_message: "invalid tx hash".into(),
             })?;
 
         // validate tx syntax, fields, signature, net_id before processing it
-        // new user transaction should always have nonce of 0
         signed_transaction
             .validate()
             .await
@@ -70,7 +126,7 @@ impl BlockChainService {
 
         // Validate the Transaction object
         tx_body
-            .validate(0)
+            .validate(user.nonce)
             .await
             .map_err(|_| DeleteUserProcessingError {
                 execution_info: ExecutionInfo::InvalidData,
@@ -104,65 +160,127 @@ impl BlockChainService {
         //
         ////////////////////////////////////
 
-        // delete user account and update all indexes
-        DatabaseService::delete(DeleteItem {
+        let internal_error = |e: anyhow::Error| DeleteUserProcessingError {
+            execution_info: ExecutionInfo::InternalNodeError,
+            error_message: e.to_string(),
+        };
+
+        // the tx fee is paid from the deleted account's spendable funds
+        let mut user = user.clone();
+        user.withdraw(tx_body.fee)
+            .map_err(|_| DeleteUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: "tx fee is greater than user balance".into(),
+            })?;
+
+        // the deleted account funds are redirected to the genesis configured account, if it is
+        // on chain, and are burned otherwise. Reserved funds of pending payments are included
+        let genesis_data = GenesisConfigService::get_genesis_data_at(event.height)
+            .await
+            .map_err(internal_error)?;
+        let deleted_amount = user.total_balance().map_err(internal_error)?;
+        let mut recipient = match genesis_data.deleted_user_funds_account_id.as_ref() {
+            Some(recipient_id) if recipient_id != account_id => {
+                read_user(&recipient_id.data, batch)
+                    .await
+                    .map_err(internal_error)?
+            }
+            _ => None,
+        };
+
+        let mut burned_amount = deleted_amount;
+        if let Some(recipient) = recipient.as_mut() {
+            recipient.deposit(deleted_amount).map_err(internal_error)?;
+            write_user(recipient, batch);
+            burned_amount = 0;
+            event.redirected_amount = deleted_amount;
+            event.redirected_to = recipient.account_id.clone();
+        }
+
+        // the account's pending payments can't be claimed or refunded once it is deleted
+        let cancelled_payments_events = self
+            .cancel_pending_payments(
+                account_id,
+                recipient.as_ref().and_then(|r| r.account_id.as_ref()),
+                event.height,
+                batch,
+            )
+            .await
+            .map_err(internal_error)?;
+
+        // delete user account and update all indexes which refer to it
+        batch.delete(DeleteItem {
             key: Bytes::from(account_id.data.clone()),
             cf: USERS_COL_FAMILY,
-        })
-        .await
-        .map_err(|_| DeleteUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "Can't delete user from db".into(),
-        })?;
+        });
 
-        DatabaseService::delete(DeleteItem {
-            key: Bytes::from(user.user_name.as_bytes().to_vec()),
-            cf: USERS_NAMES_COL_FAMILY,
-        })
+        let user_name_key = Bytes::from(user.user_name.as_bytes().to_vec());
+        if is_indexed_to(
+            user_name_key.clone(),
+            USERS_NAMES_COL_FAMILY,
+            account_id,
+            batch,
+        )
         .await
-        .map_err(|_| DeleteUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "Can't delete user name from db".into(),
-        })?;
+        .map_err(internal_error)?
+        {
+            batch.delete(DeleteItem {
+                key: user_name_key.clone(),
+                cf: USERS_NAMES_COL_FAMILY,
+            });
 
-        if let Some(mobile_number) = user.mobile_number.as_ref() {
-            DatabaseService::delete(DeleteItem {
-                key: Bytes::from(mobile_number.number.as_bytes().to_vec()),
-                cf: MOBILE_NUMBERS_COL_FAMILY,
-            })
+            // the user name can't be registered by another account until its cooldown ends
+            let released_at = tx_body.timestamp + genesis_data.user_name_cooldown_seconds * 1000;
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: user_name_key,
+                    value: Bytes::from(released_at.to_be_bytes().to_vec()),
+                },
+                cf: DELETED_USERS_NAMES_COL_FAMILY,
+                ttl: 0,
+            });
+        }
+
+        // a migrated old account's number is indexed to its new account
+        if let Some(mobile_number_hash) = user.get_mobile_number_hash().map_err(internal_error)? {
+            let number_key = Bytes::from(mobile_number_hash);
+            if is_indexed_to(
+                number_key.clone(),
+                MOBILE_NUMBERS_COL_FAMILY,
+                account_id,
+                batch,
+            )
             .await
-            .map_err(|_| DeleteUserProcessingError {
-                execution_info: ExecutionInfo::InternalNodeError,
-                error_message: "cant delete user's number".into(),
-            })?;
+            .map_err(internal_error)?
+            {
+                batch.delete(DeleteItem {
+                    key: number_key,
+                    cf: MOBILE_NUMBERS_COL_FAMILY,
+                });
+            }
         }
 
-        // ignore result
-        let _res = DatabaseService::delete(DeleteItem {
+        batch.delete(DeleteItem {
             key: Bytes::from(account_id.data.clone()),
             cf: LEADER_BOARD_COL_FAMILY,
-        })
-        .await;
+        });
 
         // index the transaction in the db by hash
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: tx_hash.clone(),
                 value: Bytes::from(tx_data),
             },
             cf: TRANSACTIONS_COL_FAMILY,
             ttl: 0,
-        })
-        .await
-        .map_err(|_| DeleteUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "failed to write tx to chain".into(),
-        })?;
+        });
 
         // index the transaction in the db by signer account id
         self.index_transaction_by_account_id(
             signed_transaction,
             Bytes::from(account_id.data.to_vec()),
+            event.height,
+            batch,
         )
         .await
         .map_err(|_| DeleteUserProcessingError {
@@ -170,11 +288,37 @@ impl BlockChainService {
             error_message: "failed to index tx by account id".into(),
         })?;
 
-        // 1 kCent protocol subsidy
-        event.fee_type = FeeType::Mint as i32;
-        event.fee = 1;
+        // the recipient of the redirected funds can find the tx in its txs history
+        if let Some(recipient) = recipient.as_ref() {
+            self.index_transaction_by_account_id(
+                signed_transaction,
+                Bytes::from(recipient.account_id.as_ref().unwrap().data.to_vec()),
+                event.height,
+                batch,
+            )
+            .await
+            .map_err(|_| DeleteUserProcessingError {
+                execution_info: ExecutionInfo::InternalNodeError,
+                error_message: "failed to index tx by account id".into(),
+            })?;
+        }
+
+        event.fee_type = FeeType::User as i32;
+        event.fee = tx_body.fee;
         event.result = ExecutionResult::Executed as i32;
+        event.info = ExecutionInfo::AccountDeleted as i32;
+        event.burned_amount = burned_amount;
+
+        info!(
+            "deleted account {}. burned: {}, redirected: {}",
+            short_hex_string(&account_id.data),
+            burned_amount,
+            event.redirected_amount
+        );
 
-        Ok(DeleteUserProcessingResponse {})
+        Ok(DeleteUserProcessingResponse {
+            burned_amount,
+            cancelled_payments_events,
+        })
     }
 }
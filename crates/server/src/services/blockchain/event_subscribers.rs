@@ -0,0 +1,173 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::tx_status::publish_tx_status;
+use anyhow::Result;
+use base::karma_coin::karma_coin_api::{
+    SubscribeAccountEventsResponse, SubscribeBlockEventsResponse,
+};
+use base::karma_coin::karma_coin_core_types::TransactionStatus::OnChain;
+use base::karma_coin::karma_coin_core_types::TransactionType::PaymentV1;
+use base::karma_coin::karma_coin_core_types::{AccountId, Block, BlockEvent, TransactionEvent};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tonic::Status;
+use xactor::*;
+
+/// Max number of events buffered for a subscriber before it is considered too slow and dropped
+pub(crate) const SUBSCRIBER_BUFFER_SIZE: usize = 256;
+
+/// A block event published once its block was committed
+#[message]
+#[derive(Clone)]
+pub(crate) struct BlockEventPublished(pub(crate) BlockEvent);
+
+/// A transaction event published once it was committed, with the accounts the tx touches
+#[message]
+#[derive(Clone)]
+pub(crate) struct TransactionEventPublished {
+    pub(crate) event: TransactionEvent,
+    pub(crate) account_ids: Vec<Vec<u8>>,
+}
+
+/// Forwards published block events to a client's SubscribeBlockEvents stream.
+/// Stops when the client's stream is closed.
+pub(crate) struct BlockEventsSubscriber {
+    pub(crate) sender: mpsc::Sender<Result<SubscribeBlockEventsResponse, Status>>,
+}
+
+#[async_trait::async_trait]
+impl Actor for BlockEventsSubscriber {
+    async fn started(&mut self, ctx: &mut Context<Self>) -> Result<()> {
+        ctx.subscribe::<BlockEventPublished>().await
+    }
+
+    async fn stopped(&mut self, ctx: &mut Context<Self>) {
+        let _ = ctx.unsubscribe::<BlockEventPublished>().await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Handler<BlockEventPublished> for BlockEventsSubscriber {
+    async fn handle(&mut self, ctx: &mut Context<Self>, msg: BlockEventPublished) {
+        let resp = SubscribeBlockEventsResponse {
+            block_event: Some(msg.0),
+        };
+        forward(ctx, &self.sender, resp);
+    }
+}
+
+/// Forwards published transaction events which touch an account to a client's
+/// SubscribeAccountEvents stream. Stops when the client's stream is closed.
+pub(crate) struct AccountEventsSubscriber {
+    pub(crate) account_id: AccountId,
+    pub(crate) sender: mpsc::Sender<Result<SubscribeAccountEventsResponse, Status>>,
+}
+
+#[async_trait::async_trait]
+impl Actor for AccountEventsSubscriber {
+    async fn started(&mut self, ctx: &mut Context<Self>) -> Result<()> {
+        ctx.subscribe::<TransactionEventPublished>().await
+    }
+
+    async fn stopped(&mut self, ctx: &mut Context<Self>) {
+        let _ = ctx.unsubscribe::<TransactionEventPublished>().await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Handler<TransactionEventPublished> for AccountEventsSubscriber {
+    async fn handle(&mut self, ctx: &mut Context<Self>, msg: TransactionEventPublished) {
+        if !msg.account_ids.contains(&self.account_id.data) {
+            return;
+        }
+
+        let resp = SubscribeAccountEventsResponse {
+            transaction_event: Some(msg.event),
+        };
+        forward(ctx, &self.sender, resp);
+    }
+}
+
+/// Start a subscriber actor which runs until its client's events stream is closed
+pub(crate) async fn start_subscriber<A: Actor, T: Send + 'static>(
+    subscriber: A,
+    sender: mpsc::Sender<Result<T, Status>>,
+) -> Result<()> {
+    let mut addr = subscriber.start().await?;
+    tokio::spawn(async move {
+        sender.closed().await;
+        let _ = addr.stop(None);
+    });
+    Ok(())
+}
+
+/// Helper function - send an event to a subscriber's stream and stop the subscriber
+/// when the stream was closed by the client or when the client doesn't keep up
+fn forward<A: Actor, T>(ctx: &mut Context<A>, sender: &mpsc::Sender<Result<T, Status>>, item: T) {
+    match sender.try_send(Ok(item)) {
+        Ok(()) => {}
+        Err(TrySendError::Closed(_)) => ctx.stop(None),
+        Err(TrySendError::Full(_)) => {
+            info!("dropping events subscriber which doesn't keep up with events");
+            let _ = sender.try_send(Err(Status::resource_exhausted(
+                "subscriber is too slow to consume events",
+            )));
+            ctx.stop(None)
+        }
+    }
+}
+
+impl BlockChainService {
+    /// Publish the events of committed txs and of a committed block, and the on chain status
+    /// of the block's txs, to subscribers.
+    /// Must only be called after the batch with the events was committed.
+    pub(crate) async fn publish_events(&mut self, block: Option<&Block>) -> Result<()> {
+        let tx_events = std::mem::take(&mut self.unpublished_tx_events);
+        let mut broker = Broker::<TransactionEventPublished>::from_registry().await?;
+        for event in tx_events {
+            let account_ids = get_tx_event_accounts(&event).await?;
+            broker.publish(TransactionEventPublished { event, account_ids })?;
+        }
+
+        if let Some(block) = block {
+            for tx_hash in block.transactions_hashes.iter() {
+                publish_tx_status(tx_hash.clone(), OnChain).await?;
+            }
+
+            if let Some(block_event) = self.get_block_event_by_height(block.height).await? {
+                Broker::<BlockEventPublished>::from_registry()
+                    .await?
+                    .publish(BlockEventPublished(block_event))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Helper function - returns the accounts a tx event touches - the tx signer and a payment's payee
+async fn get_tx_event_accounts(event: &TransactionEvent) -> Result<Vec<Vec<u8>>> {
+    let Some(tx) = event.transaction.as_ref() else {
+        return Ok(vec![]);
+    };
+
+    let mut account_ids = vec![];
+    if let Some(signer) = tx.signer.as_ref() {
+        account_ids.push(signer.data.clone());
+    }
+
+    let tx_body = tx.get_body()?;
+    if tx_body.get_tx_type()? == PaymentV1 {
+        if let Some(payee) =
+            BlockChainService::get_payee_user_from_tx_body(&tx_body, &BlockBatch::default()).await?
+        {
+            account_ids.push(payee.account_id.unwrap().data);
+        }
+    }
+
+    Ok(account_ids)
+}
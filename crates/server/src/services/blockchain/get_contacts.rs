@@ -65,9 +65,10 @@ impl Handler<GetContacts> for BlockChainService {
             };
 
             if let Some(user) = user_data {
-                if user.mobile_number.is_none() {
+                // users who signed up with a hashed number are contacts by their number hash
+                let Some(mobile_number_hash) = user.get_mobile_number_hash()? else {
                     continue;
-                }
+                };
 
                 // if caller asked for only members of a community and user is not a member of it
                 // then skip this user
@@ -79,9 +80,10 @@ impl Handler<GetContacts> for BlockChainService {
                 contacts.push(Contact {
                     user_name: user.user_name.to_string(),
                     account_id: Some(user.account_id.unwrap()),
-                    mobile_number: Some(user.mobile_number.unwrap()),
+                    mobile_number: None,
                     community_memberships: user.community_memberships,
                     trait_scores: user.trait_scores,
+                    mobile_number_hash,
                 })
             }
         }
@@ -5,7 +5,7 @@
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::db_config_service::LEADER_BOARD_COL_FAMILY;
 use anyhow::Result;
-use base::genesis_config_service::{GenesisConfigService, KARMA_REWARDS_ELIGIBILITY};
+use base::genesis_config_service::GenesisConfigService;
 use base::karma_coin::karma_coin_api::{GetLeaderBoardRequest, GetLeaderBoardResponse};
 use base::karma_coin::karma_coin_core_types::LeaderboardEntry;
 use db::db_service::{DatabaseService, ReadAllItems};
@@ -23,9 +23,9 @@ impl Handler<GetLeaderBoard> for BlockChainService {
         _msg: GetLeaderBoard,
     ) -> Result<GetLeaderBoardResponse> {
         let min_appreciations = usize::try_from(
-            GenesisConfigService::get_u64(KARMA_REWARDS_ELIGIBILITY.into())
+            GenesisConfigService::get_genesis_config()
                 .await?
-                .ok_or_else(|| anyhow::anyhow!("Karma Reward Max Users not set"))?,
+                .karma_rewards_eligibility,
         )?;
 
         let mut leaderboard_entries = vec![];
@@ -26,17 +26,9 @@ impl Handler<GetUserInfoByNumber> for BlockChainService {
             return Err(anyhow!("missing phone number from request"));
         };
 
-        // lookup accountId by phone number
+        // lookup accountId by phone number hash
         match DatabaseService::read(ReadItem {
-            key: Bytes::from(
-                msg.0
-                    .mobile_number
-                    .as_ref()
-                    .unwrap()
-                    .number
-                    .as_bytes()
-                    .to_vec(),
-            ),
+            key: Bytes::from(msg.0.mobile_number.as_ref().unwrap().get_hash()?),
             cf: MOBILE_NUMBERS_COL_FAMILY,
         })
         .await?
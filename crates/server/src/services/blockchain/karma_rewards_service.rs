@@ -2,24 +2,19 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
-use crate::services::blockchain::stats::{get_stats, GetStats, WriteStats};
 use crate::services::db_config_service::{LEADER_BOARD_COL_FAMILY, USERS_COL_FAMILY};
 use crate::Tokenomics;
-use anyhow::Result;
-use base::genesis_config_service::{
-    GenesisConfigService, KARMA_REWARD_MAX_USERS_KEY, KARMA_REWARD_PERIOD_MINUTES,
-    KARMA_REWARD_TRAIT_ID,
-};
-use base::karma_coin::karma_coin_api::GetBlockchainDataRequest;
-use base::karma_coin::karma_coin_core_types::{LeaderboardEntry, User};
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::{GenesisConfigService, KARMA_REWARD_TRAIT_ID};
+use base::karma_coin::karma_coin_core_types::{AccountId, BlockEvent, KarmaRewards, User};
 use bytes::Bytes;
-use db::db_service::{
-    DataItem, DatabaseService, DeleteAllItems, ReadAllItems, ReadItem, WriteItem,
-};
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, WriteItem};
 use prost::Message;
 use rand::prelude::*;
 use rand_core::OsRng;
+use std::collections::BTreeMap;
 use tokio::spawn;
 use tokio_schedule::{every, Job};
 use xactor::*;
@@ -36,9 +31,11 @@ impl Actor for KarmaRewardsService {
     async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
         info!("started. Registering karma rewards periodic task...");
 
-        let task_period_minutes = GenesisConfigService::get_u64(KARMA_REWARD_PERIOD_MINUTES.into())
-            .await?
-            .unwrap() as u32;
+        let task_period_minutes = u32::try_from(
+            GenesisConfigService::get_genesis_config()
+                .await?
+                .karma_reward_period_minutes,
+        )?;
 
         let task = every(task_period_minutes).minutes().perform(|| async {
             let service = KarmaRewardsService::from_registry().await;
@@ -64,127 +61,157 @@ impl Actor for KarmaRewardsService {
 #[message(result = "Result<()>")]
 pub(crate) struct ProcessKarmaRewards;
 
-/// Assign Karma Rewards based on current leader board
+/// Start a karma rewards round. The round is applied by the blockchain service in its next
+/// block so the rewards are recorded on chain
 #[async_trait::async_trait]
 impl Handler<ProcessKarmaRewards> for KarmaRewardsService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: ProcessKarmaRewards) -> Result<()> {
         info!("processing karma rewards task...");
-        let stats = get_stats().await?;
-        let tokenomics = Tokenomics::new(stats.clone());
-        let reward_amount = tokenomics.get_karma_coin_reward_amount().await?;
+        BlockChainService::from_registry()
+            .await?
+            .call(ScheduleKarmaRewards)
+            .await
+    }
+}
+
+/// Apply a karma rewards round in the next block
+#[message]
+pub(crate) struct ScheduleKarmaRewards;
+
+#[async_trait::async_trait]
+impl Handler<ScheduleKarmaRewards> for BlockChainService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: ScheduleKarmaRewards) {
+        self.karma_rewards_due = true;
+    }
+}
+
+impl BlockChainService {
+    /// Select the winners of a due karma rewards round from the leader board.
+    /// Returns None when no round is due or when the leader board is empty.
+    pub(crate) async fn select_karma_rewards(
+        &mut self,
+        tokenomics: &Tokenomics,
+        batch: &BlockBatch,
+    ) -> Result<Option<KarmaRewards>> {
+        if !std::mem::take(&mut self.karma_rewards_due) {
+            return Ok(None);
+        }
+
+        let entries = read_leader_board(batch).await?;
+        if entries.is_empty() {
+            info!("no winners to process");
+            return Ok(None);
+        }
+
+        let reward_amount = tokenomics.get_karma_coin_reward_amount();
         if reward_amount == 0 {
             info!("karma rewards depleted");
-            return DatabaseService::delete_all(DeleteAllItems {
-                cf: LEADER_BOARD_COL_FAMILY,
-            })
-            .await;
+            return Ok(Some(KarmaRewards {
+                reward_amount,
+                winners: vec![],
+            }));
         }
-        let max_winners = usize::try_from(
-            GenesisConfigService::get_u64(KARMA_REWARD_MAX_USERS_KEY.into())
-                .await?
-                .ok_or_else(|| anyhow::anyhow!("Karma Reward Max Users not set"))?,
-        )?;
-
-        let data = DatabaseService::read_all_items(ReadAllItems {
-            from_key: None,
-            max_results: 0,
-            cf: LEADER_BOARD_COL_FAMILY,
-        })
-        .await?;
 
+        let max_winners = usize::try_from(tokenomics.genesis_data.karma_reward_top_n_users)?;
         let mut rng = OsRng;
-        let winners_data: Vec<_> = data.items.choose_multiple(&mut rng, max_winners).collect();
+        let selected: Vec<_> = entries.choose_multiple(&mut rng, max_winners).collect();
         info!(
             "Selected {} winners for karma rewards from {} total in leaderboard",
-            winners_data.len(),
-            data.items.len()
+            selected.len(),
+            entries.len()
         );
 
-        if !winners_data.is_empty() {
-            let mut total_reward_amount = 0;
-            for item in winners_data.iter() {
-                let entry = LeaderboardEntry::decode(item.1.value.as_ref())?;
-
-                match self.process_winner(&entry, reward_amount).await {
-                    Ok(amount) => total_reward_amount += amount,
-                    Err(e) => error!("Error processing karma reward winner: {}", e),
-                }
+        let mut winners = vec![];
+        for (account_id, _) in selected {
+            let Some(data) = batch
+                .read(ReadItem {
+                    key: account_id.clone(),
+                    cf: USERS_COL_FAMILY,
+                })
+                .await?
+            else {
+                error!("karma reward winner not found");
+                continue;
+            };
+            let user = User::decode(data.0.as_ref())?;
+
+            // users get one karma reward and migrated accounts are not rewarded
+            if !user.is_eligible_for_karma_reward() || user.user_name.ends_with("[old account]") {
+                continue;
             }
 
-            let service = BlockChainService::from_registry().await?;
-
-            let mut resp = service
-                .call(GetStats(GetBlockchainDataRequest {}))
-                .await??;
+            winners.push(AccountId {
+                data: account_id.to_vec(),
+            });
+        }
 
-            let mut stats = resp.stats.as_mut().unwrap();
-            stats.karma_rewards_amount += total_reward_amount;
+        Ok(Some(KarmaRewards {
+            reward_amount,
+            winners,
+        }))
+    }
 
-            service.call(WriteStats(stats.clone())).await??;
+    /// Apply a karma rewards round in a block: deposit the reward to each winner, give them
+    /// the karma reward trait and reset the leader board for the next round.
+    /// Used for new blocks and for blocks which are replayed.
+    pub(crate) async fn apply_karma_rewards(
+        &self,
+        karma_rewards: &KarmaRewards,
+        block_event: &mut BlockEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
+        for account_id in karma_rewards.winners.iter() {
+            let data = batch
+                .read(ReadItem {
+                    key: Bytes::from(account_id.data.clone()),
+                    cf: USERS_COL_FAMILY,
+                })
+                .await?
+                .ok_or_else(|| anyhow!("karma reward winner not found"))?;
+            let mut user = User::decode(data.0.as_ref())?;
+
+            user.inc_trait_score(KARMA_REWARD_TRAIT_ID, 0);
+            user.deposit(karma_rewards.reward_amount)?;
+            block_event.add_karma_reward(karma_rewards.reward_amount)?;
+
+            info!(
+                "rewarding {} with {} karma coins and adding reward trait",
+                user.user_name, karma_rewards.reward_amount
+            );
+
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: Bytes::from(account_id.data.clone()),
+                    value: Bytes::from(user.encode_to_vec()),
+                },
+                cf: USERS_COL_FAMILY,
+                ttl: 0,
+            });
+        }
 
-            info!("deleting leaderboard");
-            DatabaseService::delete_all(DeleteAllItems {
+        info!("deleting leaderboard");
+        for (key, _) in read_leader_board(batch).await? {
+            batch.delete(DeleteItem {
+                key,
                 cf: LEADER_BOARD_COL_FAMILY,
-            })
-            .await
-        } else {
-            info!("no winners to process");
-            Ok(())
+            });
         }
+
+        Ok(())
     }
 }
 
-impl KarmaRewardsService {
-    /// Process an award winner - helper method
-    async fn process_winner(&self, entry: &LeaderboardEntry, reward_amount: u64) -> Result<u64> {
-        // load user
-        let account_id = entry.account_id.as_ref().unwrap().data.clone();
-        let user = DatabaseService::read(ReadItem {
-            key: Bytes::from(account_id.clone()),
-            cf: USERS_COL_FAMILY,
-        })
+/// Helper function - all leader board entries including the batch pending changes
+async fn read_leader_board(batch: &BlockBatch) -> Result<Vec<(Bytes, Bytes)>> {
+    let mut entries: BTreeMap<Bytes, Bytes> = DatabaseService::read_all(LEADER_BOARD_COL_FAMILY)
         .await?
-        .ok_or_else(|| anyhow::anyhow!("User not found"))?;
-        let mut user = User::decode(user.0.as_ref())?;
-
-        // make sure user has not already been rewarded
-        if user.get_trait_score(KARMA_REWARD_TRAIT_ID, 0) > 0 {
-            // user already got a karma reward
-            return Ok(0);
-        }
-
-        // make sure this is not a migrated account
-        if user.user_name.ends_with("[old account]") {
-            // migrated account
-            return Ok(0);
-        }
-
-        // assign karma reward
-        user.inc_trait_score(KARMA_REWARD_TRAIT_ID, 0);
-
-        // give reward
-        user.balance += reward_amount;
-
-        info!(
-            "rewarding {} with {} karma coins and adding reward trait",
-            user.user_name, reward_amount
-        );
-
-        // todo: emit event here...
-
-        // persist user
-        let mut buf = Vec::with_capacity(user.encoded_len());
-        user.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
-            data: DataItem {
-                key: Bytes::from(account_id.clone()),
-                value: Bytes::from(buf),
-            },
-            cf: USERS_COL_FAMILY,
-            ttl: 0,
-        })
-        .await?;
-
-        Ok(reward_amount)
+        .into_iter()
+        .collect();
+    for (key, value) in batch.pending_items(LEADER_BOARD_COL_FAMILY) {
+        match value {
+            Some(value) => entries.insert(key, value),
+            None => entries.remove(&key),
+        };
     }
+    Ok(entries.into_iter().collect())
 }
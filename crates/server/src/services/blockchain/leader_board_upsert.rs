@@ -3,12 +3,13 @@
 //
 
 use crate::base::hex_utils::short_hex_string;
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::db_config_service::LEADER_BOARD_COL_FAMILY;
 use anyhow::Result;
 use base::karma_coin::karma_coin_core_types::{AccountId, LeaderboardEntry, User};
 use bytes::Bytes;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use db::db_service::{DataItem, ReadItem, WriteItem};
 use prost::Message;
 
 impl BlockChainService {
@@ -17,14 +18,16 @@ impl BlockChainService {
         &mut self,
         user: &User,
         chart_trait_id: u32,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
         let account_id = user.account_id.as_ref().unwrap().data.clone();
 
-        let mut entry = match DatabaseService::read(ReadItem {
-            key: Bytes::from(account_id.clone()),
-            cf: LEADER_BOARD_COL_FAMILY,
-        })
-        .await?
+        let mut entry = match batch
+            .read(ReadItem {
+                key: Bytes::from(account_id.clone()),
+                cf: LEADER_BOARD_COL_FAMILY,
+            })
+            .await?
         {
             // fetch user by account id from db
             Some(entry) => {
@@ -60,15 +63,14 @@ impl BlockChainService {
         let mut buf = Vec::with_capacity(entry.encoded_len());
         entry.encode(&mut buf)?;
 
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(account_id),
                 value: Bytes::from(buf),
             },
             cf: LEADER_BOARD_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         Ok(())
     }
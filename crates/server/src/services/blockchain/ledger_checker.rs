@@ -0,0 +1,367 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::new_user_tx_processor::OLD_ACCOUNT;
+use crate::services::blockchain::stats::{
+    apply_block_to_stats, block_stats_divergences, get_stats,
+};
+use crate::services::db_config_service::{
+    MOBILE_NUMBERS_COL_FAMILY, STATE_TREE_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY,
+    USERS_NAMES_COL_FAMILY,
+};
+use anyhow::Result;
+use base::genesis_config_service::GenesisConfigService;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_core_types::{Block, BlockchainStats, User};
+use base::sparse_merkle::{sparse_merkle_key, sparse_merkle_nodes};
+use base::supply::total_balances;
+use bytes::Bytes;
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, WriteBatch, WriteItem};
+use prost::Message;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use xactor::*;
+
+/// The result of a ledger consistency check
+#[derive(Debug, Clone, Default)]
+pub struct LedgerCheckReport {
+    /// chain tip height according to the stored blockchain stats
+    pub tip_height: u64,
+    pub blocks_checked: u64,
+    pub users_checked: u64,
+    /// human readable description of every divergence found
+    pub divergences: Vec<String>,
+    /// number of derived index entries which were repaired
+    pub repaired_count: u64,
+}
+
+impl LedgerCheckReport {
+    /// Returns true when no divergences were found
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    fn diverged(&mut self, divergence: String) {
+        warn!("ledger divergence: {}", divergence);
+        self.divergences.push(divergence);
+    }
+}
+
+impl Display for LedgerCheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "tip height: {}, blocks checked: {}, users checked: {}, divergences: {}, repaired: {}",
+            self.tip_height,
+            self.blocks_checked,
+            self.users_checked,
+            self.divergences.len(),
+            self.repaired_count
+        )?;
+        for divergence in self.divergences.iter() {
+            writeln!(f, " - {}", divergence)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the consistency of the ledger: the blocks chain, the blocks events replayed against
+/// the stored blockchain stats and the users derived indexes.
+/// When repair is set, derived index entries which are missing or dangling are fixed.
+#[message(result = "Result<LedgerCheckReport>")]
+pub(crate) struct CheckLedger {
+    pub(crate) repair: bool,
+}
+
+#[async_trait::async_trait]
+impl Handler<CheckLedger> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: CheckLedger,
+    ) -> Result<LedgerCheckReport> {
+        let mut report = LedgerCheckReport::default();
+        self.check_blocks(&mut report).await?;
+        self.check_users_indexes(&mut report, msg.repair).await?;
+        info!("ledger check completed: {}", report);
+        Ok(report)
+    }
+}
+
+impl BlockChainService {
+    /// Verify the blocks chain up to the stats tip and replay the blocks events against the stats
+    async fn check_blocks(&self, report: &mut LedgerCheckReport) -> Result<()> {
+        let stats = get_stats().await?;
+        report.tip_height = stats.tip_height;
+        let genesis_hash = GenesisConfigService::get_genesis_data().await?.genesis_hash;
+
+        let mut replayed_stats = BlockchainStats::new();
+        let mut prev_block: Option<Block> = None;
+
+        for height in 1..=stats.tip_height {
+            let Some(block) = self.get_block_by_height(height).await? else {
+                report.diverged(format!("missing block at height {}", height));
+                return Ok(());
+            };
+            report.blocks_checked += 1;
+
+            if block.height != height {
+                report.diverged(format!(
+                    "block stored at height {} has height {}",
+                    height, block.height
+                ));
+            }
+
+            if let Err(e) = block.verify() {
+                report.diverged(format!("block {}: {}", height, e));
+            }
+
+            if !block.genesis_hash.is_empty() && block.genesis_hash != genesis_hash {
+                report.diverged(format!(
+                    "block {} genesis hash {} doesn't match the genesis config hash {}",
+                    height,
+                    short_hex_string(&block.genesis_hash),
+                    short_hex_string(&genesis_hash)
+                ));
+            }
+
+            if let Some(prev_block) = prev_block.as_ref() {
+                if let Err(e) = block.verify_parent(prev_block) {
+                    report.diverged(format!("block {}: {}", height, e));
+                }
+            } else if !block.prev_block_digest.is_empty() {
+                report.diverged(format!(
+                    "genesis block {} has a previous block digest",
+                    height
+                ));
+            }
+
+            for tx_hash in block.transactions_hashes.iter() {
+                if DatabaseService::read(ReadItem {
+                    key: Bytes::from(tx_hash.clone()),
+                    cf: TRANSACTIONS_COL_FAMILY,
+                })
+                .await?
+                .is_none()
+                {
+                    report.diverged(format!(
+                        "block {} transaction {} not found",
+                        height,
+                        short_hex_string(tx_hash)
+                    ));
+                }
+            }
+
+            match self.get_block_event_by_height(height).await? {
+                Some(block_event) => {
+                    if block_event.block_hash != block.digest {
+                        report.diverged(format!(
+                            "block {} event refers to a different block hash",
+                            height
+                        ));
+                    }
+                    if let Err(e) = apply_block_to_stats(&mut replayed_stats, &block_event, &block)
+                    {
+                        report.diverged(format!("block {} stats: {}", height, e));
+                    }
+                }
+                None => {
+                    report.diverged(format!("missing block event for block {}", height));
+                }
+            }
+
+            prev_block = Some(block);
+        }
+
+        if self
+            .get_block_by_height(stats.tip_height + 1)
+            .await?
+            .is_some()
+        {
+            report.diverged(format!(
+                "found block at height {} above the stats tip height",
+                stats.tip_height + 1
+            ));
+        }
+
+        for divergence in block_stats_divergences(&stats, &replayed_stats) {
+            report.diverged(divergence);
+        }
+
+        Ok(())
+    }
+
+    /// Verify users names and mobile numbers indexes and the accounts state tree against the
+    /// users accounts and the users balances against the stats circulation
+    async fn check_users_indexes(
+        &self,
+        report: &mut LedgerCheckReport,
+        repair: bool,
+    ) -> Result<()> {
+        let mut users: HashMap<Vec<u8>, User> = HashMap::new();
+        let mut leaves = vec![];
+        for (key, value) in DatabaseService::read_all(USERS_COL_FAMILY).await? {
+            users.insert(key.to_vec(), User::decode(value.as_ref())?);
+            leaves.push((sparse_merkle_key(&key)?, value.to_vec()));
+        }
+        report.users_checked = users.len() as u64;
+
+        // all funds held by users are accounted for in the circulation
+        let stats = get_stats().await?;
+        if let Err(e) = stats.verify_circulation(total_balances(users.values())?) {
+            report.diverged(e.to_string());
+        }
+
+        let names_index = DatabaseService::read_all(USERS_NAMES_COL_FAMILY).await?;
+        let numbers_index = DatabaseService::read_all(MOBILE_NUMBERS_COL_FAMILY).await?;
+        let mut batch = WriteBatch::default();
+
+        // the state tree nodes are derived from the users accounts
+        let (nodes, _) = sparse_merkle_nodes(&leaves)?;
+        let mut tree: HashMap<Bytes, Bytes> = DatabaseService::read_all(STATE_TREE_COL_FAMILY)
+            .await?
+            .into_iter()
+            .collect();
+        for (key, node) in nodes {
+            let key = Bytes::from(key);
+            let value = Bytes::from(node.to_bytes());
+            if tree.remove(&key).as_ref() != Some(&value) {
+                report.diverged(format!(
+                    "state tree node {} is missing or differs from the users accounts",
+                    short_hex_string(&key)
+                ));
+                batch.write(WriteItem {
+                    data: DataItem { key, value },
+                    cf: STATE_TREE_COL_FAMILY,
+                    ttl: 0,
+                });
+            }
+        }
+        for key in tree.into_keys() {
+            report.diverged(format!(
+                "state tree node {} is not derived from the users accounts",
+                short_hex_string(&key)
+            ));
+            batch.delete(DeleteItem {
+                key,
+                cf: STATE_TREE_COL_FAMILY,
+            });
+        }
+
+        // dangling index entries
+        for (name, account_id) in names_index.iter() {
+            let valid = users
+                .get(account_id.as_ref())
+                .is_some_and(|u| u.user_name.as_bytes() == name.as_ref());
+            if !valid {
+                report.diverged(format!(
+                    "user name {} is indexed to account {} which doesn't have this name",
+                    String::from_utf8_lossy(name),
+                    short_hex_string(account_id)
+                ));
+                batch.delete(DeleteItem {
+                    key: name.clone(),
+                    cf: USERS_NAMES_COL_FAMILY,
+                });
+            }
+        }
+
+        // mobile numbers are indexed by their hash
+        for (number_hash, account_id) in numbers_index.iter() {
+            let valid = match users.get(account_id.as_ref()) {
+                Some(user) => user
+                    .get_mobile_number_hash()?
+                    .is_some_and(|h| h == number_hash.as_ref()),
+                None => false,
+            };
+            if !valid {
+                report.diverged(format!(
+                    "mobile number {} is indexed to account {} which doesn't have this number",
+                    short_hex_string(number_hash),
+                    short_hex_string(account_id)
+                ));
+                batch.delete(DeleteItem {
+                    key: number_hash.clone(),
+                    cf: MOBILE_NUMBERS_COL_FAMILY,
+                });
+            }
+        }
+
+        // missing index entries. Only users who signed up with a verified mobile number are
+        // indexed - block producer accounts and migrated old accounts are not indexed by design.
+        let names: HashMap<Bytes, Bytes> = names_index.into_iter().collect();
+        let numbers: HashMap<Bytes, Bytes> = numbers_index.into_iter().collect();
+
+        for (account_id, user) in users.iter() {
+            let Some(number_hash) = user.get_mobile_number_hash()? else {
+                continue;
+            };
+
+            if user.user_name.ends_with(OLD_ACCOUNT) {
+                continue;
+            }
+
+            let name = Bytes::from(user.user_name.as_bytes().to_vec());
+            match names.get(&name) {
+                Some(indexed) if indexed.as_ref() == account_id.as_slice() => {}
+                Some(indexed) => report.diverged(format!(
+                    "user name {} of account {} is indexed to account {}",
+                    user.user_name,
+                    short_hex_string(account_id),
+                    short_hex_string(indexed)
+                )),
+                None => {
+                    report.diverged(format!(
+                        "user name {} of account {} is not indexed",
+                        user.user_name,
+                        short_hex_string(account_id)
+                    ));
+                    batch.write(WriteItem {
+                        data: DataItem {
+                            key: name,
+                            value: Bytes::from(account_id.clone()),
+                        },
+                        cf: USERS_NAMES_COL_FAMILY,
+                        ttl: 0,
+                    });
+                }
+            }
+
+            let number_hash = Bytes::from(number_hash);
+            match numbers.get(&number_hash) {
+                Some(indexed) if indexed.as_ref() == account_id.as_slice() => {}
+                Some(indexed) => report.diverged(format!(
+                    "mobile number {} of account {} is indexed to account {}",
+                    short_hex_string(&number_hash),
+                    short_hex_string(account_id),
+                    short_hex_string(indexed)
+                )),
+                None => {
+                    report.diverged(format!(
+                        "mobile number {} of account {} is not indexed",
+                        short_hex_string(&number_hash),
+                        short_hex_string(account_id)
+                    ));
+                    batch.write(WriteItem {
+                        data: DataItem {
+                            key: number_hash,
+                            value: Bytes::from(account_id.clone()),
+                        },
+                        cf: MOBILE_NUMBERS_COL_FAMILY,
+                        ttl: 0,
+                    });
+                }
+            }
+        }
+
+        if repair && !batch.is_empty() {
+            info!("repairing {} index entries", batch.len());
+            report.repaired_count = batch.len() as u64;
+            DatabaseService::write_batch(batch).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,459 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::proofs::update_state_root;
+use crate::services::blockchain::stats::{block_stats_divergences, get_stats};
+use crate::services::blockchain::tokenomics::Tokenomics;
+use crate::services::db_config_service::{
+    configure_blockchain_db, configure_server_db, DELETED_USERS_NAMES_COL_FAMILY,
+    LEADER_BOARD_COL_FAMILY, MOBILE_NUMBERS_COL_FAMILY, PARAMS_UPDATES_COL_FAMILY,
+    PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY, PENDING_PAYMENTS_COL_FAMILY,
+    PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY, STATE_TREE_COL_FAMILY, TRANSACTIONS_COL_FAMILY,
+    USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY, VERIFIERS_COL_FAMILY,
+};
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::GenesisConfigService;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_core_types::TransactionType::{
+    DeleteUserV1, NewUserV1, NewUserV2, ParamsUpdateV1, PaymentV1, UpdateUserV1, VerifierUpdateV1,
+};
+use base::karma_coin::karma_coin_core_types::{
+    Block, BlockEvent, BlockchainStats, SignedTransaction, TransactionEvent, User,
+};
+use bytes::Bytes;
+use db::db_service::{DatabaseService, Destroy, ReadItem};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use xactor::*;
+
+/// Column families which are re-derived by replaying blocks and compared with the live db
+const DERIVED_COL_FAMILIES: [&str; 11] = [
+    USERS_COL_FAMILY,
+    STATE_TREE_COL_FAMILY,
+    USERS_NAMES_COL_FAMILY,
+    DELETED_USERS_NAMES_COL_FAMILY,
+    MOBILE_NUMBERS_COL_FAMILY,
+    LEADER_BOARD_COL_FAMILY,
+    PENDING_PAYMENTS_COL_FAMILY,
+    PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY,
+    PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY,
+    PARAMS_UPDATES_COL_FAMILY,
+    VERIFIERS_COL_FAMILY,
+];
+
+/// The result of re-deriving the ledger state from blocks
+#[derive(Debug, Clone, Default)]
+pub struct LedgerReplayReport {
+    pub blocks_replayed: u64,
+    pub transactions_replayed: u64,
+    /// block transactions which failed to execute when replayed
+    pub failed_transactions: Vec<String>,
+    /// number of derived items compared with the live db
+    pub items_compared: u64,
+    /// human readable description of every difference between the live and replayed state
+    pub divergences: Vec<String>,
+}
+
+impl LedgerReplayReport {
+    /// Returns true when the replayed state is identical to the live state
+    pub fn is_consistent(&self) -> bool {
+        self.failed_transactions.is_empty() && self.divergences.is_empty()
+    }
+}
+
+impl Display for LedgerReplayReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "blocks replayed: {}, txs replayed: {}, failed txs: {}, items compared: {}, divergences: {}",
+            self.blocks_replayed,
+            self.transactions_replayed,
+            self.failed_transactions.len(),
+            self.items_compared,
+            self.divergences.len()
+        )?;
+        for failure in self.failed_transactions.iter() {
+            writeln!(f, " - failed tx: {}", failure)?;
+        }
+        for divergence in self.divergences.iter() {
+            writeln!(f, " - {}", divergence)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild the users, names and numbers indexes, leader board and blockchain stats from scratch
+/// in a fresh db by re-executing all blocks transactions, and compare them with the live db.
+/// Only the blocks and their transactions are read from the live db, which is not modified.
+/// Must be sent to an offline service so no blocks are produced and the db isn't migrated.
+#[message(result = "Result<LedgerReplayReport>")]
+pub(crate) struct ReplayLedger {
+    /// name of the temporary db the ledger is replayed into
+    pub(crate) replay_db_name: String,
+}
+
+/// Live db data required for replaying and comparing
+struct LiveLedger {
+    blocks: Vec<(Block, Vec<SignedTransaction>)>,
+    stats: BlockchainStats,
+    /// derived column families content by column family
+    derived: Vec<BTreeMap<Bytes, Bytes>>,
+}
+
+#[async_trait::async_trait]
+impl Handler<ReplayLedger> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: ReplayLedger,
+    ) -> Result<LedgerReplayReport> {
+        if !self.offline {
+            return Err(anyhow!(
+                "ledger replay requires an offline blockchain service"
+            ));
+        }
+
+        let live = self.load_live_ledger().await?;
+
+        if Path::new(&msg.replay_db_name).exists() {
+            std::fs::remove_dir_all(&msg.replay_db_name)?;
+        }
+
+        info!("replaying ledger into db {}...", msg.replay_db_name);
+        configure_blockchain_db(msg.replay_db_name, true).await?;
+
+        self.replaying = true;
+        let res = self.replay_and_compare(&live).await;
+        self.replaying = false;
+
+        // drop the replay db and reopen the live db
+        DatabaseService::from_registry()
+            .await?
+            .call(Destroy)
+            .await??;
+        configure_server_db().await?;
+
+        let report = res?;
+        info!("ledger replay completed: {}", report);
+        Ok(report)
+    }
+}
+
+impl BlockChainService {
+    /// Read all blocks and their transactions and the derived state from the live db
+    async fn load_live_ledger(&self) -> Result<LiveLedger> {
+        let stats = get_stats().await?;
+        let mut blocks = vec![];
+
+        for height in 1..=stats.tip_height {
+            let block = self
+                .get_block_by_height(height)
+                .await?
+                .ok_or_else(|| anyhow!("missing block at height {}", height))?;
+
+            let mut transactions = vec![];
+            for tx_hash in block.transactions_hashes.iter() {
+                let data = DatabaseService::read(ReadItem {
+                    key: Bytes::from(tx_hash.clone()),
+                    cf: TRANSACTIONS_COL_FAMILY,
+                })
+                .await?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "missing transaction {} of block {}",
+                        short_hex_string(tx_hash),
+                        height
+                    )
+                })?;
+                transactions.push(SignedTransaction::decode(data.0.as_ref())?);
+            }
+            blocks.push((block, transactions));
+        }
+
+        let mut derived = vec![];
+        for cf in DERIVED_COL_FAMILIES {
+            derived.push(DatabaseService::read_all(cf).await?.into_iter().collect());
+        }
+
+        Ok(LiveLedger {
+            blocks,
+            stats,
+            derived,
+        })
+    }
+
+    /// Replay all live blocks into the currently configured db and compare the results
+    async fn replay_and_compare(&mut self, live: &LiveLedger) -> Result<LedgerReplayReport> {
+        let mut report = LedgerReplayReport::default();
+
+        for (block, transactions) in live.blocks.iter() {
+            self.replay_block(block, transactions, &mut report).await?;
+            report.blocks_replayed += 1;
+        }
+
+        let stats = get_stats().await?;
+        report
+            .divergences
+            .append(&mut block_stats_divergences(&live.stats, &stats));
+
+        for (cf, live_items) in DERIVED_COL_FAMILIES.iter().zip(live.derived.iter()) {
+            let replayed_items = DatabaseService::read_all(cf).await?.into_iter().collect();
+            compare_items(cf, live_items, &replayed_items, &mut report);
+        }
+
+        Ok(report)
+    }
+
+    /// Re-execute a block's transactions in block order and apply the block
+    async fn replay_block(
+        &mut self,
+        block: &Block,
+        transactions: &[SignedTransaction],
+        report: &mut LedgerReplayReport,
+    ) -> Result<()> {
+        let stats = get_stats().await?;
+        let tokenomics = Tokenomics::new(
+            stats.clone(),
+            GenesisConfigService::get_genesis_data_at(block.height).await?,
+        );
+        let mut block_event = BlockEvent::new(block.height);
+        let mut sign_ups = HashMap::new();
+        let mut params_updates = vec![];
+        let mut batch = BlockBatch::default();
+
+        self.refund_expired_payments(block.height, block.time, &mut block_event, &mut batch)
+            .await?;
+
+        if let Some(karma_rewards) = block.karma_rewards.as_ref() {
+            self.apply_karma_rewards(karma_rewards, &mut block_event, &mut batch)
+                .await?;
+        }
+
+        for tx in transactions.iter() {
+            let tx_hash = tx.get_hash()?;
+            let tx_body = tx.get_body()?;
+            let mut tx_event = TransactionEvent::new(block.height, tx, tx_hash.as_ref());
+            let save_point = batch.save_point();
+            report.transactions_replayed += 1;
+
+            let res = match tx_body.get_tx_type()? {
+                NewUserV1 | NewUserV2 => {
+                    match self
+                        .process_new_user_transaction(tx, &tokenomics, &mut tx_event, &mut batch)
+                        .await
+                    {
+                        Ok(res) => {
+                            block_event.signups_count += 1;
+                            block_event.add_signup_reward(tx_event.signup_reward)?;
+                            let referral_reward_awarded = self
+                                .emit_pending_payments_events(
+                                    res.claimed_payments_events,
+                                    &mut block_event,
+                                    &mut batch,
+                                )
+                                .await?;
+                            if !referral_reward_awarded {
+                                sign_ups.insert(res.mobile_number_hash, tx.clone());
+                            }
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow!(e.error_message)),
+                    }
+                }
+                PaymentV1 => {
+                    let mut payer = get_signer(tx, &batch).await?;
+                    match BlockChainService::get_payee_user_from_tx_body(&tx_body, &batch).await? {
+                        Some(mut payee) => self
+                            .process_payment_transaction(
+                                tx,
+                                &mut payer,
+                                &mut payee,
+                                &mut sign_ups,
+                                &tokenomics,
+                                &mut tx_event,
+                                &mut batch,
+                            )
+                            .await
+                            .and_then(|_| {
+                                block_event.payments_count += 1;
+                                if tx_event.referral_reward != 0 {
+                                    block_event.referral_rewards_count += 1;
+                                    block_event.add_referral_reward(tx_event.referral_reward)?;
+                                }
+                                if tx_event.appreciation_char_trait_idx != 0 {
+                                    block_event.appreciations_count += 1;
+                                }
+                                Ok(())
+                            }),
+                        None if tx_body
+                            .get_payment_transaction_v1()?
+                            .get_payee_number_hash()?
+                            .is_some() =>
+                        {
+                            self.process_escrow_payment_transaction(
+                                tx,
+                                &mut payer,
+                                &tokenomics,
+                                &mut tx_event,
+                                &mut batch,
+                            )
+                            .await
+                            .map(|_| block_event.payments_count += 1)
+                        }
+                        None => Err(anyhow!("payee not found")),
+                    }
+                }
+                UpdateUserV1 => {
+                    let mut user = get_signer(tx, &batch).await?;
+                    self.process_update_transaction(
+                        tx,
+                        &tokenomics,
+                        &mut tx_event,
+                        &mut user,
+                        &mut batch,
+                    )
+                    .await
+                    .map(|_| block_event.user_updates_count += 1)
+                }
+                DeleteUserV1 => {
+                    let user = get_signer(tx, &batch).await?;
+                    match self
+                        .process_delete_user_transaction(tx, &user, &mut tx_event, &mut batch)
+                        .await
+                    {
+                        Ok(res) => {
+                            block_event.add_burned(res.burned_amount)?;
+                            self.emit_pending_payments_events(
+                                res.cancelled_payments_events,
+                                &mut block_event,
+                                &mut batch,
+                            )
+                            .await?;
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow!(e.error_message)),
+                    }
+                }
+                ParamsUpdateV1 => {
+                    let mut user = get_signer(tx, &batch).await?;
+                    self.process_params_update_transaction(
+                        tx,
+                        &mut user,
+                        &params_updates,
+                        &mut tx_event,
+                        &mut batch,
+                    )
+                    .await
+                    .map(|params_update| params_updates.push(params_update))
+                }
+                VerifierUpdateV1 => {
+                    let mut user = get_signer(tx, &batch).await?;
+                    self.process_verifier_update_transaction(
+                        tx,
+                        &mut user,
+                        &mut tx_event,
+                        &mut batch,
+                    )
+                    .await
+                }
+            };
+
+            match res {
+                Ok(_) => {
+                    block_event.add_fee(tx_body.fee)?;
+                    block_event.add_transaction_event(tx_event.clone());
+                    self.emit_tx_event(tx_event, &mut batch).await?;
+                }
+                Err(e) => {
+                    batch.rollback_to(save_point);
+                    report.failed_transactions.push(format!(
+                        "block {} tx {}: {}",
+                        block.height,
+                        short_hex_string(tx_hash.as_ref()),
+                        e
+                    ));
+                }
+            }
+        }
+
+        self.reward_block_producer(block, &mut batch).await?;
+
+        // blocks created before state roots were introduced have no state root
+        let state_root = update_state_root(&mut batch).await?;
+        if !block.state_root.is_empty() && state_root != block.state_root {
+            report
+                .divergences
+                .push(format!("block {} state root mismatch", block.height));
+        }
+
+        self.apply_block(block, stats, block_event, &mut batch)
+            .await?;
+        batch.commit().await
+    }
+}
+
+/// Helper function - get a transaction signer user from the ledger
+async fn get_signer(tx: &SignedTransaction, batch: &BlockBatch) -> Result<User> {
+    let data = batch
+        .read(ReadItem {
+            key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
+            cf: USERS_COL_FAMILY,
+        })
+        .await?
+        .ok_or_else(|| anyhow!("tx signer not found"))?;
+
+    Ok(User::decode(data.0.as_ref())?)
+}
+
+/// Helper function - compare the live and replayed items of a column family
+fn compare_items(
+    cf: &str,
+    live_items: &BTreeMap<Bytes, Bytes>,
+    replayed_items: &BTreeMap<Bytes, Bytes>,
+    report: &mut LedgerReplayReport,
+) {
+    for (key, value) in live_items.iter() {
+        report.items_compared += 1;
+        match replayed_items.get(key) {
+            Some(replayed) if replayed == value => {}
+            Some(replayed) => report.divergences.push(format!(
+                "{} {} differs: {} replayed: {}",
+                cf,
+                short_hex_string(key),
+                describe(cf, value),
+                describe(cf, replayed)
+            )),
+            None => report.divergences.push(format!(
+                "{} {} is missing in the replayed ledger",
+                cf,
+                short_hex_string(key)
+            )),
+        }
+    }
+
+    for key in replayed_items.keys() {
+        if !live_items.contains_key(key) {
+            report.items_compared += 1;
+            report.divergences.push(format!(
+                "{} {} is missing in the live ledger",
+                cf,
+                short_hex_string(key)
+            ));
+        }
+    }
+}
+
+/// Helper function - human readable description of a derived value
+fn describe(cf: &str, value: &Bytes) -> String {
+    if cf == USERS_COL_FAMILY {
+        if let Ok(user) = User::decode(value.as_ref()) {
+            return user.to_string();
+        }
+    }
+    short_hex_string(value)
+}
@@ -0,0 +1,381 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::mem_pool_service::MemPoolService;
+use crate::services::blockchain::stats::get_stats;
+use crate::services::blockchain::verifier_update_tx_processor::validate_verifiers_quorum;
+use crate::services::db_config_service::{TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY};
+use anyhow::anyhow;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason;
+use base::karma_coin::karma_coin_core_types::TransactionType::{
+    DeleteUserV1, NewUserV1, NewUserV2, ParamsUpdateV1, PaymentV1, UpdateUserV1, VerifierUpdateV1,
+};
+use base::karma_coin::karma_coin_core_types::{SignedTransaction, TransactionBody, User};
+use base::server_config_service::{
+    ServerConfigService, MEM_POOL_MAX_ITEMS_KEY, MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY,
+    MEM_POOL_MIN_TX_FEE_KEY,
+};
+use base::user_verification_data_ex::get_evidence_ex_verifiers;
+use bytes::Bytes;
+use db::db_service::{DatabaseService, ReadItem};
+use prost::Message;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A transaction which was not admitted to the mem pool and the reason it was rejected.
+/// Internal errors which are not caused by the transaction have an unspecified reason.
+#[derive(Debug, Clone)]
+pub(crate) struct AdmissionError {
+    pub(crate) reason: SubmitTransactionRejectReason,
+    pub(crate) error_message: String,
+}
+
+impl AdmissionError {
+    pub(crate) fn new(reason: SubmitTransactionRejectReason, error_message: String) -> Self {
+        AdmissionError {
+            reason,
+            error_message,
+        }
+    }
+}
+
+impl Display for AdmissionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.reason, self.error_message)
+    }
+}
+
+impl From<anyhow::Error> for AdmissionError {
+    fn from(e: anyhow::Error) -> Self {
+        AdmissionError::new(SubmitTransactionRejectReason::Unspecified, e.to_string())
+    }
+}
+
+/// Helper function - reject a tx for a reason with the error's message
+fn reject(reason: SubmitTransactionRejectReason) -> impl FnOnce(anyhow::Error) -> AdmissionError {
+    move |e| AdmissionError::new(reason, e.to_string())
+}
+
+impl MemPoolService {
+    /// Validate a submitted transaction before it is admitted to the pool.
+    /// Checks which only depend on the tx are done first, followed by checks against the
+    /// pool and the ledger. Execution in a block may still fail as the ledger may change
+    /// before the tx is executed.
+    pub(crate) async fn validate_admission(
+        &self,
+        tx: &SignedTransaction,
+        tx_hash: &[u8],
+    ) -> Result<(), AdmissionError> {
+        use SubmitTransactionRejectReason::*;
+
+        tx.verify_syntax().await.map_err(reject(InvalidData))?;
+        let tx_body = tx.get_body().map_err(reject(InvalidData))?;
+        if tx_body.transaction_data.is_none() {
+            return Err(AdmissionError::new(
+                InvalidData,
+                "required transaction data is missing".into(),
+            ));
+        }
+
+        tx_body
+            .verify_syntax()
+            .await
+            .map_err(reject(InvalidNetId))?;
+        tx.validate().await.map_err(reject(InvalidSignature))?;
+        tx_body
+            .verify_timestamp()
+            .map_err(reject(InvalidTimestamp))?;
+
+        let min_fee = ServerConfigService::get_u64(MEM_POOL_MIN_TX_FEE_KEY.into())
+            .await?
+            .unwrap();
+        if tx_body.fee < min_fee {
+            return Err(AdmissionError::new(
+                FeeTooLow,
+                format!("tx fee {} is below the min fee {}", tx_body.fee, min_fee),
+            ));
+        }
+
+        if self.transactions.contains_key(tx_hash) {
+            return Err(AdmissionError::new(
+                Duplicate,
+                "tx is already in the mem pool".into(),
+            ));
+        }
+
+        if (DatabaseService::read(ReadItem {
+            key: Bytes::from(tx_hash.to_vec()),
+            cf: TRANSACTIONS_COL_FAMILY,
+        })
+        .await?)
+            .is_some()
+        {
+            return Err(AdmissionError::new(
+                AlreadyOnChain,
+                "tx already on chain".into(),
+            ));
+        }
+
+        // reject txs with a nonce which was already used by the signer. Txs with a future
+        // nonce are accepted and wait in the pool until all txs with lower nonces are processed.
+        // Accounts which are not on chain yet have a nonce of 0
+        let signer = tx.signer.as_ref().unwrap();
+        let user = match DatabaseService::read(ReadItem {
+            key: Bytes::from(signer.data.clone()),
+            cf: USERS_COL_FAMILY,
+        })
+        .await?
+        {
+            Some(data) => Some(User::decode(data.0.as_ref()).map_err(anyhow::Error::from)?),
+            None => None,
+        };
+
+        tx_body
+            .validate_nonce(user.as_ref().map_or(0, |u| u.nonce))
+            .map_err(reject(InvalidNonce))?;
+
+        let max_account_txs = ServerConfigService::get_u64(MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY.into())
+            .await?
+            .unwrap();
+        let pending_count = self.signers_queues.get(&signer.data).map_or(0, |q| q.len());
+        if pending_count >= max_account_txs as usize {
+            return Err(AdmissionError::new(
+                AccountTxsLimit,
+                format!("signer already has {} pending txs", pending_count),
+            ));
+        }
+
+        if let Some(pending_tx_hash) = self
+            .signers_queues
+            .get(&signer.data)
+            .and_then(|queue| queue.get(&tx_body.nonce))
+        {
+            if pending_tx_hash.as_slice() != tx_hash {
+                return Err(AdmissionError::new(
+                    InvalidNonce,
+                    format!(
+                        "signer already has a pending tx with nonce {}",
+                        tx_body.nonce
+                    ),
+                ));
+            }
+        }
+
+        match tx_body.get_tx_type().map_err(reject(InvalidData))? {
+            NewUserV2 => self.validate_new_user_admission(tx, &tx_body).await,
+            // v1 evidence reveals the mobile number which would be stored on chain with the tx
+            NewUserV1 => Err(AdmissionError::new(
+                InvalidData,
+                "new user v1 txs are not accepted - use new user v2 txs".into(),
+            )),
+            PaymentV1 => {
+                let payment = tx_body
+                    .get_payment_transaction_v1()
+                    .map_err(reject(InvalidData))?;
+                payment.verify_syntax().map_err(reject(InvalidData))?;
+
+                let payer = user.ok_or_else(|| {
+                    AdmissionError::new(SignerNotFound, "payer is not on chain".into())
+                })?;
+
+                // tx fee may be subsidised by the protocol so only the amount is checked here
+                if payer.spendable_balance() < payment.amount {
+                    return Err(AdmissionError::new(
+                        InsufficientBalance,
+                        format!(
+                            "payer balance {} is lower than the payment amount {}",
+                            payer.spendable_balance(),
+                            payment.amount
+                        ),
+                    ));
+                }
+                Ok(())
+            }
+            ParamsUpdateV1 => {
+                tx_body
+                    .get_params_update_transaction_v1()
+                    .map_err(reject(InvalidData))?
+                    .proposal
+                    .ok_or_else(|| anyhow!("missing params update proposal"))
+                    .and_then(|p| p.verify_syntax())
+                    .map_err(reject(InvalidData))?;
+
+                match user {
+                    Some(_) => Ok(()),
+                    None => Err(AdmissionError::new(
+                        SignerNotFound,
+                        "tx signer is not on chain".into(),
+                    )),
+                }
+            }
+            VerifierUpdateV1 => {
+                tx_body
+                    .get_verifier_update_transaction_v1()
+                    .map_err(reject(InvalidData))?
+                    .proposal
+                    .ok_or_else(|| anyhow!("missing verifier update proposal"))
+                    .and_then(|p| p.verify_syntax())
+                    .map_err(reject(InvalidData))?;
+
+                match user {
+                    Some(_) => Ok(()),
+                    None => Err(AdmissionError::new(
+                        SignerNotFound,
+                        "tx signer is not on chain".into(),
+                    )),
+                }
+            }
+            UpdateUserV1 => {
+                tx_body
+                    .get_update_user_transaction_v1()
+                    .and_then(|tx| tx.verify_syntax())
+                    .map_err(reject(InvalidData))?;
+
+                match user {
+                    Some(_) => Ok(()),
+                    None => Err(AdmissionError::new(
+                        SignerNotFound,
+                        "tx signer is not on chain".into(),
+                    )),
+                }
+            }
+            DeleteUserV1 => match user {
+                Some(_) => Ok(()),
+                None => Err(AdmissionError::new(
+                    SignerNotFound,
+                    "tx signer is not on chain".into(),
+                )),
+            },
+        }
+    }
+
+    /// Validate a new user tx verification evidence is valid and was signed by a quorum of
+    /// registered verifiers
+    async fn validate_new_user_admission(
+        &self,
+        tx: &SignedTransaction,
+        tx_body: &TransactionBody,
+    ) -> Result<(), AdmissionError> {
+        use SubmitTransactionRejectReason::*;
+
+        // a new account's nonce is 0 so its signup tx must be its first tx
+        if tx_body.nonce != 1 {
+            return Err(AdmissionError::new(
+                InvalidNonce,
+                "new user tx nonce must be 1".into(),
+            ));
+        }
+
+        // evidence is checked against the verifiers registry at the next block height
+        let height = get_stats().await?.tip_height + 1;
+        let new_user_tx = tx_body
+            .get_new_user_transaction_v2()
+            .and_then(|tx| tx.verify_syntax().map(|_| tx))
+            .map_err(reject(InvalidData))?;
+        let evidence = new_user_tx.get_evidence();
+        let (data, verifiers) = get_evidence_ex_verifiers(&evidence.iter().collect::<Vec<_>>())
+            .map_err(reject(InvalidVerificationEvidence))?;
+        data.get_mobile_number_hash()
+            .map_err(reject(InvalidVerificationEvidence))?;
+        validate_verifiers_quorum(&verifiers, height, &BlockBatch::default())
+            .await
+            .map_err(reject(InvalidVerificationEvidence))?;
+
+        if data.account_id.as_ref() != tx.signer.as_ref() {
+            return Err(AdmissionError::new(
+                InvalidVerificationEvidence,
+                "verification evidence was issued to another account".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Make room in a full pool for a new tx by evicting pending txs with a lower fee.
+    /// Only the last pending tx of a signer may be evicted so the remaining txs of the signer
+    /// can still be executed in nonce order. The new tx's signer txs are never evicted.
+    /// All evicted txs are picked before any tx is removed, so the pool is unchanged when
+    /// there isn't enough room for the new tx. Returns the evicted txs by hash.
+    pub(crate) async fn make_room(
+        &mut self,
+        tx: &SignedTransaction,
+    ) -> Result<Vec<(Vec<u8>, SignedTransaction)>, AdmissionError> {
+        let max_size = ServerConfigService::get_u64(MEM_POOL_MAX_ITEMS_KEY.into())
+            .await?
+            .unwrap() as usize;
+
+        let signer = &tx.signer.as_ref().unwrap().data;
+        let fee = tx.get_body()?.fee;
+
+        // number of txs picked for eviction from the end of each signer's queue
+        let mut picked_per_signer: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut picked = vec![];
+
+        while self.transactions.len() - picked.len() >= max_size {
+            let candidate = self.eviction_candidate(signer, &picked_per_signer)?;
+            let Some((candidate_fee, candidate_signer, tx_hash)) =
+                candidate.filter(|(f, _, _)| *f < fee)
+            else {
+                return Err(AdmissionError::new(
+                    SubmitTransactionRejectReason::MemPoolFull,
+                    "mem pool is full - tx fee is too low to replace pending txs".into(),
+                ));
+            };
+
+            *picked_per_signer.entry(candidate_signer).or_default() += 1;
+            picked.push((candidate_fee, tx_hash));
+        }
+
+        let mut evicted = vec![];
+        for (candidate_fee, tx_hash) in picked {
+            info!(
+                "evicting tx {} with fee {} from full pool",
+                short_hex_string(&tx_hash),
+                candidate_fee
+            );
+            if let Some(evicted_tx) = self.remove_transaction(&tx_hash) {
+                evicted.push((tx_hash, evicted_tx));
+            }
+        }
+
+        Ok(evicted)
+    }
+
+    /// Returns the fee, signer and hash of the pending tx which should be evicted first.
+    /// This is the lowest fee last tx of all signers but the provided one, ignoring the txs
+    /// already picked for eviction from the end of each signer's queue.
+    /// Among txs with the same fee the most recent tx is evicted.
+    fn eviction_candidate(
+        &self,
+        excluded_signer: &[u8],
+        picked_per_signer: &HashMap<Vec<u8>, usize>,
+    ) -> anyhow::Result<Option<(u64, Vec<u8>, Vec<u8>)>> {
+        let mut candidate: Option<(u64, Reverse<u64>, Vec<u8>, Vec<u8>)> = None;
+        for (signer, queue) in self.signers_queues.iter() {
+            if signer.as_slice() == excluded_signer {
+                continue;
+            }
+            let picked = picked_per_signer.get(signer).copied().unwrap_or(0);
+            let Some(tx_hash) = queue.values().rev().nth(picked) else {
+                continue;
+            };
+
+            let tx_body = self.transactions[tx_hash].get_body()?;
+            let key = (
+                tx_body.fee,
+                Reverse(tx_body.timestamp),
+                tx_hash.clone(),
+                signer.clone(),
+            );
+            if candidate.as_ref().is_none_or(|c| key < *c) {
+                candidate = Some(key);
+            }
+        }
+
+        Ok(candidate.map(|(fee, _, tx_hash, signer)| (fee, signer, tx_hash)))
+    }
+}
@@ -2,84 +2,92 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::mem_pool_admission::AdmissionError;
+use crate::services::blockchain::tx_status::publish_tx_status;
 use crate::services::db_config_service::{
     TRANSACTIONS_COL_FAMILY, TXS_POOL_COL_FAMILY, TXS_POOL_KEY,
 };
 use anyhow::{anyhow, Result};
 use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason::{
+    AlreadyOnChain, Duplicate, Unspecified,
+};
+use base::karma_coin::karma_coin_core_types::TransactionStatus::Submitted;
+use base::karma_coin::karma_coin_core_types::TransactionType::{NewUserV1, NewUserV2, PaymentV1};
 use base::karma_coin::karma_coin_core_types::{MemPool, SignedTransaction};
 use base::server_config_service::{
-    ServerConfigService, MEM_POOL_MAX_ITEMS_KEY, MEM_POOL_MAX_TX_AGE_HOURS,
+    ServerConfigService, MEM_POOL_EXPIRY_INTERVAL_SECS_KEY, MEM_POOL_MAX_TX_AGE_HOURS,
 };
 use bytes::Bytes;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, WriteBatch, WriteItem};
 use prost::Message;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{btree_map, BTreeMap, BinaryHeap, HashMap};
+use std::time::Duration;
 use xactor::*;
 
 /// A simple transactions pool service
 /// This service is used to store transactions that are not yet included in a block
+/// Pending transactions are queued per signer and ordered by nonce. A signer may have
+/// at most one pending transaction per nonce.
+/// Each pending transaction is persisted under its hash in the txs pool column family.
 #[derive(Debug, Clone, Default)]
 pub(crate) struct MemPoolService {
     pub(crate) transactions: HashMap<Vec<u8>, SignedTransaction>,
+    /// Pending transactions hashes by signer account id, ordered by tx nonce
+    pub(crate) signers_queues: HashMap<Vec<u8>, BTreeMap<u64, Vec<u8>>>,
 }
 
 #[async_trait::async_trait]
 impl Actor for MemPoolService {
-    async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
+    async fn started(&mut self, ctx: &mut Context<Self>) -> Result<()> {
         // load pool transactions from store...
-        if let Some(data) = DatabaseService::read(ReadItem {
-            key: Bytes::from(TXS_POOL_KEY.as_bytes()),
-            cf: TXS_POOL_COL_FAMILY,
-        })
-        .await?
-        {
-            self.transactions = HashMap::new();
-            let mem_pool = MemPool::decode(data.0.as_ref())?;
-            for tx in mem_pool.transactions {
-                self.transactions
-                    .insert(tx.get_hash().unwrap().as_ref().to_vec(), tx);
+        self.transactions = HashMap::new();
+        self.signers_queues = HashMap::new();
+        for (key, value) in DatabaseService::read_all(TXS_POOL_COL_FAMILY).await? {
+            if key.as_ref() == TXS_POOL_KEY.as_bytes() {
+                self.migrate_pool_blob(value).await?;
+                continue;
+            }
+
+            let res = SignedTransaction::decode(value.as_ref())
+                .map_err(anyhow::Error::from)
+                .and_then(|tx| self.insert_transaction(key.to_vec(), tx));
+            if let Err(e) = res {
+                info!("dropping persisted pool tx: {}", e);
+                self.delete_stored_transactions(&[key.to_vec()]).await?;
             }
         }
 
         info!("started. Pool size: {:?}", self.transactions.len());
 
+        let expiry_interval_secs =
+            ServerConfigService::get_u64(MEM_POOL_EXPIRY_INTERVAL_SECS_KEY.into())
+                .await?
+                .unwrap();
+        ctx.send_interval(
+            RemoveOldTransactions,
+            Duration::from_secs(expiry_interval_secs),
+        );
+
         Ok(())
     }
 }
 
 impl Service for MemPoolService {}
 
-#[message(result = "Result<()>")]
+/// Expired transactions removal tick. Sent by the pool to itself on the configured interval
+#[message]
+#[derive(Clone)]
 pub(crate) struct RemoveOldTransactions;
 
 /// Remove old transactions from the pool
 #[async_trait::async_trait]
 impl Handler<RemoveOldTransactions> for MemPoolService {
-    async fn handle(
-        &mut self,
-        _ctx: &mut Context<Self>,
-        _msg: RemoveOldTransactions,
-    ) -> Result<()> {
-        let max_age = ServerConfigService::get_u64(MEM_POOL_MAX_TX_AGE_HOURS.into())
-            .await?
-            .unwrap();
-        let duration = chrono::Duration::hours(max_age as i64).num_milliseconds() as u64;
-        let txs = self.transactions.clone();
-        for (tx_hash, tx) in txs.iter() {
-            let tx_body = match tx.get_body() {
-                Ok(tx_body) => tx_body,
-                Err(_) => {
-                    info!("failed to get tx body... deal with this case");
-                    continue;
-                }
-            };
-
-            if tx_body.timestamp < (chrono::Utc::now().timestamp_millis() as u64 - duration) {
-                self.transactions.remove(tx_hash);
-            }
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: RemoveOldTransactions) {
+        if let Err(e) = self.remove_old_transactions().await {
+            error!("failed to remove old txs from pool: {}", e);
         }
-        self.persist().await
     }
 }
 
@@ -98,6 +106,58 @@ impl Handler<GetTransactions> for MemPoolService {
     }
 }
 
+#[message(result = "Result<Option<SignedTransaction>>")]
+pub(crate) struct GetTransaction(pub(crate) Vec<u8>);
+
+/// Returns a pending transaction by its hash
+#[async_trait::async_trait]
+impl Handler<GetTransaction> for MemPoolService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetTransaction,
+    ) -> Result<Option<SignedTransaction>> {
+        Ok(self.transactions.get(&msg.0).cloned())
+    }
+}
+
+#[message(result = "Result<Vec<SignedTransaction>>")]
+pub(crate) struct GetPendingPayments {
+    pub(crate) account_id: Vec<u8>,
+    pub(crate) mobile_number_hash: Option<Vec<u8>>,
+}
+
+/// Returns pending payments to an account id or to a mobile number hash, newest first
+#[async_trait::async_trait]
+impl Handler<GetPendingPayments> for MemPoolService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetPendingPayments,
+    ) -> Result<Vec<SignedTransaction>> {
+        let mut payments = vec![];
+        for tx in self.transactions.values() {
+            let tx_body = tx.get_body()?;
+            if tx_body.get_tx_type()? != PaymentV1 {
+                continue;
+            }
+
+            let payment = tx_body.get_payment_transaction_v1()?;
+            let to_account = payment
+                .to_account_id
+                .is_some_and(|id| id.data == msg.account_id);
+            let to_number = msg.mobile_number_hash.is_some()
+                && payment.get_payee_number_hash().ok().flatten() == msg.mobile_number_hash;
+            if to_account || to_number {
+                payments.push((tx_body.timestamp, tx.clone()));
+            }
+        }
+
+        payments.sort_by_key(|(timestamp, _)| Reverse(*timestamp));
+        Ok(payments.into_iter().map(|(_, tx)| tx).collect())
+    }
+}
+
 #[message(result = "Result<()>")]
 pub(crate) struct RemoveOnChainTransactions;
 
@@ -109,11 +169,9 @@ impl Handler<RemoveOnChainTransactions> for MemPoolService {
         _ctx: &mut Context<Self>,
         _msg: RemoveOnChainTransactions,
     ) -> Result<()> {
-        let txs = self.transactions.clone();
-
         // remove all txs which are on chain from pool
-        for (tx_hash, _) in txs.iter() {
-            // reject transaction already on chain
+        let mut on_chain_tx_hashes = vec![];
+        for tx_hash in self.transactions.keys() {
             if (DatabaseService::read(ReadItem {
                 key: Bytes::from(tx_hash.clone()),
                 cf: TRANSACTIONS_COL_FAMILY,
@@ -121,53 +179,56 @@ impl Handler<RemoveOnChainTransactions> for MemPoolService {
             .await?)
                 .is_some()
             {
-                self.transactions.remove(tx_hash);
+                on_chain_tx_hashes.push(tx_hash.clone());
             }
         }
-        self.persist().await
+        self.remove_transactions(&on_chain_tx_hashes).await
     }
 }
 
-#[message(result = "Result<()>")]
+#[message(result = "Result<(), AdmissionError>")]
 pub(crate) struct AddTransaction(pub(crate) SignedTransaction);
 
-/// Create a block with zero or more transactions
+/// Validate a submitted transaction and add it to the pool
 #[async_trait::async_trait]
 impl Handler<AddTransaction> for MemPoolService {
-    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: AddTransaction) -> Result<()> {
-        let max_size = ServerConfigService::get_u64(MEM_POOL_MAX_ITEMS_KEY.into())
-            .await?
-            .unwrap();
-
-        if self.transactions.len() > max_size as usize {
-            return Err(anyhow::anyhow!("Mempool is full - transaction discarded"));
-        }
-
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: AddTransaction,
+    ) -> Result<(), AdmissionError> {
         let tx = msg.0;
         let tx_hash = tx.get_hash()?.as_ref().to_vec();
 
-        // reject transaction which already exists on chain
-        if (DatabaseService::read(ReadItem {
-            key: Bytes::from(tx_hash.clone()),
-            cf: TRANSACTIONS_COL_FAMILY,
-        })
-        .await?)
-            .is_some()
-        {
-            return Err(anyhow!("tx already on chain"));
-        }
-
-        // basic common tx validation before inserting to mem pool
-        // note that we don't check nonce here as txs with 'wrong' nonce
-        // may be good after other transactions are processed in the pool
-        tx.validate().await?;
-        let tx_body = tx.get_body()?;
-        tx_body.verify_timestamp()?;
-        tx_body.verify_tx_fee()?;
+        let admission = match self.validate_admission(&tx, &tx_hash).await {
+            Ok(()) => self.make_room(&tx).await,
+            Err(e) => Err(e),
+        };
 
-        self.transactions.insert(tx_hash, tx);
+        let evicted_txs = match admission {
+            Ok(evicted_txs) => evicted_txs,
+            Err(e) => {
+                // a duplicate or on chain tx keeps its current status
+                if !matches!(e.reason, Unspecified | Duplicate | AlreadyOnChain) {
+                    self.record_rejected_transactions(vec![(tx_hash, tx)], &e.to_string())
+                        .await?;
+                }
+                return Err(e);
+            }
+        };
 
-        self.persist().await
+        let evicted_tx_hashes: Vec<Vec<u8>> = evicted_txs.iter().map(|t| t.0.clone()).collect();
+        self.delete_stored_transactions(&evicted_tx_hashes).await?;
+        self.record_rejected_transactions(
+            evicted_txs,
+            "evicted from a full mem pool by a higher fee tx",
+        )
+        .await?;
+
+        self.insert_transaction(tx_hash.clone(), tx.clone())?;
+        self.store_transaction(tx_hash.clone(), &tx).await?;
+        self.clear_rejected_transaction(&tx_hash).await?;
+        Ok(publish_tx_status(tx_hash, Submitted).await?)
     }
 }
 
@@ -187,9 +248,9 @@ impl Handler<RemoveTransactionByHash> for MemPoolService {
             "removing tx by hash: {:?}",
             short_hex_string(msg.0.as_slice())
         );
-        self.transactions.remove(msg.0.as_slice());
+        self.remove_transactions(&[msg.0]).await?;
         info!("count after remove: {:?}", self.transactions.len());
-        self.persist().await
+        Ok(())
     }
 }
 
@@ -204,10 +265,21 @@ impl Handler<RemoveTransactionsByHashes> for MemPoolService {
         _ctx: &mut Context<Self>,
         msg: RemoveTransactionsByHashes,
     ) -> Result<()> {
-        for tx_hash in msg.0.iter() {
-            self.transactions.remove(tx_hash.as_slice());
-        }
-        self.persist().await
+        self.remove_transactions(&msg.0).await
+    }
+}
+
+#[message(result = "Result<()>")]
+pub(crate) struct RejectTransactions {
+    pub(crate) tx_hashes: Vec<Vec<u8>>,
+    pub(crate) reason: String,
+}
+
+/// Remove transactions which will not be executed from the pool and record their rejection
+#[async_trait::async_trait]
+impl Handler<RejectTransactions> for MemPoolService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: RejectTransactions) -> Result<()> {
+        self.reject_transactions(&msg.tx_hashes, &msg.reason).await
     }
 }
 
@@ -218,12 +290,72 @@ pub(crate) struct RemoveTransaction(pub(crate) SignedTransaction);
 #[async_trait::async_trait]
 impl Handler<RemoveTransaction> for MemPoolService {
     async fn handle(&mut self, _ctx: &mut Context<Self>, msg: RemoveTransaction) -> Result<()> {
-        self.transactions
-            .remove(&*msg.0.get_hash().unwrap().as_ref().to_vec());
-        self.persist().await
+        self.remove_transactions(&[msg.0.get_hash()?.as_ref().to_vec()])
+            .await
     }
 }
 
+#[message(result = "Result<Vec<(Vec<u8>, SignedTransaction)>>")]
+pub(crate) struct GetOrderedTransactions;
+
+/// Returns all pool transactions in block assembly order.
+/// New user transactions come first so accounts created in a block exist when the block's
+/// other transactions are executed. Within each of these two groups, transactions are ordered
+/// by fee (highest first), then by timestamp, nonce and hash.
+/// A signer's transactions are always returned in nonce order - a tx is only ordered
+/// after all of its signer's pending txs with lower nonces.
+#[async_trait::async_trait]
+impl Handler<GetOrderedTransactions> for MemPoolService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        _msg: GetOrderedTransactions,
+    ) -> Result<Vec<(Vec<u8>, SignedTransaction)>> {
+        // signers' queues with each queue's head tx as a candidate for the next position
+        let mut queues: HashMap<&Vec<u8>, btree_map::Values<u64, Vec<u8>>> = HashMap::new();
+        let mut candidates = BinaryHeap::new();
+
+        for (signer, queue) in self.signers_queues.iter() {
+            let mut queue = queue.values();
+            if let Some(tx_hash) = queue.next() {
+                candidates.push(Reverse(self.ordering_key(tx_hash, signer)?));
+            }
+            queues.insert(signer, queue);
+        }
+
+        let mut ordered = Vec::with_capacity(self.transactions.len());
+        while let Some(Reverse(key)) = candidates.pop() {
+            ordered.push((key.tx_hash.clone(), self.transactions[&key.tx_hash].clone()));
+
+            if let Some(tx_hash) = queues.get_mut(&key.signer).unwrap().next() {
+                candidates.push(Reverse(self.ordering_key(tx_hash, &key.signer)?));
+            }
+        }
+
+        // stable partition so each group keeps the fee, timestamp, nonce and hash order
+        let (mut new_user_txs, other_txs): (Vec<_>, Vec<_>) =
+            ordered.into_iter().partition(|(_, tx)| {
+                matches!(
+                    tx.get_body().and_then(|body| body.get_tx_type()),
+                    Ok(NewUserV1 | NewUserV2)
+                )
+            });
+        new_user_txs.extend(other_txs);
+
+        Ok(new_user_txs)
+    }
+}
+
+/// Block assembly ordering key of a pool transaction
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct OrderingKey {
+    fee: Reverse<u64>,
+    timestamp: u64,
+    nonce: u64,
+    tx_hash: Vec<u8>,
+    signer: Vec<u8>,
+}
+
 #[message(result = "Result<u64>")]
 pub(crate) struct PoolSize;
 
@@ -236,25 +368,163 @@ impl Handler<PoolSize> for MemPoolService {
 }
 
 impl MemPoolService {
-    /// Persist the mem_pool to the db
-    /// internal helper function
-    async fn persist(&self) -> Result<()> {
-        let mem_pool = MemPool {
-            transactions: self.transactions.values().cloned().collect(),
+    /// Returns the block assembly ordering key of a pool transaction
+    fn ordering_key(&self, tx_hash: &[u8], signer: &[u8]) -> Result<OrderingKey> {
+        let tx_body = self.transactions[tx_hash].get_body()?;
+        Ok(OrderingKey {
+            fee: Reverse(tx_body.fee),
+            timestamp: tx_body.timestamp,
+            nonce: tx_body.nonce,
+            tx_hash: tx_hash.to_vec(),
+            signer: signer.to_vec(),
+        })
+    }
+
+    /// Add a transaction to the pool and to its signer's queue
+    /// Returns an error if the signer already has a pending tx with the same nonce
+    fn insert_transaction(&mut self, tx_hash: Vec<u8>, tx: SignedTransaction) -> Result<()> {
+        let signer = tx
+            .signer
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing tx signer"))?
+            .data
+            .clone();
+        let nonce = tx.get_body()?.nonce;
+
+        let queue = self.signers_queues.entry(signer).or_default();
+        if let Some(pending_tx_hash) = queue.get(&nonce) {
+            if *pending_tx_hash == tx_hash {
+                return Ok(());
+            }
+            return Err(anyhow!(
+                "signer already has a pending tx with nonce {}",
+                nonce
+            ));
+        }
+
+        queue.insert(nonce, tx_hash.clone());
+        self.transactions.insert(tx_hash, tx);
+        Ok(())
+    }
+
+    /// Remove a transaction from the pool and from its signer's queue
+    /// The transaction is not removed from the store
+    pub(crate) fn remove_transaction(&mut self, tx_hash: &[u8]) -> Option<SignedTransaction> {
+        let tx = self.transactions.remove(tx_hash)?;
+
+        let (Some(signer), Ok(tx_body)) = (tx.signer.as_ref(), tx.get_body()) else {
+            return Some(tx);
         };
 
-        let mut buf = Vec::with_capacity(mem_pool.encoded_len());
-        mem_pool.encode(&mut buf)?;
+        if let Some(queue) = self.signers_queues.get_mut(&signer.data) {
+            queue.remove(&tx_body.nonce);
+            if queue.is_empty() {
+                self.signers_queues.remove(&signer.data);
+            }
+        }
+        Some(tx)
+    }
+
+    /// Remove transactions from the pool and from the store
+    async fn remove_transactions(&mut self, tx_hashes: &[Vec<u8>]) -> Result<()> {
+        for tx_hash in tx_hashes.iter() {
+            self.remove_transaction(tx_hash);
+        }
+        self.delete_stored_transactions(tx_hashes).await
+    }
+
+    /// Remove transactions which will not be executed from the pool and record their rejection
+    async fn reject_transactions(&mut self, tx_hashes: &[Vec<u8>], reason: &str) -> Result<()> {
+        let mut rejected_txs = vec![];
+        for tx_hash in tx_hashes.iter() {
+            if let Some(tx) = self.remove_transaction(tx_hash) {
+                rejected_txs.push((tx_hash.clone(), tx));
+            }
+        }
+        self.delete_stored_transactions(tx_hashes).await?;
+        self.record_rejected_transactions(rejected_txs, reason)
+            .await
+    }
 
-        // persist the mem_pool
+    /// Remove all transactions which are older than the max tx age from the pool
+    async fn remove_old_transactions(&mut self) -> Result<()> {
+        let max_age = ServerConfigService::get_u64(MEM_POOL_MAX_TX_AGE_HOURS.into())
+            .await?
+            .unwrap();
+        let duration = chrono::Duration::hours(max_age as i64).num_milliseconds() as u64;
+        let min_timestamp = (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(duration);
+
+        let mut old_tx_hashes = vec![];
+        for (tx_hash, tx) in self.transactions.iter() {
+            let tx_body = match tx.get_body() {
+                Ok(tx_body) => tx_body,
+                Err(_) => {
+                    info!("failed to get tx body... deal with this case");
+                    continue;
+                }
+            };
+
+            if tx_body.timestamp < min_timestamp {
+                old_tx_hashes.push(tx_hash.clone());
+            }
+        }
+
+        if !old_tx_hashes.is_empty() {
+            info!("removing {} expired txs from pool", old_tx_hashes.len());
+        }
+        self.reject_transactions(&old_tx_hashes, "expired in the mem pool")
+            .await
+    }
+
+    /// Store a pool transaction in the db keyed by its hash
+    async fn store_transaction(&self, tx_hash: Vec<u8>, tx: &SignedTransaction) -> Result<()> {
         DatabaseService::write(WriteItem {
             data: DataItem {
-                key: Bytes::from(TXS_POOL_KEY.as_bytes()),
-                value: Bytes::from(buf),
+                key: Bytes::from(tx_hash),
+                value: Bytes::from(tx.encode_to_vec()),
             },
             cf: TXS_POOL_COL_FAMILY,
             ttl: 0,
         })
         .await
     }
+
+    /// Delete pool transactions from the db
+    async fn delete_stored_transactions(&self, tx_hashes: &[Vec<u8>]) -> Result<()> {
+        if tx_hashes.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = WriteBatch::default();
+        for tx_hash in tx_hashes.iter() {
+            batch.delete(DeleteItem {
+                key: Bytes::from(tx_hash.clone()),
+                cf: TXS_POOL_COL_FAMILY,
+            });
+        }
+        DatabaseService::write_batch(batch).await
+    }
+
+    /// Move the txs of a pool which was persisted as a single MemPool value to their own keys
+    async fn migrate_pool_blob(&mut self, data: Bytes) -> Result<()> {
+        let mem_pool = MemPool::decode(data.as_ref())?;
+        info!(
+            "migrating {} persisted pool txs",
+            mem_pool.transactions.len()
+        );
+
+        for tx in mem_pool.transactions {
+            let tx_hash = tx.get_hash()?.as_ref().to_vec();
+            match self.insert_transaction(tx_hash.clone(), tx.clone()) {
+                Ok(()) => self.store_transaction(tx_hash, &tx).await?,
+                Err(e) => info!("dropping persisted pool tx: {}", e),
+            }
+        }
+
+        DatabaseService::delete(DeleteItem {
+            key: Bytes::from(TXS_POOL_KEY.as_bytes()),
+            cf: TXS_POOL_COL_FAMILY,
+        })
+        .await
+    }
 }
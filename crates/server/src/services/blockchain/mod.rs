@@ -4,12 +4,14 @@
 
 pub(crate) mod backup_chain_service;
 pub(crate) mod backup_chain_task;
+pub(crate) mod block_batch;
 pub(crate) mod block_creator;
 pub(crate) mod block_event;
 /// Blockchain module provides low-level blockchain functionality such as creating blocks and processing transactions
 pub(crate) mod blockchain_service;
 pub(crate) mod blocks_store;
 pub(crate) mod delete_user_tx_processor;
+pub(crate) mod event_subscribers;
 pub mod get_all_users;
 pub mod get_contacts;
 pub mod get_leader_board;
@@ -18,13 +20,22 @@ pub mod get_user_by_number;
 pub mod get_user_by_user_name;
 pub(crate) mod karma_rewards_service;
 pub(crate) mod leader_board_upsert;
+pub(crate) mod ledger_checker;
+pub(crate) mod ledger_replay;
+pub(crate) mod mem_pool_admission;
 pub(crate) mod mem_pool_service;
 pub(crate) mod new_user_tx_processor;
+pub(crate) mod params_update_tx_processor;
 pub(crate) mod payment_tx_processor;
+pub(crate) mod pending_payments;
+pub(crate) mod proofs;
 pub mod set_community_admin;
 pub(crate) mod stats;
 pub(crate) mod tokenomics;
+pub(crate) mod tokenomics_simulator;
 pub(crate) mod tx_event;
+pub(crate) mod tx_status;
 pub(crate) mod txs_processor;
 pub(crate) mod txs_store;
 pub(crate) mod update_tx_processor;
+pub(crate) mod verifier_update_tx_processor;
@@ -2,9 +2,11 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use crate::base::signed_trait::SignedTrait;
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::delete_user_tx_processor::validate_user_name_released;
 use crate::services::blockchain::tokenomics::Tokenomics;
+use crate::services::blockchain::verifier_update_tx_processor::validate_verifiers_quorum;
 use crate::services::db_config_service::{
     MOBILE_NUMBERS_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY,
 };
@@ -12,18 +14,31 @@ use anyhow::Result;
 use base::genesis_config_service::SIGNUP_CHAR_TRAIT_ID;
 use base::hex_utils::short_hex_string;
 use base::karma_coin::karma_coin_core_types::{
-    CommunityMembership, ExecutionInfo, ExecutionResult, FeeType, SignedTransaction, TraitScore,
-    TransactionBody, TransactionEvent, TransactionType, User,
+    AccountId, Balance, CommunityMembership, ExecutionInfo, ExecutionResult, FeeType,
+    SignedTransaction, TraitScore, TransactionBody, TransactionEvent, TransactionType, User,
 };
+use base::mobile_number::mobile_number_hash;
+use base::supply::move_free_funds;
+use base::user_verification_data_ex::get_evidence_ex_verifiers;
 use bytes::Bytes;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use db::db_service::{DataItem, ReadItem, WriteItem};
 use prost::Message;
 
-static OLD_ACCOUNT: &str = " [old account]";
+pub(crate) static OLD_ACCOUNT: &str = " [old account]";
 
 #[derive(Debug, Clone)]
 pub(crate) struct NewUserProcessingResponse {
-    pub(crate) mobile_number: String,
+    pub(crate) mobile_number_hash: Vec<u8>,
+    /// events of pending payments to the new user's number which it claimed
+    pub(crate) claimed_payments_events: Vec<TransactionEvent>,
+}
+
+/// User data attested by a new user tx verification evidence
+struct VerifiedUser {
+    account_id: Option<AccountId>,
+    requested_user_name: String,
+    /// verified mobile number hash. Only the hash of a number is stored on chain
+    mobile_number_hash: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +55,7 @@ impl BlockChainService {
         signed_transaction: &SignedTransaction,
         tokenomics: &Tokenomics,
         event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
     ) -> Result<NewUserProcessingResponse, NewUserProcessingError> {
         let account_id =
             signed_transaction
@@ -58,7 +74,6 @@ impl BlockChainService {
             })?;
 
         // validate tx syntax, fields, signature, net_id before processing it
-        // new user transaction should always have nonce of 0
         signed_transaction
             .validate()
             .await
@@ -84,81 +99,50 @@ impl BlockChainService {
                 error_message: "Invalid transaction data".into(),
             })?;
 
+        // a new account's nonce is 0 so its signup tx must be its first tx
+        if tx_body.nonce != 1 {
+            return Err(NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: "new user tx nonce must be 1".into(),
+            });
+        }
+
         let tx_fee_amount = tx_body.fee;
-        let new_user_tx =
-            tx_body
-                .get_new_user_transaction_v1()
-                .map_err(|_| NewUserProcessingError {
-                    execution_info: ExecutionInfo::InvalidData,
-                    error_message: "Invalid new user tx data".into(),
-                })?;
+        let tx_type = tx_body.get_tx_type().map_err(|_| NewUserProcessingError {
+            execution_info: ExecutionInfo::InvalidData,
+            error_message: "Invalid transaction data".into(),
+        })?;
 
-        let verification_evidence =
-            new_user_tx
-                .verify_number_response
-                .ok_or_else(|| NewUserProcessingError {
+        // V1 evidence attests to the mobile number, which would be stored on chain with the tx,
+        // so only V2 evidence which attests to the number hash is accepted
+        let verified_user = match tx_type {
+            TransactionType::NewUserV2 => self.verify_new_user_v2(&tx_body, event, batch).await?,
+            TransactionType::NewUserV1 => {
+                return Err(NewUserProcessingError {
                     execution_info: ExecutionInfo::InvalidData,
-                    error_message: "missing verification evidence".into(),
-                })?;
-
-        // todo: verify verifier is valid according to consensus rules and genesis config
-
-        // verify evidence signature
-        verification_evidence
-            .verify_signature()
-            .map_err(|_| NewUserProcessingError {
-                execution_info: ExecutionInfo::InvalidData,
-                error_message: "invalid verification signature".into(),
-            })?;
-
-        // verify verifier is valid according to consensus rules and genesis config
-        let verifier_key = self
-            .verifier_key_pair
-            .as_ref()
-            .unwrap()
-            .public_key
-            .as_ref()
-            .unwrap()
-            .key
-            .clone();
-
-        if let Some(verifier_account_id) = verification_evidence.verifier_account_id.as_ref() {
-            if verifier_account_id.data != verifier_key {
+                    error_message: "new user v1 txs are not accepted - use new user v2 txs".into(),
+                })
+            }
+            _ => {
                 return Err(NewUserProcessingError {
                     execution_info: ExecutionInfo::InvalidData,
-                    error_message: format!(
-                        "Unrecognized verifier. provided: {}, expected: {}",
-                        short_hex_string(verifier_account_id.data.as_ref()),
-                        short_hex_string(verifier_key.as_ref())
-                    ),
-                });
+                    error_message: "Invalid new user tx data".into(),
+                })
             }
-        } else {
-            return Err(NewUserProcessingError {
-                execution_info: ExecutionInfo::InvalidData,
-                error_message: "Missing verifier account id in verification evidence".into(),
-            });
-        }
+        };
 
         info!("verifier verified :-)");
 
-        if verification_evidence.requested_user_name.trim().is_empty() {
+        if verified_user.requested_user_name.trim().is_empty() {
             return Err(NewUserProcessingError {
                 execution_info: ExecutionInfo::InvalidData,
                 error_message: "Requested user name must not be empty".into(),
             });
         }
 
-        let mobile_number =
-            verification_evidence
-                .mobile_number
-                .ok_or_else(|| NewUserProcessingError {
-                    execution_info: ExecutionInfo::InvalidData,
-                    error_message: "missing mobile number in evidence".into(),
-                })?;
-
+        let number_hash = verified_user.mobile_number_hash;
         let evidence_account_id =
-            verification_evidence
+            verified_user
                 .account_id
                 .ok_or_else(|| NewUserProcessingError {
                     execution_info: ExecutionInfo::InvalidData,
@@ -173,15 +157,16 @@ impl BlockChainService {
         }
 
         // Check user account id is not already on chain
-        if (DatabaseService::read(ReadItem {
-            key: Bytes::from(account_id.data.clone()),
-            cf: USERS_COL_FAMILY,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InvalidData,
-            error_message: "internal node error".into(),
-        }))?
+        if (batch
+            .read(ReadItem {
+                key: Bytes::from(account_id.data.clone()),
+                cf: USERS_COL_FAMILY,
+            })
+            .await
+            .map_err(|_| NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: "internal node error".into(),
+            }))?
         .is_some()
         {
             return Err(NewUserProcessingError {
@@ -194,24 +179,27 @@ impl BlockChainService {
         // check for existing account with this phone number
         let mut existing_account: Option<User> = None;
 
-        if let Some(existing_user_account_id_data) = DatabaseService::read(ReadItem {
-            key: Bytes::from(mobile_number.number.as_bytes().to_vec()),
-            cf: MOBILE_NUMBERS_COL_FAMILY,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InvalidData,
-            error_message: "internal node error".into(),
-        })? {
-            match DatabaseService::read(ReadItem {
-                key: existing_user_account_id_data.0,
-                cf: USERS_COL_FAMILY,
+        if let Some(existing_user_account_id_data) = batch
+            .read(ReadItem {
+                key: Bytes::from(number_hash.clone()),
+                cf: MOBILE_NUMBERS_COL_FAMILY,
             })
             .await
             .map_err(|_| NewUserProcessingError {
                 execution_info: ExecutionInfo::InvalidData,
                 error_message: "internal node error".into(),
-            })? {
+            })?
+        {
+            match batch
+                .read(ReadItem {
+                    key: existing_user_account_id_data.0,
+                    cf: USERS_COL_FAMILY,
+                })
+                .await
+                .map_err(|_| NewUserProcessingError {
+                    execution_info: ExecutionInfo::InvalidData,
+                    error_message: "internal node error".into(),
+                })? {
                 Some(existing_user_data) => {
                     existing_account =
                         Some(User::decode(existing_user_data.0.as_ref()).map_err(|_| {
@@ -231,22 +219,23 @@ impl BlockChainService {
 
         info!(
             "new user transaction for {}, {}, accountId: {}",
-            verification_evidence.requested_user_name,
-            mobile_number.number,
+            verified_user.requested_user_name,
+            short_hex_string(&number_hash),
             short_hex_string(account_id.data.as_ref()),
         );
 
         // Check requested user name is not already on chain only if we are
         // NOT migrating an old account with this tx
-        if (DatabaseService::read(ReadItem {
-            key: Bytes::from(verification_evidence.requested_user_name.clone()),
-            cf: USERS_NAMES_COL_FAMILY,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InvalidData,
-            error_message: "internal node error".into(),
-        }))?
+        if (batch
+            .read(ReadItem {
+                key: Bytes::from(verified_user.requested_user_name.clone()),
+                cf: USERS_NAMES_COL_FAMILY,
+            })
+            .await
+            .map_err(|_| NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: "internal node error".into(),
+            }))?
         .is_some()
             && existing_account.is_none()
         {
@@ -256,6 +245,20 @@ impl BlockChainService {
             });
         }
 
+        // a deleted account's user name is only available after its cooldown
+        if existing_account.is_none() {
+            validate_user_name_released(
+                &verified_user.requested_user_name,
+                tx_body.timestamp,
+                batch,
+            )
+            .await
+            .map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::NicknameNotAvailable,
+                error_message: e.to_string(),
+            })?;
+        }
+
         //
         // end of user data validation part
         //
@@ -273,23 +276,18 @@ impl BlockChainService {
         let mut new_user = User {
             account_id: Some(account_id.clone()),
             nonce: 1, // signup tx nonce is 1, so the next tx nonce should be 2
-            user_name: verification_evidence.requested_user_name.clone(),
-            mobile_number: Some(mobile_number.clone()),
-            balance: 0,
+            user_name: verified_user.requested_user_name.clone(),
+            mobile_number: None,
+            mobile_number_hash: number_hash.clone(),
+            legacy_balance: 0,
             trait_scores: vec![sign_up_trait_score],
             pre_keys: vec![],
             karma_score: 1, // initial karma score is 1 for getting the signup trait score
             community_memberships: community_memberships.clone(),
+            balance: Some(Balance::default()),
         };
 
-        let mut signup_reward_amount =
-            tokenomics
-                .get_signup_reward_amount()
-                .await
-                .map_err(|_| NewUserProcessingError {
-                    execution_info: ExecutionInfo::InternalNodeError,
-                    error_message: "internal node error".into(),
-                })?;
+        let mut signup_reward_amount = tokenomics.get_signup_reward_amount();
 
         if let Some(old_user) = existing_account.as_mut() {
             info!("migrating old account to new one...");
@@ -300,9 +298,12 @@ impl BlockChainService {
                 old_user.user_name += OLD_ACCOUNT;
             }
 
-            // copy over balance from old user
-            new_user.balance = old_user.balance;
-            old_user.balance = 0;
+            // move over free and frozen funds from old user. Reserved funds stay with the old
+            // account as they are reserved for its pending payments
+            move_free_funds(old_user, &mut new_user).map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::InternalNodeError,
+                error_message: e.to_string(),
+            })?;
 
             // overwrite with existing user scores
             new_user.trait_scores = old_user.trait_scores.clone();
@@ -313,14 +314,20 @@ impl BlockChainService {
 
             new_user.karma_score = old_user.karma_score;
             old_user.karma_score = 0;
+
+            // the number must not remain on chain on the old account
+            old_user.mobile_number = None;
+            old_user.mobile_number_hash = number_hash.clone();
+
             // no signup reward when migrating an old account
             signup_reward_amount = 0;
         }
 
         // hack to set admin for specific numbers in test community. should be handled by admin api / sudo
-        if (mobile_number.number == "+972549805380" || mobile_number.number == "+972549805381")
-            && new_user.get_community_membership(1).is_none()
-        {
+        let is_test_admin = ["+972549805380", "+972549805381"]
+            .iter()
+            .any(|n| mobile_number_hash(n).is_ok_and(|h| h == number_hash));
+        if is_test_admin && new_user.get_community_membership(1).is_none() {
             new_user.community_memberships.push(CommunityMembership {
                 community_id: 1,
                 karma_score: 1, // initial community karma score is 1 for joining
@@ -328,13 +335,7 @@ impl BlockChainService {
             });
         }
 
-        let apply_subsidy = tokenomics
-            .should_subsidise_transaction_fee(0, tx_fee_amount, TransactionType::NewUserV1)
-            .await
-            .map_err(|_| NewUserProcessingError {
-                execution_info: ExecutionInfo::InternalNodeError,
-                error_message: "internal node error".into(),
-            })?;
+        let apply_subsidy = tokenomics.should_subsidise_transaction_fee(0, tx_fee_amount, tx_type);
 
         info!("signup reward amount: {}", signup_reward_amount);
 
@@ -356,9 +357,23 @@ impl BlockChainService {
             FeeType::User
         };
 
-        new_user.balance += signup_reward_amount - user_tx_fee_amount;
+        new_user
+            .deposit(signup_reward_amount - user_tx_fee_amount)
+            .map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::InternalNodeError,
+                error_message: e.to_string(),
+            })?;
 
-        info!("new user balance: {}", new_user.balance);
+        info!("new user balance: {}", new_user.free_balance());
+
+        // pay the new user pending payments to its mobile number
+        let claimed_payments_events = self
+            .claim_pending_payments(&mut new_user, &number_hash, tokenomics, event.height, batch)
+            .await
+            .map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::InternalNodeError,
+                error_message: format!("failed to claim pending payments: {}", e),
+            })?;
 
         // persist the new user to db
 
@@ -370,19 +385,14 @@ impl BlockChainService {
                 error_message: "internal node error".into(),
             })?;
 
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(account_id.data.to_vec()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "internal node error".into(),
-        })?;
+        });
 
         info!("added user to db");
 
@@ -397,50 +407,35 @@ impl BlockChainService {
                     error_message: "internal node error".into(),
                 })?;
 
-            DatabaseService::write(WriteItem {
+            batch.write(WriteItem {
                 data: DataItem {
                     key: Bytes::from(old_user.account_id.unwrap().data.to_vec()),
                     value: Bytes::from(buf1),
                 },
                 cf: USERS_COL_FAMILY,
                 ttl: 0,
-            })
-            .await
-            .map_err(|_| NewUserProcessingError {
-                execution_info: ExecutionInfo::InternalNodeError,
-                error_message: "internal node error".into(),
-            })?;
+            });
         }
 
         // update users names index
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(new_user.user_name.as_bytes().to_vec()),
                 value: Bytes::from(account_id.data.to_vec()),
             },
             cf: USERS_NAMES_COL_FAMILY,
             ttl: 0,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InvalidData,
-            error_message: "internal node error".into(),
-        })?;
+        });
 
         // update mobile numbers index
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
-                key: Bytes::from(mobile_number.number.as_bytes().to_vec()),
+                key: Bytes::from(number_hash.clone()),
                 value: Bytes::from(account_id.data.to_vec()),
             },
             cf: MOBILE_NUMBERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "internal node error".into(),
-        })?;
+        });
 
         let mut tx_data = Vec::with_capacity(signed_transaction.encoded_len());
         info!(
@@ -456,24 +451,21 @@ impl BlockChainService {
             })?;
 
         // index the transaction in the db by hash
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: tx_hash.clone(),
                 value: Bytes::from(tx_data),
             },
             cf: TRANSACTIONS_COL_FAMILY,
             ttl: 0,
-        })
-        .await
-        .map_err(|_| NewUserProcessingError {
-            execution_info: ExecutionInfo::InternalNodeError,
-            error_message: "internal node error".into(),
-        })?;
+        });
 
         // index the transaction in the db by signer account id
         self.index_transaction_by_account_id(
             signed_transaction,
             Bytes::from(account_id.data.to_vec()),
+            event.height,
+            batch,
         )
         .await
         .map_err(|_| NewUserProcessingError {
@@ -488,7 +480,50 @@ impl BlockChainService {
         event.result = ExecutionResult::Executed as i32;
 
         Ok(NewUserProcessingResponse {
-            mobile_number: mobile_number.number.clone(),
+            mobile_number_hash: number_hash,
+            claimed_payments_events,
+        })
+    }
+
+    /// Verify a new user v2 tx evidence and return the verified user data
+    async fn verify_new_user_v2(
+        &self,
+        tx_body: &TransactionBody,
+        event: &TransactionEvent,
+        batch: &BlockBatch,
+    ) -> Result<VerifiedUser, NewUserProcessingError> {
+        let new_user_tx = tx_body
+            .get_new_user_transaction_v2()
+            .and_then(|tx| tx.verify_syntax().map(|_| tx))
+            .map_err(|_| NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: "Invalid new user tx data".into(),
+            })?;
+
+        // verify evidence signatures and that the evidence was signed by a quorum of the
+        // verifiers in the verifiers registry which are valid at the block height
+        let evidence = new_user_tx.get_evidence();
+        let (data, verifiers) = get_evidence_ex_verifiers(&evidence.iter().collect::<Vec<_>>())
+            .map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: e.to_string(),
+            })?;
+        validate_verifiers_quorum(&verifiers, event.height, batch)
+            .await
+            .map_err(|e| NewUserProcessingError {
+                execution_info: ExecutionInfo::InvalidData,
+                error_message: e.to_string(),
+            })?;
+
+        Ok(VerifiedUser {
+            mobile_number_hash: data.get_mobile_number_hash().map_err(|e| {
+                NewUserProcessingError {
+                    execution_info: ExecutionInfo::InvalidData,
+                    error_message: e.to_string(),
+                }
+            })?,
+            account_id: data.account_id,
+            requested_user_name: data.requested_user_name,
         })
     }
 }
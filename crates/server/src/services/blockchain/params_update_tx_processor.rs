@@ -0,0 +1,171 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::stats::get_stats;
+use crate::services::db_config_service::{
+    PARAMS_UPDATES_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY,
+};
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::{GenesisConfigService, SetParamsUpdates, ValidateParamsUpdates};
+use base::karma_coin::karma_coin_core_types::{
+    ExecutionResult, FeeType, ParamsUpdate, SignedTransaction, TransactionEvent, User,
+};
+use bytes::Bytes;
+use db::db_service::{DataItem, DatabaseService, WriteItem};
+use prost::Message;
+use xactor::*;
+
+/// Helper function - key of a params update in the params updates col family
+fn params_update_key(params_update: &ParamsUpdate) -> Bytes {
+    let mut key = Vec::with_capacity(16 + params_update.tx_hash.len());
+    key.extend_from_slice(&params_update.target_height().to_be_bytes());
+    key.extend_from_slice(&params_update.height.to_be_bytes());
+    key.extend_from_slice(&params_update.tx_hash);
+    Bytes::from(key)
+}
+
+impl BlockChainService {
+    /// Load all executed params updates so the genesis params in effect at each block height
+    /// are available for blocks production
+    pub(crate) async fn load_params_updates(&self) -> Result<()> {
+        let mut params_updates = vec![];
+        for (_, value) in DatabaseService::read_all(PARAMS_UPDATES_COL_FAMILY).await? {
+            params_updates.push(ParamsUpdate::decode(value.as_ref())?);
+        }
+
+        info!("loaded {} params updates", params_updates.len());
+        GenesisConfigService::from_registry()
+            .await?
+            .call(SetParamsUpdates {
+                params_updates,
+                next_height: get_stats().await?.tip_height + 1,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Process a params update transaction. The update takes effect from its target height.
+    /// params_updates are the updates executed earlier in the same block, which are not
+    /// yet known to the genesis config service.
+    pub(crate) async fn process_params_update_transaction(
+        &mut self,
+        signed_transaction: &SignedTransaction,
+        user: &mut User,
+        params_updates: &[ParamsUpdate],
+        event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<ParamsUpdate> {
+        let account_id = signed_transaction
+            .signer
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing account id in tx"))?;
+        let tx_hash = signed_transaction.get_hash()?;
+
+        // validate tx syntax, fields, signature, net_id before processing it
+        signed_transaction.validate().await?;
+        let tx_body = signed_transaction.get_body()?;
+        tx_body.validate(user.nonce).await?;
+
+        let params_update_tx = tx_body.get_params_update_transaction_v1()?;
+        let proposal = params_update_tx
+            .proposal
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing params update proposal"))?;
+        proposal.verify_syntax()?;
+
+        if proposal.target_height <= event.height {
+            return Err(anyhow!(
+                "target height {} must be after the tx block height {}",
+                proposal.target_height,
+                event.height
+            ));
+        }
+
+        // approvals are verified against the admins in effect at the tx block height
+        let genesis_data = GenesisConfigService::get_genesis_data_at(event.height).await?;
+        if genesis_data.governance_admins.is_empty() {
+            return Err(anyhow!("params updates are disabled"));
+        }
+
+        let approvers = params_update_tx
+            .get_approvers(&genesis_data.governance_admins, &genesis_data.genesis_hash)?;
+        if (approvers.len() as u64) < genesis_data.governance_threshold {
+            return Err(anyhow!(
+                "params update has {} approvals. Required: {}",
+                approvers.len(),
+                genesis_data.governance_threshold
+            ));
+        }
+
+        let params_update = ParamsUpdate {
+            tx_hash: tx_hash.to_vec(),
+            height: event.height,
+            proposal: Some(proposal.clone()),
+            approvers,
+        };
+
+        let mut new_params_updates = params_updates.to_vec();
+        new_params_updates.push(params_update.clone());
+        GenesisConfigService::from_registry()
+            .await?
+            .call(ValidateParamsUpdates {
+                params_updates: new_params_updates,
+            })
+            .await??;
+
+        // params updates are not subsidised
+        let tx_fee = tx_body.fee;
+        if tx_fee > user.spendable_balance() {
+            return Err(anyhow!("tx fee is greater than user balance"));
+        }
+        user.withdraw(tx_fee)?;
+        user.nonce += 1;
+
+        batch.write(WriteItem {
+            data: DataItem {
+                key: Bytes::from(account_id.data.clone()),
+                value: Bytes::from(user.encode_to_vec()),
+            },
+            cf: USERS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        batch.write(WriteItem {
+            data: DataItem {
+                key: params_update_key(&params_update),
+                value: Bytes::from(params_update.encode_to_vec()),
+            },
+            cf: PARAMS_UPDATES_COL_FAMILY,
+            ttl: 0,
+        });
+
+        // index the transaction in the db by hash
+        batch.write(WriteItem {
+            data: DataItem {
+                key: tx_hash.clone(),
+                value: Bytes::from(signed_transaction.encode_to_vec()),
+            },
+            cf: TRANSACTIONS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        // index the transaction in the db by signer account id
+        self.index_transaction_by_account_id(
+            signed_transaction,
+            Bytes::from(account_id.data.to_vec()),
+            event.height,
+            batch,
+        )
+        .await?;
+
+        event.fee = tx_fee;
+        event.fee_type = FeeType::User as i32;
+        event.result = ExecutionResult::Executed as i32;
+
+        info!("params update executed: {}", params_update);
+        Ok(params_update)
+    }
+}
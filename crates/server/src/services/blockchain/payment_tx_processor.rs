@@ -2,6 +2,7 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::blockchain::tokenomics::Tokenomics;
 use crate::services::db_config_service::{
@@ -17,31 +18,42 @@ use base::karma_coin::karma_coin_core_types::{
     TransactionBody, TransactionEvent, TransactionType, User,
 };
 use base::karma_coin_format::format_kc_amount;
+use base::supply::transfer;
 use bytes::Bytes;
 use data_encoding::BASE64;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use db::db_service::{DataItem, ReadItem, WriteItem};
 use prost::Message;
 use std::collections::HashMap;
 
+/// A payment transaction which passed validation and the fee its payer pays
+pub(crate) struct ValidatedPayment {
+    pub(crate) tx_body: TransactionBody,
+    pub(crate) payment_tx: PaymentTransactionV1,
+    /// 0 when the fee is subsidised by the protocol
+    pub(crate) user_tx_fee_amount: u64,
+    pub(crate) fee_type: FeeType,
+}
+
 impl BlockChainService {
-    /// Get payee User from chain from the body of a payment transaction
+    /// Get payee User from chain from the body of a payment transaction.
+    /// Pending changes in the provided batch are taken into account.
     pub(crate) async fn get_payee_user_from_tx_body(
         tx_body: &TransactionBody,
+        batch: &BlockBatch,
     ) -> Result<Option<User>> {
         let payment_tx: PaymentTransactionV1 = tx_body.get_payment_transaction_v1()?;
 
         // find payee account id by phone number of from tx to_account_id field
-        let payee_account_id = match payment_tx.to_number {
-            Some(to_number) => {
-                let mobile_number: String = to_number.number;
-
-                // locate payee's account Id by mobile number form the index
+        let payee_account_id = match payment_tx.get_payee_number_hash()? {
+            Some(number_hash) => {
+                // locate payee's account Id by mobile number hash form the index
                 // note that this index always have the last created account with this phone number
-                let payee_account_id_data = DatabaseService::read(ReadItem {
-                    key: Bytes::from(mobile_number.as_bytes().to_vec()),
-                    cf: MOBILE_NUMBERS_COL_FAMILY,
-                })
-                .await?;
+                let payee_account_id_data = batch
+                    .read(ReadItem {
+                        key: Bytes::from(number_hash),
+                        cf: MOBILE_NUMBERS_COL_FAMILY,
+                    })
+                    .await?;
 
                 if payee_account_id_data.is_none() {
                     return Ok(None);
@@ -52,11 +64,12 @@ impl BlockChainService {
             None => payment_tx.to_account_id.as_ref().unwrap().data.clone(),
         };
 
-        let payee_user_data = DatabaseService::read(ReadItem {
-            key: Bytes::from(payee_account_id.clone()),
-            cf: USERS_COL_FAMILY,
-        })
-        .await?;
+        let payee_user_data = batch
+            .read(ReadItem {
+                key: Bytes::from(payee_account_id.clone()),
+                cf: USERS_COL_FAMILY,
+            })
+            .await?;
 
         if payee_user_data.is_none() {
             warn!("payee account not found on chain");
@@ -73,11 +86,76 @@ impl BlockChainService {
         signed_transaction: &SignedTransaction,
     ) -> Result<Option<User>> {
         let tx_body = signed_transaction.get_body()?;
-        BlockChainService::get_payee_user_from_tx_body(&tx_body).await
+        BlockChainService::get_payee_user_from_tx_body(&tx_body, &BlockBatch::default()).await
+    }
+
+    /// Validate a payment transaction signed by payer, including the payer's balance.
+    /// Returns the payment and the tx fee to be paid by the payer
+    pub(crate) async fn validate_payment_transaction(
+        signed_transaction: &SignedTransaction,
+        payer: &User,
+        tokenomics: &Tokenomics,
+    ) -> Result<ValidatedPayment> {
+        // validate the transaction
+        signed_transaction.validate().await?;
+        let tx_body = signed_transaction.get_body()?;
+
+        // validate tx body and user nonce
+        tx_body.validate(payer.nonce).await?;
+
+        info!("Processing payment transaction: {}", signed_transaction);
+        info!("Body: {}", tx_body);
+        info!("From user: {}", payer);
+
+        let payment_tx: PaymentTransactionV1 = tx_body.get_payment_transaction_v1()?;
+        payment_tx.verify_syntax()?;
+
+        if payer.account_id.as_ref().unwrap().data != payment_tx.from.as_ref().unwrap().data {
+            return Err(anyhow!(
+                "From account in payment tx must be the same as the signer account "
+            ));
+        }
+
+        info!("Payment data: {}", payment_tx);
+
+        let apply_subsidy =
+            tokenomics.should_subsidise_transaction_fee(0, tx_body.fee, TransactionType::PaymentV1);
+
+        info!("fee subsidised applied: {}", apply_subsidy);
+
+        // actual fee amount to be paid by the user. 0 if fee is subsidised by the protocol
+        let user_tx_fee_amount = if apply_subsidy { 0 } else { tx_body.fee };
+
+        let fee_type = if apply_subsidy {
+            FeeType::Mint
+        } else {
+            FeeType::User
+        };
+
+        let total_amount = payment_tx
+            .amount
+            .checked_add(user_tx_fee_amount)
+            .ok_or_else(|| anyhow!("payment amount overflow"))?;
+
+        if payer.spendable_balance() < total_amount {
+            // we reject the transaction and don't mint tx fee subsidy in this case
+            // to avoid spamming the network with txs with insufficient funds
+            return Err(anyhow!(format!(
+                "payer has insufficient balance to pay. spendable balance: {}, amount: {}, user tx fee: {}",
+                payer.spendable_balance(), payment_tx.amount, user_tx_fee_amount
+            )));
+        }
+
+        Ok(ValidatedPayment {
+            tx_body,
+            payment_tx,
+            user_tx_fee_amount,
+            fee_type,
+        })
     }
 
     /// Process a user to user appreciation part of a payment transaction
-    fn process_community_appreciation(
+    pub(crate) fn process_community_appreciation(
         &mut self,
         payer: &mut User,
         payee: &mut User,
@@ -133,6 +211,7 @@ impl BlockChainService {
 
     /// Process a payment transaction from payer to payee - update ledger state, emit tx event
     /// This is a helper method for the block creator and is used as part of block creation flow
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn process_payment_transaction(
         &mut self,
         signed_transaction: &SignedTransaction,
@@ -141,6 +220,7 @@ impl BlockChainService {
         sign_ups: &mut HashMap<Vec<u8>, SignedTransaction>,
         tokenomics: &Tokenomics,
         event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
         let tx_hash = signed_transaction.get_hash()?;
 
@@ -154,66 +234,27 @@ impl BlockChainService {
             return Err(anyhow!("You can't send karma coins to yourself"));
         }
 
-        // validate the transaction
-        signed_transaction.validate().await?;
-        let tx_body = signed_transaction.get_body()?;
-
-        // validate tx body and user nonce
-        tx_body.validate(payer.nonce).await?;
+        let ValidatedPayment {
+            tx_body,
+            payment_tx,
+            user_tx_fee_amount,
+            fee_type,
+        } = BlockChainService::validate_payment_transaction(signed_transaction, payer, tokenomics)
+            .await?;
 
-        info!("Processing payment transaction: {}", signed_transaction);
-        info!("Body: {}", tx_body);
-        info!("From user: {}", payer);
         info!("To user: {}", payee);
 
-        let payment_tx: PaymentTransactionV1 = tx_body.get_payment_transaction_v1()?;
-        payment_tx.verify_syntax()?;
-
-        if payer.account_id.as_ref().unwrap().data != payment_tx.from.as_ref().unwrap().data {
-            return Err(anyhow!(
-                "From account in payment tx must be the same as the signer account "
-            ));
-        }
-
-        info!("Payment data: {}", payment_tx);
-
         let payment_amount = payment_tx.amount;
 
-        let apply_subsidy = tokenomics
-            .should_subsidise_transaction_fee(0, tx_body.fee, TransactionType::PaymentV1)
-            .await?;
-
-        info!("fee subsidised applied: {}", apply_subsidy);
-
-        // actual fee amount to be paid by the user. 0 if fee is subsidised by the protocol
-        let user_tx_fee_amount = if apply_subsidy { 0 } else { tx_body.fee };
-
-        let fee_type = if apply_subsidy {
-            FeeType::Mint
-        } else {
-            FeeType::User
-        };
-
-        if payer.balance < payment_amount + user_tx_fee_amount {
-            // we reject the transaction and don't mint tx fee subsidy in this case
-            // to avoid spamming the network with txs with insufficient funds
-            return Err(anyhow!(format!(
-                "payer has insufficient balance to pay. balance: {}, amount: {}, user tx fee: {}",
-                payer.balance, payment_amount, user_tx_fee_amount
-            )));
-        }
-
-        // update payee balance to reflect payment and tx fee (when applicable)
-        payee.balance += payment_amount;
-
         info!("user paid tx fee: {}", user_tx_fee_amount);
 
-        info!("payer balance before tx: {}", payer.balance);
+        info!("payer balance before tx: {}", payer.free_balance());
 
-        // update payer balance to reflect payment
-        payer.balance -= payment_amount + user_tx_fee_amount;
+        // pay the tx fee (when applicable) and the payment to the payee
+        payer.withdraw(user_tx_fee_amount)?;
+        transfer(payer, payee, payment_amount)?;
 
-        info!("payer balance after tx: {}", payer.balance);
+        info!("payer balance after tx: {}", payer.free_balance());
 
         if payment_tx.char_trait_id != 0 {
             self.process_community_appreciation(payer, payee, &payment_tx, event);
@@ -224,15 +265,14 @@ impl BlockChainService {
             payer.karma_score += 1;
         }
 
-        let referral_reward_amount = tokenomics.get_referral_reward_amount().await?;
+        let referral_reward_amount = tokenomics.get_referral_reward_amount();
 
         let mut referral_reward_awarded = false;
-        if let Some(payee_number) = payment_tx.to_number {
-            let number = payee_number.number;
+        if let Some(number_hash) = payment_tx.get_payee_number_hash()? {
             // apply new user referral reward to the payer if applicable
-            if sign_ups.contains_key(number.as_bytes()) {
+            if sign_ups.contains_key(&number_hash) {
                 // remove from signups map to prevent double referral rewards for for the same new user
-                sign_ups.remove(number.as_bytes());
+                sign_ups.remove(&number_hash);
 
                 // this is a new user referral payment tx - payer should get the referral fee!
                 //let _sign_up_tx = sign_ups.get(mobile_number.as_bytes()).unwrap();
@@ -241,7 +281,7 @@ impl BlockChainService {
                     "apply referral reward amount: {} to: {}",
                     referral_reward_amount, payer.user_name
                 );
-                payer.balance += referral_reward_amount;
+                payer.deposit(referral_reward_amount)?;
 
                 // Give payer the ambassador trait and 1 karma point for helping to grow the network
                 payer.inc_trait_score(AMBASSADOR_CHAR_TRAIT_ID, 0);
@@ -256,12 +296,10 @@ impl BlockChainService {
 
         // add user to leaderboard only if karma rewards are still allocated
         // and user is eligible for a reward
-        if tokenomics.get_karma_coin_reward_amount().await? > 0
-            && payer.is_eligible_for_karma_reward()
-        {
+        if tokenomics.get_karma_coin_reward_amount() > 0 && payer.is_eligible_for_karma_reward() {
             info!("Adding payer to leaderboard");
             // update leader board for an appreciation
-            self.leader_board_upsert(payer, payment_tx.char_trait_id)
+            self.leader_board_upsert(payer, payment_tx.char_trait_id, batch)
                 .await?;
         }
 
@@ -277,61 +315,68 @@ impl BlockChainService {
         );
 
         signed_transaction.encode(&mut tx_data)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: tx_hash.clone(),
                 value: Bytes::from(tx_data),
             },
             cf: TRANSACTIONS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         // index the transaction in the db for both payer and payee
         self.index_transaction_by_account_id(
             signed_transaction,
             Bytes::from(payer.account_id.as_ref().unwrap().data.to_vec()),
+            event.height,
+            batch,
         )
         .await?;
 
         self.index_transaction_by_account_id(
             signed_transaction,
             Bytes::from(payee.account_id.as_ref().unwrap().data.to_vec()),
+            event.height,
+            batch,
         )
         .await?;
 
         // Update payer on chain account
         let mut buf = Vec::with_capacity(payer.encoded_len());
         payer.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(payer.account_id.as_ref().unwrap().data.to_vec()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         // Update payee on chain account
         let mut buf = Vec::with_capacity(payee.encoded_len());
         payee.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(payee.account_id.as_ref().unwrap().data.to_vec()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         // update tx event
-        event.referral_reward = referral_reward_amount;
+        if referral_reward_awarded {
+            event.referral_reward = referral_reward_amount;
+        }
         event.fee_type = fee_type as i32;
         event.fee = tx_body.fee;
         event.result = ExecutionResult::Executed as i32;
 
+        if self.replaying {
+            return Ok(());
+        }
+
         // handle push notes
         if referral_reward_awarded {
             info!("Send push note about referral reward to payer");
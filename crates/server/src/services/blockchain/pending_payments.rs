@@ -0,0 +1,526 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::payment_tx_processor::ValidatedPayment;
+use crate::services::blockchain::tokenomics::Tokenomics;
+use crate::services::db_config_service::{
+    PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY, PENDING_PAYMENTS_COL_FAMILY,
+    PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY,
+};
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::{
+    GenesisConfigService, AMBASSADOR_CHAR_TRAIT_ID, SPENDER_CHAR_TRAIT_ID,
+};
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_core_types::{
+    AccountId, BlockEvent, ExecutionInfo, ExecutionResult, PendingPayment, PendingPayments,
+    SignedTransaction, TransactionEvent, User,
+};
+use bytes::Bytes;
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, ReadPrefixItems, WriteItem};
+use prost::Message;
+use std::collections::BTreeSet;
+
+/// Max number of expired pending payments refunded in a block
+const MAX_REFUNDS_PER_BLOCK: u32 = 100;
+
+/// Length of the sha256 hashes in pending payments expiry index keys
+const HASH_LEN: usize = 32;
+
+/// Helper function - returns a pending payment expiry index key which orders pending payments
+/// by expiry time
+fn expiry_index_key(expires_at: u64, number_hash: &[u8], tx_hash: &[u8]) -> Bytes {
+    let mut key = Vec::with_capacity(8 + number_hash.len() + tx_hash.len());
+    key.extend_from_slice(&expires_at.to_be_bytes());
+    key.extend_from_slice(number_hash);
+    key.extend_from_slice(tx_hash);
+    Bytes::from(key)
+}
+
+/// Helper function - returns a pending payment payer index key
+fn payer_index_key(payer_id: &AccountId, expiry_key: &[u8]) -> Bytes {
+    let mut key = Vec::with_capacity(payer_id.data.len() + expiry_key.len());
+    key.extend_from_slice(&payer_id.data);
+    key.extend_from_slice(expiry_key);
+    Bytes::from(key)
+}
+
+/// Helper function - add a pending payment to the expiry and payer indexes
+fn index_pending_payment(payer_id: &AccountId, expiry_key: Bytes, batch: &mut BlockBatch) {
+    batch.write(WriteItem {
+        data: DataItem {
+            key: payer_index_key(payer_id, &expiry_key),
+            value: Bytes::new(),
+        },
+        cf: PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY,
+        ttl: 0,
+    });
+    batch.write(WriteItem {
+        data: DataItem {
+            key: expiry_key,
+            value: Bytes::new(),
+        },
+        cf: PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY,
+        ttl: 0,
+    });
+}
+
+/// Helper function - remove a pending payment from the expiry and payer indexes
+fn unindex_pending_payment(payer_id: &AccountId, expiry_key: Bytes, batch: &mut BlockBatch) {
+    batch.delete(DeleteItem {
+        key: payer_index_key(payer_id, &expiry_key),
+        cf: PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY,
+    });
+    batch.delete(DeleteItem {
+        key: expiry_key,
+        cf: PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY,
+    });
+}
+
+/// Helper function - returns the expiry index keys of a payer's pending payments, including
+/// payments escrowed or removed earlier in the block
+async fn read_payer_expiry_keys(payer_id: &AccountId, batch: &BlockBatch) -> Result<Vec<Bytes>> {
+    let prefix = Bytes::from(payer_id.data.clone());
+    let mut keys: BTreeSet<Bytes> = DatabaseService::read_prefix_items(ReadPrefixItems {
+        prefix: prefix.clone(),
+        from_key: None,
+        reverse: false,
+        max_results: 0,
+        cf: PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY,
+    })
+    .await?
+    .into_iter()
+    .map(|(key, _)| key)
+    .collect();
+
+    for (key, value) in batch.pending_items(PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY) {
+        if !key.starts_with(prefix.as_ref()) {
+            continue;
+        }
+        match value {
+            Some(_) => keys.insert(key),
+            None => keys.remove(&key),
+        };
+    }
+
+    keys.into_iter()
+        .map(|key| {
+            if key.len() != prefix.len() + 8 + 2 * HASH_LEN {
+                return Err(anyhow!("invalid pending payments payer index key"));
+            }
+            Ok(key.slice(prefix.len()..))
+        })
+        .collect()
+}
+
+/// Helper function - read the pending payments to a mobile number hash
+async fn read_pending_payments(number_hash: &Bytes, batch: &BlockBatch) -> Result<PendingPayments> {
+    match batch
+        .read(ReadItem {
+            key: number_hash.clone(),
+            cf: PENDING_PAYMENTS_COL_FAMILY,
+        })
+        .await?
+    {
+        Some(data) => Ok(PendingPayments::decode(data.0.as_ref())?),
+        None => Ok(PendingPayments::default()),
+    }
+}
+
+/// Helper function - write the pending payments to a mobile number hash, or delete them
+/// when there are none
+fn write_pending_payments(number_hash: Bytes, payments: &PendingPayments, batch: &mut BlockBatch) {
+    if payments.payments.is_empty() {
+        batch.delete(DeleteItem {
+            key: number_hash,
+            cf: PENDING_PAYMENTS_COL_FAMILY,
+        });
+        return;
+    }
+
+    batch.write(WriteItem {
+        data: DataItem {
+            key: number_hash,
+            value: Bytes::from(payments.encode_to_vec()),
+        },
+        cf: PENDING_PAYMENTS_COL_FAMILY,
+        ttl: 0,
+    });
+}
+
+/// Helper function - read a user from the ledger
+pub(crate) async fn read_user(account_id: &[u8], batch: &BlockBatch) -> Result<Option<User>> {
+    match batch
+        .read(ReadItem {
+            key: Bytes::from(account_id.to_vec()),
+            cf: USERS_COL_FAMILY,
+        })
+        .await?
+    {
+        Some(data) => Ok(Some(User::decode(data.0.as_ref())?)),
+        None => Ok(None),
+    }
+}
+
+/// Helper function - write a user to the ledger
+pub(crate) fn write_user(user: &User, batch: &mut BlockBatch) {
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from(user.account_id.as_ref().unwrap().data.to_vec()),
+            value: Bytes::from(user.encode_to_vec()),
+        },
+        cf: USERS_COL_FAMILY,
+        ttl: 0,
+    });
+}
+
+/// Helper function - read an on-chain transaction by its hash
+async fn read_transaction(tx_hash: &[u8], batch: &BlockBatch) -> Result<SignedTransaction> {
+    let data = batch
+        .read(ReadItem {
+            key: Bytes::from(tx_hash.to_vec()),
+            cf: TRANSACTIONS_COL_FAMILY,
+        })
+        .await?
+        .ok_or_else(|| anyhow!("missing pending payment tx {}", short_hex_string(tx_hash)))?;
+
+    Ok(SignedTransaction::decode(data.0.as_ref())?)
+}
+
+impl BlockChainService {
+    /// Process a payment transaction to a mobile number without an on-chain account.
+    /// The amount is reserved from the payer's balance and is escrowed in a pending payment
+    /// until the payee signs up or until the payment expires and is refunded.
+    pub(crate) async fn process_escrow_payment_transaction(
+        &mut self,
+        signed_transaction: &SignedTransaction,
+        payer: &mut User,
+        tokenomics: &Tokenomics,
+        event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
+        let tx_hash = signed_transaction.get_hash()?;
+
+        info!(
+            "Processing escrow payment transaction with hash {}",
+            short_hex_string(tx_hash.as_ref())
+        );
+
+        let ValidatedPayment {
+            tx_body,
+            payment_tx,
+            user_tx_fee_amount,
+            fee_type,
+        } = BlockChainService::validate_payment_transaction(signed_transaction, payer, tokenomics)
+            .await?;
+
+        let number_hash = Bytes::from(
+            payment_tx
+                .get_payee_number_hash()?
+                .ok_or_else(|| anyhow!("only payments to a mobile number can be escrowed"))?,
+        );
+
+        // reserve the payment amount and pay the tx fee
+        payer.withdraw(user_tx_fee_amount)?;
+        payer.reserve(payment_tx.amount)?;
+
+        // payer gets 1 point in spender char trait and in karma score. The appreciation is
+        // applied when the payment is claimed
+        payer.inc_trait_score(SPENDER_CHAR_TRAIT_ID, 0);
+        payer.karma_score += 1;
+
+        if tokenomics.get_karma_coin_reward_amount() > 0 && payer.is_eligible_for_karma_reward() {
+            info!("Adding payer to leaderboard");
+            self.leader_board_upsert(payer, payment_tx.char_trait_id, batch)
+                .await?;
+        }
+
+        payer.nonce += 1;
+
+        // the payment expiry is derived from the tx and the config in effect at the block
+        // height so it is the same when blocks are replayed
+        let expiry_seconds = GenesisConfigService::get_genesis_config_at(event.height)
+            .await?
+            .pending_payment_expiry_seconds;
+        let expires_at = tx_body.timestamp + expiry_seconds * 1000;
+
+        let mut pending_payments = read_pending_payments(&number_hash, batch).await?;
+        pending_payments.payments.push(PendingPayment {
+            transaction_hash: tx_hash.to_vec(),
+            from: payment_tx.from.clone(),
+            amount: payment_tx.amount,
+            char_trait_id: payment_tx.char_trait_id,
+            community_id: payment_tx.community_id,
+            height: event.height,
+            expires_at,
+        });
+        write_pending_payments(number_hash.clone(), &pending_payments, batch);
+        index_pending_payment(
+            payment_tx.from.as_ref().unwrap(),
+            expiry_index_key(expires_at, &number_hash, tx_hash.as_ref()),
+            batch,
+        );
+
+        // index the transaction in the db by hash and for the payer
+        batch.write(WriteItem {
+            data: DataItem {
+                key: tx_hash.clone(),
+                value: Bytes::from(signed_transaction.encode_to_vec()),
+            },
+            cf: TRANSACTIONS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        self.index_transaction_by_account_id(
+            signed_transaction,
+            Bytes::from(payer.account_id.as_ref().unwrap().data.to_vec()),
+            event.height,
+            batch,
+        )
+        .await?;
+
+        write_user(payer, batch);
+
+        info!(
+            "escrowed payment of {} to {} until {}",
+            payment_tx.amount,
+            short_hex_string(number_hash.as_ref()),
+            expires_at
+        );
+
+        event.fee_type = fee_type as i32;
+        event.fee = tx_body.fee;
+        event.result = ExecutionResult::Executed as i32;
+        event.info = ExecutionInfo::PaymentEscrowed as i32;
+
+        Ok(())
+    }
+
+    /// Pay a new user all pending payments to its mobile number hash.
+    /// The payer of the oldest payment gets the referral reward.
+    /// Returns the claimed payments tx events which should be emitted by the caller.
+    pub(crate) async fn claim_pending_payments(
+        &mut self,
+        payee: &mut User,
+        mobile_number_hash: &[u8],
+        tokenomics: &Tokenomics,
+        height: u64,
+        batch: &mut BlockBatch,
+    ) -> Result<Vec<TransactionEvent>> {
+        let number_hash = Bytes::copy_from_slice(mobile_number_hash);
+        let pending_payments = read_pending_payments(&number_hash, batch).await?;
+        let mut events = vec![];
+
+        for pending_payment in pending_payments.payments.iter() {
+            let tx_hash = pending_payment.transaction_hash.as_slice();
+            let tx = read_transaction(tx_hash, batch).await?;
+            let payment_tx = tx.get_body()?.get_payment_transaction_v1()?;
+            let mut event = TransactionEvent::new(height, &tx, tx_hash);
+
+            unindex_pending_payment(
+                pending_payment.from.as_ref().unwrap(),
+                expiry_index_key(pending_payment.expires_at, &number_hash, tx_hash),
+                batch,
+            );
+
+            // pending payments are cancelled when their payer account is deleted
+            let payer_account_id = pending_payment.from.as_ref().unwrap().data.as_slice();
+            let Some(mut payer) = read_user(payer_account_id, batch).await? else {
+                warn!("pending payment payer not found on chain");
+                continue;
+            };
+
+            payer.withdraw_reserved(pending_payment.amount)?;
+            payee.deposit(pending_payment.amount)?;
+            if payment_tx.char_trait_id != 0 {
+                self.process_community_appreciation(&mut payer, payee, &payment_tx, &mut event);
+            }
+
+            if events.is_empty() {
+                // the new user was referred by the sender of the oldest payment
+                event.referral_reward = tokenomics.get_referral_reward_amount();
+                info!(
+                    "apply referral reward amount: {} to: {}",
+                    event.referral_reward, payer.user_name
+                );
+                payer.deposit(event.referral_reward)?;
+                payer.inc_trait_score(AMBASSADOR_CHAR_TRAIT_ID, 0);
+                payer.karma_score += 1;
+            }
+            write_user(&payer, batch);
+
+            self.index_transaction_by_account_id(
+                &tx,
+                Bytes::from(payee.account_id.as_ref().unwrap().data.to_vec()),
+                height,
+                batch,
+            )
+            .await?;
+
+            info!(
+                "pending payment {} of {} claimed by {}",
+                short_hex_string(tx_hash),
+                pending_payment.amount,
+                payee.user_name
+            );
+
+            event.info = ExecutionInfo::PaymentClaimed as i32;
+            events.push(event);
+        }
+
+        write_pending_payments(number_hash, &PendingPayments::default(), batch);
+        Ok(events)
+    }
+
+    /// Emit the tx events of pending payments claimed by a new user or cancelled by a deleted
+    /// payer and add them to the block event. Returns true if a referral reward was awarded.
+    pub(crate) async fn emit_pending_payments_events(
+        &mut self,
+        events: Vec<TransactionEvent>,
+        block_event: &mut BlockEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<bool> {
+        let mut referral_reward_awarded = false;
+        for event in events {
+            if event.referral_reward != 0 {
+                block_event.referral_rewards_count += 1;
+                block_event.add_referral_reward(event.referral_reward)?;
+                referral_reward_awarded = true;
+            }
+            if event.appreciation_char_trait_idx != 0 {
+                block_event.appreciations_count += 1;
+            }
+            block_event.add_transaction_event(event.clone());
+            self.emit_tx_event(event, batch).await?;
+        }
+        Ok(referral_reward_awarded)
+    }
+
+    /// Refund to their payers pending payments which expired at the block time.
+    /// Called before a block's txs are executed. Returns the number of refunded payments.
+    pub(crate) async fn refund_expired_payments(
+        &mut self,
+        height: u64,
+        block_time: u64,
+        block_event: &mut BlockEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<u64> {
+        let expired = DatabaseService::read_prefix_items(ReadPrefixItems {
+            prefix: Bytes::new(),
+            from_key: None,
+            reverse: false,
+            max_results: MAX_REFUNDS_PER_BLOCK,
+            cf: PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY,
+        })
+        .await?;
+
+        let mut refunds_count = 0;
+        for (key, _) in expired {
+            if key.len() != 8 + 2 * HASH_LEN {
+                return Err(anyhow!("invalid pending payments expiry index key"));
+            }
+
+            let expires_at = u64::from_be_bytes(key[..8].try_into()?);
+            if expires_at > block_time {
+                // index is ordered by expiry time
+                break;
+            }
+
+            let number_hash = key.slice(8..8 + HASH_LEN);
+            let tx_hash = key.slice(8 + HASH_LEN..);
+
+            let mut pending_payments = read_pending_payments(&number_hash, batch).await?;
+            let Some(index) = pending_payments
+                .payments
+                .iter()
+                .position(|p| p.transaction_hash == tx_hash.as_ref())
+            else {
+                return Err(anyhow!(
+                    "missing pending payment {}",
+                    short_hex_string(tx_hash.as_ref())
+                ));
+            };
+            let pending_payment = pending_payments.payments.remove(index);
+            write_pending_payments(number_hash, &pending_payments, batch);
+            unindex_pending_payment(pending_payment.from.as_ref().unwrap(), key, batch);
+
+            let payer_account_id = pending_payment.from.as_ref().unwrap().data.as_slice();
+            if let Some(mut payer) = read_user(payer_account_id, batch).await? {
+                payer.unreserve(pending_payment.amount)?;
+                write_user(&payer, batch);
+            } else {
+                warn!("expired pending payment payer not found on chain");
+            }
+
+            info!(
+                "pending payment {} of {} expired and was refunded",
+                short_hex_string(tx_hash.as_ref()),
+                pending_payment.amount
+            );
+
+            let tx = read_transaction(tx_hash.as_ref(), batch).await?;
+            let mut event = TransactionEvent::new(height, &tx, tx_hash.as_ref());
+            event.info = ExecutionInfo::PaymentRefunded as i32;
+            block_event.add_transaction_event(event.clone());
+            self.emit_tx_event(event, batch).await?;
+            refunds_count += 1;
+        }
+
+        Ok(refunds_count)
+    }
+
+    /// Cancel all pending payments of a deleted payer account. Their reserved funds are
+    /// burned or redirected to the provided recipient together with the account's balance.
+    /// Returns the cancelled payments tx events which should be emitted by the caller.
+    pub(crate) async fn cancel_pending_payments(
+        &mut self,
+        payer_id: &AccountId,
+        recipient_id: Option<&AccountId>,
+        height: u64,
+        batch: &mut BlockBatch,
+    ) -> Result<Vec<TransactionEvent>> {
+        let mut events = vec![];
+        for key in read_payer_expiry_keys(payer_id, batch).await? {
+            let number_hash = key.slice(8..8 + HASH_LEN);
+            let tx_hash = key.slice(8 + HASH_LEN..);
+
+            let mut pending_payments = read_pending_payments(&number_hash, batch).await?;
+            let Some(index) = pending_payments
+                .payments
+                .iter()
+                .position(|p| p.transaction_hash == tx_hash.as_ref())
+            else {
+                return Err(anyhow!(
+                    "missing pending payment {}",
+                    short_hex_string(tx_hash.as_ref())
+                ));
+            };
+            let pending_payment = pending_payments.payments.remove(index);
+            write_pending_payments(number_hash, &pending_payments, batch);
+            unindex_pending_payment(payer_id, key, batch);
+
+            info!(
+                "pending payment {} of {} cancelled",
+                short_hex_string(tx_hash.as_ref()),
+                pending_payment.amount
+            );
+
+            let tx = read_transaction(tx_hash.as_ref(), batch).await?;
+            let mut event = TransactionEvent::new(height, &tx, tx_hash.as_ref());
+            match recipient_id {
+                Some(recipient_id) => {
+                    event.redirected_amount = pending_payment.amount;
+                    event.redirected_to = Some(recipient_id.clone());
+                }
+                None => event.burned_amount = pending_payment.amount,
+            }
+            event.info = ExecutionInfo::PaymentCancelled as i32;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
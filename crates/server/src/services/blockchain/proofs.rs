@@ -0,0 +1,271 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::stats::get_stats;
+use crate::services::db_config_service::{STATE_TREE_COL_FAMILY, USERS_COL_FAMILY};
+use anyhow::{anyhow, Result};
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_api::{GetAccountProofResponse, GetTransactionProofResponse};
+use base::karma_coin::karma_coin_core_types::{AccountId, SparseMerkleProof, User};
+use base::merkle::MerkleTree;
+use base::sparse_merkle::{
+    key_bit, node_storage_key, sibling_key, sparse_merkle_key, SparseMerkleNode,
+    EMPTY_SUBTREE_HASH, SPARSE_MERKLE_MAX_DEPTH,
+};
+use bytes::Bytes;
+use db::db_service::{DataItem, DeleteItem, ReadItem, WriteItem};
+use prost::Message;
+use xactor::*;
+
+/// Get an inclusion proof of an on-chain transaction in its block transactions root
+#[message(result = "Result<GetTransactionProofResponse>")]
+pub(crate) struct GetTransactionProof {
+    pub(crate) tx_hash: Bytes,
+}
+
+#[async_trait::async_trait]
+impl Handler<GetTransactionProof> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetTransactionProof,
+    ) -> Result<GetTransactionProofResponse> {
+        // a tx may have events from several blocks (e.g. invalid and then executed).
+        // Find the block that includes it
+        let tx_events = self.get_tx_events(msg.tx_hash.clone()).await?;
+        for event in tx_events.events.iter() {
+            let block = match self.get_block_by_height(event.height).await? {
+                Some(block) => block,
+                None => continue,
+            };
+
+            let Some(index) = block
+                .transactions_hashes
+                .iter()
+                .position(|h| h.as_slice() == msg.tx_hash.as_ref())
+            else {
+                continue;
+            };
+
+            if block.transactions_root.is_empty() {
+                return Err(anyhow!(
+                    "block {} was created without a transactions root",
+                    block.height
+                ));
+            }
+
+            let proof = MerkleTree::new(&block.transactions_hashes)?.proof(index)?;
+            return Ok(GetTransactionProofResponse {
+                block: Some(block),
+                proof: Some(proof),
+            });
+        }
+
+        Err(anyhow!(
+            "transaction {} is not on chain",
+            short_hex_string(msg.tx_hash.as_ref())
+        ))
+    }
+}
+
+/// Get an inclusion proof of an account in the state root of the current tip block
+#[message(result = "Result<GetAccountProofResponse>")]
+pub(crate) struct GetAccountProof {
+    pub(crate) account_id: AccountId,
+}
+
+#[async_trait::async_trait]
+impl Handler<GetAccountProof> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetAccountProof,
+    ) -> Result<GetAccountProofResponse> {
+        let stats = get_stats().await?;
+        let block = self
+            .get_block_by_height(stats.tip_height)
+            .await?
+            .ok_or_else(|| anyhow!("no blocks on chain"))?;
+
+        // users may be updated outside of blocks (e.g. community admin updates) in which case
+        // the current state can't be proved against the tip block
+        let batch = BlockBatch::default();
+        if get_state_root(&batch).await? != block.state_root {
+            return Err(anyhow!(
+                "accounts state was modified since block {}",
+                block.height
+            ));
+        }
+
+        let account_id = Bytes::from(msg.account_id.data.clone());
+        let not_found = || anyhow!("account {} not found", short_hex_string(&account_id));
+
+        // collect the siblings hashes on the path from the root down to the account's leaf
+        let key = sparse_merkle_key(&account_id)?;
+        let mut siblings = vec![];
+        let mut depth = 0;
+        loop {
+            match read_node(&batch, depth, &key).await? {
+                Some(SparseMerkleNode::Internal { .. }) => {
+                    depth += 1;
+                    let sibling = read_node(&batch, depth, &sibling_key(&key, depth)).await?;
+                    siblings
+                        .push(sibling.map_or(EMPTY_SUBTREE_HASH.to_vec(), |n| n.hash().to_vec()));
+                }
+                Some(SparseMerkleNode::Leaf { key: leaf_key, .. }) if leaf_key == key => break,
+                _ => return Err(not_found()),
+            }
+        }
+
+        let (user_data, _) = batch
+            .read(ReadItem {
+                key: account_id.clone(),
+                cf: USERS_COL_FAMILY,
+            })
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(GetAccountProofResponse {
+            user: Some(User::decode(user_data.as_ref())?),
+            block: Some(block),
+            proof: Some(SparseMerkleProof { siblings }),
+        })
+    }
+}
+
+/// Apply the batch pending users accounts changes to the accounts state tree and return the
+/// updated state root. Only the paths of the changed accounts are read and written.
+pub(crate) async fn update_state_root(batch: &mut BlockBatch) -> Result<Vec<u8>> {
+    for (account_id, user_data) in batch.pending_items(USERS_COL_FAMILY) {
+        let key = sparse_merkle_key(&account_id)?;
+        let leaf = user_data
+            .map(|data| SparseMerkleNode::leaf(&key, &data))
+            .transpose()?;
+        update_state_tree(&key, leaf, batch).await?;
+    }
+    get_state_root(batch).await
+}
+
+/// Helper function - returns the accounts state root including the batch pending changes
+pub(crate) async fn get_state_root(batch: &BlockBatch) -> Result<Vec<u8>> {
+    Ok(read_node(batch, 0, &EMPTY_SUBTREE_HASH)
+        .await?
+        .map_or(EMPTY_SUBTREE_HASH.to_vec(), |n| n.hash().to_vec()))
+}
+
+/// Helper function - set the leaf of key in the state tree, or remove it when leaf is None,
+/// and update the nodes on its path
+async fn update_state_tree(
+    key: &[u8],
+    leaf: Option<SparseMerkleNode>,
+    batch: &mut BlockBatch,
+) -> Result<()> {
+    // descend to the key's leaf or to the empty subtree or other leaf at its position
+    let mut depth = 0;
+    loop {
+        match read_node(batch, depth, key).await? {
+            Some(SparseMerkleNode::Internal { .. }) if depth < SPARSE_MERKLE_MAX_DEPTH => {
+                depth += 1
+            }
+            Some(SparseMerkleNode::Internal { .. }) => {
+                return Err(anyhow!("invalid state tree node at max depth"))
+            }
+            Some(SparseMerkleNode::Leaf {
+                key: other_key,
+                hash,
+            }) if other_key != key => {
+                if leaf.is_none() {
+                    // the key is not in the tree
+                    return Ok(());
+                }
+
+                // move the other leaf down to where the keys paths diverge
+                let split = (depth..SPARSE_MERKLE_MAX_DEPTH)
+                    .find(|d| key_bit(key, *d) != key_bit(&other_key, *d))
+                    .ok_or_else(|| anyhow!("duplicate state tree key"))?;
+                write_node(batch, depth, key, None);
+                write_node(
+                    batch,
+                    split + 1,
+                    &other_key,
+                    Some(&SparseMerkleNode::Leaf {
+                        key: other_key.clone(),
+                        hash,
+                    }),
+                );
+                depth = split + 1;
+                break;
+            }
+            None if leaf.is_none() => return Ok(()),
+            _ => break,
+        }
+    }
+
+    // ascend to the root and update the nodes on the path. A subtree with a single leaf is
+    // represented by the leaf itself
+    let mut node = leaf;
+    while depth > 0 {
+        let sibling_path = sibling_key(key, depth);
+        let sibling = read_node(batch, depth, &sibling_path).await?;
+        match (&node, &sibling) {
+            (None, None) | (Some(SparseMerkleNode::Leaf { .. }), None) => {
+                write_node(batch, depth, key, None);
+            }
+            (None, Some(SparseMerkleNode::Leaf { .. })) => {
+                write_node(batch, depth, key, None);
+                write_node(batch, depth, &sibling_path, None);
+                node = sibling;
+            }
+            _ => {
+                write_node(batch, depth, key, node.as_ref());
+                node = Some(if key_bit(key, depth - 1) {
+                    SparseMerkleNode::internal(sibling.as_ref(), node.as_ref())?
+                } else {
+                    SparseMerkleNode::internal(node.as_ref(), sibling.as_ref())?
+                });
+            }
+        }
+        depth -= 1;
+    }
+    write_node(batch, 0, key, node.as_ref());
+    Ok(())
+}
+
+/// Helper function - read the state tree node at depth on key's path
+async fn read_node(
+    batch: &BlockBatch,
+    depth: usize,
+    key: &[u8],
+) -> Result<Option<SparseMerkleNode>> {
+    batch
+        .read(ReadItem {
+            key: Bytes::from(node_storage_key(depth, key)),
+            cf: STATE_TREE_COL_FAMILY,
+        })
+        .await?
+        .map(|(data, _)| SparseMerkleNode::from_bytes(&data))
+        .transpose()
+}
+
+/// Helper function - write the state tree node at depth on key's path. Deletes the node when
+/// node is None
+fn write_node(batch: &mut BlockBatch, depth: usize, key: &[u8], node: Option<&SparseMerkleNode>) {
+    let storage_key = Bytes::from(node_storage_key(depth, key));
+    match node {
+        Some(node) => batch.write(WriteItem {
+            data: DataItem {
+                key: storage_key,
+                value: Bytes::from(node.to_bytes()),
+            },
+            cf: STATE_TREE_COL_FAMILY,
+            ttl: 0,
+        }),
+        None => batch.delete(DeleteItem {
+            key: storage_key,
+            cf: STATE_TREE_COL_FAMILY,
+        }),
+    }
+}
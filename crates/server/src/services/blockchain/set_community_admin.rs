@@ -2,7 +2,9 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::proofs::update_state_root;
 use crate::services::db_config_service::USERS_COL_FAMILY;
 use anyhow::{anyhow, Result};
 use base::hex_utils::short_hex_string;
@@ -105,18 +107,20 @@ impl Handler<SetCommunityAdmin> for BlockChainService {
             }),
         }
 
-        // Update invited onchain data
+        // Update invited onchain data and the accounts state tree
         let mut buf = Vec::with_capacity(invited.encoded_len());
         invited.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        let mut batch = BlockBatch::default();
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(invited.account_id.as_ref().unwrap().data.to_vec()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
+        update_state_root(&mut batch).await?;
+        batch.commit().await?;
 
         info!(
             "{} set as admin for {}",
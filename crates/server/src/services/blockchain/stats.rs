@@ -2,69 +2,193 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
-use crate::services::db_config_service::{BLOCKCHAIN_DATA_COL_FAMILY, CHAIN_AGG_DATA_KEY};
+use crate::services::db_config_service::{
+    BLOCKCHAIN_DATA_COL_FAMILY, CHAIN_AGG_DATA_KEY, USERS_COL_FAMILY,
+};
 use anyhow::Result;
 use base::karma_coin::karma_coin_api::{GetBlockchainDataRequest, GetBlockchainDataResponse};
-use base::karma_coin::karma_coin_core_types::{Block, BlockEvent, BlockchainStats, FeeType};
+use base::karma_coin::karma_coin_core_types::{Block, BlockEvent, BlockchainStats, FeeType, User};
+use base::supply::{checked_add, checked_sub};
 use bytes::Bytes;
 use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
 use prost::Message;
 use xactor::*;
 
 impl BlockChainService {
-    /// Update blockchain stats with new block data and store in db
+    /// Update blockchain stats with new block data and add them to the block's batch.
+    /// Fails when the block's balance changes are not accounted for in the stats circulation.
     pub(crate) async fn update_blockchain_stats(
         &self,
         mut stats: BlockchainStats,
         block_event: &BlockEvent,
         block: &Block,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
-        stats.last_block_time = block.time;
-        stats.tip_height += 1;
-        stats.transactions_count += block.transactions_hashes.len() as u64;
-        stats.users_count += block_event.signups_count;
-
-        stats.update_user_transactions_count += block_event.user_updates_count;
-
-        stats.payments_transactions_count += block_event.payments_count;
-        stats.signup_rewards_amount += block_event.signup_rewards_amount;
-        stats.signup_rewards_count += block_event.signups_count;
-        stats.referral_rewards_amount += block_event.referral_rewards_amount;
-        stats.referral_rewards_count += block_event.referral_rewards_count;
-
-        stats.fees_amount += block_event.fees_amount;
-
-        stats.minted_amount += block_event.reward
-            + block_event.referral_rewards_amount
-            + block_event.signup_rewards_amount;
-
-        for tx_event in block_event.transactions_events.iter() {
-            if tx_event.fee_type == FeeType::Mint as i32 {
-                stats.fee_subs_count += 1;
-                stats.fee_subs_amount += tx_event.fee;
-                stats.minted_amount += tx_event.fee;
-            }
-        }
+        let prev_circulation = stats.circulation;
+        apply_block_to_stats(&mut stats, block_event, block)?;
+        verify_circulation(prev_circulation, &stats, batch).await?;
 
-        self.write_stats(stats).await
+        info!("updating stats");
+        batch.write(stats_write_item(&stats)?);
+        Ok(())
     }
+}
 
-    /// Helper function to write stats to the db - todo: make actor
-    pub(crate) async fn write_stats(&self, stats: BlockchainStats) -> Result<()> {
-        info!("updating stats");
-        let mut buf = Vec::with_capacity(stats.encoded_len());
-        stats.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
-            data: DataItem {
-                key: Bytes::from(CHAIN_AGG_DATA_KEY.as_bytes()),
-                value: Bytes::from(buf),
-            },
-            cf: BLOCKCHAIN_DATA_COL_FAMILY,
-            ttl: 0,
+/// Apply a new block and its event to blockchain stats
+pub(crate) fn apply_block_to_stats(
+    stats: &mut BlockchainStats,
+    block_event: &BlockEvent,
+    block: &Block,
+) -> Result<()> {
+    stats.last_block_time = block.time;
+    stats.tip_height += 1;
+    stats.transactions_count += block.transactions_hashes.len() as u64;
+    stats.users_count += block_event.signups_count;
+
+    stats.update_user_transactions_count += block_event.user_updates_count;
+
+    stats.payments_transactions_count += block_event.payments_count;
+    stats.signup_rewards_amount = checked_add(
+        stats.signup_rewards_amount,
+        block_event.signup_rewards_amount,
+        "signup rewards amount",
+    )?;
+    stats.signup_rewards_count += block_event.signups_count;
+    stats.referral_rewards_amount = checked_add(
+        stats.referral_rewards_amount,
+        block_event.referral_rewards_amount,
+        "referral rewards amount",
+    )?;
+    stats.referral_rewards_count += block_event.referral_rewards_count;
+
+    stats.fees_amount = checked_add(stats.fees_amount, block_event.fees_amount, "fees amount")?;
+
+    stats.mint(block_event.reward)?;
+    stats.mint(block_event.referral_rewards_amount)?;
+    stats.mint(block_event.signup_rewards_amount)?;
+    stats.mint_karma_rewards(block_event.karma_rewards_amount)?;
+    stats.karma_rewards_count += block_event.karma_rewards_count;
+
+    for tx_event in block_event.transactions_events.iter() {
+        if tx_event.fee_type == FeeType::Mint as i32 {
+            stats.fee_subs_count += 1;
+            stats.fee_subs_amount =
+                checked_add(stats.fee_subs_amount, tx_event.fee, "fee subsidies amount")?;
+            stats.mint(tx_event.fee)?;
+        }
+    }
+
+    stats.burn(block_event.burned_amount)
+}
+
+/// Verify that the users balances changes in the batch equal the stats circulation change.
+/// Only the users changed by the batch are read. The sum of all users balances is verified
+/// against the circulation when the blockchain service starts and by the ledger checker.
+pub(crate) async fn verify_circulation(
+    prev_circulation: u64,
+    stats: &BlockchainStats,
+    batch: &BlockBatch,
+) -> Result<()> {
+    let mut users_balances = prev_circulation;
+    for (key, value) in batch.pending_items(USERS_COL_FAMILY) {
+        if let Some(data) = DatabaseService::read(ReadItem {
+            key,
+            cf: USERS_COL_FAMILY,
         })
-        .await
+        .await?
+        {
+            let prev_balance = User::decode(data.0.as_ref())?.total_balance()?;
+            users_balances = checked_sub(users_balances, prev_balance, "users balances")?;
+        }
+        if let Some(value) = value {
+            let balance = User::decode(value.as_ref())?.total_balance()?;
+            users_balances = checked_add(users_balances, balance, "users balances")?;
+        }
     }
+
+    stats.verify_circulation(users_balances)
+}
+
+/// Compare the stats fields which are derived from blocks with stats derived by replaying blocks.
+/// Returns a description of each field which differs.
+pub(crate) fn block_stats_divergences(
+    stats: &BlockchainStats,
+    derived: &BlockchainStats,
+) -> Vec<String> {
+    let fields = [
+        ("tip_height", stats.tip_height, derived.tip_height),
+        (
+            "transactions_count",
+            stats.transactions_count,
+            derived.transactions_count,
+        ),
+        ("users_count", stats.users_count, derived.users_count),
+        (
+            "payments_transactions_count",
+            stats.payments_transactions_count,
+            derived.payments_transactions_count,
+        ),
+        (
+            "update_user_transactions_count",
+            stats.update_user_transactions_count,
+            derived.update_user_transactions_count,
+        ),
+        ("fees_amount", stats.fees_amount, derived.fees_amount),
+        ("minted_amount", stats.minted_amount, derived.minted_amount),
+        ("burned_amount", stats.burned_amount, derived.burned_amount),
+        (
+            "signup_rewards_amount",
+            stats.signup_rewards_amount,
+            derived.signup_rewards_amount,
+        ),
+        (
+            "referral_rewards_amount",
+            stats.referral_rewards_amount,
+            derived.referral_rewards_amount,
+        ),
+        (
+            "fee_subs_amount",
+            stats.fee_subs_amount,
+            derived.fee_subs_amount,
+        ),
+        (
+            "karma_rewards_amount",
+            stats.karma_rewards_amount,
+            derived.karma_rewards_amount,
+        ),
+        (
+            "karma_rewards_count",
+            stats.karma_rewards_count,
+            derived.karma_rewards_count,
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, actual, expected)| actual != expected)
+        .map(|(name, actual, expected)| {
+            format!(
+                "stats {} is {} but replaying blocks gives {}",
+                name, actual, expected
+            )
+        })
+        .collect()
+}
+
+/// Helper function to create the db write item for blockchain stats
+pub(crate) fn stats_write_item(stats: &BlockchainStats) -> Result<WriteItem> {
+    let mut buf = Vec::with_capacity(stats.encoded_len());
+    stats.encode(&mut buf)?;
+    Ok(WriteItem {
+        data: DataItem {
+            key: Bytes::from(CHAIN_AGG_DATA_KEY.as_bytes()),
+            value: Bytes::from(buf),
+        },
+        cf: BLOCKCHAIN_DATA_COL_FAMILY,
+        ttl: 0,
+    })
 }
 
 #[message(result = "Result<GetBlockchainDataResponse>")]
@@ -99,13 +223,3 @@ pub(crate) async fn get_stats() -> Result<BlockchainStats> {
         Ok(BlockchainStats::new())
     }
 }
-
-#[message(result = "Result<()>")]
-pub(crate) struct WriteStats(pub(crate) BlockchainStats);
-
-#[async_trait::async_trait]
-impl Handler<WriteStats> for BlockChainService {
-    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: WriteStats) -> Result<()> {
-        self.write_stats(msg.0).await
-    }
-}
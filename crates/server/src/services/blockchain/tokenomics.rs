@@ -2,193 +2,121 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use anyhow::Result;
-use base::genesis_config_service::*;
-use base::karma_coin::karma_coin_core_types::{BlockchainStats, TransactionType};
+use base::karma_coin::karma_coin_core_types::{BlockchainStats, GenesisData, TransactionType};
 
+/// Consensus rewards and tx fee subsidies rules.
+/// Created once per block from the blockchain stats and a validated genesis data snapshot.
 pub struct Tokenomics {
     pub stats: BlockchainStats,
+    pub genesis_data: GenesisData,
 }
 
 impl Tokenomics {
-    pub fn new(stats: BlockchainStats) -> Self {
-        Self { stats }
+    pub fn new(stats: BlockchainStats, genesis_data: GenesisData) -> Self {
+        Self {
+            stats,
+            genesis_data,
+        }
     }
 }
 
 impl Tokenomics {
-    pub async fn get_karma_coin_reward_amount(&self) -> Result<u64> {
-        let karma_rewards_allocation =
-            GenesisConfigService::get_u64(KARAM_REWARDS_ALLOCATION_KEY.into())
-                .await?
-                .unwrap()
-                * ONE_KC_IN_KCENTS;
-
-        if self.stats.karma_rewards_amount >= karma_rewards_allocation {
-            return Ok(0);
+    pub fn get_karma_coin_reward_amount(&self) -> u64 {
+        if self.stats.karma_rewards_amount >= self.genesis_data.karma_reward_alloc_kcents() {
+            return 0;
         }
 
-        Ok(GenesisConfigService::get_u64(KARMA_REWARD_AMOUNT.into())
-            .await?
-            .unwrap())
+        self.genesis_data.karma_reward_amount
     }
 
     /// Get current signup reward amount based on consensus rules, genesis config and blockchain data
-    pub async fn get_signup_reward_amount(&self) -> Result<u64> {
-        Ok(
-            GenesisConfigService::get_u64(SIGNUP_REWARD_AMOUNT_PHASE1_KEY.into())
-                .await?
-                .unwrap(),
-        )
+    pub fn get_signup_reward_amount(&self) -> u64 {
+        self.genesis_data.signup_reward_phase1_amount
         /*
-        // In KCents
-        let signup_rewards_alloc_phase1 =
-            GenesisConfigService::get_u64(SIGNUP_REWARD_ALLOCATION_PHASE1_KEY.into())
-                .await?
-                .unwrap()
-                * ONE_KC_IN_KCENTS;
-
-        // In KCents
-        let signup_rewards_alloc_phase2 =
-            GenesisConfigService::get_u64(SIGNUP_REWARD_ALLOCATION_PHASE1_KEY.into())
-                .await?
-                .unwrap()
-                * ONE_KC_IN_KCENTS;
-
+        let genesis = &self.genesis_data;
         if self.stats.signup_rewards_amount
-            > signup_rewards_alloc_phase1 + signup_rewards_alloc_phase2
+            > genesis.signup_reward_phase1_alloc + genesis.signup_reward_phase2_alloc
         {
             // We are in phase 3
-            Ok(
-                GenesisConfigService::get_u64(SIGNUP_REWARD_AMOUNT_PHASE3_KEY.into())
-                    .await?
-                    .unwrap(),
-            )
-        } else if self.stats.signup_rewards_amount > signup_rewards_alloc_phase1 {
+            genesis.signup_reward_phase3_start
+        } else if self.stats.signup_rewards_amount > genesis.signup_reward_phase1_alloc {
             // We are in phase 2
-            Ok(
-                GenesisConfigService::get_u64(SIGNUP_REWARD_AMOUNT_PHASE2_KEY.into())
-                    .await?
-                    .unwrap(),
-            )
+            genesis.signup_reward_phase2_amount
         } else {
             info!("Signup rewards phase I");
             // We are in phase 1
-            Ok(
-                GenesisConfigService::get_u64(SIGNUP_REWARD_AMOUNT_PHASE1_KEY.into())
-                    .await?
-                    .unwrap(),
-            )
+            genesis.signup_reward_phase1_amount
         }*/
     }
 
     /// Get current referral; reward amount based on consensus rules, genesis config and blockchain data
-    pub async fn get_referral_reward_amount(&self) -> Result<u64> {
-        Ok(
-            GenesisConfigService::get_u64(REFERRAL_REWARD_AMOUNT_PHASE1_KEY.into())
-                .await?
-                .unwrap(),
-        )
+    pub fn get_referral_reward_amount(&self) -> u64 {
+        self.genesis_data.referral_reward_phase1_amount
         /*
-        let rewards_alloc_phase1 =
-            GenesisConfigService::get_u64(REFERRAL_REWARD_ALLOCATION_PHASE1_KEY.into())
-                .await?
-                .unwrap();
-
-        let rewards_alloc_phase2 =
-            GenesisConfigService::get_u64(REFERRAL_REWARD_ALLOCATION_PHASE2_KEY.into())
-                .await?
-                .unwrap();
-
-        if self.stats.referral_rewards_amount > rewards_alloc_phase2 + rewards_alloc_phase1 {
-            Ok(0)
-        } else if self.stats.referral_rewards_amount > rewards_alloc_phase1 {
-            Ok(
-                GenesisConfigService::get_u64(REFERRAL_REWARD_AMOUNT_PHASE2_KEY.into())
-                    .await?
-                    .unwrap(),
-            )
+        let genesis = &self.genesis_data;
+        if self.stats.referral_rewards_amount
+            > genesis.referral_reward_phase2_alloc + genesis.referral_reward_phase1_alloc
+        {
+            0
+        } else if self.stats.referral_rewards_amount > genesis.referral_reward_phase1_alloc {
+            genesis.referral_reward_phase2_amount
         } else {
-            Ok(
-                GenesisConfigService::get_u64(REFERRAL_REWARD_AMOUNT_PHASE1_KEY.into())
-                    .await?
-                    .unwrap(),
-            )
+            genesis.referral_reward_phase1_amount
         }*/
     }
 
     /// Return true iff transaction should be subsidised by the protocol
-    pub async fn should_subsidise_transaction_fee(
+    pub fn should_subsidise_transaction_fee(
         &self,
         user_nonce: u64,
         fee_amount: u64,
         tx_type: TransactionType,
-    ) -> Result<bool> {
-        if fee_amount
-            > GenesisConfigService::get_u64(TX_FEE_SUBSIDY_MAX_AMOUNT_KEY.into())
-                .await?
-                .unwrap()
-        {
+    ) -> bool {
+        let genesis = &self.genesis_data;
+        if fee_amount > genesis.tx_fee_subsidy_max_amount {
             // tx fee too high for subsidy
             info!("tx fee too high for subsidy");
-            return Ok(false);
+            return false;
         }
 
-        if user_nonce
-            > GenesisConfigService::get_u64(TX_FEE_SUBSIDY_MAX_TXS_PER_USER_KEY.into())
-                .await?
-                .unwrap()
-        {
-            return Ok(false);
+        if user_nonce > genesis.tx_fee_subsidy_max_per_user {
+            return false;
         }
 
-        if self.stats.fee_subs_amount
-            <= GenesisConfigService::get_u64(TX_FEE_SUBSIDY_ALLOCATION_PHASE1_KEY.into())
-                .await?
-                .unwrap()
-        {
-            // we are in phase 1 subsidies, validate fee is below the max phase1 subsidy amount
-            if fee_amount
-                > GenesisConfigService::get_u64(TX_FEE_SUBSIDY_MAX_AMOUNT_PHASE1_KEY.into())
-                    .await?
-                    .unwrap()
-            {
-                return Ok(false);
-            }
-
-            return Ok(true);
+        if self.get_tx_fee_subsidy_phase() == 1 {
+            // validate fee is below the max phase1 subsidy amount
+            return fee_amount <= genesis.tx_fee_subsidy_max_amount_phase1;
         }
 
         // we are beyond phase 1 subsidies, only signup txs up to max fee are subsided
-        if tx_type != TransactionType::NewUserV1 {
-            return Ok(false);
+        if !matches!(
+            tx_type,
+            TransactionType::NewUserV1 | TransactionType::NewUserV2
+        ) {
+            return false;
         }
 
         // Validate signup tx fee is below max subsidy amount
-        if fee_amount
-            > GenesisConfigService::get_u64(TX_FEE_SUBSIDY_MAX_AMOUNT_KEY.into())
-                .await?
-                .unwrap()
-        {
-            return Ok(false);
-        }
+        fee_amount <= genesis.tx_fee_subsidy_max_amount
+    }
 
-        Ok(true)
+    /// Returns the current tx fee subsidies phase. In phase 1 all txs types are subsidised and in
+    /// phase 2, once the phase 1 allocation is used, only signup txs are subsidised
+    pub fn get_tx_fee_subsidy_phase(&self) -> u64 {
+        if self.stats.fee_subs_amount <= self.genesis_data.tx_fee_subsidies_alloc {
+            1
+        } else {
+            2
+        }
     }
 
     /// Gets the current block reward for block producer based on block height in KCents
-    pub async fn get_block_reward_amount(&self, block_height: u64) -> Result<u64> {
-        if block_height
-            > GenesisConfigService::get_u64(BLOCK_REWARDS_LAST_BLOCK.into())
-                .await?
-                .unwrap()
-        {
+    pub fn get_block_reward_amount(&self, block_height: u64) -> u64 {
+        if block_height > self.genesis_data.block_reward_last_block {
             // no more block rewards
-            return Ok(0);
+            return 0;
         }
 
-        Ok(GenesisConfigService::get_u64(BLOCK_REWARDS_AMOUNT.into())
-            .await?
-            .unwrap())
+        self.genesis_data.block_reward_amount
     }
 }
@@ -0,0 +1,447 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Offline tokenomics simulator.
+//! Drives Tokenomics with a stream of per step signups and payments and applies the resulting
+//! block events to blockchain stats the same way blocks are applied on chain.
+//! Each step produces at most one block with all the step's txs - steps without txs don't produce
+//! a block. Karma rewards are paid at the end of every karma rewards period to up to
+//! karma_reward_top_n_users winners, which are approximated by the period's payments count.
+
+use crate::services::blockchain::stats::apply_block_to_stats;
+use crate::services::blockchain::tokenomics::Tokenomics;
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_core_types::{
+    Block, BlockEvent, BlockchainStats, FeeType, GenesisData, TransactionEvent, TransactionType,
+};
+use base::supply::checked_add;
+use serde::Serialize;
+use std::io::Write;
+
+/// Txs submitted in one simulation step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepActivity {
+    pub signups: u64,
+    pub payments: u64,
+    /// payments to users who signed up in the same step, which award the payer a referral reward
+    pub referrals: u64,
+}
+
+impl StepActivity {
+    /// Parse a recorded activity stream. One step per line in the format `signups,payments,referrals`.
+    /// Empty lines, comments starting with # and a header line are ignored.
+    pub fn parse_csv(data: &str) -> Result<Vec<StepActivity>> {
+        let mut steps = vec![];
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("signups") {
+                continue;
+            }
+
+            let values = line
+                .split(',')
+                .map(|v| v.trim().parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow!("invalid activity at line {}: {}", index + 1, e))?;
+
+            if values.len() != 3 {
+                return Err(anyhow!(
+                    "invalid activity at line {}: expected signups,payments,referrals",
+                    index + 1
+                ));
+            }
+
+            steps.push(StepActivity {
+                signups: values[0],
+                payments: values[1],
+                referrals: values[2],
+            });
+        }
+        Ok(steps)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.referrals > self.payments || self.referrals > self.signups {
+            return Err(anyhow!(
+                "referrals must not exceed payments or signups: {:?}",
+                self
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Simulation state after a step
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SimulationRecord {
+    pub step: u64,
+    pub elapsed_secs: u64,
+    pub height: u64,
+    pub users: u64,
+    pub signups: u64,
+    pub rejected_signups: u64,
+    pub payments: u64,
+    pub referrals: u64,
+    pub karma_winners: u64,
+    pub signup_reward: u64,
+    pub referral_reward: u64,
+    pub block_reward: u64,
+    pub karma_reward: u64,
+    pub fee_subsidy_phase: u64,
+    pub signup_rewards_amount: u64,
+    pub signup_rewards_remaining: u64,
+    pub referral_rewards_amount: u64,
+    pub referral_rewards_remaining: u64,
+    pub fee_subsidies_amount: u64,
+    pub fee_subsidies_remaining: u64,
+    pub karma_rewards_amount: u64,
+    pub karma_rewards_remaining: u64,
+    pub block_rewards_amount: u64,
+    pub fees_amount: u64,
+    pub minted_amount: u64,
+    pub circulation: u64,
+}
+
+impl SimulationRecord {
+    const CSV_HEADER: &'static str = "step,elapsed_secs,height,users,signups,rejected_signups,\
+        payments,referrals,karma_winners,signup_reward,referral_reward,block_reward,karma_reward,\
+        fee_subsidy_phase,signup_rewards_amount,signup_rewards_remaining,referral_rewards_amount,\
+        referral_rewards_remaining,fee_subsidies_amount,fee_subsidies_remaining,\
+        karma_rewards_amount,karma_rewards_remaining,block_rewards_amount,fees_amount,\
+        minted_amount,circulation";
+
+    fn csv_values(&self) -> [u64; 26] {
+        [
+            self.step,
+            self.elapsed_secs,
+            self.height,
+            self.users,
+            self.signups,
+            self.rejected_signups,
+            self.payments,
+            self.referrals,
+            self.karma_winners,
+            self.signup_reward,
+            self.referral_reward,
+            self.block_reward,
+            self.karma_reward,
+            self.fee_subsidy_phase,
+            self.signup_rewards_amount,
+            self.signup_rewards_remaining,
+            self.referral_rewards_amount,
+            self.referral_rewards_remaining,
+            self.fee_subsidies_amount,
+            self.fee_subsidies_remaining,
+            self.karma_rewards_amount,
+            self.karma_rewards_remaining,
+            self.block_rewards_amount,
+            self.fees_amount,
+            self.minted_amount,
+            self.circulation,
+        ]
+    }
+}
+
+/// A change of a reward amount or a fee subsidies phase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PhaseSwitch {
+    pub step: u64,
+    pub height: u64,
+    pub name: String,
+    pub from: u64,
+    pub to: u64,
+}
+
+/// Simulation results
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SimulationReport {
+    pub records: Vec<SimulationRecord>,
+    pub phase_switches: Vec<PhaseSwitch>,
+}
+
+impl SimulationReport {
+    /// Write the records as csv
+    pub fn write_csv(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "{}", SimulationRecord::CSV_HEADER)?;
+        for record in self.records.iter() {
+            let values = record
+                .csv_values()
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>();
+            writeln!(writer, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Write the records and phase switches as json
+    pub fn write_json(&self, writer: &mut impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// Simulates rewards and tx fee subsidies over a stream of signups and payments
+pub struct TokenomicsSimulator {
+    genesis_data: GenesisData,
+    /// simulated time of a step
+    step_secs: u64,
+    /// fee paid by each simulated tx in KCents
+    tx_fee: u64,
+    stats: BlockchainStats,
+    block_rewards_amount: u64,
+    elapsed_secs: u64,
+    karma_period_elapsed_secs: u64,
+    karma_period_payments: u64,
+    report: SimulationReport,
+}
+
+impl TokenomicsSimulator {
+    pub fn new(genesis_data: GenesisData, step_secs: u64, tx_fee: u64) -> Result<Self> {
+        genesis_data.validate()?;
+        if step_secs == 0 {
+            return Err(anyhow!("simulation step must be positive"));
+        }
+
+        Ok(Self {
+            genesis_data,
+            step_secs,
+            tx_fee,
+            stats: BlockchainStats::new(),
+            block_rewards_amount: 0,
+            elapsed_secs: 0,
+            karma_period_elapsed_secs: 0,
+            karma_period_payments: 0,
+            report: SimulationReport::default(),
+        })
+    }
+
+    /// Run the simulation over the provided steps and return its report
+    pub fn run(mut self, steps: &[StepActivity]) -> Result<SimulationReport> {
+        for activity in steps.iter() {
+            self.step(activity)?;
+        }
+        Ok(self.report)
+    }
+
+    fn tokenomics(&self) -> Tokenomics {
+        Tokenomics::new(self.stats.clone(), self.genesis_data.clone())
+    }
+
+    /// Simulate one step - produce a block with the step's txs and pay karma rewards at the
+    /// end of a karma rewards period
+    fn step(&mut self, activity: &StepActivity) -> Result<()> {
+        activity.validate()?;
+
+        self.elapsed_secs = checked_add(self.elapsed_secs, self.step_secs, "elapsed time")?;
+        let mut record = SimulationRecord {
+            step: self.report.records.len() as u64 + 1,
+            elapsed_secs: self.elapsed_secs,
+            payments: activity.payments,
+            ..Default::default()
+        };
+
+        // rewards and subsidies are determined by the stats at the start of the block
+        let tokenomics = self.tokenomics();
+        record.signup_reward = tokenomics.get_signup_reward_amount();
+        record.referral_reward = tokenomics.get_referral_reward_amount();
+        record.karma_reward = tokenomics.get_karma_coin_reward_amount();
+        record.fee_subsidy_phase = tokenomics.get_tx_fee_subsidy_phase();
+
+        if activity.signups + activity.payments > 0 {
+            let height = self.stats.tip_height + 1;
+            let mut block_event = BlockEvent::new(height);
+            let mut txs_count = 0;
+
+            let signup_subsidy = tokenomics.should_subsidise_transaction_fee(
+                0,
+                self.tx_fee,
+                TransactionType::NewUserV2,
+            );
+            for _ in 0..activity.signups {
+                if !signup_subsidy && self.tx_fee >= record.signup_reward {
+                    // same as the new user tx processor - the fee must be covered by the reward
+                    record.rejected_signups += 1;
+                    continue;
+                }
+                block_event.inc_total_signups();
+                block_event.add_signup_reward(record.signup_reward)?;
+                self.add_tx_fee(&mut block_event, signup_subsidy)?;
+                txs_count += 1;
+            }
+            record.signups = block_event.signups_count;
+
+            let payment_subsidy = tokenomics.should_subsidise_transaction_fee(
+                0,
+                self.tx_fee,
+                TransactionType::PaymentV1,
+            );
+            for _ in 0..activity.payments {
+                block_event.inc_total_payments();
+                self.add_tx_fee(&mut block_event, payment_subsidy)?;
+                txs_count += 1;
+            }
+
+            // referrals are only awarded for payments to users who signed up
+            record.referrals = activity.referrals.min(record.signups);
+            for _ in 0..record.referrals {
+                block_event.add_referral_reward(record.referral_reward)?;
+                block_event.referral_rewards_count += 1;
+            }
+
+            record.block_reward = tokenomics.get_block_reward_amount(height);
+            block_event.reward = record.block_reward;
+
+            let block = Block {
+                time: self.elapsed_secs * 1000,
+                height,
+                transactions_hashes: vec![vec![]; txs_count],
+                reward: record.block_reward,
+                ..Default::default()
+            };
+            apply_block_to_stats(&mut self.stats, &block_event, &block)?;
+            self.block_rewards_amount = checked_add(
+                self.block_rewards_amount,
+                record.block_reward,
+                "block rewards amount",
+            )?;
+        }
+
+        self.karma_period_payments =
+            checked_add(self.karma_period_payments, activity.payments, "payments")?;
+        self.karma_period_elapsed_secs += self.step_secs;
+        // the karma rewards period genesis field is set from the period minutes config key
+        let karma_period_secs = self
+            .genesis_data
+            .karma_rewards_period_hours
+            .saturating_mul(60);
+        if self.karma_period_elapsed_secs >= karma_period_secs {
+            record.karma_winners = self.pay_karma_rewards()?;
+        }
+
+        self.update_record(&mut record);
+        self.add_record(record);
+        Ok(())
+    }
+
+    // Account for a tx fee in a block event. Subsidised fees are minted by the protocol
+    fn add_tx_fee(&self, block_event: &mut BlockEvent, subsidy: bool) -> Result<()> {
+        let fee_type = if subsidy {
+            FeeType::Mint
+        } else {
+            FeeType::User
+        };
+        block_event.add_fee(self.tx_fee)?;
+        block_event.add_transaction_event(TransactionEvent {
+            fee: self.tx_fee,
+            fee_type: fee_type as i32,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    // Pay karma rewards for the ended period and return the number of winners
+    fn pay_karma_rewards(&mut self) -> Result<u64> {
+        self.karma_period_elapsed_secs = 0;
+        let payments = std::mem::take(&mut self.karma_period_payments);
+
+        let reward_amount = self.tokenomics().get_karma_coin_reward_amount();
+        if reward_amount == 0 {
+            return Ok(0);
+        }
+
+        let winners = payments
+            .min(self.genesis_data.karma_reward_top_n_users)
+            .min(self.stats.users_count);
+        let total = reward_amount
+            .checked_mul(winners)
+            .ok_or_else(|| anyhow!("karma rewards amount overflow"))?;
+        self.stats.mint_karma_rewards(total)?;
+        Ok(winners)
+    }
+
+    fn update_record(&self, record: &mut SimulationRecord) {
+        let genesis = &self.genesis_data;
+        let stats = &self.stats;
+
+        record.height = stats.tip_height;
+        record.users = stats.users_count;
+        record.signup_rewards_amount = stats.signup_rewards_amount;
+        record.signup_rewards_remaining = genesis
+            .signup_reward_phase1_alloc
+            .saturating_add(genesis.signup_reward_phase2_alloc)
+            .saturating_sub(stats.signup_rewards_amount);
+        record.referral_rewards_amount = stats.referral_rewards_amount;
+        record.referral_rewards_remaining = genesis
+            .referral_reward_phase1_alloc
+            .saturating_add(genesis.referral_reward_phase2_alloc)
+            .saturating_sub(stats.referral_rewards_amount);
+        record.fee_subsidies_amount = stats.fee_subs_amount;
+        record.fee_subsidies_remaining = genesis
+            .tx_fee_subsidies_alloc
+            .saturating_sub(stats.fee_subs_amount);
+        record.karma_rewards_amount = stats.karma_rewards_amount;
+        record.karma_rewards_remaining = genesis
+            .karma_reward_alloc_kcents()
+            .saturating_sub(stats.karma_rewards_amount);
+        record.block_rewards_amount = self.block_rewards_amount;
+        record.fees_amount = stats.fees_amount;
+        record.minted_amount = stats.minted_amount;
+        record.circulation = stats.circulation;
+    }
+
+    // Add a record and a phase switch for each reward amount or phase which changed since the
+    // previous step
+    fn add_record(&mut self, record: SimulationRecord) {
+        if let Some(prev) = self.report.records.last() {
+            let values = [
+                ("signup_reward", prev.signup_reward, record.signup_reward),
+                (
+                    "referral_reward",
+                    prev.referral_reward,
+                    record.referral_reward,
+                ),
+                ("karma_reward", prev.karma_reward, record.karma_reward),
+                (
+                    "fee_subsidy_phase",
+                    prev.fee_subsidy_phase,
+                    record.fee_subsidy_phase,
+                ),
+            ];
+            for (name, from, to) in values {
+                if from != to {
+                    self.report.phase_switches.push(PhaseSwitch {
+                        step: record.step,
+                        height: record.height,
+                        name: name.into(),
+                        from,
+                        to,
+                    });
+                }
+            }
+        }
+
+        // block rewards are only known for steps with a block so their end is tracked separately
+        let block_rewards_ended = self
+            .report
+            .phase_switches
+            .iter()
+            .any(|s| s.name == "block_reward");
+        if !block_rewards_ended
+            && record.height > 0
+            && record.height >= self.genesis_data.block_reward_last_block
+        {
+            self.report.phase_switches.push(PhaseSwitch {
+                step: record.step,
+                height: record.height,
+                name: "block_reward".into(),
+                from: self.genesis_data.block_reward_amount,
+                to: 0,
+            });
+        }
+
+        self.report.records.push(record);
+    }
+}
@@ -2,6 +2,7 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::db_config_service::TRANSACTIONS_EVENTS_COL_FAMILY;
 use anyhow::Result;
@@ -43,24 +44,41 @@ impl BlockChainService {
         }
     }
     /// emit a transaction processing event
-    pub(crate) async fn emit_tx_event(&self, event: TransactionEvent) -> Result<()> {
+    /// The event is published to subscribers once the batch was committed
+    pub(crate) async fn emit_tx_event(
+        &mut self,
+        event: TransactionEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
         let key = event.transaction_hash.clone();
-        let mut transaction_events = self.get_tx_events(Bytes::from(key.clone())).await?;
+        let mut transaction_events = if let Some(data) = batch
+            .read(ReadItem {
+                key: Bytes::from(key.clone()),
+                cf: TRANSACTIONS_EVENTS_COL_FAMILY,
+            })
+            .await?
+        {
+            TransactionEvents::decode(data.0.as_ref())?
+        } else {
+            TransactionEvents::default()
+        };
 
         transaction_events.events.push(event.clone());
         let mut buf = Vec::with_capacity(transaction_events.encoded_len());
         transaction_events.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(key),
                 value: Bytes::from(buf),
             },
             cf: TRANSACTIONS_EVENTS_COL_FAMILY,
             ttl: 0, // todo: ttl should be based on node being archive or standard....
-        })
-        .await?;
+        });
 
         info!("Tx event emitted: {}", event);
+        if !self.replaying {
+            self.unpublished_tx_events.push(event);
+        }
 
         Ok(())
     }
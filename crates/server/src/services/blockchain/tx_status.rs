@@ -0,0 +1,189 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::mem_pool_service::MemPoolService;
+use crate::services::blockchain::tx_event::GetTransactionEvents;
+use crate::services::blockchain::txs_store::GetTransactionByHash;
+use crate::services::db_config_service::REJECTED_TXS_COL_FAMILY;
+use anyhow::Result;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_api::WaitForTransactionResponse;
+use base::karma_coin::karma_coin_core_types::TransactionStatus::{OnChain, Rejected};
+use base::karma_coin::karma_coin_core_types::{
+    RejectedTransaction, SignedTransaction, TransactionStatus,
+};
+use base::server_config_service::{ServerConfigService, MEM_POOL_MAX_TX_AGE_HOURS};
+use bytes::Bytes;
+use chrono::Utc;
+use db::db_service::{DataItem, DatabaseService, DeleteItem, ReadItem, WriteBatch, WriteItem};
+use prost::Message;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use xactor::*;
+
+/// Max time a client may wait for a transaction status
+pub(crate) const MAX_WAIT_FOR_TX_TIMEOUT_MS: u64 = 60 * 1000;
+
+/// Published whenever a submitted transaction is admitted to the pool, rejected or
+/// dropped from the pool, or included in a committed block
+#[message]
+#[derive(Clone)]
+pub(crate) struct TransactionStatusChanged {
+    pub(crate) tx_hash: Vec<u8>,
+    pub(crate) status: TransactionStatus,
+}
+
+/// Helper function - publish a transaction status change to subscribers
+pub(crate) async fn publish_tx_status(tx_hash: Vec<u8>, status: TransactionStatus) -> Result<()> {
+    Broker::<TransactionStatusChanged>::from_registry()
+        .await?
+        .publish(TransactionStatusChanged { tx_hash, status })
+}
+
+/// Helper function - returns a rejected transaction by its hash
+pub(crate) async fn get_rejected_transaction(
+    tx_hash: &[u8],
+) -> Result<Option<RejectedTransaction>> {
+    match DatabaseService::read(ReadItem {
+        key: Bytes::from(tx_hash.to_vec()),
+        cf: REJECTED_TXS_COL_FAMILY,
+    })
+    .await?
+    {
+        Some(data) => Ok(Some(RejectedTransaction::decode(data.0.as_ref())?)),
+        None => Ok(None),
+    }
+}
+
+impl MemPoolService {
+    /// Record transactions which were rejected or dropped from the pool so their status can be
+    /// queried by clients. Records expire after the max pool tx age.
+    pub(crate) async fn record_rejected_transactions(
+        &self,
+        txs: Vec<(Vec<u8>, SignedTransaction)>,
+        reason: &str,
+    ) -> Result<()> {
+        if txs.is_empty() {
+            return Ok(());
+        }
+
+        let max_age_hours = ServerConfigService::get_u64(MEM_POOL_MAX_TX_AGE_HOURS.into())
+            .await?
+            .unwrap();
+
+        let mut batch = WriteBatch::default();
+        for (tx_hash, tx) in txs.iter() {
+            info!("tx {} rejected: {}", short_hex_string(tx_hash), reason);
+            let rejected_tx = RejectedTransaction {
+                transaction: Some(tx.clone()),
+                reason: reason.into(),
+                timestamp: Utc::now().timestamp_millis() as u64,
+            };
+            batch.write(WriteItem {
+                data: DataItem {
+                    key: Bytes::from(tx_hash.clone()),
+                    value: Bytes::from(rejected_tx.encode_to_vec()),
+                },
+                cf: REJECTED_TXS_COL_FAMILY,
+                ttl: max_age_hours * 60 * 60,
+            });
+        }
+        DatabaseService::write_batch(batch).await?;
+
+        for (tx_hash, _) in txs {
+            publish_tx_status(tx_hash, Rejected).await?;
+        }
+        Ok(())
+    }
+
+    /// Clear the rejection record of a transaction which was submitted again and admitted
+    pub(crate) async fn clear_rejected_transaction(&self, tx_hash: &[u8]) -> Result<()> {
+        DatabaseService::delete(DeleteItem {
+            key: Bytes::from(tx_hash.to_vec()),
+            cf: REJECTED_TXS_COL_FAMILY,
+        })
+        .await
+    }
+}
+
+/// Forwards the final status of a transaction to a waiting client.
+/// A final status is either on chain or rejected.
+struct TransactionStatusWaiter {
+    tx_hash: Vec<u8>,
+    sender: mpsc::Sender<TransactionStatus>,
+}
+
+#[async_trait::async_trait]
+impl Actor for TransactionStatusWaiter {
+    async fn started(&mut self, ctx: &mut Context<Self>) -> Result<()> {
+        ctx.subscribe::<TransactionStatusChanged>().await
+    }
+
+    async fn stopped(&mut self, ctx: &mut Context<Self>) {
+        let _ = ctx.unsubscribe::<TransactionStatusChanged>().await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Handler<TransactionStatusChanged> for TransactionStatusWaiter {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: TransactionStatusChanged) {
+        if msg.tx_hash == self.tx_hash && is_final(msg.status) {
+            let _ = self.sender.try_send(msg.status);
+        }
+    }
+}
+
+/// Wait until a transaction is on chain or rejected, or until the timeout.
+/// Returns the transaction with its status when the wait ended.
+pub(crate) async fn wait_for_transaction(
+    tx_hash: Bytes,
+    timeout: Duration,
+) -> Result<WaitForTransactionResponse> {
+    // subscribe before the current status is read so no status change is missed
+    let (sender, mut receiver) = mpsc::channel(1);
+    let mut waiter = TransactionStatusWaiter {
+        tx_hash: tx_hash.to_vec(),
+        sender,
+    }
+    .start()
+    .await?;
+
+    let blockchain = BlockChainService::from_registry().await?;
+    let tx = blockchain
+        .call(GetTransactionByHash {
+            hash: tx_hash.clone(),
+        })
+        .await??;
+
+    let done = tx.as_ref().is_some_and(|tx| is_final(tx.status()));
+    let timed_out = !done
+        && tokio::time::timeout(timeout, receiver.recv())
+            .await
+            .is_err();
+    let _ = waiter.stop(None);
+
+    let tx = if done {
+        tx
+    } else {
+        blockchain
+            .call(GetTransactionByHash {
+                hash: tx_hash.clone(),
+            })
+            .await??
+    };
+
+    let tx_events = blockchain.call(GetTransactionEvents { tx_hash }).await??;
+
+    Ok(WaitForTransactionResponse {
+        transaction: tx,
+        tx_events: Some(tx_events),
+        timed_out,
+    })
+}
+
+/// Helper function - returns true when a transaction's status will not change
+fn is_final(status: TransactionStatus) -> bool {
+    status == OnChain || status == Rejected
+}
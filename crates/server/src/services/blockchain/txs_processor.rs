@@ -2,64 +2,120 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
 use crate::services::blockchain::mem_pool_service::{
-    GetTransactions, MemPoolService, RemoveOldTransactions, RemoveOnChainTransactions,
-    RemoveTransactionByHash, RemoveTransactionsByHashes,
+    GetOrderedTransactions, MemPoolService, RejectTransactions, RemoveOnChainTransactions,
+    RemoveTransactionsByHashes,
 };
 use crate::services::blockchain::stats::get_stats;
 use crate::services::db_config_service::USERS_COL_FAMILY;
 use anyhow::Result;
+use base::genesis_config_service::{AddParamsUpdates, GenesisConfigService};
 use base::hex_utils::short_hex_string;
-use base::karma_coin::karma_coin_core_types::TransactionType::NewUserV1;
+use base::karma_coin::karma_coin_core_types::TransactionType::{NewUserV1, NewUserV2};
 use base::karma_coin::karma_coin_core_types::*;
 use bytes::Bytes;
-use db::db_service::{DatabaseService, ReadItem};
+use chrono::Utc;
+use db::db_service::ReadItem;
 use prost::Message;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 use crate::services::blockchain::tokenomics::Tokenomics;
 use xactor::*;
 
-#[message(result = "Result<Option<Block>>")]
+/// Rejection reason of txs which failed execution. Details are in the tx events
+const TX_EXECUTION_FAILED_REASON: &str = "transaction execution failed";
+
+/// Block production tick. Sent by the blockchain service to itself on the configured block interval
+#[message]
+#[derive(Clone)]
 pub(crate) struct ProcessTransactions;
 
-/// Process transactions in the mem-pool and optionally create a block if one or more transactions were processed
 #[async_trait::async_trait]
 impl Handler<ProcessTransactions> for BlockChainService {
-    async fn handle(
-        &mut self,
-        _ctx: &mut Context<Self>,
-        _msg: ProcessTransactions,
-    ) -> Result<Option<Block>> {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: ProcessTransactions) {
+        match self.process_transactions().await {
+            Ok(Some(block)) => info!(
+                "produced block {} with {} txs",
+                block.height,
+                block.transactions_hashes.len()
+            ),
+            Ok(None) => {}
+            Err(e) => error!("failed to produce block: {}", e),
+        }
+    }
+}
+
+impl BlockChainService {
+    /// Process transactions in the mem-pool and optionally create a block if one or more transactions were processed.
+    /// Up to max_txs_per_block transactions are executed in a block.
+    /// All ledger changes made by the block's transactions are committed to the db atomically with the block.
+    pub(crate) async fn process_transactions(&mut self) -> Result<Option<Block>> {
         let mem_pool = MemPoolService::from_registry().await?;
+        self.unpublished_tx_events.clear();
 
         // remove from pool all transactions that are already on chain
         // this block execution of transactions that were already processed in a previous block
         mem_pool.call(RemoveOnChainTransactions).await??;
 
-        // get all pending txs from mem-pool
-        let transactions_map = mem_pool.call(GetTransactions).await??;
-
-        if transactions_map.is_empty() {
-            info!("mem pool empty");
-            return Ok(None);
-        }
+        // get all pending txs from mem-pool in block assembly order: new user txs first and then
+        // all other txs, each group by fee, timestamp, nonce and hash. Txs are executed in two
+        // passes over this list - new user txs and then all other txs - which is the same order,
+        // and their hashes are added to the block in execution order, so re-executing a block's
+        // transactions_hashes reproduces the exact same state
+        let transactions = mem_pool.call(GetOrderedTransactions).await??;
 
-        // get current blockchain stats and tokenomics
+        // get current blockchain stats and the tokenomics in effect at the new block height
         let stats = get_stats().await?;
-        let tokenomics = Tokenomics::new(stats.clone());
         let block_height = stats.tip_height + 1;
+        let tokenomics = Tokenomics::new(
+            stats.clone(),
+            GenesisConfigService::get_genesis_data_at(block_height).await?,
+        );
+        let block_time = Utc::now().timestamp_millis() as u64;
         let mut tx_hashes: Vec<Vec<u8>> = vec![];
 
         // the block event for the new block
         let mut block_event = BlockEvent::new(block_height);
 
+        // all state changes made while producing this block
+        let mut batch = BlockBatch::default();
+
+        // refund expired pending payments before executing the block's txs.
+        // A block is produced for refunds even when there are no txs to execute
+        let refunds_count = self
+            .refund_expired_payments(block_height, block_time, &mut block_event, &mut batch)
+            .await?;
+
+        // apply a due karma rewards round. Its winners are recorded in the block, which is
+        // produced for the round even when there are no txs to execute
+        let karma_rewards = self.select_karma_rewards(&tokenomics, &batch).await?;
+        if let Some(karma_rewards) = karma_rewards.as_ref() {
+            self.apply_karma_rewards(karma_rewards, &mut block_event, &mut batch)
+                .await?;
+        }
+
+        if transactions.is_empty() && refunds_count == 0 && karma_rewards.is_none() {
+            return Ok(None);
+        }
+
         // new signups txs in this block indexed by mobile number - used for referral reward calculations
 
         let mut sign_ups: HashMap<Vec<u8>, SignedTransaction> = HashMap::new();
 
-        for (tx_hash, tx) in transactions_map.iter() {
+        // invalid txs which should be removed from the pool once the batch is committed
+        let mut discarded_tx_hashes: Vec<Vec<u8>> = vec![];
+
+        // params updates executed in this block
+        let mut params_updates: Vec<ParamsUpdate> = vec![];
+
+        for (tx_hash, tx) in transactions.iter() {
+            if tx_hashes.len() as u64 >= self.max_txs_per_block {
+                break;
+            }
+
             let tx_body = match tx.get_body() {
                 Ok(body) => body,
                 Err(_) => {
@@ -67,7 +123,7 @@ impl Handler<ProcessTransactions> for BlockChainService {
                 }
             };
 
-            if tx_body.get_tx_type()? != NewUserV1 {
+            if !matches!(tx_body.get_tx_type()?, NewUserV1 | NewUserV2) {
                 // in this loop we only process new user transactions
                 continue;
             }
@@ -76,9 +132,10 @@ impl Handler<ProcessTransactions> for BlockChainService {
 
             // the transaction event for the new user transaction
             let mut tx_event = TransactionEvent::new(block_height, tx, tx_hash);
+            let save_point = batch.save_point();
 
             match self
-                .process_new_user_transaction(tx, &tokenomics, &mut tx_event)
+                .process_new_user_transaction(tx, &tokenomics, &mut tx_event, &mut batch)
                 .await
             {
                 Ok(res) => {
@@ -89,11 +146,20 @@ impl Handler<ProcessTransactions> for BlockChainService {
                     );
                     tx_hashes.push(tx_hash.to_vec());
                     block_event.signups_count += 1;
-                    block_event.signup_rewards_amount += tx_event.signup_reward;
-                    block_event.add_fee(tx_body.fee);
+                    block_event.add_signup_reward(tx_event.signup_reward)?;
+                    block_event.add_fee(tx_body.fee)?;
                     block_event.add_transaction_event(tx_event.clone());
+                    let referral_reward_awarded = self
+                        .emit_pending_payments_events(
+                            res.claimed_payments_events,
+                            &mut block_event,
+                            &mut batch,
+                        )
+                        .await?;
                     // update new signups map - used for referrals and payments
-                    sign_ups.insert(res.mobile_number.as_bytes().to_vec(), tx.clone());
+                    if !referral_reward_awarded {
+                        sign_ups.insert(res.mobile_number_hash, tx.clone());
+                    }
                 }
                 Err(e) => {
                     error!(
@@ -101,23 +167,25 @@ impl Handler<ProcessTransactions> for BlockChainService {
                         short_hex_string(tx_hash),
                         e
                     );
+                    batch.rollback_to(save_point);
+                    discarded_tx_hashes.push(tx_hash.to_vec());
                     tx_event.result = ExecutionResult::Invalid as i32;
                     tx_event.info = e.execution_info as i32;
                     tx_event.error_message = e.error_message;
                 }
             }
 
-            // remove the processed tx from the pool
-            mem_pool
-                .call(RemoveTransactionByHash(tx_hash.to_vec()))
-                .await??;
-
             // emit the tx event
-            self.emit_tx_event(tx_event).await?;
+            self.emit_tx_event(tx_event, &mut batch).await?;
         }
 
         // process other transactions types (update user and payments)
-        for (tx_hash, tx) in mem_pool.call(GetTransactions).await??.iter() {
+        for (tx_hash, tx) in transactions.iter() {
+            if tx_hashes.len() as u64 >= self.max_txs_per_block {
+                info!("block is full - leaving remaining txs in mem pool");
+                break;
+            }
+
             let tx_body = match tx.get_body() {
                 Ok(body) => body,
                 Err(_) => {
@@ -127,14 +195,21 @@ impl Handler<ProcessTransactions> for BlockChainService {
             };
 
             let tx_type = tx_body.get_tx_type()?;
+            if matches!(tx_type, NewUserV1 | NewUserV2) {
+                // new user transactions were processed above
+                continue;
+            }
+
             let mut tx_event = TransactionEvent::new(block_height, tx, tx_hash);
+            let save_point = batch.save_point();
 
             // Get tx issuer user from chain and IGNORE tx if it doesn't exist
-            let mut user = match DatabaseService::read(ReadItem {
-                key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
-                cf: USERS_COL_FAMILY,
-            })
-            .await?
+            let mut user = match batch
+                .read(ReadItem {
+                    key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
+                    cf: USERS_COL_FAMILY,
+                })
+                .await?
             {
                 Some(data) => User::decode(data.0.as_ref())?,
                 None => {
@@ -145,64 +220,109 @@ impl Handler<ProcessTransactions> for BlockChainService {
                 }
             };
 
-            // check nonce and ignore txs with wrong nonce here
-            // Nonce checking is disabled until design is figured out
-            /*
-            if tx_body.validate_nonce(user.nonce).is_err() {
-                info!("Tx nonce is invalid - ignoring tx and leaving it in mempool");
-                continue;
-            }*/
+            // only execute the signer's next tx. User is read via the batch so its nonce
+            // includes txs by the signer which were executed earlier in this block
+            match tx_body.nonce.cmp(&(user.nonce + 1)) {
+                Ordering::Less => {
+                    info!(
+                        "Tx nonce {} was already used by signer - discarding tx from mem pool",
+                        tx_body.nonce
+                    );
+                    discarded_tx_hashes.push(tx_hash.to_vec());
+                    tx_event.result = ExecutionResult::Invalid as i32;
+                    tx_event.error_message = format!(
+                        "invalid nonce {}. Expected nonce: {}",
+                        tx_body.nonce,
+                        user.nonce + 1
+                    );
+                    self.emit_tx_event(tx_event, &mut batch).await?;
+                    continue;
+                }
+                Ordering::Greater => {
+                    info!(
+                        "Tx nonce {} is a future nonce - leaving tx in mem pool",
+                        tx_body.nonce
+                    );
+                    continue;
+                }
+                Ordering::Equal => {}
+            }
 
             match tx_type {
                 TransactionType::PaymentV1 => {
-                    if let Some(mut payee) = BlockChainService::get_payee_user(tx).await? {
-                        match self
-                            .process_payment_transaction(
+                    let res = match BlockChainService::get_payee_user_from_tx_body(&tx_body, &batch)
+                        .await?
+                    {
+                        Some(mut payee) => {
+                            self.process_payment_transaction(
                                 tx,
                                 &mut user,
                                 &mut payee,
                                 &mut sign_ups,
                                 &tokenomics,
                                 &mut tx_event,
+                                &mut batch,
                             )
                             .await
+                        }
+                        None if tx_body
+                            .get_payment_transaction_v1()?
+                            .get_payee_number_hash()?
+                            .is_some() =>
                         {
-                            Ok(_) => {
-                                info!("payment transaction processed: {}", tx_event);
-                                tx_hashes.push(tx_hash.to_vec());
-                                block_event.payments_count += 1;
-                                block_event.add_fee(tx_body.fee);
-                                if tx_event.referral_reward != 0 {
-                                    block_event.referral_rewards_count += 1;
-                                    block_event.referral_rewards_amount += tx_event.referral_reward;
-                                }
-                                if tx_event.appreciation_char_trait_idx != 0 {
-                                    block_event.appreciations_count += 1;
-                                }
+                            info!("Payee mobile number has no account - escrowing the payment");
+                            self.process_escrow_payment_transaction(
+                                tx,
+                                &mut user,
+                                &tokenomics,
+                                &mut tx_event,
+                                &mut batch,
+                            )
+                            .await
+                        }
+                        None => {
+                            info!("Payee user not found on chain - keeping this tx in the mem pool for later processing...");
+                            continue;
+                        }
+                    };
 
-                                block_event.add_transaction_event(tx_event.clone());
+                    match res {
+                        Ok(_) => {
+                            info!("payment transaction processed: {}", tx_event);
+                            tx_hashes.push(tx_hash.to_vec());
+                            block_event.payments_count += 1;
+                            block_event.add_fee(tx_body.fee)?;
+                            if tx_event.referral_reward != 0 {
+                                block_event.referral_rewards_count += 1;
+                                block_event.add_referral_reward(tx_event.referral_reward)?;
                             }
-                            Err(e) => {
-                                info!("payment transaction failed: {:?}", e);
-                                tx_event.result = ExecutionResult::Invalid as i32;
-                                tx_event.error_message = e.to_string();
+                            if tx_event.appreciation_char_trait_idx != 0 {
+                                block_event.appreciations_count += 1;
                             }
-                        }
 
-                        self.emit_tx_event(tx_event).await?;
-                    } else {
-                        info!("Payee user not found on chain - keeping this tx in the mem pool for later processing...");
-                        continue;
+                            block_event.add_transaction_event(tx_event.clone());
+                        }
+                        Err(e) => {
+                            info!("payment transaction failed: {:?}", e);
+                            batch.rollback_to(save_point);
+                            // failed txs are not retried in later blocks
+                            discarded_tx_hashes.push(tx_hash.to_vec());
+                            tx_event.result = ExecutionResult::Invalid as i32;
+                            tx_event.error_message = e.to_string();
+                        }
                     }
+
+                    self.emit_tx_event(tx_event, &mut batch).await?;
                 }
                 TransactionType::UpdateUserV1 => {
                     info!("processing update user transaction");
                     // Get tx signer user from chain and reject tx if it doesn't exist
-                    match DatabaseService::read(ReadItem {
-                        key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
-                        cf: USERS_COL_FAMILY,
-                    })
-                    .await?
+                    match batch
+                        .read(ReadItem {
+                            key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
+                            cf: USERS_COL_FAMILY,
+                        })
+                        .await?
                     {
                         Some(data) => {
                             info!("user found on chain");
@@ -214,95 +334,178 @@ impl Handler<ProcessTransactions> for BlockChainService {
                                     &tokenomics,
                                     &mut tx_event,
                                     &mut user,
+                                    &mut batch,
                                 )
                                 .await
                             {
                                 Ok(_) => {
                                     info!("update user transaction processed: {}", tx_event);
                                     tx_hashes.push(tx_hash.to_vec());
-                                    block_event.add_fee(tx_body.fee);
+                                    block_event.add_fee(tx_body.fee)?;
                                     block_event.add_transaction_event(tx_event.clone());
                                     block_event.user_updates_count += 1;
                                 }
                                 Err(e) => {
                                     error!("Failed to process update user transaction: {:?}", e);
+                                    batch.rollback_to(save_point);
+                                    // failed txs are not retried in later blocks
+                                    discarded_tx_hashes.push(tx_hash.to_vec());
                                     tx_event.result = ExecutionResult::Invalid as i32;
                                     tx_event.error_message = e.to_string();
                                 }
                             }
-                            self.emit_tx_event(tx_event).await?;
+                            self.emit_tx_event(tx_event, &mut batch).await?;
                         }
                         None => {
                             info!("Tx signer not on chain - rejecting & removing tx from pool");
 
-                            mem_pool
-                                .call(RemoveTransactionByHash(tx_hash.to_vec()))
-                                .await??;
+                            discarded_tx_hashes.push(tx_hash.to_vec());
                             tx_event.result = ExecutionResult::Invalid as i32;
                             tx_event.error_message =
                                 "Tx signer user not found on chain - discarding tx from mem pool"
                                     .to_string();
 
-                            self.emit_tx_event(tx_event.clone()).await?;
+                            self.emit_tx_event(tx_event.clone(), &mut batch).await?;
                         }
                     };
                 }
                 TransactionType::DeleteUserV1 => {
                     info!("processing delete user transaction");
-                    match DatabaseService::read(ReadItem {
-                        key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
-                        cf: USERS_COL_FAMILY,
-                    })
-                    .await?
+                    match batch
+                        .read(ReadItem {
+                            key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
+                            cf: USERS_COL_FAMILY,
+                        })
+                        .await?
                     {
                         Some(data) => {
                             info!("user found on chain");
                             let user = User::decode(data.0.as_ref())?;
                             match self
-                                .process_delete_user_transaction(tx, &user, &mut tx_event)
+                                .process_delete_user_transaction(
+                                    tx,
+                                    &user,
+                                    &mut tx_event,
+                                    &mut batch,
+                                )
                                 .await
                             {
-                                Ok(_) => {
+                                Ok(res) => {
                                     info!("delete user transaction processed: {}", tx_event);
                                     tx_hashes.push(tx_hash.to_vec());
-                                    block_event.add_fee(tx_body.fee);
+                                    block_event.add_fee(tx_body.fee)?;
+                                    block_event.add_burned(res.burned_amount)?;
                                     block_event.add_transaction_event(tx_event.clone());
+                                    self.emit_pending_payments_events(
+                                        res.cancelled_payments_events,
+                                        &mut block_event,
+                                        &mut batch,
+                                    )
+                                    .await?;
                                 }
                                 Err(e) => {
                                     error!(
                                         "Failed to process delete user transaction: {:?}",
                                         e.execution_info
                                     );
+                                    batch.rollback_to(save_point);
+                                    // failed txs are not retried in later blocks
+                                    discarded_tx_hashes.push(tx_hash.to_vec());
                                     tx_event.result = ExecutionResult::Invalid as i32;
                                     tx_event.error_message = e.error_message;
                                 }
                             }
-                            self.emit_tx_event(tx_event).await?;
+                            self.emit_tx_event(tx_event, &mut batch).await?;
                         }
                         None => {
                             info!("Tx signer not on chain - rejecting & removing tx from pool");
 
-                            mem_pool
-                                .call(RemoveTransactionByHash(tx_hash.to_vec()))
-                                .await??;
+                            discarded_tx_hashes.push(tx_hash.to_vec());
                             tx_event.result = ExecutionResult::Invalid as i32;
                             tx_event.error_message =
                                 "Tx signer user not found on chain - discarding tx from mem pool"
                                     .to_string();
 
-                            self.emit_tx_event(tx_event.clone()).await?;
+                            self.emit_tx_event(tx_event.clone(), &mut batch).await?;
                             continue;
                         }
                     };
                 }
+                TransactionType::ParamsUpdateV1 => {
+                    info!("processing params update transaction");
+                    match self
+                        .process_params_update_transaction(
+                            tx,
+                            &mut user,
+                            &params_updates,
+                            &mut tx_event,
+                            &mut batch,
+                        )
+                        .await
+                    {
+                        Ok(params_update) => {
+                            info!("params update transaction processed: {}", tx_event);
+                            tx_hashes.push(tx_hash.to_vec());
+                            block_event.add_fee(tx_body.fee)?;
+                            block_event.add_transaction_event(tx_event.clone());
+                            params_updates.push(params_update);
+                        }
+                        Err(e) => {
+                            error!("Failed to process params update transaction: {:?}", e);
+                            batch.rollback_to(save_point);
+                            // failed txs are not retried in later blocks
+                            discarded_tx_hashes.push(tx_hash.to_vec());
+                            tx_event.result = ExecutionResult::Invalid as i32;
+                            tx_event.error_message = e.to_string();
+                        }
+                    }
+                    self.emit_tx_event(tx_event, &mut batch).await?;
+                }
+                TransactionType::VerifierUpdateV1 => {
+                    info!("processing verifier update transaction");
+                    match self
+                        .process_verifier_update_transaction(
+                            tx,
+                            &mut user,
+                            &mut tx_event,
+                            &mut batch,
+                        )
+                        .await
+                    {
+                        Ok(_) => {
+                            info!("verifier update transaction processed: {}", tx_event);
+                            tx_hashes.push(tx_hash.to_vec());
+                            block_event.add_fee(tx_body.fee)?;
+                            block_event.add_transaction_event(tx_event.clone());
+                        }
+                        Err(e) => {
+                            error!("Failed to process verifier update transaction: {:?}", e);
+                            batch.rollback_to(save_point);
+                            // failed txs are not retried in later blocks
+                            discarded_tx_hashes.push(tx_hash.to_vec());
+                            tx_event.result = ExecutionResult::Invalid as i32;
+                            tx_event.error_message = e.to_string();
+                        }
+                    }
+                    self.emit_tx_event(tx_event, &mut batch).await?;
+                }
                 _ => {
                     // ignore any other transaction types
                 }
             }
         }
 
-        if tx_hashes.is_empty() {
+        if tx_hashes.is_empty() && refunds_count == 0 && karma_rewards.is_none() {
             info!("no txs to add to the block - skip block creation....");
+            // persist events of invalid txs
+            batch.commit().await?;
+            self.publish_events(None).await?;
+            mem_pool
+                .call(RejectTransactions {
+                    tx_hashes: discarded_tx_hashes,
+                    reason: TX_EXECUTION_FAILED_REASON.into(),
+                })
+                .await??;
             return Ok(None);
         }
 
@@ -310,21 +513,41 @@ impl Handler<ProcessTransactions> for BlockChainService {
         let block = self
             .create_block(
                 &tx_hashes,
+                block_time,
                 stats,
                 &tokenomics,
                 block_event,
+                karma_rewards,
                 block_height,
                 self.id_key_pair.as_ref().unwrap(),
+                &mut batch,
             )
             .await?;
 
-        // remove processed txs from the mem pool
+        // atomically commit the block and all of its state changes
+        batch.commit().await?;
+
+        // params updates executed in the block take effect from their target heights
+        GenesisConfigService::from_registry()
+            .await?
+            .call(AddParamsUpdates {
+                params_updates,
+                next_height: block_height + 1,
+            })
+            .await?;
+
+        self.publish_events(Some(&block)).await?;
+
+        // remove processed and invalid txs from the mem pool
         mem_pool
             .call(RemoveTransactionsByHashes(tx_hashes))
             .await??;
-
-        // remove old txs from the mem pool
-        mem_pool.call(RemoveOldTransactions).await??;
+        mem_pool
+            .call(RejectTransactions {
+                tx_hashes: discarded_tx_hashes,
+                reason: TX_EXECUTION_FAILED_REASON.into(),
+            })
+            .await??;
         Ok(Some(block))
     }
 }
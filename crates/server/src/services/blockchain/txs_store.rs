@@ -2,21 +2,48 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::mem_pool_service::{
+    GetPendingPayments, GetTransaction, MemPoolService,
+};
+use crate::services::blockchain::tx_status::get_rejected_transaction;
 use crate::services::db_config_service::{
-    TRANSACTIONS_COL_FAMILY, TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY, USERS_COL_FAMILY,
+    TRANSACTIONS_COL_FAMILY, TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
+    TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY, TRANSACTIONS_HASHES_BY_SIGNER_IDX_COL_FAMILY,
+    USERS_COL_FAMILY,
 };
 use anyhow::{anyhow, Result};
-use base::karma_coin::karma_coin_core_types::TransactionStatus::OnChain;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_api::{
+    GetIncomingTransactionsResponse, GetTransactionsResponse, TransactionDirection,
+    TransactionsFilter,
+};
+use base::karma_coin::karma_coin_core_types::TransactionStatus::{OnChain, Rejected, Submitted};
 use base::karma_coin::karma_coin_core_types::*;
 use bytes::Bytes;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use db::db_service::{
+    DataItem, DatabaseService, DeleteItem, ReadItem, ReadPrefixItems, WriteBatch, WriteItem,
+};
 use prost::Message;
 use xactor::*;
 
-#[message(result = "Result<(Vec<SignedTransactionWithStatus>, TransactionEvents)>")]
+/// Default number of transactions in a page of an account's transactions
+pub(crate) const DEFAULT_TXS_PAGE_SIZE: u32 = 100;
+
+/// Max number of transactions in a page of an account's transactions
+pub(crate) const MAX_TXS_PAGE_SIZE: u32 = 1000;
+
+/// Max number of index entries scanned for one page. A page of a filter which matches few
+/// transactions may have less transactions than requested and a cursor to continue from
+const MAX_SCANNED_INDEX_ENTRIES: u32 = 10000;
+
+#[message(result = "Result<GetTransactionsResponse>")]
 pub(crate) struct GetTransactionsAndEventsByAccountId {
     pub(crate) account_id: Bytes,
+    pub(crate) limit: u32,
+    pub(crate) cursor: Option<Bytes>,
+    pub(crate) filter: TransactionsFilter,
 }
 
 #[async_trait::async_trait]
@@ -25,8 +52,32 @@ impl Handler<GetTransactionsAndEventsByAccountId> for BlockChainService {
         &mut self,
         _ctx: &mut Context<Self>,
         msg: GetTransactionsAndEventsByAccountId,
-    ) -> Result<(Vec<SignedTransactionWithStatus>, TransactionEvents)> {
-        self.get_transactions_and_events_by_account_id(msg.account_id)
+    ) -> Result<GetTransactionsResponse> {
+        self.get_transactions_and_events_by_account_id(
+            msg.account_id,
+            msg.limit,
+            msg.cursor,
+            &msg.filter,
+        )
+        .await
+    }
+}
+
+#[message(result = "Result<GetIncomingTransactionsResponse>")]
+pub(crate) struct GetIncomingTransactions {
+    pub(crate) account_id: Bytes,
+    pub(crate) limit: u32,
+    pub(crate) cursor: Option<Bytes>,
+}
+
+#[async_trait::async_trait]
+impl Handler<GetIncomingTransactions> for BlockChainService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetIncomingTransactions,
+    ) -> Result<GetIncomingTransactionsResponse> {
+        self.get_incoming_transactions(msg.account_id, msg.limit, msg.cursor)
             .await
     }
 }
@@ -68,6 +119,7 @@ impl BlockChainService {
     /// create signed tx with status from a signed tx
     pub(crate) async fn create_signed_tx_with_status(
         tx: &SignedTransaction,
+        status: TransactionStatus,
     ) -> Result<SignedTransactionWithStatus> {
         let sender = match DatabaseService::read(ReadItem {
             key: Bytes::from(tx.signer.as_ref().unwrap().data.clone()),
@@ -86,7 +138,8 @@ impl BlockChainService {
         let tx_body = tx.get_body()?;
         let receiver = match tx_body.get_tx_type()? {
             TransactionType::PaymentV1 => {
-                BlockChainService::get_payee_user_from_tx_body(&tx_body).await?
+                BlockChainService::get_payee_user_from_tx_body(&tx_body, &BlockBatch::default())
+                    .await?
             }
             _ => None,
         };
@@ -95,124 +148,384 @@ impl BlockChainService {
             from: sender,
             to: receiver,
             transaction: Some(tx.clone()),
-            status: OnChain as i32,
+            status: status as i32,
+            rejection_reason: String::new(),
         })
     }
 
+    /// Get a transaction by its hash. Transactions which are pending in the mem pool are
+    /// returned with a Submitted status, transactions which are on chain with an OnChain status
+    /// and transactions which were rejected or dropped from the pool with a Rejected status
     pub(crate) async fn get_transaction_by_hash(
         &self,
         hash: Bytes,
     ) -> Result<Option<SignedTransactionWithStatus>> {
         if let Some(data) = DatabaseService::read(ReadItem {
-            key: hash,
+            key: hash.clone(),
             cf: TRANSACTIONS_COL_FAMILY,
         })
         .await?
         {
             let tx = SignedTransaction::decode(data.0.as_ref())?;
-            Ok(Some(
-                BlockChainService::create_signed_tx_with_status(&tx).await?,
-            ))
-        } else {
-            Ok(None)
+            return Ok(Some(
+                BlockChainService::create_signed_tx_with_status(&tx, OnChain).await?,
+            ));
+        }
+
+        match MemPoolService::from_registry()
+            .await?
+            .call(GetTransaction(hash.to_vec()))
+            .await??
+        {
+            Some(tx) => Ok(Some(
+                BlockChainService::create_signed_tx_with_status(&tx, Submitted).await?,
+            )),
+            None => match get_rejected_transaction(&hash).await? {
+                Some(rejected_tx) => {
+                    let tx = rejected_tx
+                        .transaction
+                        .ok_or_else(|| anyhow!("missing rejected transaction"))?;
+                    let mut tx_with_status =
+                        BlockChainService::create_signed_tx_with_status(&tx, Rejected).await?;
+                    tx_with_status.rejection_reason = rejected_tx.reason;
+                    Ok(Some(tx_with_status))
+                }
+                None => Ok(None),
+            },
         }
     }
 
+    /// Get a page of an account's transactions which match a filter, newest first, with their
+    /// events. The page starts after the cursor, when provided
     pub(crate) async fn get_transactions_and_events_by_account_id(
         &self,
         account_id: Bytes,
-    ) -> Result<(Vec<SignedTransactionWithStatus>, TransactionEvents)> {
-        let txs = self.get_transactions_by_account_id(account_id).await?;
+        limit: u32,
+        cursor: Option<Bytes>,
+        filter: &TransactionsFilter,
+    ) -> Result<GetTransactionsResponse> {
+        let index_cf = match filter.direction() {
+            TransactionDirection::Any => TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
+            TransactionDirection::Sent => TRANSACTIONS_HASHES_BY_SIGNER_IDX_COL_FAMILY,
+            TransactionDirection::Received => TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY,
+        };
+
+        self.get_indexed_transactions_page(index_cf, account_id, limit, cursor, filter)
+            .await
+    }
+
+    /// Get a page of on chain transactions received by an account, newest first, with their
+    /// events. The first page also includes pending payments to the account and to its
+    /// verified mobile number which are waiting in the mem pool
+    pub(crate) async fn get_incoming_transactions(
+        &self,
+        account_id: Bytes,
+        limit: u32,
+        cursor: Option<Bytes>,
+    ) -> Result<GetIncomingTransactionsResponse> {
+        let pending_transactions = match cursor {
+            Some(_) => vec![],
+            None => self.get_pending_incoming_payments(&account_id).await?,
+        };
+
+        let page = self
+            .get_indexed_transactions_page(
+                TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY,
+                account_id,
+                limit,
+                cursor,
+                &TransactionsFilter::default(),
+            )
+            .await?;
+
+        Ok(GetIncomingTransactionsResponse {
+            transactions: page.transactions,
+            tx_events: page.tx_events,
+            next_cursor: page.next_cursor,
+            pending_transactions,
+        })
+    }
+
+    /// Get pending payments to an account or to its mobile number from the mem pool
+    async fn get_pending_incoming_payments(
+        &self,
+        account_id: &[u8],
+    ) -> Result<Vec<SignedTransactionWithStatus>> {
+        let mobile_number_hash = match DatabaseService::read(ReadItem {
+            key: Bytes::copy_from_slice(account_id),
+            cf: USERS_COL_FAMILY,
+        })
+        .await?
+        {
+            Some(data) => User::decode(data.0.as_ref())?.get_mobile_number_hash()?,
+            None => None,
+        };
+
+        let pending_payments = MemPoolService::from_registry()
+            .await?
+            .call(GetPendingPayments {
+                account_id: account_id.to_vec(),
+                mobile_number_hash,
+            })
+            .await??;
+
+        let mut txs = vec![];
+        for tx in pending_payments.iter() {
+            txs.push(BlockChainService::create_signed_tx_with_status(tx, Submitted).await?);
+        }
+        Ok(txs)
+    }
+
+    /// Get a page of transactions from an account txs index which match a filter,
+    /// newest first, with their events. The page starts after the cursor, when provided
+    async fn get_indexed_transactions_page(
+        &self,
+        index_cf: &'static str,
+        account_id: Bytes,
+        limit: u32,
+        cursor: Option<Bytes>,
+        filter: &TransactionsFilter,
+    ) -> Result<GetTransactionsResponse> {
+        let limit = match limit {
+            0 => DEFAULT_TXS_PAGE_SIZE,
+            _ => limit.min(MAX_TXS_PAGE_SIZE),
+        };
+
+        let entries = DatabaseService::read_prefix_items(ReadPrefixItems {
+            prefix: account_id.clone(),
+            from_key: cursor,
+            reverse: true,
+            max_results: MAX_SCANNED_INDEX_ENTRIES,
+            cf: index_cf,
+        })
+        .await?;
+        let scanned_all = entries.len() < MAX_SCANNED_INDEX_ENTRIES as usize;
+
+        let mut txs = vec![];
         let mut tx_events = TransactionEvents { events: vec![] };
-        for tx_data in txs.iter() {
-            let tx = tx_data
-                .transaction
-                .as_ref()
-                .ok_or_else(|| anyhow!("missing transaction in transaction with status"))?;
-
-            let tx_hash = tx.get_hash()?;
-            let events_data = self.get_tx_events(tx_hash).await?;
-            for event in events_data.events {
-                tx_events.events.push(event);
+        let mut scanned = 0;
+        for (key, _) in entries.iter() {
+            if txs.len() >= limit as usize {
+                break;
+            }
+            scanned += 1;
+
+            let tx_hash = Bytes::copy_from_slice(&key[key.len() - TX_HASH_LEN..]);
+            let Some(tx) = get_on_chain_transaction(tx_hash.clone()).await? else {
+                warn!("indexed tx not found on chain");
+                continue;
+            };
+
+            if !filter_matches(filter, &tx)? {
+                continue;
+            }
+
+            if let Ok(signed_tx) =
+                BlockChainService::create_signed_tx_with_status(&tx, OnChain).await
+            {
+                txs.push(signed_tx);
+                tx_events
+                    .events
+                    .append(&mut self.get_tx_events(tx_hash).await?.events);
+            } else {
+                warn!("ignoring tx - dead account");
             }
         }
 
-        Ok((txs, tx_events))
+        // the cursor is the last scanned entry unless all of the account's entries were scanned
+        let next_cursor = if scanned == entries.len() && scanned_all {
+            vec![]
+        } else {
+            entries[scanned - 1].0.to_vec()
+        };
+
+        Ok(GetTransactionsResponse {
+            transactions: txs,
+            tx_events: Some(tx_events),
+            next_cursor,
+        })
     }
 
-    /// Get transactions by account id
+    /// Get transactions by account id, newest first
     /// These include all transactions to and from this account
     pub(crate) async fn get_transactions_by_account_id(
         &self,
         account_id: Bytes,
     ) -> Result<Vec<SignedTransactionWithStatus>> {
-        if let Some(data) = DatabaseService::read(ReadItem {
-            key: account_id,
+        let entries = DatabaseService::read_prefix_items(ReadPrefixItems {
+            prefix: account_id,
+            from_key: None,
+            reverse: true,
+            max_results: 0,
             cf: TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
         })
-        .await?
-        {
-            let data = SignedTransactionsHashes::decode(data.0.as_ref())?;
-            let mut txs = vec![];
-
-            for tx_hash in data.hashes {
-                if let Some(data) = DatabaseService::read(ReadItem {
-                    key: Bytes::from(tx_hash),
-                    cf: TRANSACTIONS_COL_FAMILY,
-                })
-                .await?
+        .await?;
+
+        let mut txs = vec![];
+        for (key, _) in entries {
+            let tx_hash = Bytes::copy_from_slice(&key[key.len() - TX_HASH_LEN..]);
+            if let Some(tx) = get_on_chain_transaction(tx_hash).await? {
+                if let Ok(signed_tx) =
+                    BlockChainService::create_signed_tx_with_status(&tx, OnChain).await
                 {
-                    let tx = SignedTransaction::decode(data.0.as_ref())?;
-
-                    if let Ok(signed_tx) =
-                        BlockChainService::create_signed_tx_with_status(&tx).await
-                    {
-                        txs.push(signed_tx);
-                    } else {
-                        warn!("ignoring tx - dead account");
-                    }
+                    txs.push(signed_tx);
+                } else {
+                    warn!("ignoring tx - dead account");
                 }
             }
-            Ok(txs)
-        } else {
-            Ok(vec![])
         }
+        Ok(txs)
     }
 
-    /// Index a transaction by an account id
+    /// Index a transaction by an account id and the height of the block it was executed in.
+    /// Index keys are ordered by account and height so an account's txs can be read in pages.
+    /// The tx is also indexed by signer when the account signed it, and by payee otherwise
     pub(crate) async fn index_transaction_by_account_id(
         &mut self,
         transaction: &SignedTransaction,
         account_id: Bytes,
+        height: u64,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
-        let tx_hash = transaction.get_hash()?;
+        for item in account_tx_index_items(transaction, &account_id, height)? {
+            batch.write(item);
+        }
+        Ok(())
+    }
 
-        let tx_hashes = if let Some(data) = DatabaseService::read(ReadItem {
-            key: account_id.clone(),
-            cf: TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
-        })
-        .await?
+    /// Move account txs indexed in a single SignedTransactionsHashes value per account
+    /// to ordered account, signer and payee index keys
+    pub(crate) async fn migrate_account_txs_index(&self) -> Result<()> {
+        for (account_id, value) in
+            DatabaseService::read_all(TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY).await?
         {
-            let mut data = SignedTransactionsHashes::decode(data.0.as_ref())?;
-            data.hashes.push(tx_hash.clone().to_vec());
-            data
-        } else {
-            SignedTransactionsHashes {
-                hashes: vec![tx_hash.clone().to_vec()],
+            // ordered index entries have no value
+            if value.is_empty() {
+                continue;
             }
-        };
 
-        let mut buf = Vec::with_capacity(tx_hashes.encoded_len());
-        tx_hashes.encode(&mut buf)?;
+            let tx_hashes = SignedTransactionsHashes::decode(value.as_ref())?;
+            info!(
+                "migrating {} indexed txs of account {}",
+                tx_hashes.hashes.len(),
+                short_hex_string(&account_id)
+            );
+
+            let mut batch = WriteBatch::default();
+            for tx_hash in tx_hashes.hashes {
+                let Some(tx) = get_on_chain_transaction(Bytes::from(tx_hash.clone())).await? else {
+                    warn!("indexed tx not found on chain");
+                    continue;
+                };
+
+                let height = self
+                    .get_tx_events(Bytes::from(tx_hash))
+                    .await?
+                    .events
+                    .first()
+                    .map_or(0, |e| e.height);
 
-        DatabaseService::write(WriteItem {
-            data: DataItem {
+                for item in account_tx_index_items(&tx, &account_id, height)? {
+                    batch.write(item);
+                }
+            }
+            batch.delete(DeleteItem {
                 key: account_id,
-                value: Bytes::from(buf),
-            },
-            cf: TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
-            ttl: 0,
-        })
-        .await
+                cf: TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
+            });
+            DatabaseService::write_batch(batch).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Length of a tx hash at the end of an account tx index key
+const TX_HASH_LEN: usize = 32;
+
+/// Helper function - returns the index key of an account tx: account id, big-endian block
+/// height and tx hash
+fn account_tx_index_key(account_id: &[u8], height: u64, tx_hash: &[u8]) -> Bytes {
+    let mut key = Vec::with_capacity(account_id.len() + 8 + tx_hash.len());
+    key.extend_from_slice(account_id);
+    key.extend_from_slice(&height.to_be_bytes());
+    key.extend_from_slice(tx_hash);
+    Bytes::from(key)
+}
+
+/// Helper function - returns the index items of a tx of an account executed at a block height
+fn account_tx_index_items(
+    transaction: &SignedTransaction,
+    account_id: &[u8],
+    height: u64,
+) -> Result<Vec<WriteItem>> {
+    let key = account_tx_index_key(account_id, height, &transaction.get_hash()?);
+    let signed_by_account = transaction
+        .signer
+        .as_ref()
+        .is_some_and(|signer| signer.data == account_id);
+    let direction_cf = match signed_by_account {
+        true => TRANSACTIONS_HASHES_BY_SIGNER_IDX_COL_FAMILY,
+        false => TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY,
+    };
+
+    Ok(
+        [TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY, direction_cf]
+            .into_iter()
+            .map(|cf| WriteItem {
+                data: DataItem {
+                    key: key.clone(),
+                    value: Bytes::new(),
+                },
+                cf,
+                ttl: 0,
+            })
+            .collect(),
+    )
+}
+
+/// Helper function - returns an on chain tx by its hash
+async fn get_on_chain_transaction(tx_hash: Bytes) -> Result<Option<SignedTransaction>> {
+    match DatabaseService::read(ReadItem {
+        key: tx_hash,
+        cf: TRANSACTIONS_COL_FAMILY,
+    })
+    .await?
+    {
+        Some(data) => Ok(Some(SignedTransaction::decode(data.0.as_ref())?)),
+        None => Ok(None),
+    }
+}
+
+/// Helper function - returns true when a tx matches a filter.
+/// The filter's direction is matched by the index the tx was read from
+fn filter_matches(filter: &TransactionsFilter, tx: &SignedTransaction) -> Result<bool> {
+    let tx_body = tx.get_body()?;
+    let tx_type = tx_body.get_tx_type()?;
+    if !filter.transaction_types.is_empty() && !filter.transaction_types.contains(&(tx_type as i32))
+    {
+        return Ok(false);
     }
+
+    if filter.char_trait_id != 0 || filter.community_id != 0 {
+        if tx_type != TransactionType::PaymentV1 {
+            return Ok(false);
+        }
+        let payment = tx_body.get_payment_transaction_v1()?;
+        if filter.char_trait_id != 0 && payment.char_trait_id != filter.char_trait_id {
+            return Ok(false);
+        }
+        if filter.community_id != 0 && payment.community_id != filter.community_id {
+            return Ok(false);
+        }
+    }
+
+    if filter.from_time != 0 && tx_body.timestamp < filter.from_time {
+        return Ok(false);
+    }
+    if filter.to_time != 0 && tx_body.timestamp > filter.to_time {
+        return Ok(false);
+    }
+
+    Ok(true)
 }
@@ -5,8 +5,11 @@
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
 
+use crate::services::blockchain::block_batch::BlockBatch;
 use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::delete_user_tx_processor::validate_user_name_released;
 use crate::services::blockchain::tokenomics::Tokenomics;
+use crate::services::blockchain::verifier_update_tx_processor::validate_verifiers_quorum;
 use crate::services::db_config_service::{
     MOBILE_NUMBERS_COL_FAMILY, TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY,
 };
@@ -16,50 +19,61 @@ use base::karma_coin::karma_coin_core_types::ExecutionInfo::{
 use base::karma_coin::karma_coin_core_types::{
     ExecutionResult, FeeType, SignedTransaction, TransactionEvent, TransactionType, User,
 };
-use base::signed_trait::SignedTrait;
-use db::db_service::{DataItem, DatabaseService, ReadItem, WriteItem};
+use base::user_verification_data_ex::get_evidence_ex_verifiers;
+use db::db_service::{DataItem, ReadItem, WriteItem};
 use prost::Message;
 
 impl BlockChainService {
-    /// Helper function - update user's nickname
+    /// Helper function - update user's nickname at the provided tx time
     async fn update_username(
         &mut self,
         user: &mut User,
         nickname: String,
+        timestamp: u64,
         event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
         let nick_name_key = Bytes::from(nickname.as_bytes().to_vec());
         let account_id = user.account_id.as_ref().unwrap();
 
         // verify that the requested nickname not registered to another user
-        if (DatabaseService::read(ReadItem {
-            key: nick_name_key.clone(),
-            cf: USERS_NAMES_COL_FAMILY,
-        })
-        .await?)
+        if (batch
+            .read(ReadItem {
+                key: nick_name_key.clone(),
+                cf: USERS_NAMES_COL_FAMILY,
+            })
+            .await?)
             .is_some()
         {
             event.info = NicknameNotAvailable as i32;
             return Ok(());
         }
 
+        // a deleted account's user name is only available after its cooldown
+        if validate_user_name_released(&nickname, timestamp, batch)
+            .await
+            .is_err()
+        {
+            event.info = NicknameNotAvailable as i32;
+            return Ok(());
+        }
+
         // update user's nickname
         user.user_name = nickname.clone();
 
         // update nickname index
-        let result = DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: nick_name_key,
                 value: Bytes::from(account_id.data.to_vec()),
             },
             cf: USERS_NAMES_COL_FAMILY,
             ttl: 0,
-        })
-        .await;
+        });
 
         info!("Updated user name to {}", nickname);
 
-        result
+        Ok(())
     }
 
     /// Process a user update transaction
@@ -69,6 +83,7 @@ impl BlockChainService {
         tokenomics: &Tokenomics,
         event: &mut TransactionEvent,
         user: &mut User,
+        batch: &mut BlockBatch,
     ) -> Result<()> {
         let account_id = signed_transaction
             .signer
@@ -84,9 +99,8 @@ impl BlockChainService {
 
         // check tx fee
         let tx_fee = tx_body.fee;
-        let apply_subsidy = tokenomics
-            .should_subsidise_transaction_fee(0, tx_fee, TransactionType::UpdateUserV1)
-            .await?;
+        let apply_subsidy =
+            tokenomics.should_subsidise_transaction_fee(0, tx_fee, TransactionType::UpdateUserV1);
 
         let fee_type = if apply_subsidy {
             FeeType::Mint
@@ -94,7 +108,7 @@ impl BlockChainService {
             FeeType::User
         };
 
-        if !apply_subsidy && tx_fee >= user.balance {
+        if !apply_subsidy && tx_fee >= user.spendable_balance() {
             // invalid tx - tx fee is higher than user balance
             return Err(anyhow!(
                 "tx fee is greater than user balance no tx fee subsidy is applied"
@@ -103,19 +117,18 @@ impl BlockChainService {
 
         // apply tx fee to user balance no subsidy is applied
         if !apply_subsidy {
-            user.balance -= tx_fee;
+            user.withdraw(tx_fee)?;
             let mut buf = Vec::with_capacity(user.encoded_len());
             user.encode(&mut buf)?;
 
-            DatabaseService::write(WriteItem {
+            batch.write(WriteItem {
                 data: DataItem {
                     key: Bytes::from(account_id.data.clone()),
                     value: Bytes::from(buf),
                 },
                 cf: USERS_COL_FAMILY,
                 ttl: 0,
-            })
-            .await?;
+            });
         }
 
         event.fee = tx_fee;
@@ -126,7 +139,7 @@ impl BlockChainService {
         update_user_tx.verify_syntax()?;
 
         let requested_nickname = update_user_tx.nickname;
-        if requested_nickname.is_empty() && update_user_tx.user_verification_data.is_none() {
+        if requested_nickname.is_empty() && update_user_tx.user_verification_data_ex.is_empty() {
             event.info = NicknameInvalid as i32;
             return Ok(());
         }
@@ -134,30 +147,33 @@ impl BlockChainService {
         // handle nickname update request...
 
         if user.user_name != requested_nickname {
-            self.update_username(user, requested_nickname, event)
+            self.update_username(user, requested_nickname, tx_body.timestamp, event, batch)
                 .await?;
         }
 
         // handle mobile number update, if requested
 
-        if let Some(evidence) = update_user_tx.user_verification_data {
-            if evidence.verify_signature().is_err() {
-                event.info = InvalidData as i32;
-                return Ok(());
-            }
-
-            // todo: verify evidence fields are valid
-
-            let new_mobile_number = update_user_tx.mobile_number.unwrap();
-            let verified_number = evidence.mobile_number.unwrap();
-
-            if new_mobile_number.number != verified_number.number {
-                event.info = InvalidData as i32;
-                return Ok(());
-            }
-
-            let evidence_account_id = evidence
+        if !update_user_tx.user_verification_data_ex.is_empty() {
+            // evidence must be signed by a quorum of verifiers valid at the block height
+            let evidence = &update_user_tx.user_verification_data_ex;
+            let data = match get_evidence_ex_verifiers(&evidence.iter().collect::<Vec<_>>()) {
+                Ok((data, verifiers)) => validate_verifiers_quorum(&verifiers, event.height, batch)
+                    .await
+                    .map(|_| data),
+                Err(e) => Err(e),
+            };
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    info!("invalid mobile number update evidence: {}", e);
+                    event.info = InvalidData as i32;
+                    return Ok(());
+                }
+            };
+
+            let evidence_account_id = data
                 .account_id
+                .as_ref()
                 .ok_or_else(|| anyhow!("missing account id in verifier data"))?;
 
             if account_id.data != evidence_account_id.data {
@@ -165,19 +181,24 @@ impl BlockChainService {
                 return Ok(());
             }
 
-            // update user's mobile number
-            user.mobile_number = Some(new_mobile_number.clone());
+            let Ok(new_mobile_number_hash) = data.get_mobile_number_hash() else {
+                event.info = InvalidData as i32;
+                return Ok(());
+            };
+
+            // update user's mobile number. Only its hash is stored on chain
+            user.mobile_number_hash = new_mobile_number_hash;
+            user.mobile_number = None;
 
             // update mobile numbers index
-            DatabaseService::write(WriteItem {
+            batch.write(WriteItem {
                 data: DataItem {
-                    key: Bytes::from(new_mobile_number.number.as_bytes().to_vec()),
+                    key: Bytes::from(user.mobile_number_hash.clone()),
                     value: Bytes::from(account_id.data.to_vec()),
                 },
                 cf: MOBILE_NUMBERS_COL_FAMILY,
                 ttl: 0,
-            })
-            .await?;
+            });
         }
 
         // update user nonce and account
@@ -186,15 +207,14 @@ impl BlockChainService {
 
         let mut buf = Vec::with_capacity(user.encoded_len());
         user.encode(&mut buf)?;
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: Bytes::from(account_id.data.clone()),
                 value: Bytes::from(buf),
             },
             cf: USERS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         let mut tx_data = Vec::with_capacity(signed_transaction.encoded_len());
         info!(
@@ -204,20 +224,21 @@ impl BlockChainService {
         signed_transaction.encode(&mut tx_data)?;
 
         // index the transaction in the db by hash
-        DatabaseService::write(WriteItem {
+        batch.write(WriteItem {
             data: DataItem {
                 key: tx_hash.clone(),
                 value: Bytes::from(tx_data),
             },
             cf: TRANSACTIONS_COL_FAMILY,
             ttl: 0,
-        })
-        .await?;
+        });
 
         // index the transaction in the db for the user's account id
         self.index_transaction_by_account_id(
             signed_transaction,
             Bytes::from(account_id.data.to_vec()),
+            event.height,
+            batch,
         )
         .await?;
 
@@ -0,0 +1,295 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::blockchain::block_batch::BlockBatch;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::db_config_service::{
+    TRANSACTIONS_COL_FAMILY, USERS_COL_FAMILY, VERIFIERS_COL_FAMILY,
+};
+use anyhow::{anyhow, Result};
+use base::genesis_config_service::GenesisConfigService;
+use base::hex_utils::short_hex_string;
+use base::karma_coin::karma_coin_core_types::{
+    AccountId, ExecutionResult, FeeType, RegisteredVerifier, SignedTransaction, TransactionEvent,
+    User, VerifierAction,
+};
+use bytes::Bytes;
+use db::db_service::{DataItem, ReadItem, WriteItem};
+use prost::Message;
+
+/// Helper function - write a verifier to the verifiers registry
+fn write_verifier(verifier: &RegisteredVerifier, batch: &mut BlockBatch) {
+    batch.write(WriteItem {
+        data: DataItem {
+            key: Bytes::from(verifier.account_id.as_ref().unwrap().data.clone()),
+            value: Bytes::from(verifier.encode_to_vec()),
+        },
+        cf: VERIFIERS_COL_FAMILY,
+        ttl: 0,
+    });
+}
+
+/// Returns the registered verifier with the provided account id, if any.
+/// Verifiers added, rotated or revoked on chain are in the verifiers col family.
+/// Genesis verifiers without an on-chain record are valid from genesis. Genesis verifiers can't
+/// be changed by params updates so after genesis verifiers only change via the registry.
+pub(crate) async fn get_verifier(
+    account_id: &AccountId,
+    height: u64,
+    batch: &BlockBatch,
+) -> Result<Option<RegisteredVerifier>> {
+    if let Some((data, _)) = batch
+        .read(ReadItem {
+            key: Bytes::from(account_id.data.clone()),
+            cf: VERIFIERS_COL_FAMILY,
+        })
+        .await?
+    {
+        return Ok(Some(RegisteredVerifier::decode(data.as_ref())?));
+    }
+
+    let genesis_data = GenesisConfigService::get_genesis_data_at(height).await?;
+    if let Some(verifier) = genesis_data
+        .verifiers
+        .iter()
+        .find(|v| v.account_id.as_ref() == Some(account_id))
+    {
+        return Ok(Some(RegisteredVerifier {
+            account_id: verifier.account_id.clone(),
+            name: verifier.name.clone(),
+            ..Default::default()
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Validate that verification evidence signed by the provided verifier is accepted in a
+/// block at the provided height
+pub(crate) async fn validate_verifier(
+    account_id: Option<&AccountId>,
+    height: u64,
+    batch: &BlockBatch,
+) -> Result<()> {
+    let account_id = account_id
+        .ok_or_else(|| anyhow!("missing verifier account id in verification evidence"))?;
+
+    match get_verifier(account_id, height, batch).await? {
+        Some(verifier) if verifier.is_valid_at(height) => Ok(()),
+        Some(verifier) => Err(anyhow!(
+            "verifier {} is not valid at height {}",
+            verifier,
+            height
+        )),
+        None => Err(anyhow!(
+            "unrecognized verifier {}",
+            short_hex_string(&account_id.data)
+        )),
+    }
+}
+
+/// Validate that the distinct verifiers of mobile number evidence are at least the genesis
+/// verifiers threshold and are all valid at the provided block height
+pub(crate) async fn validate_verifiers_quorum(
+    verifiers: &[AccountId],
+    height: u64,
+    batch: &BlockBatch,
+) -> Result<()> {
+    for verifier in verifiers.iter() {
+        validate_verifier(Some(verifier), height, batch).await?;
+    }
+
+    let threshold = GenesisConfigService::get_genesis_data_at(height)
+        .await?
+        .verifiers_threshold;
+    if (verifiers.len() as u64) < threshold {
+        return Err(anyhow!(
+            "mobile number evidence by {} verifiers. Required: {}",
+            verifiers.len(),
+            threshold
+        ));
+    }
+
+    Ok(())
+}
+
+impl BlockChainService {
+    /// Process a verifier update transaction. Registry changes take effect from the
+    /// proposal's height, which is always after the tx block.
+    pub(crate) async fn process_verifier_update_transaction(
+        &mut self,
+        signed_transaction: &SignedTransaction,
+        user: &mut User,
+        event: &mut TransactionEvent,
+        batch: &mut BlockBatch,
+    ) -> Result<()> {
+        let account_id = signed_transaction
+            .signer
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing account id in tx"))?;
+        let tx_hash = signed_transaction.get_hash()?;
+
+        // validate tx syntax, fields, signature, net_id before processing it
+        signed_transaction.validate().await?;
+        let tx_body = signed_transaction.get_body()?;
+        tx_body.validate(user.nonce).await?;
+
+        let verifier_update_tx = tx_body.get_verifier_update_transaction_v1()?;
+        let proposal = verifier_update_tx
+            .proposal
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing verifier update proposal"))?;
+        proposal.verify_syntax()?;
+
+        let height = match proposal.height {
+            0 => event.height + 1,
+            h if h > event.height => h,
+            h => {
+                return Err(anyhow!(
+                    "verifier update height {} must be after the tx block height {}",
+                    h,
+                    event.height
+                ))
+            }
+        };
+
+        // a revoked key's validity window is not changed by its revocation
+        if proposal.action != VerifierAction::Revoke as i32
+            && proposal.valid_until != 0
+            && proposal.valid_until < height
+        {
+            return Err(anyhow!(
+                "verifier valid until height {} is before its first height {}",
+                proposal.valid_until,
+                height
+            ));
+        }
+
+        // approvals are verified against the admins in effect at the tx block height
+        let genesis_data = GenesisConfigService::get_genesis_data_at(event.height).await?;
+        if genesis_data.governance_admins.is_empty() {
+            return Err(anyhow!("verifier updates are disabled"));
+        }
+
+        let approvers = verifier_update_tx
+            .get_approvers(&genesis_data.governance_admins, &genesis_data.genesis_hash)?;
+        if (approvers.len() as u64) < genesis_data.governance_threshold {
+            return Err(anyhow!(
+                "verifier update has {} approvals. Required: {}",
+                approvers.len(),
+                genesis_data.governance_threshold
+            ));
+        }
+
+        let verifier_id = proposal.account_id.as_ref().unwrap();
+        let verifier = get_verifier(verifier_id, event.height, batch).await?;
+
+        match VerifierAction::from_i32(proposal.action).unwrap() {
+            VerifierAction::Add => {
+                if verifier.is_some() {
+                    return Err(anyhow!("verifier is already registered"));
+                }
+                write_verifier(
+                    &RegisteredVerifier {
+                        account_id: Some(verifier_id.clone()),
+                        name: proposal.name.clone(),
+                        valid_from: height,
+                        valid_until: proposal.valid_until,
+                        revoked_at: 0,
+                        rotated_to: None,
+                    },
+                    batch,
+                );
+            }
+            VerifierAction::Rotate => {
+                let mut verifier =
+                    verifier.ok_or_else(|| anyhow!("rotated verifier is not registered"))?;
+                if verifier.revoked_at != 0 || verifier.rotated_to.is_some() {
+                    return Err(anyhow!("rotated verifier was already revoked or rotated"));
+                }
+
+                let new_id = proposal.new_account_id.as_ref().unwrap();
+                if get_verifier(new_id, event.height, batch).await?.is_some() {
+                    return Err(anyhow!("verifier new key is already registered"));
+                }
+
+                // the old key is accepted up to the block before the new key takes effect
+                if verifier.valid_until == 0 || verifier.valid_until >= height {
+                    verifier.valid_until = height - 1;
+                }
+                verifier.rotated_to = Some(new_id.clone());
+                write_verifier(&verifier, batch);
+
+                write_verifier(
+                    &RegisteredVerifier {
+                        account_id: Some(new_id.clone()),
+                        name: verifier.name.clone(),
+                        valid_from: height,
+                        valid_until: proposal.valid_until,
+                        revoked_at: 0,
+                        rotated_to: None,
+                    },
+                    batch,
+                );
+            }
+            VerifierAction::Revoke => {
+                let mut verifier =
+                    verifier.ok_or_else(|| anyhow!("revoked verifier is not registered"))?;
+                if verifier.revoked_at != 0 {
+                    return Err(anyhow!("verifier was already revoked"));
+                }
+                verifier.revoked_at = height;
+                write_verifier(&verifier, batch);
+            }
+        }
+
+        // verifier updates are not subsidised
+        let tx_fee = tx_body.fee;
+        if tx_fee > user.spendable_balance() {
+            return Err(anyhow!("tx fee is greater than user balance"));
+        }
+        user.withdraw(tx_fee)?;
+        user.nonce += 1;
+
+        batch.write(WriteItem {
+            data: DataItem {
+                key: Bytes::from(account_id.data.clone()),
+                value: Bytes::from(user.encode_to_vec()),
+            },
+            cf: USERS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        // index the transaction in the db by hash
+        batch.write(WriteItem {
+            data: DataItem {
+                key: tx_hash.clone(),
+                value: Bytes::from(signed_transaction.encode_to_vec()),
+            },
+            cf: TRANSACTIONS_COL_FAMILY,
+            ttl: 0,
+        });
+
+        // index the transaction in the db by signer account id
+        self.index_transaction_by_account_id(
+            signed_transaction,
+            Bytes::from(account_id.data.to_vec()),
+            event.height,
+            batch,
+        )
+        .await?;
+
+        event.fee = tx_fee;
+        event.fee_type = FeeType::User as i32;
+        event.result = ExecutionResult::Executed as i32;
+
+        info!(
+            "verifier update executed. action: {}, verifier: {}, height: {}",
+            proposal.action,
+            short_hex_string(&verifier_id.data),
+            height
+        );
+        Ok(())
+    }
+}
@@ -36,8 +36,9 @@ pub const TRANSACTIONS_EVENTS_COL_FAMILY: &str = "txs_events_cf";
 /// key: block height, value: zero or more events emitted by txs in the block
 pub const BLOCK_EVENTS_COL_FAMILY: &str = "bc_events_cf";
 
-/// col family for verifiers on-chain data. index: accountId, data: Verifier dial-up info
-/// this data is in consensus on genesis and can only change via a runtime update
+/// The on-chain verifiers registry. index: accountId, data: RegisteredVerifier
+/// Genesis verifiers without a record are valid from genesis. Records are only changed by
+/// admin approved verifier update transactions
 pub const VERIFIERS_COL_FAMILY: &str = "verifiers_cf";
 
 /// A mapping of account ids to users. key: accountId, data: User
@@ -45,6 +46,11 @@ pub const VERIFIERS_COL_FAMILY: &str = "verifiers_cf";
 /// All users accounts in consensus on-chain.
 pub const USERS_COL_FAMILY: &str = "users_cf";
 
+/// Sparse merkle tree of all users accounts. The tree root is the blocks state root.
+/// This is on-chain data derived from on-chain users accounts data.
+/// Keys: big-endian node depth and the node's path prefix. Data: serialized tree node
+pub const STATE_TREE_COL_FAMILY: &str = "state_tree_cf";
+
 /// Index: accountId. Data: LeaderBoardEntry
 pub const LEADER_BOARD_COL_FAMILY: &str = "leader_board_cf";
 
@@ -53,19 +59,51 @@ pub const LEADER_BOARD_COL_FAMILY: &str = "leader_board_cf";
 /// key: nickname (utf8 encoded string). value: accountId.
 pub const USERS_NAMES_COL_FAMILY: &str = "user_names_cf";
 
-/// A mapping from mobile phone numbers to registered users.
+/// User names of deleted accounts which may not be registered again before a cooldown.
+/// This is on-chain data derived from delete user transactions.
+/// key: user name (utf8 encoded string). value: big-endian time in millis from which the name is
+/// released
+pub const DELETED_USERS_NAMES_COL_FAMILY: &str = "deleted_user_names_cf";
+
+/// A mapping from mobile phone numbers hashes to registered users.
 /// This is on-chain data derived from on-chain users accounts data.
-/// key: mobile number (utf-8 encoded). value: accountId
+/// key: sha256 hash of the mobile number (utf-8 encoded). value: accountId
 pub const MOBILE_NUMBERS_COL_FAMILY: &str = "mobile_number_cf";
 
 /// Signed transactions indexed by their hash. Data: SignTransaction
 /// This is on-chain data
 pub const TRANSACTIONS_COL_FAMILY: &str = "txs_cf";
 
-/// Signed transactions indexed by account ids. Data: TransactionHash
+/// Signed transactions indexed by account ids. Keys: account id, big-endian block height and
+/// tx hash so an account's txs are ordered by height. Data: empty
 pub const TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY: &str = "txs_hashes_by_account_idx_cf";
 
-// todo: add column family for transactions indexes - by signer and by payee for payment txs
+/// Signed transactions indexed by their signer account id. Keys and data as in
+/// TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY
+pub const TRANSACTIONS_HASHES_BY_SIGNER_IDX_COL_FAMILY: &str = "txs_hashes_by_signer_idx_cf";
+
+/// Signed transactions indexed by the account id of accounts which received them, but did not
+/// sign them. e.g. a payment's payee. Keys and data as in
+/// TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY
+pub const TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY: &str = "txs_hashes_by_payee_idx_cf";
+
+/// Escrowed payments to mobile numbers without an account.
+/// key: sha256 hash of the payee mobile number. value: PendingPayments
+/// This is on-chain data
+pub const PENDING_PAYMENTS_COL_FAMILY: &str = "pending_payments_cf";
+
+/// Escrowed payments ordered by expiry time. Keys: big-endian expiry time in millis, payee
+/// number hash and tx hash. Data: empty
+pub const PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY: &str = "pending_payments_expiry_idx_cf";
+
+/// Escrowed payments by payer. Keys: payer account id followed by the payment's expiry index
+/// key. Data: empty
+pub const PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY: &str = "pending_payments_by_payer_idx_cf";
+
+/// Executed genesis params updates ordered by target height. Keys: big-endian target height,
+/// big-endian execution block height and tx hash. Data: ParamsUpdate
+/// This is on-chain data
+pub const PARAMS_UPDATES_COL_FAMILY: &str = "params_updates_cf";
 
 /// Blocks keyed by block number - the blockchain. index: block height. value: Block
 /// This is the actual blockchain
@@ -74,14 +112,20 @@ pub const BLOCKS_COL_FAMILY: &str = "blocks_cf";
 /// stores data about sent sms invites
 pub const INVITE_SMS_COL_FAMILY: &str = "welcome_sms_col_family";
 
-/// Valid transactions submitted to the chain, not yet processed and queued in the txs pool
-/// This is off-chain tx pool data
-/// key: tx MEM_POOL. value: MemPool
-///
+/// Legacy key of the whole txs pool persisted as a single MemPool value.
+/// Migrated to per-tx keys when the pool is loaded.
 pub const TXS_POOL_KEY: &str = "txs_pool_key";
 
+/// Valid transactions submitted to the chain, not yet processed and queued in the txs pool
+/// This is off-chain tx pool data
+/// key: tx hash. value: SignedTransaction
 pub const TXS_POOL_COL_FAMILY: &str = "txs_pool_cf";
 
+/// Submitted transactions which were rejected or dropped from the txs pool
+/// This is off-chain data which expires after the max pool tx age
+/// key: tx hash. value: RejectedTransaction
+pub const REJECTED_TXS_COL_FAMILY: &str = "rejected_txs_cf";
+
 /// Used for db testing - doesn't hold any app data
 pub const TESTS_COL_FAMILY: &str = "tests_cf"; // col family for db tests
 
@@ -98,46 +142,73 @@ impl Default for BlockchainConfigService {
 #[async_trait::async_trait]
 impl Actor for BlockchainConfigService {
     async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
-        info!("configuring blockchain db...");
-
-        let db_name = ServerConfigService::get(DB_NAME_CONFIG_KEY.into())
-            .await?
-            .unwrap();
-
-        let drop_on_exit = ServerConfigService::get_bool(DROP_DB_CONFIG_KEY.into())
-            .await?
-            .unwrap();
-
-        info!("drop on exit: {}", drop_on_exit);
-
-        // configure the db
-        DatabaseService::config_db(db::db_service::Configure {
-            drop_on_exit,
-            db_name,
-            col_descriptors: vec![
-                // verifier data
-                ColumnFamilyDescriptor::new(VERIFIERS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(INVITE_SMS_COL_FAMILY, Options::default()),
-                // blockchain data
-                ColumnFamilyDescriptor::new(USERS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(LEADER_BOARD_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(USERS_NAMES_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(MOBILE_NUMBERS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(TESTS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(BLOCKS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(BLOCK_EVENTS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(BLOCKCHAIN_DATA_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(TXS_POOL_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(TRANSACTIONS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(TRANSACTIONS_EVENTS_COL_FAMILY, Options::default()),
-                ColumnFamilyDescriptor::new(
-                    TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
-                    Options::default(),
-                ),
-            ],
-        })
-        .await
+        configure_server_db().await
     }
 }
 
 impl Service for BlockchainConfigService {}
+
+/// Open the server's blockchain db as set in the server config
+pub(crate) async fn configure_server_db() -> Result<()> {
+    info!("configuring blockchain db...");
+
+    let db_name = ServerConfigService::get(DB_NAME_CONFIG_KEY.into())
+        .await?
+        .unwrap();
+
+    let drop_on_exit = ServerConfigService::get_bool(DROP_DB_CONFIG_KEY.into())
+        .await?
+        .unwrap();
+
+    info!("drop on exit: {}", drop_on_exit);
+
+    configure_blockchain_db(db_name, drop_on_exit).await
+}
+
+/// Open the blockchain db with the provided name and all blockchain column families
+pub(crate) async fn configure_blockchain_db(db_name: String, drop_on_exit: bool) -> Result<()> {
+    DatabaseService::config_db(db::db_service::Configure {
+        drop_on_exit,
+        db_name,
+        col_descriptors: vec![
+            // verifier data
+            ColumnFamilyDescriptor::new(VERIFIERS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(INVITE_SMS_COL_FAMILY, Options::default()),
+            // blockchain data
+            ColumnFamilyDescriptor::new(USERS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(STATE_TREE_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(LEADER_BOARD_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(USERS_NAMES_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(DELETED_USERS_NAMES_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(MOBILE_NUMBERS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(TESTS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(BLOCKS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(BLOCK_EVENTS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(BLOCKCHAIN_DATA_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(TXS_POOL_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(REJECTED_TXS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(TRANSACTIONS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(TRANSACTIONS_EVENTS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(
+                TRANSACTIONS_HASHES_BY_ACCOUNT_IDX_COL_FAMILY,
+                Options::default(),
+            ),
+            ColumnFamilyDescriptor::new(
+                TRANSACTIONS_HASHES_BY_SIGNER_IDX_COL_FAMILY,
+                Options::default(),
+            ),
+            ColumnFamilyDescriptor::new(
+                TRANSACTIONS_HASHES_BY_PAYEE_IDX_COL_FAMILY,
+                Options::default(),
+            ),
+            ColumnFamilyDescriptor::new(PENDING_PAYMENTS_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(PENDING_PAYMENTS_EXPIRY_IDX_COL_FAMILY, Options::default()),
+            ColumnFamilyDescriptor::new(
+                PENDING_PAYMENTS_BY_PAYER_IDX_COL_FAMILY,
+                Options::default(),
+            ),
+            ColumnFamilyDescriptor::new(PARAMS_UPDATES_COL_FAMILY, Options::default()),
+        ],
+    })
+    .await
+}
@@ -6,7 +6,7 @@ use crate::services::api::api_service::ApiService;
 use crate::services::db_config_service::BlockchainConfigService;
 use crate::services::verifier::verifier_service::VerifierService;
 use anyhow::Result;
-use base::genesis_config_service::{GenesisConfigService, GetGenesisData};
+use base::genesis_config_service::{GenesisConfigService, SetConfigFile as GenesisSetConfigFile};
 use base::karma_coin::karma_coin_api::api_service_server::ApiServiceServer;
 use base::karma_coin::karma_coin_verifier::verifier_service_server::VerifierServiceServer;
 use base::server_config_service::{
@@ -14,6 +14,10 @@ use base::server_config_service::{
     SERVER_NAME_CONFIG_KEY,
 };
 use base::server_config_service::{SetConfigFile, START_VERIFIER_SERVICE_CONFIG_KEY};
+use base::server_config_service::{
+    CHECK_LEDGER_ON_STARTUP_CONFIG_KEY, DB_NAME_CONFIG_KEY, DEFAULT_CONFIG_FILE,
+    GENESIS_CONFIG_FILE_CONFIG_KEY, REPAIR_LEDGER_INDEXES_ON_STARTUP_CONFIG_KEY,
+};
 use db::db_service::{DatabaseService, Destroy};
 use tonic::transport::*;
 
@@ -22,7 +26,9 @@ use crate::services::blockchain::karma_rewards_service::{
 };
 
 use crate::services::blockchain::backup_chain_service::BackupChainService;
-use base::karma_coin::karma_coin_api::GetGenesisDataRequest;
+use crate::services::blockchain::blockchain_service::BlockChainService;
+use crate::services::blockchain::ledger_checker::{CheckLedger, LedgerCheckReport};
+use crate::services::blockchain::ledger_replay::{LedgerReplayReport, ReplayLedger};
 use tonic_web::GrpcWebLayer;
 use tower_http::cors::CorsLayer;
 use xactor::*;
@@ -36,8 +42,8 @@ pub struct ServerService {}
 impl Actor for ServerService {
     async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
         // start the config services to config db, blockchain and the server
-        let genesis_config_service = GenesisConfigService::from_registry().await?;
-        let server_config_service = ServerConfigService::from_registry().await?;
+        GenesisConfigService::from_registry().await?;
+        ServerConfigService::from_registry().await?;
 
         // generate some keys
         /*
@@ -61,24 +67,42 @@ impl Actor for ServerService {
             hex_string(key2.public_key.unwrap().key.as_ref())
         );*/
 
-        server_config_service
-            .call(SetConfigFile {
-                config_file: "./config.yaml".to_string(),
-            })
-            .await??;
+        ServerService::load_config_files(DEFAULT_CONFIG_FILE).await?;
 
-        let genesis_data = genesis_config_service
-            .call(GetGenesisData {
-                request: GetGenesisDataRequest {},
-            })
-            .await??
-            .genesis_data
-            .unwrap();
+        // validate the typed genesis config on startup so an invalid genesis file fails here
+        // and not in a later runtime path
+        let genesis_data = GenesisConfigService::get_genesis_data()
+            .await
+            .map_err(|e| {
+                error!("invalid genesis config: {}", e);
+                e
+            })?;
 
         info!("genesis data: {}", genesis_data);
 
         BlockchainConfigService::from_registry().await?;
 
+        if ServerConfigService::get_bool(CHECK_LEDGER_ON_STARTUP_CONFIG_KEY.into())
+            .await?
+            .unwrap()
+        {
+            let repair =
+                ServerConfigService::get_bool(REPAIR_LEDGER_INDEXES_ON_STARTUP_CONFIG_KEY.into())
+                    .await?
+                    .unwrap();
+
+            let report = BlockChainService::from_registry()
+                .await?
+                .call(CheckLedger { repair })
+                .await??;
+
+            if report.is_consistent() {
+                info!("ledger is consistent: {}", report);
+            } else {
+                warn!("ledger is inconsistent: {}", report);
+            }
+        }
+
         // if we start a verifier then load private secrets from an external verifier config file
         if ServerConfigService::get_bool(START_VERIFIER_SERVICE_CONFIG_KEY.into())
             .await?
@@ -162,6 +186,72 @@ impl Handler<Startup> for ServerService {
 }
 
 impl ServerService {
+    /// Merge the server config file and then the genesis config file it points to
+    async fn load_config_files(config_file: &str) -> Result<()> {
+        ServerConfigService::from_registry()
+            .await?
+            .call(SetConfigFile {
+                config_file: config_file.to_string(),
+            })
+            .await??;
+
+        let genesis_config_file = ServerConfigService::get(GENESIS_CONFIG_FILE_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        GenesisConfigService::from_registry()
+            .await?
+            .call(GenesisSetConfigFile {
+                config_file: genesis_config_file,
+            })
+            .await?
+    }
+
+    /// Check the ledger consistency of the local db without starting the server.
+    /// Derived indexes are repaired when repair is set. No blocks are produced while checking.
+    pub async fn check_ledger(config_file: &str, repair: bool) -> Result<LedgerCheckReport> {
+        ServerService::load_config_files(config_file).await?;
+
+        BlockchainConfigService::from_registry().await?;
+
+        BlockChainService::offline()
+            .start()
+            .await?
+            .call(CheckLedger { repair })
+            .await?
+    }
+
+    /// Rebuild the ledger state from the local db blocks in a temporary db and compare it
+    /// with the local db state, without starting the server. The local db is not modified:
+    /// the replay runs on an offline blockchain service which doesn't migrate the db or
+    /// produce blocks. The temporary db is the configured db until the replay completes
+    /// so it should not run alongside a block producing server.
+    pub async fn replay_ledger(config_file: &str) -> Result<LedgerReplayReport> {
+        ServerService::load_config_files(config_file).await?;
+
+        BlockchainConfigService::from_registry().await?;
+
+        let db_name = ServerConfigService::get(DB_NAME_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        BlockChainService::offline()
+            .start()
+            .await?
+            .call(ReplayLedger {
+                replay_db_name: format!("{}_replay", db_name),
+            })
+            .await?
+    }
+
+    /// Start a karma rewards round on a running server. The rewards are paid in its next block
+    pub async fn process_karma_rewards() -> Result<()> {
+        KarmaRewardsService::from_registry()
+            .await?
+            .call(ProcessKarmaRewards)
+            .await?
+    }
+
     /// Starts the server's grpc services
     async fn start_grpc_server(&self, port: u32, host: String, peer_name: String) -> Result<()> {
         // setup grpc server and services
@@ -167,10 +167,11 @@ impl VerifierService {
         let payment_tx = tx_body.get_payment_transaction_v1().unwrap();
         let invite_tx_hash = signed_tx.get_hash()?;
 
-        let invite_mobile_number = payment_tx
-            .to_number
-            .as_ref()
-            .ok_or_else(|| anyhow!("missing tx receiver's mobile number"))?;
+        // payments only reveal the payee's mobile number hash so the number to invite is unknown
+        let Some(invite_mobile_number) = payment_tx.to_number.as_ref() else {
+            info!("skipping invite to a mobile number hash");
+            return Ok(());
+        };
 
         let invite_number = invite_mobile_number.number.clone();
 
@@ -197,12 +198,8 @@ impl VerifierService {
             ),
         };
 
-        let inviter_phone_number = inviter
-            .mobile_number
-            .as_ref()
-            .ok_or_else(|| anyhow!("inviter mobile number not found on chain"))?
-            .number
-            .clone();
+        // inviters mobile numbers are not stored on chain
+        let inviter_name = inviter.user_name.clone();
 
         let now = Utc::now().timestamp_millis() as u64;
 
@@ -234,17 +231,15 @@ impl VerifierService {
                     })?;
 
                 format!(
-                    "{} ({}) says that you are {} {} and sent you Karma Coins. Get them on the Karma Coin App available at https://karmaco.in",
-                    inviter.user_name.clone(),
-                    inviter_phone_number,
+                    "{} says that you are {} {} and sent you Karma Coins. Get them on the Karma Coin App available at https://karmaco.in",
+                    inviter_name,
                     t.name,
                     t.emoji
                 )
             } else {
                 format!(
-                    "{} ({}) just sent you Karma Coins! Get them on the Karma Coin App available at https://karmaco.in",
-                    inviter.user_name.clone(),
-                    inviter_phone_number
+                    "{} just sent you Karma Coins! Get them on the Karma Coin App available at https://karmaco.in",
+                    inviter_name
                 )
             }
         };
@@ -5,7 +5,7 @@
 use crate::services::verifier::send_verification_code::SendVerificationCode;
 use crate::services::verifier::sms_invites_sender::SendInvites;
 use crate::services::verifier::verify_number::Verify;
-use crate::services::verifier::verify_number_ex::VerifyEx;
+use crate::services::verifier::verify_number_ex::{VerifyEx, VerifyExV2};
 use anyhow::{anyhow, Result};
 use base::hex_utils::short_hex_string;
 use base::karma_coin::karma_coin_auth::auth_service_client::AuthServiceClient;
@@ -13,7 +13,7 @@ use base::karma_coin::karma_coin_core_types::{AccountId, KeyPair};
 use base::karma_coin::karma_coin_verifier::verifier_service_server::VerifierService as VerifierServiceTrait;
 use base::karma_coin::karma_coin_verifier::{
     SendVerificationCodeRequest, SendVerificationCodeResponse, VerifyNumberRequest,
-    VerifyNumberRequestEx, VerifyNumberResponse,
+    VerifyNumberRequestEx, VerifyNumberResponse, VerifyNumberResponseEx,
 };
 use base::server_config_service::{
     GetVerifierIdKeyPair, ServerConfigService, AUTH_SERVICE_HOST_KEY, AUTH_SERVICE_PORT_KEY,
@@ -217,4 +217,28 @@ impl VerifierServiceTrait for VerifierService {
             Err(e) => Err(Status::internal(format!("internal error: {:?}", e))),
         }
     }
+
+    /// User requests to verify a number and get signed evidence on the number hash
+    async fn verify_number_ex_v2(
+        &self,
+        request: Request<VerifyNumberRequestEx>,
+    ) -> Result<Response<VerifyNumberResponseEx>, Status> {
+        let service = VerifierService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
+
+        match service
+            .call(VerifyExV2(request.into_inner()))
+            .await
+            .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
+        {
+            Ok(evidence) => {
+                info!("verification successful");
+                Ok(Response::new(VerifyNumberResponseEx {
+                    evidence: Some(evidence),
+                }))
+            }
+            Err(e) => Err(Status::internal(format!("internal error: {:?}", e))),
+        }
+    }
 }
@@ -5,8 +5,12 @@
 use crate::services::db_config_service::{USERS_COL_FAMILY, USERS_NAMES_COL_FAMILY};
 use crate::services::verifier::verifier_service::VerifierService;
 use anyhow::Result;
-use base::karma_coin::karma_coin_core_types::{User, UserVerificationData, VerificationResult};
+use base::karma_coin::karma_coin_core_types::{
+    AccountId, MobileNumber, SignedUserVerificationDataEx, User, UserVerificationData,
+    UserVerificationDataEx, VerificationResult,
+};
 use base::karma_coin::karma_coin_verifier::{VerifyNumberRequestDataEx, VerifyNumberRequestEx};
+use base::mobile_number::mobile_number_hash;
 use base::server_config_service::{ServerConfigService, AUTH_SERVICE_BYPASS_KEY};
 use base::signed_trait::SignedTrait;
 use bytes::Bytes;
@@ -22,6 +26,9 @@ use xactor::*;
 #[message(result = "Result<UserVerificationData>")]
 pub(crate) struct VerifyEx(pub VerifyNumberRequestEx);
 
+#[message(result = "Result<SignedUserVerificationDataEx>")]
+pub(crate) struct VerifyExV2(pub VerifyNumberRequestEx);
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct OTPVerifyResponse {
     pub status: String,
@@ -35,22 +42,81 @@ impl Handler<VerifyEx> for VerifierService {
         _ctx: &mut Context<Self>,
         msg: VerifyEx,
     ) -> Result<UserVerificationData> {
-        let req = msg.0;
-
         info!("verify phone number ex called");
 
+        let (account_id, requested_user_name, phone_number) =
+            match self.verify_request_ex(msg.0).await? {
+                Ok(verified) => verified,
+                Err(result) => return self.gen_result(result).await,
+            };
+
+        // create signed verified response and return it
+        let key_pair = self.get_key_pair().await?.to_ed2559_keypair();
+
+        let mut resp = UserVerificationData::from(VerificationResult::Verified);
+
+        // signed attestation details - user account id, nickname and verified mobile number
+        resp.account_id = Some(account_id);
+        resp.verifier_account_id = Some(self.get_account_id().await?);
+        resp.requested_user_name = requested_user_name;
+        resp.mobile_number = Some(phone_number);
+        resp.signature = Some(resp.sign(&key_pair)?);
+        info!("Returning verification response");
+        Ok(resp)
+    }
+}
+
+/// Request to complete verification and sign up with evidence on the mobile number hash
+#[async_trait::async_trait]
+impl Handler<VerifyExV2> for VerifierService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: VerifyExV2,
+    ) -> Result<SignedUserVerificationDataEx> {
+        info!("verify phone number ex v2 called");
+
+        let (account_id, requested_user_name, phone_number) =
+            match self.verify_request_ex(msg.0).await? {
+                Ok(verified) => verified,
+                Err(result) => return self.gen_result_ex(result).await,
+            };
+
+        // create signed verified response and return it
+        let key_pair = self.get_key_pair().await?.to_ed2559_keypair();
+
+        let mut resp = UserVerificationDataEx::from(VerificationResult::Verified);
+
+        // signed attestation details - user account id, nickname and verified mobile number hash
+        resp.account_id = Some(account_id);
+        resp.verifier_account_id = Some(self.get_account_id().await?);
+        resp.requested_user_name = requested_user_name;
+        resp.mobile_number_hash = hex::encode(mobile_number_hash(&phone_number.number)?);
+        info!("Returning verification response");
+        SignedUserVerificationDataEx::new(&resp, &key_pair)
+    }
+}
+
+impl VerifierService {
+    /// Verifies an extended request and its Twilio code.
+    /// Returns the account id, requested user name and mobile number of a verified request,
+    /// or the verification result to return to the caller otherwise
+    async fn verify_request_ex(
+        &self,
+        req: VerifyNumberRequestEx,
+    ) -> Result<std::result::Result<(AccountId, String, MobileNumber), VerificationResult>> {
         // decode request data
         let user_data = match VerifyNumberRequestDataEx::decode(req.data.as_ref()) {
             Ok(user_data) => user_data,
             Err(_) => {
-                return self.gen_result(VerificationResult::MissingData).await;
+                return Ok(Err(VerificationResult::MissingData));
             }
         };
 
         let account_id = match user_data.account_id {
             Some(id) => id.clone(),
             None => {
-                return self.gen_result(VerificationResult::MissingData).await;
+                return Ok(Err(VerificationResult::MissingData));
             }
         };
 
@@ -61,7 +127,7 @@ impl Handler<VerifyEx> for VerifierService {
 
         // verify request data signature
         if pub_key.verify(req.data.as_ref(), signature).is_err() {
-            return self.gen_result(VerificationResult::InvalidSignature).await;
+            return Ok(Err(VerificationResult::InvalidSignature));
         };
 
         // verify provided Twilio code
@@ -70,13 +136,13 @@ impl Handler<VerifyEx> for VerifierService {
         let requested_user_name = user_data.requested_user_name.clone();
 
         if requested_user_name.is_empty() {
-            return self.gen_result(VerificationResult::MissingData).await;
+            return Ok(Err(VerificationResult::MissingData));
         }
 
         let phone_number = match user_data.mobile_number {
             Some(n) => n,
             None => {
-                return self.gen_result(VerificationResult::MissingData).await;
+                return Ok(Err(VerificationResult::MissingData));
             }
         };
 
@@ -93,7 +159,7 @@ impl Handler<VerifyEx> for VerifierService {
             let user = User::decode(user_data.0.as_ref())?;
             if user.user_name != requested_user_name {
                 // don't allow giving evidence on new requested user name in case of existing user
-                return self.gen_result(VerificationResult::UserNameTaken).await;
+                return Ok(Err(VerificationResult::UserNameTaken));
             }
         } else {
             // no user for account id - check requested name availability
@@ -106,7 +172,7 @@ impl Handler<VerifyEx> for VerifierService {
             .await?)
                 .is_some()
             {
-                return self.gen_result(VerificationResult::UserNameTaken).await;
+                return Ok(Err(VerificationResult::UserNameTaken));
             }
         }
 
@@ -121,7 +187,7 @@ impl Handler<VerifyEx> for VerifierService {
             let user = User::decode(user_data.0.as_ref())?;
             if user.user_name != requested_user_name {
                 // don't allow giving evidence on requested user name in case of existing user
-                return self.gen_result(VerificationResult::UserNameTaken).await;
+                return Ok(Err(VerificationResult::UserNameTaken));
             }
         } else {
             // verify that the requested username not already registered to another user
@@ -133,7 +199,7 @@ impl Handler<VerifyEx> for VerifierService {
             .await?)
                 .is_some()
             {
-                return self.gen_result(VerificationResult::UserNameTaken).await;
+                return Ok(Err(VerificationResult::UserNameTaken));
             }
         }
 
@@ -191,7 +257,7 @@ impl Handler<VerifyEx> for VerifierService {
                 Ok(response) => {
                     if response.status() != StatusCode::OK {
                         info!("twilio response status code != 200");
-                        return self.gen_result(VerificationResult::Unverified).await;
+                        return Ok(Err(VerificationResult::Unverified));
                     }
 
                     let data = response.json::<OTPVerifyResponse>().await;
@@ -201,39 +267,38 @@ impl Handler<VerifyEx> for VerifierService {
                                 // validate sid
                                 if result.sid != user_data.verification_sid {
                                     info!("twilio sid mismatch");
-                                    return self.gen_result(VerificationResult::MissingData).await;
+                                    return Ok(Err(VerificationResult::MissingData));
                                 }
                                 info!("Twilio approved code!");
                             } else {
                                 info!("Twilio result != approved");
-                                return self.gen_result(VerificationResult::Unverified).await;
+                                return Ok(Err(VerificationResult::Unverified));
                             }
                         }
                         Err(e) => {
                             info!("error parsing twilio resp: {}", e);
-                            return self.gen_result(VerificationResult::Unverified).await;
+                            return Ok(Err(VerificationResult::Unverified));
                         }
                     }
                 }
                 Err(e) => {
                     info!("error calling twilio: {}", e);
-                    return self.gen_result(VerificationResult::Unverified).await;
+                    return Ok(Err(VerificationResult::Unverified));
                 }
             }
         }
 
-        // create signed verified response and return it
-        let key_pair = self.get_key_pair().await?.to_ed2559_keypair();
-
-        let mut resp = UserVerificationData::from(VerificationResult::Verified);
+        Ok(Ok((account_id, requested_user_name, phone_number)))
+    }
 
-        // signed attestation details - user account id, nickname and verified mobile number
-        resp.account_id = Some(account_id);
-        resp.verifier_account_id = Some(self.get_account_id().await?);
-        resp.requested_user_name = requested_user_name;
-        resp.mobile_number = Some(phone_number);
-        resp.signature = Some(resp.sign(&key_pair)?);
-        info!("Returning verification response");
-        Ok(resp)
+    /// Returns signed evidence with the provided verification result
+    pub(crate) async fn gen_result_ex(
+        &mut self,
+        value: VerificationResult,
+    ) -> Result<SignedUserVerificationDataEx> {
+        let mut data = UserVerificationDataEx::from(value);
+        let verifier_key_pair = self.get_key_pair().await?.to_ed2559_keypair();
+        data.verifier_account_id = Some(self.get_account_id().await?);
+        SignedUserVerificationDataEx::new(&data, &verifier_key_pair)
     }
 }
@@ -5,16 +5,22 @@
 use anyhow::anyhow;
 use base::genesis_config_service::{GenesisConfigService, NET_ID_KEY};
 use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
-use base::karma_coin::karma_coin_api::{SubmitTransactionRequest, SubmitTransactionResult};
-use base::karma_coin::karma_coin_core_types::TransactionType::NewUserV1;
+use base::karma_coin::karma_coin_api::{
+    GetUserInfoByAccountRequest, SubmitTransactionRejectReason, SubmitTransactionRequest,
+    SubmitTransactionResult, WaitForTransactionRequest, WaitForTransactionResponse,
+};
+use base::karma_coin::karma_coin_core_types::TransactionType::{NewUserV2, PaymentV1};
 use base::karma_coin::karma_coin_core_types::{
-    AccountId, KeyPair, MobileNumber, TransactionBody, VerificationResult,
+    AccountId, KeyPair, MobileNumber, PaymentTransactionV1, SignedUserVerificationDataEx,
+    TransactionBody, TransactionEvent, User, UserVerificationDataEx, VerificationResult,
 };
 use base::karma_coin::karma_coin_core_types::{
-    NewUserTransactionV1, SignedTransaction, TransactionData,
+    NewUserTransactionV2, SignedTransaction, TransactionData,
 };
 use base::karma_coin::karma_coin_verifier::verifier_service_client::VerifierServiceClient;
 use base::karma_coin::karma_coin_verifier::VerifyNumberRequest;
+use base::mobile_number::mobile_number_hash;
+use base::server_config_service::{GetVerifierIdKeyPair, ServerConfigService};
 use base::signed_trait::SignedTrait;
 use base::tests_helpers::enable_logger;
 use bytes::Bytes;
@@ -22,6 +28,7 @@ use chrono::Utc;
 use db::db_service::DatabaseService;
 use log::info;
 use prost::Message;
+use tonic::transport::Channel;
 use xactor::*;
 
 // helper function to create a new user
@@ -67,8 +74,22 @@ pub async fn create_user(
 
     info!("verify evidence verified");
 
-    let new_user_tx = NewUserTransactionV1 {
-        verify_number_response: Some(data.clone()),
+    // the verifier's evidence on the number hash, which is the only evidence accepted on chain
+    let verifier = ServerConfigService::from_registry()
+        .await?
+        .call(GetVerifierIdKeyPair)
+        .await??;
+    let evidence = create_evidence(
+        &[&verifier],
+        &user_key_pair,
+        &data.requested_user_name,
+        &mobile_number.number,
+    )
+    .remove(0);
+    let new_user_tx = NewUserTransactionV2 {
+        user_verification_data_ex: evidence.user_verification_data_ex,
+        verifier_signature: evidence.verifier_signature,
+        additional_evidence: vec![],
     };
 
     let mut buf = Vec::with_capacity(new_user_tx.encoded_len());
@@ -85,7 +106,7 @@ pub async fn create_user(
         fee: 1,
         transaction_data: Some(TransactionData {
             transaction_data: buf,
-            transaction_type: NewUserV1 as i32,
+            transaction_type: NewUserV2 as i32,
         }),
         net_id,
     };
@@ -121,9 +142,210 @@ pub async fn create_user(
 
     let tx_hash = signed_tx.get_hash().unwrap();
 
+    // wait for the tx to be processed in a block
+    wait_for_tx(&tx_hash).await;
+
     Ok((user_key_pair, mobile_number, tx_hash))
 }
 
+// helper function to wait until a submitted tx was processed by the block producer.
+// Returns the tx and its events once it is on chain or was rejected
+#[allow(dead_code)]
+pub async fn wait_for_tx(tx_hash: &[u8]) -> WaitForTransactionResponse {
+    let mut api_client = ApiServiceClient::connect("http://[::1]:9080")
+        .await
+        .unwrap();
+
+    let resp = api_client
+        .wait_for_transaction(WaitForTransactionRequest {
+            tx_hash: tx_hash.to_vec(),
+            timeout_ms: 10 * 1000,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    if resp.timed_out {
+        panic!("timed out waiting for tx to be processed");
+    }
+    resp
+}
+
+// helper function - returns the account id of a key pair
+#[allow(dead_code)]
+pub fn account_id(key_pair: &KeyPair) -> AccountId {
+    AccountId {
+        data: key_pair.public_key.as_ref().unwrap().key.clone(),
+    }
+}
+
+// helper function to create a signed transaction
+#[allow(dead_code)]
+pub async fn create_tx(
+    key_pair: &KeyPair,
+    transaction_data: TransactionData,
+    nonce: u64,
+) -> SignedTransaction {
+    let net_id = GenesisConfigService::get_u64(NET_ID_KEY.into())
+        .await
+        .unwrap()
+        .unwrap() as u32;
+
+    let tx_body = TransactionBody {
+        timestamp: Utc::now().timestamp_millis() as u64,
+        nonce,
+        fee: 1,
+        transaction_data: Some(transaction_data),
+        net_id,
+    };
+
+    let mut signed_tx = SignedTransaction {
+        signer: Some(account_id(key_pair)),
+        transaction_body: tx_body.encode_to_vec(),
+        signature: None,
+    };
+
+    signed_tx.signature = Some(signed_tx.sign(&key_pair.to_ed2559_keypair()).unwrap());
+    signed_tx
+}
+
+// helper function to create evidence on a mobile number hash by each of the provided verifiers
+#[allow(dead_code)]
+pub fn create_evidence(
+    verifiers: &[&KeyPair],
+    user_key_pair: &KeyPair,
+    user_name: &str,
+    number: &str,
+) -> Vec<SignedUserVerificationDataEx> {
+    verifiers
+        .iter()
+        .map(|verifier| {
+            let mut evidence = UserVerificationDataEx::from(VerificationResult::Verified);
+            evidence.account_id = Some(account_id(user_key_pair));
+            evidence.verifier_account_id = Some(account_id(verifier));
+            evidence.requested_user_name = user_name.into();
+            evidence.mobile_number_hash = hex::encode(mobile_number_hash(number).unwrap());
+            SignedUserVerificationDataEx::new(&evidence, &verifier.to_ed2559_keypair()).unwrap()
+        })
+        .collect()
+}
+
+// helper function to create a new user tx with the provided evidence
+#[allow(dead_code)]
+pub async fn create_new_user_tx(
+    user_key_pair: &KeyPair,
+    mut evidence: Vec<SignedUserVerificationDataEx>,
+) -> SignedTransaction {
+    let first = evidence.remove(0);
+    let new_user_tx = NewUserTransactionV2 {
+        user_verification_data_ex: first.user_verification_data_ex,
+        verifier_signature: first.verifier_signature,
+        additional_evidence: evidence,
+    };
+
+    create_tx(
+        user_key_pair,
+        TransactionData {
+            transaction_data: new_user_tx.encode_to_vec(),
+            transaction_type: NewUserV2 as i32,
+        },
+        1,
+    )
+    .await
+}
+
+// helper function to submit a tx and return its rejection reason
+#[allow(dead_code)]
+pub async fn submit(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx: &SignedTransaction,
+) -> SubmitTransactionRejectReason {
+    let resp = api_client
+        .submit_transaction(SubmitTransactionRequest {
+            transaction: Some(tx.clone()),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    if resp.submit_transaction_result == SubmitTransactionResult::Submitted as i32 {
+        assert!(resp.reject_message.is_empty());
+    } else {
+        assert!(!resp.reject_message.is_empty());
+    }
+
+    SubmitTransactionRejectReason::from_i32(resp.reject_reason).unwrap()
+}
+
+// helper function to submit a tx, wait for it to be processed and return its events
+#[allow(dead_code)]
+pub async fn submit_and_wait(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx: &SignedTransaction,
+) -> Vec<TransactionEvent> {
+    assert_eq!(
+        submit(api_client, tx).await,
+        SubmitTransactionRejectReason::Unspecified
+    );
+    wait_for_tx(&tx.get_hash().unwrap())
+        .await
+        .tx_events
+        .unwrap()
+        .events
+}
+
+// helper function to get a user by account id
+#[allow(dead_code)]
+pub async fn get_user(
+    api_client: &mut ApiServiceClient<Channel>,
+    key_pair: &KeyPair,
+) -> Option<User> {
+    api_client
+        .get_user_info_by_account(GetUserInfoByAccountRequest {
+            account_id: Some(account_id(key_pair)),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .user
+}
+
+// helper function to create a signed payment transaction
+#[allow(dead_code)]
+pub async fn create_payment_tx(
+    key_pair: &KeyPair,
+    to_number: &MobileNumber,
+    amount: u64,
+    nonce: u64,
+) -> SignedTransaction {
+    let payment_tx = PaymentTransactionV1 {
+        from: Some(account_id(key_pair)),
+        to_number: None,
+        to_account_id: None,
+        amount,
+        char_trait_id: 0,
+        community_id: 0,
+        to_number_hash: to_number.get_hash().unwrap(),
+    };
+
+    create_tx(
+        key_pair,
+        TransactionData {
+            transaction_data: payment_tx.encode_to_vec(),
+            transaction_type: PaymentV1 as i32,
+        },
+        nonce,
+    )
+    .await
+}
+
+// helper function to replace a signed tx body and re-sign it
+#[allow(dead_code)]
+pub fn resign_tx(tx: &mut SignedTransaction, body: &TransactionBody, key_pair: &KeyPair) {
+    tx.transaction_body = body.encode_to_vec();
+    tx.signature = Some(tx.sign(&key_pair.to_ed2559_keypair()).unwrap());
+}
+
 /// Helper
 #[allow(dead_code)]
 pub async fn init_test() {
@@ -0,0 +1,258 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::genesis_config_service::{
+    GenesisConfigService, BLOCK_REWARDS_AMOUNT, DELETED_USER_FUNDS_ACCOUNT_ID_KEY,
+    USER_NAME_COOLDOWN_SECONDS_KEY,
+};
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetBlockchainDataRequest, GetTransactionRequest, GetUserInfoByNumberRequest,
+    GetUserInfoByUserNameRequest,
+};
+use base::karma_coin::karma_coin_core_types::TransactionType::DeleteUserV1;
+use base::karma_coin::karma_coin_core_types::{
+    BlockchainStats, DeleteUserTransactionV1, ExecutionInfo, ExecutionResult, FeeType, KeyPair,
+    MobileNumber, SignedTransaction, TransactionData, TransactionEvent,
+};
+use base::server_config_service::{
+    GetVerifierIdKeyPair, ServerConfigService, DEFAULT_GRPC_SERVER_PORT,
+};
+use common::{
+    account_id, create_evidence, create_new_user_tx, create_payment_tx, create_tx, create_user,
+    finalize_test, get_user, init_test, resign_tx, submit_and_wait,
+};
+use prost::Message;
+use server::server_service::{ServerService, Startup};
+use tonic::transport::Channel;
+use xactor::Service;
+
+// helper function to create a delete user tx
+async fn create_delete_user_tx(key_pair: &KeyPair, nonce: u64) -> SignedTransaction {
+    create_tx(
+        key_pair,
+        TransactionData {
+            transaction_data: DeleteUserTransactionV1 {}.encode_to_vec(),
+            transaction_type: DeleteUserV1 as i32,
+        },
+        nonce,
+    )
+    .await
+}
+
+// helper function to get the events of a payment tx
+async fn get_payment_events(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx_hash: &[u8],
+) -> Vec<TransactionEvent> {
+    api_client
+        .get_transaction(GetTransactionRequest {
+            tx_hash: tx_hash.to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .tx_events
+        .unwrap()
+        .events
+}
+
+// helper function to get the blockchain stats
+async fn get_stats(api_client: &mut ApiServiceClient<Channel>) -> BlockchainStats {
+    api_client
+        .get_blockchain_data(GetBlockchainDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .stats
+        .unwrap()
+}
+
+/// Test that a deleted account is removed from all indexes, that its balance is redirected to
+/// the genesis configured account or burned after paying the tx fee and that its user name is
+/// released after a cooldown. A deleted account's pending payments are cancelled
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_user_test() {
+    init_test().await;
+
+    let treasury_key_pair = KeyPair::new();
+    GenesisConfigService::set(
+        DELETED_USER_FUNDS_ACCOUNT_ID_KEY.into(),
+        hex::encode(&account_id(&treasury_key_pair).data),
+    )
+    .await
+    .unwrap();
+    GenesisConfigService::set_u64(USER_NAME_COOLDOWN_SECONDS_KEY.into(), 2)
+        .await
+        .unwrap();
+    // no block rewards so circulation only changes by the txs
+    GenesisConfigService::set_u64(BLOCK_REWARDS_AMOUNT.into(), 0)
+        .await
+        .unwrap();
+
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let verifier = ServerConfigService::from_registry()
+        .await
+        .unwrap()
+        .call(GetVerifierIdKeyPair)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let evidence = create_evidence(
+        &[&verifier],
+        &treasury_key_pair,
+        "treasury",
+        "+972549805380",
+    );
+    let tx = create_new_user_tx(&treasury_key_pair, evidence).await;
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.result, ExecutionResult::Executed as i32);
+
+    let number = MobileNumber {
+        number: "+972549805381".into(),
+    };
+    let (user_key_pair, _, _) = create_user("avive".into(), number.number.clone())
+        .await
+        .unwrap();
+    let user = get_user(&mut api_client, &user_key_pair).await.unwrap();
+    let treasury = get_user(&mut api_client, &treasury_key_pair).await.unwrap();
+
+    // a tx fee greater than the account balance is not minted
+    let stats = get_stats(&mut api_client).await;
+    let mut tx = create_delete_user_tx(&user_key_pair, 2).await;
+    let mut tx_body = tx.get_body().unwrap();
+    tx_body.fee = user.total_balance().unwrap() * 1000;
+    resign_tx(&mut tx, &tx_body, &user_key_pair);
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.result, ExecutionResult::Invalid as i32);
+    assert!(get_user(&mut api_client, &user_key_pair).await.is_some());
+
+    let updated_stats = get_stats(&mut api_client).await;
+    assert_eq!(updated_stats.circulation, stats.circulation);
+    assert_eq!(updated_stats.fee_subs_amount, stats.fee_subs_amount);
+
+    // the deleted account pays the tx fee and its balance is redirected to the genesis
+    // configured account
+    let tx = create_delete_user_tx(&user_key_pair, 2).await;
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.result, ExecutionResult::Executed as i32);
+    assert_eq!(event.info, ExecutionInfo::AccountDeleted as i32);
+    assert_eq!(event.fee_type, FeeType::User as i32);
+    assert_eq!(event.burned_amount, 0);
+    assert_eq!(
+        event.redirected_amount,
+        user.total_balance().unwrap() - event.fee
+    );
+    assert_eq!(event.redirected_to, Some(account_id(&treasury_key_pair)));
+    assert_eq!(
+        get_stats(&mut api_client).await.circulation,
+        stats.circulation
+    );
+
+    let updated_treasury = get_user(&mut api_client, &treasury_key_pair).await.unwrap();
+    assert_eq!(
+        updated_treasury.free_balance(),
+        treasury.free_balance() + event.redirected_amount
+    );
+
+    // the account is not found by its id, user name or number
+    assert!(get_user(&mut api_client, &user_key_pair).await.is_none());
+    let resp = api_client
+        .get_user_info_by_user_name(GetUserInfoByUserNameRequest {
+            user_name: "avive".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.user.is_none());
+    let resp = api_client
+        .get_user_info_by_number(GetUserInfoByNumberRequest {
+            mobile_number: Some(number.clone()),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.user.is_none());
+
+    // the user name is not available during its cooldown
+    let new_user_key_pair = KeyPair::new();
+    let evidence = create_evidence(&[&verifier], &new_user_key_pair, "avive", &number.number);
+    let tx = create_new_user_tx(&new_user_key_pair, evidence).await;
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.result, ExecutionResult::Invalid as i32);
+    assert_eq!(event.info, ExecutionInfo::NicknameNotAvailable as i32);
+
+    // the user name and number are available after the cooldown
+    sleep(Duration::from_secs(2)).await;
+    let evidence = create_evidence(&[&verifier], &new_user_key_pair, "avive", &number.number);
+    let tx = create_new_user_tx(&new_user_key_pair, evidence).await;
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.result, ExecutionResult::Executed as i32);
+
+    // the funds account escrows a payment to a number without an account
+    let payee_number = "+972549805382";
+    let tx = create_payment_tx(
+        &treasury_key_pair,
+        &MobileNumber {
+            number: payee_number.into(),
+        },
+        10,
+        2,
+    )
+    .await;
+    let payment_hash = tx.get_hash().unwrap().to_vec();
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.info, ExecutionInfo::PaymentEscrowed as i32);
+
+    // the funds account's own balance, including its reserved funds, is burned when it is
+    // deleted and its pending payment is cancelled
+    let treasury = get_user(&mut api_client, &treasury_key_pair).await.unwrap();
+    assert_eq!(treasury.reserved_balance(), 10);
+    let tx = create_delete_user_tx(&treasury_key_pair, 3).await;
+    let event = submit_and_wait(&mut api_client, &tx).await.remove(0);
+    assert_eq!(event.info, ExecutionInfo::AccountDeleted as i32);
+    assert_eq!(
+        event.burned_amount,
+        treasury.total_balance().unwrap() - event.fee
+    );
+    assert_eq!(event.redirected_amount, 0);
+    assert!(event.redirected_to.is_none());
+
+    let payment_events = get_payment_events(&mut api_client, &payment_hash).await;
+    assert_eq!(payment_events.len(), 2);
+    assert_eq!(
+        payment_events[1].info,
+        ExecutionInfo::PaymentCancelled as i32
+    );
+    assert_eq!(payment_events[1].burned_amount, 10);
+
+    // the payee signs up without claiming the cancelled payment
+    let (payee_key_pair, _, _) = create_user("rachel".into(), payee_number.into())
+        .await
+        .unwrap();
+    let payee = get_user(&mut api_client, &payee_key_pair).await.unwrap();
+    assert_eq!(payee.reserved_balance(), 0);
+    assert_eq!(
+        get_payment_events(&mut api_client, &payment_hash)
+            .await
+            .len(),
+        2
+    );
+
+    finalize_test().await;
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::genesis_config_service::{GenesisConfigService, PENDING_PAYMENT_EXPIRY_SECONDS_KEY};
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{GetTransactionRequest, SubmitTransactionRejectReason};
+use base::karma_coin::karma_coin_core_types::ExecutionInfo::{
+    PaymentClaimed, PaymentEscrowed, PaymentRefunded,
+};
+use base::karma_coin::karma_coin_core_types::{FeeType, MobileNumber, TransactionEvent};
+use base::server_config_service::{DEFAULT_CONFIG_FILE, DEFAULT_GRPC_SERVER_PORT};
+use common::{
+    create_payment_tx, create_user, finalize_test, get_user, init_test, submit, submit_and_wait,
+};
+use tonic::transport::Channel;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to get a tx events
+async fn get_events(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx_hash: &[u8],
+) -> Vec<TransactionEvent> {
+    api_client
+        .get_transaction(GetTransactionRequest {
+            tx_hash: tx_hash.to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .tx_events
+        .unwrap()
+        .events
+}
+
+// helper function - returns the fee paid by the user for a tx
+fn user_fee(event: &TransactionEvent) -> u64 {
+    if event.fee_type == FeeType::User as i32 {
+        event.fee
+    } else {
+        0
+    }
+}
+
+/// Test escrowed payments to numbers without an account are claimed on signup
+/// or refunded when they expire
+#[tokio::test(flavor = "multi_thread")]
+async fn escrow_payments_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    // escrowed payments expire after a few seconds
+    GenesisConfigService::set_u64(PENDING_PAYMENT_EXPIRY_SECONDS_KEY.into(), 5)
+        .await
+        .unwrap();
+
+    let user1 = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    let balance_pre = user1.free_balance();
+
+    // a payment to a number without an account is escrowed
+    let user2_number = MobileNumber {
+        number: "+972549805382".into(),
+    };
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let payment1_hash = tx.get_hash().unwrap().to_vec();
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].info, PaymentEscrowed as i32);
+    let fee1 = user_fee(&events[0]);
+
+    let user1 = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    assert_eq!(user1.free_balance(), balance_pre - 10 - fee1);
+    assert_eq!(user1.reserved_balance(), 10);
+
+    // reserved funds can't be spent
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, user1.free_balance() + 5, 3).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        SubmitTransactionRejectReason::InsufficientBalance
+    );
+
+    // the payee claims the payment on signup and the payer gets the referral reward
+    let (user2_key_pair, _, signup_tx_hash) =
+        create_user("rachel".into(), user2_number.number.clone())
+            .await
+            .unwrap();
+
+    let events = get_events(&mut api_client, &payment1_hash).await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].info, PaymentClaimed as i32);
+    let referral_reward = events[1].referral_reward;
+    assert!(referral_reward > 0);
+
+    let user1 = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    assert_eq!(
+        user1.free_balance(),
+        balance_pre - 10 - fee1 + referral_reward
+    );
+    assert_eq!(user1.reserved_balance(), 0);
+
+    let signup_event = get_events(&mut api_client, &signup_tx_hash).await.remove(0);
+    let user2 = get_user(&mut api_client, &user2_key_pair).await.unwrap();
+    assert_eq!(
+        user2.free_balance(),
+        signup_event.signup_reward - user_fee(&signup_event) + 10
+    );
+
+    // an expired escrowed payment is refunded to the payer
+    let balance_pre = user1.free_balance();
+    let user3_number = MobileNumber {
+        number: "+972549805383".into(),
+    };
+    let tx = create_payment_tx(&user1_key_pair, &user3_number, 20, 3).await;
+    let payment2_hash = tx.get_hash().unwrap().to_vec();
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].info, PaymentEscrowed as i32);
+    let fee2 = user_fee(&events[0]);
+
+    // a block is produced for the refund even though there are no txs to execute
+    let mut events = vec![];
+    for _ in 0..100 {
+        events = get_events(&mut api_client, &payment2_hash).await;
+        if events.len() == 2 {
+            break;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].info, PaymentRefunded as i32);
+
+    let user1 = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    assert_eq!(user1.free_balance(), balance_pre - fee2);
+    assert_eq!(user1.reserved_balance(), 0);
+
+    // escrow, claim and refund are re-derived when the ledger is replayed
+    let report = ServerService::replay_ledger(DEFAULT_CONFIG_FILE)
+        .await
+        .unwrap();
+    assert!(report.is_consistent(), "{}", report);
+
+    finalize_test().await;
+}
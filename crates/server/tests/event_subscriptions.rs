@@ -0,0 +1,129 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    SubmitTransactionRequest, SubscribeAccountEventsRequest, SubscribeBlockEventsRequest,
+    SubscribeBlockEventsResponse,
+};
+use base::karma_coin::karma_coin_core_types::AccountId;
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_payment_tx, create_user, finalize_test, init_test, wait_for_tx};
+use prost::Message;
+use tokio::time::{sleep, timeout, Duration};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test block and account events streams over grpc and grpc-web
+#[tokio::test(flavor = "multi_thread")]
+async fn event_subscriptions_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (user2_key_pair, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let mut block_events = api_client
+        .subscribe_block_events(SubscribeBlockEventsRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut payee_events = api_client
+        .subscribe_account_events(SubscribeAccountEventsRequest {
+            account_id: Some(AccountId {
+                data: user2_key_pair.public_key.as_ref().unwrap().key.clone(),
+            }),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // grpc-web request frame of an empty message
+    let mut grpc_web_resp = reqwest::Client::new()
+        .post(format!(
+            "http://[::1]:{}/karma_coin.api.ApiService/SubscribeBlockEvents",
+            DEFAULT_GRPC_SERVER_PORT
+        ))
+        .header("content-type", "application/grpc-web+proto")
+        .header("x-grpc-web", "1")
+        .body(vec![0u8, 0, 0, 0, 0])
+        .send()
+        .await
+        .unwrap();
+    assert!(grpc_web_resp.status().is_success());
+
+    // let the subscriptions register before the block is produced
+    sleep(Duration::from_millis(100)).await;
+
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 100, 2).await;
+    let tx_hash = tx.get_hash().unwrap().to_vec();
+    api_client
+        .submit_transaction(SubmitTransactionRequest {
+            transaction: Some(tx),
+        })
+        .await
+        .unwrap();
+    wait_for_tx(&tx_hash).await;
+
+    let block_event = timeout(Duration::from_secs(5), block_events.message())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .block_event
+        .unwrap();
+    assert_eq!(block_event.payments_count, 1);
+    assert_eq!(block_event.transactions_events[0].transaction_hash, tx_hash);
+
+    let tx_event = timeout(Duration::from_secs(5), payee_events.message())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .transaction_event
+        .unwrap();
+    assert_eq!(tx_event.transaction_hash, tx_hash);
+    assert_eq!(tx_event.height, block_event.height);
+
+    // read the first grpc-web data frame: 1 flags byte, 4 bytes length and the message
+    let mut frame = vec![];
+    while frame.len() < 5
+        || frame.len() < 5 + u32::from_be_bytes(frame[1..5].try_into().unwrap()) as usize
+    {
+        let chunk = timeout(Duration::from_secs(5), grpc_web_resp.chunk())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        frame.extend_from_slice(&chunk);
+    }
+    assert_eq!(frame[0], 0);
+    let resp = SubscribeBlockEventsResponse::decode(&frame[5..]).unwrap();
+    assert_eq!(resp.block_event.unwrap().height, block_event.height);
+
+    finalize_test().await;
+}
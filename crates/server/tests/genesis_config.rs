@@ -5,6 +5,10 @@
 #[path = "common/mod.rs"]
 mod common;
 
+use base::genesis_config_service::{
+    GenesisConfigService, BLOCK_REWARDS_AMOUNT, ONE_KC_IN_KCENTS, SIGNUP_REWARD_AMOUNT_PHASE2_KEY,
+    VALIDATORS_POOL_ACCOUNT_ID_KEY,
+};
 use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
 use log::info;
 /// tests in this file should be run sequentially and not in parallel
@@ -12,8 +16,8 @@ use server::server_service::{ServerService, Startup};
 use xactor::Service;
 
 use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
-use base::karma_coin::karma_coin_api::GetGenesisDataRequest;
-use common::{finalize_test, init_test};
+use base::karma_coin::karma_coin_api::{GetBlocksRequest, GetGenesisDataRequest};
+use common::{create_user, finalize_test, init_test};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn get_genesis_config() {
@@ -40,6 +44,92 @@ async fn get_genesis_config() {
         .into_inner();
 
     info!("genesis data: {:?}", resp);
+    let genesis_data = resp.genesis_data.unwrap();
+    assert_eq!(genesis_data.block_reward_amount, 10 * ONE_KC_IN_KCENTS);
+    assert_eq!(genesis_data.tx_fee_subsidy_max_amount_phase1, 1);
+    assert_eq!(
+        genesis_data.genesis_hash,
+        genesis_data.compute_genesis_hash().unwrap()
+    );
+    for verifier in genesis_data.verifiers.iter() {
+        assert_eq!(verifier.account_id.as_ref().unwrap().data.len(), 32);
+    }
+
+    let genesis_config = GenesisConfigService::get_genesis_config().await.unwrap();
+    assert_eq!(genesis_config.net_id, genesis_data.net_id);
+    assert_eq!(
+        genesis_config.karma_reward_period_minutes,
+        genesis_data.karma_rewards_period_hours
+    );
+
+    // blocks reference the genesis hash
+    create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+    let blocks = api_client
+        .get_blocks(GetBlocksRequest {
+            from_block_height: 1,
+            to_block_height: 1,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .blocks;
+    assert_eq!(blocks[0].genesis_hash, genesis_data.genesis_hash);
+
+    // values of the wrong type are rejected
+    GenesisConfigService::set(BLOCK_REWARDS_AMOUNT.into(), "ten".into())
+        .await
+        .unwrap();
+    let err = GenesisConfigService::get_genesis_data()
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(BLOCK_REWARDS_AMOUNT), "{}", err);
+    GenesisConfigService::set_u64(BLOCK_REWARDS_AMOUNT.into(), 10 * ONE_KC_IN_KCENTS)
+        .await
+        .unwrap();
+
+    // all schema violations are reported together
+    GenesisConfigService::set_u64(
+        SIGNUP_REWARD_AMOUNT_PHASE2_KEY.into(),
+        20 * ONE_KC_IN_KCENTS,
+    )
+    .await
+    .unwrap();
+    GenesisConfigService::set(VALIDATORS_POOL_ACCOUNT_ID_KEY.into(), "not a key".into())
+        .await
+        .unwrap();
+    let err = GenesisConfigService::get_genesis_data()
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("signup reward amounts"), "{}", err);
+    assert!(err.contains("validators pool account id"), "{}", err);
+
+    GenesisConfigService::set_u64(SIGNUP_REWARD_AMOUNT_PHASE2_KEY.into(), ONE_KC_IN_KCENTS)
+        .await
+        .unwrap();
+    GenesisConfigService::set(
+        VALIDATORS_POOL_ACCOUNT_ID_KEY.into(),
+        genesis_config.validators_pool_account_id.clone(),
+    )
+    .await
+    .unwrap();
+
+    // the same genesis values have the same genesis hash
+    let restored = GenesisConfigService::get_genesis_data().await.unwrap();
+    assert_eq!(restored.genesis_hash, genesis_data.genesis_hash);
+
+    // a typo in a genesis key is rejected
+    GenesisConfigService::set_u64("block_reward_amount".into(), 1)
+        .await
+        .unwrap();
+    let err = GenesisConfigService::get_genesis_data()
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("block_reward_amount"), "{}", err);
 
     finalize_test().await;
 }
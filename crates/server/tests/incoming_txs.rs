@@ -0,0 +1,168 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason::Unspecified;
+use base::karma_coin::karma_coin_api::{
+    GetIncomingTransactionsRequest, GetIncomingTransactionsResponse, GetTransactionsRequest,
+    TransactionDirection, TransactionsFilter,
+};
+use base::karma_coin::karma_coin_core_types::TransactionStatus::{OnChain, Submitted};
+use base::karma_coin::karma_coin_core_types::{
+    AccountId, MobileNumber, SignedTransactionWithStatus,
+};
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_payment_tx, create_user, finalize_test, init_test, submit, wait_for_tx};
+use tonic::transport::Channel;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to get the first page of an account's incoming txs
+async fn get_incoming(
+    api_client: &mut ApiServiceClient<Channel>,
+    account_id: &[u8],
+) -> GetIncomingTransactionsResponse {
+    api_client
+        .get_incoming_transactions(GetIncomingTransactionsRequest {
+            account_id: Some(AccountId {
+                data: account_id.to_vec(),
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+// helper function to get the hashes and statuses of txs
+fn hashes(txs: &[SignedTransactionWithStatus]) -> Vec<(Vec<u8>, i32)> {
+    txs.iter()
+        .map(|tx| {
+            (
+                tx.transaction
+                    .as_ref()
+                    .unwrap()
+                    .get_hash()
+                    .unwrap()
+                    .to_vec(),
+                tx.status,
+            )
+        })
+        .collect()
+}
+
+/// Test payee side indexing of payments and pending incoming payments
+#[tokio::test(flavor = "multi_thread")]
+async fn incoming_txs_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+    let user1_account_id = user1_key_pair.public_key.as_ref().unwrap().key.clone();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    // a payment to a number of a user who didn't sign up yet waits in the pool
+    let user2_number = MobileNumber {
+        number: "+972549805382".into(),
+    };
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let payment1_hash = tx.get_hash().unwrap().to_vec();
+    assert_eq!(submit(&mut api_client, &tx).await, Unspecified);
+
+    let (user2_key_pair, _, _) = create_user("rachel".into(), user2_number.number.clone())
+        .await
+        .unwrap();
+    let user2_account_id = user2_key_pair.public_key.as_ref().unwrap().key.clone();
+
+    // the payment is executed once the payee signed up and is indexed by the payee
+    wait_for_tx(&payment1_hash).await;
+    let resp = get_incoming(&mut api_client, &user2_account_id).await;
+    assert_eq!(
+        hashes(&resp.transactions),
+        vec![(payment1_hash.clone(), OnChain as i32)]
+    );
+    assert!(resp.pending_transactions.is_empty());
+    assert!(resp.next_cursor.is_empty());
+
+    // the payer has no incoming txs
+    let resp = get_incoming(&mut api_client, &user1_account_id).await;
+    assert!(resp.transactions.is_empty());
+
+    // a payment with a future nonce is pending
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 4).await;
+    let payment3_hash = tx.get_hash().unwrap().to_vec();
+    assert_eq!(submit(&mut api_client, &tx).await, Unspecified);
+    let resp = get_incoming(&mut api_client, &user2_account_id).await;
+    assert_eq!(
+        hashes(&resp.pending_transactions),
+        vec![(payment3_hash.clone(), Submitted as i32)]
+    );
+    assert_eq!(resp.transactions.len(), 1);
+
+    // pending payments are only included in the first page
+    let resp = api_client
+        .get_incoming_transactions(GetIncomingTransactionsRequest {
+            account_id: Some(AccountId {
+                data: user2_account_id.clone(),
+            }),
+            limit: 1,
+            cursor: user2_account_id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.pending_transactions.is_empty());
+
+    // once the nonce gap is filled both payments are executed
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 3).await;
+    let payment2_hash = tx.get_hash().unwrap().to_vec();
+    assert_eq!(submit(&mut api_client, &tx).await, Unspecified);
+    wait_for_tx(&payment3_hash).await;
+
+    let resp = get_incoming(&mut api_client, &user2_account_id).await;
+    assert!(resp.pending_transactions.is_empty());
+    let incoming = hashes(&resp.transactions);
+    assert_eq!(incoming.len(), 3);
+    assert_eq!(incoming[2], (payment1_hash, OnChain as i32));
+    assert!(incoming.contains(&(payment2_hash, OnChain as i32)));
+    assert!(incoming.contains(&(payment3_hash, OnChain as i32)));
+
+    // received txs of an account are its incoming txs
+    let resp = api_client
+        .get_transactions(GetTransactionsRequest {
+            account_id: Some(AccountId {
+                data: user2_account_id.clone(),
+            }),
+            filter: Some(TransactionsFilter {
+                direction: TransactionDirection::Received as i32,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(hashes(&resp.transactions), incoming);
+
+    finalize_test().await;
+}
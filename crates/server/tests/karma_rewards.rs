@@ -0,0 +1,136 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::genesis_config_service::KARMA_REWARD_TRAIT_ID;
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetBlockchainDataRequest, GetBlocksRequest, GetLeaderBoardRequest,
+};
+use base::server_config_service::{DEFAULT_CONFIG_FILE, DEFAULT_GRPC_SERVER_PORT};
+use common::{
+    account_id, create_payment_tx, create_user, finalize_test, get_user, init_test, submit_and_wait,
+};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test that karma rewards are paid in a block which records the winners, and that the
+/// rewards are re-derived when the ledger is replayed
+#[tokio::test(flavor = "multi_thread")]
+async fn karma_rewards_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (_, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    // payers are added to the leader board
+    for nonce in 2..4 {
+        let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, nonce).await;
+        submit_and_wait(&mut api_client, &tx).await;
+    }
+
+    let leader_board = api_client
+        .get_leader_board(GetLeaderBoardRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .leaderboard_entries;
+    assert_eq!(leader_board.len(), 1);
+    assert_eq!(
+        leader_board[0].account_id,
+        Some(account_id(&user1_key_pair))
+    );
+
+    let user1 = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    let stats = api_client
+        .get_blockchain_data(GetBlockchainDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .stats
+        .unwrap();
+
+    // a block is produced for the round even though there are no txs to execute
+    ServerService::process_karma_rewards().await.unwrap();
+    let mut new_stats = stats.clone();
+    for _ in 0..100 {
+        new_stats = api_client
+            .get_blockchain_data(GetBlockchainDataRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .stats
+            .unwrap();
+        if new_stats.karma_rewards_count != 0 {
+            break;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    assert_eq!(new_stats.karma_rewards_count, 1);
+    assert_eq!(new_stats.tip_height, stats.tip_height + 1);
+
+    let reward_amount = new_stats.karma_rewards_amount - stats.karma_rewards_amount;
+    assert!(reward_amount > 0);
+    assert_eq!(new_stats.circulation, stats.circulation + reward_amount);
+
+    // the winner is recorded in the block
+    let block = api_client
+        .get_blocks(GetBlocksRequest {
+            from_block_height: new_stats.tip_height,
+            to_block_height: new_stats.tip_height,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .blocks
+        .remove(0);
+    let karma_rewards = block.karma_rewards.unwrap();
+    assert_eq!(karma_rewards.reward_amount, reward_amount);
+    assert_eq!(karma_rewards.winners, vec![account_id(&user1_key_pair)]);
+
+    // the winner was paid and can't win again, and the leader board was reset
+    let winner = get_user(&mut api_client, &user1_key_pair).await.unwrap();
+    assert_eq!(winner.free_balance(), user1.free_balance() + reward_amount);
+    assert_eq!(winner.get_trait_score(KARMA_REWARD_TRAIT_ID, 0), 1);
+
+    let leader_board = api_client
+        .get_leader_board(GetLeaderBoardRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .leaderboard_entries;
+    assert!(leader_board.is_empty());
+
+    // the round is re-applied from the block when the ledger is replayed
+    let report = ServerService::replay_ledger(DEFAULT_CONFIG_FILE)
+        .await
+        .unwrap();
+    assert!(report.is_consistent(), "unexpected divergences: {}", report);
+    assert_eq!(report.blocks_replayed, new_stats.tip_height);
+
+    finalize_test().await;
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::server_config_service::DEFAULT_CONFIG_FILE;
+use bytes::Bytes;
+use common::{create_user, finalize_test, init_test};
+use db::db_service::{DatabaseService, DeleteItem};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// users names index column family
+const USERS_NAMES_COL_FAMILY: &str = "user_names_cf";
+
+/// Test ledger consistency check and derived indexes repair
+#[tokio::test(flavor = "multi_thread")]
+async fn ledger_check_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let report = ServerService::check_ledger(DEFAULT_CONFIG_FILE, false)
+        .await
+        .unwrap();
+    assert!(report.is_consistent(), "unexpected divergences: {}", report);
+    assert_eq!(report.tip_height, 2);
+    assert_eq!(report.blocks_checked, 2);
+    // 2 users and the block producer account
+    assert_eq!(report.users_checked, 3);
+
+    // corrupt a derived index
+    DatabaseService::delete(DeleteItem {
+        key: Bytes::from("rachel"),
+        cf: USERS_NAMES_COL_FAMILY,
+    })
+    .await
+    .unwrap();
+
+    let report = ServerService::check_ledger(DEFAULT_CONFIG_FILE, true)
+        .await
+        .unwrap();
+    assert_eq!(report.divergences.len(), 1, "expected missing name index");
+    assert_eq!(report.repaired_count, 1);
+
+    let report = ServerService::check_ledger(DEFAULT_CONFIG_FILE, false)
+        .await
+        .unwrap();
+    assert!(report.is_consistent(), "unexpected divergences: {}", report);
+
+    finalize_test().await;
+}
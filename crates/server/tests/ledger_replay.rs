@@ -0,0 +1,82 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetBlockchainDataRequest, SubmitTransactionRequest, SubmitTransactionResult,
+};
+use base::server_config_service::{DEFAULT_CONFIG_FILE, DEFAULT_GRPC_SERVER_PORT};
+use common::{create_payment_tx, create_user, finalize_test, init_test, wait_for_tx};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test re-deriving the ledger state by replaying blocks
+#[tokio::test(flavor = "multi_thread")]
+async fn ledger_replay_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (_, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let payment_tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let tx_hash = payment_tx.get_hash().unwrap();
+    let resp = api_client
+        .submit_transaction(SubmitTransactionRequest {
+            transaction: Some(payment_tx),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(
+        resp.submit_transaction_result,
+        SubmitTransactionResult::Submitted as i32,
+    );
+
+    wait_for_tx(&tx_hash).await;
+
+    let report = ServerService::replay_ledger(DEFAULT_CONFIG_FILE)
+        .await
+        .unwrap();
+    assert!(report.is_consistent(), "unexpected divergences: {}", report);
+    assert_eq!(report.blocks_replayed, 3);
+    assert_eq!(report.transactions_replayed, 3);
+
+    // the live db should be available after the replay
+    let stats = api_client
+        .get_blockchain_data(GetBlockchainDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .stats
+        .unwrap();
+
+    assert_eq!(stats.tip_height, 3);
+
+    finalize_test().await;
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason;
+use base::karma_coin::karma_coin_core_types::KeyPair;
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_payment_tx, create_user, finalize_test, init_test, resign_tx, submit};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test that invalid transactions are rejected with a reason when they are submitted
+#[tokio::test(flavor = "multi_thread")]
+async fn mem_pool_admission_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (_, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    use SubmitTransactionRejectReason::*;
+
+    // invalid signature
+    let mut tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    tx.signature.as_mut().unwrap().signature[0] ^= 1;
+    assert_eq!(submit(&mut api_client, &tx).await, InvalidSignature);
+
+    // wrong net id
+    let mut tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let mut body = tx.get_body().unwrap();
+    body.net_id += 1;
+    resign_tx(&mut tx, &body, &user1_key_pair);
+    assert_eq!(submit(&mut api_client, &tx).await, InvalidNetId);
+
+    // stale timestamp
+    let mut tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let mut body = tx.get_body().unwrap();
+    body.timestamp -= 1000 * 60 * 60 * 24 * 7;
+    resign_tx(&mut tx, &body, &user1_key_pair);
+    assert_eq!(submit(&mut api_client, &tx).await, InvalidTimestamp);
+
+    // fee below the min fee
+    let mut tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let mut body = tx.get_body().unwrap();
+    body.fee = 0;
+    resign_tx(&mut tx, &body, &user1_key_pair);
+    assert_eq!(submit(&mut api_client, &tx).await, FeeTooLow);
+
+    // payer not on chain
+    let tx = create_payment_tx(&KeyPair::new(), &user2_number, 10, 1).await;
+    assert_eq!(submit(&mut api_client, &tx).await, SignerNotFound);
+
+    // a future nonce tx is admitted once
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 3).await;
+    assert_eq!(submit(&mut api_client, &tx).await, Unspecified);
+    assert_eq!(submit(&mut api_client, &tx).await, Duplicate);
+
+    // another tx with the same nonce by the same signer
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 11, 3).await;
+    assert_eq!(submit(&mut api_client, &tx).await, InvalidNonce);
+
+    finalize_test().await;
+}
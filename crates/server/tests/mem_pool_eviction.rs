@@ -0,0 +1,143 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{GetTransactionRequest, SubmitTransactionRejectReason};
+use base::karma_coin::karma_coin_core_types::{
+    KeyPair, MobileNumber, SignedTransaction, TransactionStatus,
+};
+use base::server_config_service::{
+    ServerConfigService, DEFAULT_GRPC_SERVER_PORT, MEM_POOL_MAX_ITEMS_KEY,
+    MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY,
+};
+use common::{create_payment_tx, create_user, finalize_test, init_test, resign_tx, submit};
+use tonic::transport::Channel;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to create a payment tx with a provided fee
+async fn create_payment_tx_with_fee(
+    key_pair: &KeyPair,
+    to_number: &MobileNumber,
+    nonce: u64,
+    fee: u64,
+) -> SignedTransaction {
+    let mut tx = create_payment_tx(key_pair, to_number, 10, nonce).await;
+    let mut body = tx.get_body().unwrap();
+    body.fee = fee;
+    resign_tx(&mut tx, &body, key_pair);
+    tx
+}
+
+// helper function - returns a tx status
+async fn get_status(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx: &SignedTransaction,
+) -> TransactionStatus {
+    let status = api_client
+        .get_transaction(GetTransactionRequest {
+            tx_hash: tx.get_hash().unwrap().to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .transaction
+        .unwrap()
+        .status;
+    TransactionStatus::from_i32(status).unwrap()
+}
+
+/// Test per account pending txs limit and eviction of low fee txs from a full pool
+#[tokio::test(flavor = "multi_thread")]
+async fn mem_pool_eviction_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, user1_number, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (user2_key_pair, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let (user3_key_pair, _, _) = create_user("ofer".into(), "+972549805383".into())
+        .await
+        .unwrap();
+
+    ServerConfigService::set_u64(MEM_POOL_MAX_TXS_PER_ACCOUNT_KEY.into(), 2)
+        .await
+        .unwrap();
+    ServerConfigService::set_u64(MEM_POOL_MAX_ITEMS_KEY.into(), 3)
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    use SubmitTransactionRejectReason::*;
+    use TransactionStatus::{Rejected, Submitted};
+
+    // future nonce txs stay in the pool until the signer's lower nonce txs are submitted
+    let tx1 = create_payment_tx_with_fee(&user1_key_pair, &user2_number, 5, 1).await;
+    let tx2 = create_payment_tx_with_fee(&user1_key_pair, &user2_number, 6, 1).await;
+    assert_eq!(submit(&mut api_client, &tx1).await, Unspecified);
+    assert_eq!(submit(&mut api_client, &tx2).await, Unspecified);
+
+    // signer pending txs limit
+    let tx = create_payment_tx_with_fee(&user1_key_pair, &user2_number, 7, 1).await;
+    assert_eq!(submit(&mut api_client, &tx).await, AccountTxsLimit);
+
+    let tx3 = create_payment_tx_with_fee(&user2_key_pair, &user1_number, 5, 1).await;
+    assert_eq!(submit(&mut api_client, &tx3).await, Unspecified);
+
+    // pool is full and the tx doesn't pay more than pending txs
+    let tx = create_payment_tx_with_fee(&user3_key_pair, &user1_number, 5, 1).await;
+    assert_eq!(submit(&mut api_client, &tx).await, MemPoolFull);
+
+    // a higher fee tx evicts the most recent last tx of the lowest fee signers
+    let tx4 = create_payment_tx_with_fee(&user3_key_pair, &user1_number, 5, 10).await;
+    assert_eq!(submit(&mut api_client, &tx4).await, Unspecified);
+
+    assert_eq!(get_status(&mut api_client, &tx1).await, Submitted);
+    assert_eq!(get_status(&mut api_client, &tx2).await, Submitted);
+    assert_eq!(get_status(&mut api_client, &tx3).await, Rejected);
+    assert_eq!(get_status(&mut api_client, &tx4).await, Submitted);
+
+    // only the last pending tx of a signer is evicted
+    let tx5 = create_payment_tx_with_fee(&user2_key_pair, &user1_number, 5, 20).await;
+    assert_eq!(submit(&mut api_client, &tx5).await, Unspecified);
+    assert_eq!(get_status(&mut api_client, &tx1).await, Submitted);
+    assert_eq!(get_status(&mut api_client, &tx2).await, Rejected);
+    assert_eq!(get_status(&mut api_client, &tx5).await, Submitted);
+
+    // no tx is evicted when the pool can't make enough room. Two txs must be evicted and
+    // the second candidate doesn't pay less than the new tx
+    ServerConfigService::set_u64(MEM_POOL_MAX_ITEMS_KEY.into(), 2)
+        .await
+        .unwrap();
+    let tx = create_payment_tx_with_fee(&user1_key_pair, &user2_number, 6, 15).await;
+    assert_eq!(submit(&mut api_client, &tx).await, MemPoolFull);
+    assert_eq!(get_status(&mut api_client, &tx4).await, Submitted);
+    assert_eq!(get_status(&mut api_client, &tx5).await, Submitted);
+    assert_eq!(submit(&mut api_client, &tx4).await, Duplicate);
+    assert_eq!(submit(&mut api_client, &tx5).await, Duplicate);
+
+    finalize_test().await;
+}
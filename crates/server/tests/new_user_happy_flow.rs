@@ -5,7 +5,7 @@
 #[path = "common/mod.rs"]
 mod common;
 
-use base::genesis_config_service::SIGNUP_CHAR_TRAIT_ID;
+use base::genesis_config_service::{GenesisConfigService, SIGNUP_CHAR_TRAIT_ID};
 use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
 use base::karma_coin::karma_coin_api::{
     GetBlockchainDataRequest, GetBlockchainEventsRequest, GetBlocksRequest, GetTransactionsRequest,
@@ -13,6 +13,7 @@ use base::karma_coin::karma_coin_api::{
 };
 use base::karma_coin::karma_coin_core_types::TransactionStatus::OnChain;
 use base::karma_coin::karma_coin_core_types::{AccountId, BlockchainStats, MobileNumber};
+use base::mobile_number::mobile_number_hash;
 use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
 use common::{create_user, finalize_test, init_test};
 
@@ -64,17 +65,22 @@ async fn new_user_happy_flow_test() {
     let resp_user = resp.user.as_ref().unwrap();
 
     assert_eq!(resp_user.user_name, user_name);
+    assert!(resp_user.mobile_number.is_none());
     assert_eq!(
-        resp_user.mobile_number.as_ref().unwrap().number,
-        mobile_number
+        resp_user.mobile_number_hash,
+        mobile_number_hash(mobile_number).unwrap()
     );
     assert_eq!(resp_user.nonce, 1);
 
-    let tokenomics = Tokenomics::new(BlockchainStats::new());
-    let signup_reward_amount = tokenomics.get_signup_reward_amount().await.unwrap();
+    let tokenomics = Tokenomics::new(
+        BlockchainStats::new(),
+        GenesisConfigService::get_genesis_data().await.unwrap(),
+    );
+    let signup_reward_amount = tokenomics.get_signup_reward_amount();
 
     assert_eq!(
-        resp_user.balance, signup_reward_amount,
+        resp_user.free_balance(),
+        signup_reward_amount,
         "expected signup rewards balance"
     );
 
@@ -89,9 +95,10 @@ async fn new_user_happy_flow_test() {
 
     let resp_user = resp.user.as_ref().unwrap();
     assert_eq!(resp_user.user_name, user_name);
+    assert!(resp_user.mobile_number.is_none());
     assert_eq!(
-        resp_user.mobile_number.as_ref().unwrap().number,
-        mobile_number
+        resp_user.mobile_number_hash,
+        mobile_number_hash(mobile_number).unwrap()
     );
     assert_eq!(resp_user.nonce, 1);
 
@@ -108,9 +115,10 @@ async fn new_user_happy_flow_test() {
 
     let resp_user = resp.user.as_ref().unwrap();
     assert_eq!(resp_user.user_name, user_name);
+    assert!(resp_user.mobile_number.is_none());
     assert_eq!(
-        resp_user.mobile_number.as_ref().unwrap().number,
-        mobile_number
+        resp_user.mobile_number_hash,
+        mobile_number_hash(mobile_number).unwrap()
     );
     assert_eq!(resp_user.nonce, 1);
     assert_eq!(
@@ -131,6 +139,7 @@ async fn new_user_happy_flow_test() {
             account_id: Some(AccountId {
                 data: account_id.clone(),
             }),
+            ..Default::default()
         })
         .await
         .unwrap()
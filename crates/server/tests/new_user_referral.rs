@@ -6,7 +6,7 @@ extern crate log;
 
 #[path = "common/mod.rs"]
 mod common;
-use common::{create_user, finalize_test, init_test};
+use common::{create_user, finalize_test, init_test, wait_for_tx};
 
 use base::genesis_config_service::{
     GenesisConfigService, AMBASSADOR_CHAR_TRAIT_ID, NET_ID_KEY, SIGNUP_CHAR_TRAIT_ID,
@@ -20,9 +20,10 @@ use base::karma_coin::karma_coin_api::{
 use base::karma_coin::karma_coin_core_types::TransactionStatus::OnChain;
 use base::karma_coin::karma_coin_core_types::TransactionType::PaymentV1;
 use base::karma_coin::karma_coin_core_types::{
-    AccountId, BlockchainStats, MobileNumber, PaymentTransactionV1, SignedTransaction,
-    TransactionBody, TransactionData,
+    AccountId, BlockchainStats, PaymentTransactionV1, SignedTransaction, TransactionBody,
+    TransactionData,
 };
+use base::mobile_number::mobile_number_hash;
 use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
 use chrono::Utc;
 use log::info;
@@ -71,13 +72,12 @@ async fn referral_signup_happy_flow_test() {
     // Appreciation from user 1 to person 2 with phone number (not yet user 2)
     let payment_tx = PaymentTransactionV1 {
         from: Some(user1_account_id.clone()),
-        to_number: Some(MobileNumber {
-            number: user2_phone_number.into(),
-        }),
+        to_number: None,
         to_account_id: None,
         amount: payment_amount,
         char_trait_id,
         community_id: 0,
+        to_number_hash: mobile_number_hash(user2_phone_number).unwrap(),
     };
 
     let user1 = api_client
@@ -90,7 +90,7 @@ async fn referral_signup_happy_flow_test() {
         .user
         .unwrap();
 
-    let user1_balance_pre = user1.balance;
+    let user1_balance_pre = user1.free_balance();
     info!("user balance pre referral: {}", user1_balance_pre);
 
     let mut buf = Vec::with_capacity(payment_tx.encoded_len());
@@ -103,7 +103,7 @@ async fn referral_signup_happy_flow_test() {
 
     let tx_body = TransactionBody {
         timestamp: Utc::now().timestamp_millis() as u64,
-        nonce: 1,
+        nonce: 2,
         fee: 1,
         transaction_data: Some(TransactionData {
             transaction_data: buf,
@@ -143,6 +143,9 @@ async fn referral_signup_happy_flow_test() {
         .await
         .unwrap();
 
+    // the pending payment is executed once its payee signed up
+    wait_for_tx(&signed_tx.get_hash().unwrap()).await;
+
     let user2_account_id = AccountId {
         data: user2_key_pair.public_key.as_ref().unwrap().key.clone(),
     };
@@ -169,12 +172,15 @@ async fn referral_signup_happy_flow_test() {
         .user
         .unwrap();
 
-    let tokenomics = Tokenomics::new(BlockchainStats::new());
-    let referral_reward = tokenomics.get_referral_reward_amount().await.unwrap();
+    let tokenomics = Tokenomics::new(
+        BlockchainStats::new(),
+        GenesisConfigService::get_genesis_data().await.unwrap(),
+    );
+    let referral_reward = tokenomics.get_referral_reward_amount();
 
     assert_eq!(
         user1_balance_pre + referral_reward - payment_amount,
-        user1.balance,
+        user1.free_balance(),
         "unexpected payer balance"
     );
 
@@ -199,6 +205,7 @@ async fn referral_signup_happy_flow_test() {
             account_id: Some(AccountId {
                 data: user1_account_id.data.clone(),
             }),
+            ..Default::default()
         })
         .await
         .unwrap()
@@ -210,8 +217,8 @@ async fn referral_signup_happy_flow_test() {
         "user1 should have 2 transactions"
     );
 
-    // check that the payment transaction is on chain
-    let tx = &resp.transactions[1];
+    // check that the payment transaction, which is the newest one, is on chain
+    let tx = &resp.transactions[0];
     assert_eq!(tx.status, OnChain as i32);
 
     // verify that the payment transaction is on chain indexed by user 2
@@ -220,6 +227,7 @@ async fn referral_signup_happy_flow_test() {
             account_id: Some(AccountId {
                 data: user2_account_id.data.clone(),
             }),
+            ..Default::default()
         })
         .await
         .unwrap()
@@ -231,8 +239,8 @@ async fn referral_signup_happy_flow_test() {
         "user2 should have 2 transactions"
     );
 
-    // check that the payment transaction is on chain
-    let tx = &resp.transactions[1];
+    // check that the payment transaction, which is the newest one, is on chain
+    let tx = &resp.transactions[0];
     assert_eq!(tx.status, OnChain as i32);
 
     finalize_test().await;
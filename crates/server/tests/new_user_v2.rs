@@ -0,0 +1,207 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{GetUserInfoByNumberRequest, SubmitTransactionRejectReason};
+use base::karma_coin::karma_coin_core_types::ExecutionInfo::{PaymentClaimed, PaymentEscrowed};
+use base::karma_coin::karma_coin_core_types::TransactionType::{NewUserV1, NewUserV2, PaymentV1};
+use base::karma_coin::karma_coin_core_types::{
+    ExecutionResult, KeyPair, MobileNumber, NewUserTransactionV1, NewUserTransactionV2,
+    PaymentTransactionV1, SignedTransaction, SignedUserVerificationDataEx, TransactionData,
+    UserVerificationData, UserVerificationDataEx, VerificationResult,
+};
+use base::mobile_number::mobile_number_hash;
+use base::server_config_service::{
+    GetVerifierIdKeyPair, ServerConfigService, DEFAULT_GRPC_SERVER_PORT,
+};
+use base::signed_trait::SignedTrait;
+use common::{
+    account_id, create_payment_tx, create_tx, create_user, finalize_test, get_user, init_test,
+    submit, submit_and_wait, wait_for_tx,
+};
+use prost::Message;
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to create a new user v2 tx with evidence about a number hash
+async fn create_new_user_v2_tx(
+    verifier: &KeyPair,
+    user_key_pair: &KeyPair,
+    user_name: &str,
+    mobile_number_hash: &str,
+) -> SignedTransaction {
+    let mut evidence = UserVerificationDataEx::from(VerificationResult::Verified);
+    evidence.account_id = Some(account_id(user_key_pair));
+    evidence.verifier_account_id = Some(account_id(verifier));
+    evidence.requested_user_name = user_name.into();
+    evidence.mobile_number_hash = mobile_number_hash.into();
+    let evidence =
+        SignedUserVerificationDataEx::new(&evidence, &verifier.to_ed2559_keypair()).unwrap();
+
+    let new_user_tx = NewUserTransactionV2 {
+        user_verification_data_ex: evidence.user_verification_data_ex,
+        verifier_signature: evidence.verifier_signature,
+        additional_evidence: vec![],
+    };
+
+    create_tx(
+        user_key_pair,
+        TransactionData {
+            transaction_data: new_user_tx.encode_to_vec(),
+            transaction_type: NewUserV2 as i32,
+        },
+        1,
+    )
+    .await
+}
+
+/// Test that users who sign up with a mobile number hash have no number on chain, that
+/// payments and pending payments to their number hash are resolved by the hash and that
+/// txs with raw mobile numbers are rejected
+#[tokio::test(flavor = "multi_thread")]
+async fn new_user_v2_test() {
+    init_test().await;
+
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let verifier = ServerConfigService::from_registry()
+        .await
+        .unwrap()
+        .call(GetVerifierIdKeyPair)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    // a payment to a number without an account is escrowed
+    let user2_number = MobileNumber {
+        number: "+972549805382".into(),
+    };
+    let user2_number_hash = mobile_number_hash(&user2_number.number).unwrap();
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let payment_hash = tx.get_hash().unwrap().to_vec();
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].info, PaymentEscrowed as i32);
+
+    // evidence must attest to a valid number hash
+    let user2_key_pair = KeyPair::new();
+    let tx = create_new_user_v2_tx(&verifier, &user2_key_pair, "rachel", "+972549805382").await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        SubmitTransactionRejectReason::InvalidVerificationEvidence
+    );
+
+    // sign up with the number hash and claim the escrowed payment
+    let tx = create_new_user_v2_tx(
+        &verifier,
+        &user2_key_pair,
+        "rachel",
+        &hex::encode(&user2_number_hash),
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+    assert!(events[0].signup_reward > 0);
+
+    let payment_events = wait_for_tx(&payment_hash).await.tx_events.unwrap().events;
+    assert!(payment_events
+        .iter()
+        .any(|e| e.info == PaymentClaimed as i32));
+
+    // the raw number is not on chain
+    let user2 = get_user(&mut api_client, &user2_key_pair).await.unwrap();
+    assert!(user2.mobile_number.is_none());
+    assert_eq!(user2.mobile_number_hash, user2_number_hash);
+    assert_eq!(user2.free_balance(), events[0].signup_reward + 10);
+
+    // the user is found by its number
+    let user = api_client
+        .get_user_info_by_number(GetUserInfoByNumberRequest {
+            mobile_number: Some(user2_number.clone()),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .user
+        .unwrap();
+    assert_eq!(user.account_id, Some(account_id(&user2_key_pair)));
+
+    // a payment to the number is paid to the user
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 5, 3).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+    assert_ne!(events[0].info, PaymentEscrowed as i32);
+
+    let user2 = get_user(&mut api_client, &user2_key_pair).await.unwrap();
+    assert_eq!(user2.free_balance(), user.free_balance() + 5);
+
+    // a payment to the raw number is rejected so the number is never stored on chain
+    let payment_tx = PaymentTransactionV1 {
+        from: Some(account_id(&user1_key_pair)),
+        to_number: Some(user2_number.clone()),
+        to_account_id: None,
+        amount: 7,
+        char_trait_id: 0,
+        community_id: 0,
+        to_number_hash: user2_number_hash.clone(),
+    };
+    let tx = create_tx(
+        &user1_key_pair,
+        TransactionData {
+            transaction_data: payment_tx.encode_to_vec(),
+            transaction_type: PaymentV1 as i32,
+        },
+        4,
+    )
+    .await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        SubmitTransactionRejectReason::InvalidData
+    );
+
+    // new user v1 txs carry the raw number in their evidence and are rejected
+    let user3_key_pair = KeyPair::new();
+    let mut evidence = UserVerificationData::from(VerificationResult::Verified);
+    evidence.account_id = Some(account_id(&user3_key_pair));
+    evidence.verifier_account_id = Some(account_id(&verifier));
+    evidence.requested_user_name = "angel".into();
+    evidence.mobile_number = Some(MobileNumber {
+        number: "+972549805383".into(),
+    });
+    evidence.signature = Some(evidence.sign(&verifier.to_ed2559_keypair()).unwrap());
+    let new_user_tx = NewUserTransactionV1 {
+        verify_number_response: Some(evidence),
+        additional_verify_number_responses: vec![],
+    };
+    let tx = create_tx(
+        &user3_key_pair,
+        TransactionData {
+            transaction_data: new_user_tx.encode_to_vec(),
+            transaction_type: NewUserV1 as i32,
+        },
+        1,
+    )
+    .await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        SubmitTransactionRejectReason::InvalidData
+    );
+
+    finalize_test().await;
+}
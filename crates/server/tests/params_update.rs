@@ -0,0 +1,259 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::admin_approval::{get_approval_message, VERIFIER_UPDATE_APPROVAL_DOMAIN};
+use base::genesis_config_service::{
+    GenesisConfigService, BLOCK_REWARDS_AMOUNT, GOVERNANCE_ADMINS_ACCOUNTS_IDS,
+    GOVERNANCE_THRESHOLD_KEY, ONE_KC_IN_KCENTS, VERIFIERS_ACCOUNTS_IDS,
+};
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetBlockchainDataRequest, GetGenesisDataRequest, GetParamsUpdatesRequest,
+};
+use base::karma_coin::karma_coin_core_types::TransactionType::ParamsUpdateV1;
+use base::karma_coin::karma_coin_core_types::{
+    AdminApproval, ExecutionResult, KeyPair, ParamChange, ParamsUpdateProposal,
+    ParamsUpdateTransactionV1, SignedTransaction, TransactionData,
+};
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_tx, create_user, finalize_test, init_test, submit_and_wait};
+use prost::Message;
+use server::server_service::{ServerService, Startup};
+use std::collections::HashMap;
+use xactor::Service;
+
+// helper function to create a signed params update transaction
+async fn create_params_update_tx(
+    key_pair: &KeyPair,
+    proposal: &ParamsUpdateProposal,
+    approvals: Vec<AdminApproval>,
+    nonce: u64,
+) -> SignedTransaction {
+    let params_update_tx = ParamsUpdateTransactionV1 {
+        proposal: Some(proposal.clone()),
+        approvals,
+    };
+
+    create_tx(
+        key_pair,
+        TransactionData {
+            transaction_data: params_update_tx.encode_to_vec(),
+            transaction_type: ParamsUpdateV1 as i32,
+        },
+        nonce,
+    )
+    .await
+}
+
+/// Test that a params update requires the admins threshold approvals and that it takes
+/// effect from its target height
+#[tokio::test(flavor = "multi_thread")]
+async fn params_update_test() {
+    init_test().await;
+
+    // 2 of 3 governance admins must approve params updates
+    let admins: Vec<KeyPair> = (0..3).map(|_| KeyPair::new()).collect();
+    let admins_ids: HashMap<String, String> = admins
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            (
+                format!("Admin {}", i + 1),
+                hex::encode(&k.public_key.as_ref().unwrap().key),
+            )
+        })
+        .collect();
+    GenesisConfigService::set_map(GOVERNANCE_ADMINS_ACCOUNTS_IDS.into(), admins_ids)
+        .await
+        .unwrap();
+    GenesisConfigService::set_u64(GOVERNANCE_THRESHOLD_KEY.into(), 2)
+        .await
+        .unwrap();
+
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let genesis_data = api_client
+        .get_genesis_data(GetGenesisDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .genesis_data
+        .unwrap();
+    assert_eq!(genesis_data.governance_admins.len(), 3);
+    assert_eq!(genesis_data.governance_threshold, 2);
+    assert_eq!(genesis_data.block_reward_amount, 10 * ONE_KC_IN_KCENTS);
+
+    // any user may submit an approved params update
+    let (key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let tip_height = api_client
+        .get_blockchain_data(GetBlockchainDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .stats
+        .unwrap()
+        .tip_height;
+
+    // the update is executed in the next block and takes effect 2 blocks later
+    let proposal = ParamsUpdateProposal {
+        target_height: tip_height + 3,
+        changes: vec![ParamChange {
+            key: BLOCK_REWARDS_AMOUNT.into(),
+            value: (20 * ONE_KC_IN_KCENTS).to_string(),
+        }],
+        description: "double block rewards".into(),
+    };
+    let approve =
+        |k: &KeyPair| proposal.approve(&genesis_data.genesis_hash, &k.to_ed2559_keypair());
+
+    // an update without enough approvals fails
+    let tx = create_params_update_tx(&key_pair, &proposal, vec![approve(&admins[0])], 2).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+    assert!(events[0].error_message.contains("approvals"));
+
+    // a non admin approval fails
+    let tx = create_params_update_tx(
+        &key_pair,
+        &proposal,
+        vec![approve(&admins[0]), approve(&KeyPair::new())],
+        2,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+
+    // approvals of another proposal type with the same encoding fail
+    let approve_as_verifier_update = |k: &KeyPair| {
+        AdminApproval::new(
+            &get_approval_message(
+                VERIFIER_UPDATE_APPROVAL_DOMAIN,
+                &genesis_data.genesis_hash,
+                &proposal,
+            ),
+            &k.to_ed2559_keypair(),
+        )
+    };
+    let tx = create_params_update_tx(
+        &key_pair,
+        &proposal,
+        vec![
+            approve_as_verifier_update(&admins[0]),
+            approve_as_verifier_update(&admins[1]),
+        ],
+        2,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+
+    let tip_height = api_client
+        .get_blockchain_data(GetBlockchainDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .stats
+        .unwrap()
+        .tip_height;
+    let proposal = ParamsUpdateProposal {
+        target_height: tip_height + 3,
+        ..proposal.clone()
+    };
+    let approve =
+        |k: &KeyPair| proposal.approve(&genesis_data.genesis_hash, &k.to_ed2559_keypair());
+
+    let tx = create_params_update_tx(
+        &key_pair,
+        &proposal,
+        vec![approve(&admins[0]), approve(&admins[2])],
+        2,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    // the update is in the params updates history
+    let params_updates = api_client
+        .get_params_updates(GetParamsUpdatesRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .params_updates;
+    assert_eq!(params_updates.len(), 1);
+    assert_eq!(params_updates[0].tx_hash, tx.get_hash().unwrap().to_vec());
+    assert_eq!(params_updates[0].height, tip_height + 1);
+    assert_eq!(params_updates[0].proposal.as_ref(), Some(&proposal));
+    assert_eq!(params_updates[0].approvers.len(), 2);
+
+    // not in effect before its target height
+    let genesis = GenesisConfigService::get_genesis_data().await.unwrap();
+    assert_eq!(genesis.block_reward_amount, 10 * ONE_KC_IN_KCENTS);
+
+    create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    // in effect from its target height. The chain's genesis hash is not changed
+    let genesis = GenesisConfigService::get_genesis_data().await.unwrap();
+    assert_eq!(genesis.block_reward_amount, 20 * ONE_KC_IN_KCENTS);
+    assert_eq!(genesis.genesis_hash, genesis_data.genesis_hash);
+    let genesis = GenesisConfigService::get_genesis_data_at(tip_height + 2)
+        .await
+        .unwrap();
+    assert_eq!(genesis.block_reward_amount, 10 * ONE_KC_IN_KCENTS);
+
+    // the same approved proposal can't be executed twice
+    let tx = create_params_update_tx(
+        &key_pair,
+        &proposal,
+        vec![approve(&admins[1]), approve(&admins[2])],
+        3,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+
+    // genesis verifiers are only changed by verifier update txs
+    let verifiers_proposal = ParamsUpdateProposal {
+        target_height: proposal.target_height + 10,
+        changes: vec![ParamChange {
+            key: VERIFIERS_ACCOUNTS_IDS.into(),
+            value: format!(
+                "{{\"Verifier\": \"{}\"}}",
+                hex::encode(&KeyPair::new().public_key.unwrap().key)
+            ),
+        }],
+        description: "add a verifier".into(),
+    };
+    let tx = create_params_update_tx(
+        &key_pair,
+        &verifiers_proposal,
+        vec![
+            verifiers_proposal.approve(&genesis_data.genesis_hash, &admins[0].to_ed2559_keypair()),
+            verifiers_proposal.approve(&genesis_data.genesis_hash, &admins[1].to_ed2559_keypair()),
+        ],
+        3,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+    assert!(events[0].error_message.contains("can't be updated"));
+
+    finalize_test().await;
+}
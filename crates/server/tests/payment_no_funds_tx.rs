@@ -9,7 +9,8 @@ use common::{create_user, finalize_test, init_test};
 use base::genesis_config_service::{GenesisConfigService, NET_ID_KEY};
 use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
 use base::karma_coin::karma_coin_api::{
-    GetUserInfoByAccountRequest, SubmitTransactionRequest, SubmitTransactionResult,
+    GetUserInfoByAccountRequest, SubmitTransactionRejectReason, SubmitTransactionRequest,
+    SubmitTransactionResult,
 };
 use base::karma_coin::karma_coin_core_types::TransactionType::PaymentV1;
 use base::karma_coin::karma_coin_core_types::{
@@ -66,7 +67,7 @@ async fn payment_tx_no_funds_test() {
         .user
         .unwrap();
 
-    let user1_balance_pre = user1.balance;
+    let user1_balance_pre = user1.free_balance();
     let payment_amount = user1_balance_pre + 1;
 
     let user2 = api_client
@@ -79,16 +80,17 @@ async fn payment_tx_no_funds_test() {
         .user
         .unwrap();
 
-    let user2_balance_pre = user2.balance;
+    let user2_balance_pre = user2.free_balance();
 
     // payment from user 1 to user 2
     let payment_tx = PaymentTransactionV1 {
         from: Some(user1_account_id.clone()),
-        to_number: Some(user2_number.clone()),
+        to_number: None,
         to_account_id: None,
         amount: payment_amount,
         char_trait_id: 1,
         community_id: 0,
+        to_number_hash: user2_number.get_hash().unwrap(),
     };
 
     let mut buf = Vec::with_capacity(payment_tx.encoded_len());
@@ -101,7 +103,7 @@ async fn payment_tx_no_funds_test() {
 
     let tx_body = TransactionBody {
         timestamp: Utc::now().timestamp_millis() as u64,
-        nonce: 1,
+        nonce: 2,
         fee: 1,
         transaction_data: Some(TransactionData {
             transaction_data: buf,
@@ -131,9 +133,14 @@ async fn payment_tx_no_funds_test() {
         .unwrap()
         .into_inner();
 
+    // the payment should be rejected before it is added to the mem pool
     assert_eq!(
         resp.submit_transaction_result,
-        SubmitTransactionResult::Submitted as i32,
+        SubmitTransactionResult::Rejected as i32,
+    );
+    assert_eq!(
+        resp.reject_reason,
+        SubmitTransactionRejectReason::InsufficientBalance as i32,
     );
 
     // read updated user chain data
@@ -159,8 +166,8 @@ async fn payment_tx_no_funds_test() {
         .unwrap();
 
     // check no balances were changed (assumed tx_fee subsidy)
-    assert_eq!(user1_balance_pre, user1.balance);
-    assert_eq!(user2_balance_pre, user2.balance);
+    assert_eq!(user1_balance_pre, user1.free_balance());
+    assert_eq!(user2_balance_pre, user2.free_balance());
 
     finalize_test().await;
 }
@@ -4,7 +4,7 @@
 
 #[path = "common/mod.rs"]
 mod common;
-use common::{create_user, finalize_test, init_test};
+use common::{create_user, finalize_test, init_test, wait_for_tx};
 
 use base::genesis_config_service::{GenesisConfigService, NET_ID_KEY};
 use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
@@ -62,11 +62,12 @@ async fn payment_with_appreciation_tx_happy_flow() {
     // payment from user 1 to user 2
     let payment_tx = PaymentTransactionV1 {
         from: Some(user1_account_id.clone()),
-        to_number: Some(user2_number.clone()),
+        to_number: None,
         to_account_id: None,
         amount: payment_amount,
         char_trait_id,
         community_id: 0,
+        to_number_hash: user2_number.get_hash().unwrap(),
     };
 
     let user2_account_id = AccountId {
@@ -83,7 +84,7 @@ async fn payment_with_appreciation_tx_happy_flow() {
         .user
         .unwrap();
 
-    let user1_balance_pre = user1.balance;
+    let user1_balance_pre = user1.free_balance();
 
     // get user by account id
     let user2 = api_client
@@ -96,7 +97,7 @@ async fn payment_with_appreciation_tx_happy_flow() {
         .user
         .unwrap();
 
-    let user2_balance_pre = user2.balance;
+    let user2_balance_pre = user2.free_balance();
 
     let mut buf = Vec::with_capacity(payment_tx.encoded_len());
     payment_tx.encode(&mut buf).unwrap();
@@ -108,7 +109,7 @@ async fn payment_with_appreciation_tx_happy_flow() {
 
     let tx_body = TransactionBody {
         timestamp: Utc::now().timestamp_millis() as u64,
-        nonce: 1,
+        nonce: 2,
         fee: 1,
         transaction_data: Some(TransactionData {
             transaction_data: buf,
@@ -143,6 +144,8 @@ async fn payment_with_appreciation_tx_happy_flow() {
         SubmitTransactionResult::Submitted as i32,
     );
 
+    wait_for_tx(&signed_tx.get_hash().unwrap()).await;
+
     // read updated user chain data
     let user1 = api_client
         .get_user_info_by_account(GetUserInfoByAccountRequest {
@@ -165,8 +168,8 @@ async fn payment_with_appreciation_tx_happy_flow() {
         .user
         .unwrap();
 
-    assert_eq!(user1_balance_pre - payment_amount, user1.balance);
-    assert_eq!(user2_balance_pre + payment_amount, user2.balance);
+    assert_eq!(user1_balance_pre - payment_amount, user1.free_balance());
+    assert_eq!(user2_balance_pre + payment_amount, user2.free_balance());
 
     // Should be 2 - one for signup and 1 for the received appreciation
     assert_eq!(user2.trait_scores.len(), 2);
@@ -178,6 +181,7 @@ async fn payment_with_appreciation_tx_happy_flow() {
             account_id: Some(AccountId {
                 data: user1_account_id.data.clone(),
             }),
+            ..Default::default()
         })
         .await
         .unwrap()
@@ -189,8 +193,8 @@ async fn payment_with_appreciation_tx_happy_flow() {
         "user1 should have 2 transactions"
     );
 
-    // check that the payment transaction is on chain
-    let tx = &resp.transactions[1];
+    // check that the payment transaction, which is the newest one, is on chain
+    let tx = &resp.transactions[0];
     assert_eq!(tx.status, OnChain as i32);
 
     // verify that the payment transaction is on chain indexed by user 2
@@ -199,6 +203,7 @@ async fn payment_with_appreciation_tx_happy_flow() {
             account_id: Some(AccountId {
                 data: user2_account_id.data.clone(),
             }),
+            ..Default::default()
         })
         .await
         .unwrap()
@@ -210,8 +215,8 @@ async fn payment_with_appreciation_tx_happy_flow() {
         "user2 should have 2 transactions"
     );
 
-    // check that the payment transaction is on chain
-    let tx = &resp.transactions[1];
+    // check that the payment transaction, which is the newest one, is on chain
+    let tx = &resp.transactions[0];
     assert_eq!(tx.status, OnChain as i32);
 
     finalize_test().await;
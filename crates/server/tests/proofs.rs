@@ -0,0 +1,181 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetAccountProofRequest, GetBlockchainDataRequest, GetTransactionProofRequest,
+    SubmitTransactionRequest, SubmitTransactionResult,
+};
+use base::merkle::verify_merkle_proof;
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use base::sparse_merkle::verify_sparse_merkle_proof;
+use common::{account_id, create_payment_tx, create_user, finalize_test, init_test, wait_for_tx};
+use prost::Message;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test transaction and account inclusion proofs can be verified against block roots,
+/// including after a karma rewards round
+#[tokio::test(flavor = "multi_thread")]
+async fn proofs_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (user2_key_pair, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let payment_tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let tx_hash = payment_tx.get_hash().unwrap().to_vec();
+    let resp = api_client
+        .submit_transaction(SubmitTransactionRequest {
+            transaction: Some(payment_tx),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(
+        resp.submit_transaction_result,
+        SubmitTransactionResult::Submitted as i32,
+    );
+
+    wait_for_tx(&tx_hash).await;
+
+    // transaction proof
+    let resp = api_client
+        .get_transaction_proof(GetTransactionProofRequest {
+            tx_hash: tx_hash.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let block = resp.block.unwrap();
+    let proof = resp.proof.unwrap();
+    assert_eq!(block.height, 3);
+    assert!(verify_merkle_proof(
+        &block.transactions_root,
+        &tx_hash,
+        &proof
+    ));
+    assert!(!verify_merkle_proof(
+        &block.transactions_root,
+        &[0; 32],
+        &proof
+    ));
+
+    // unknown transactions have no proof
+    assert!(api_client
+        .get_transaction_proof(GetTransactionProofRequest {
+            tx_hash: vec![0; 32],
+        })
+        .await
+        .is_err());
+
+    // account proof
+    let resp = api_client
+        .get_account_proof(GetAccountProofRequest {
+            account_id: Some(account_id(&user2_key_pair)),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let user = resp.user.unwrap();
+    let block = resp.block.unwrap();
+    let proof = resp.proof.unwrap();
+    assert_eq!(block.height, 3);
+    assert!(verify_sparse_merkle_proof(
+        &block.state_root,
+        &account_id(&user2_key_pair).data,
+        &user.encode_to_vec(),
+        &proof
+    ));
+
+    // a tampered account should not verify
+    let mut tampered = user.clone();
+    tampered.deposit(1).unwrap();
+    assert!(!verify_sparse_merkle_proof(
+        &block.state_root,
+        &account_id(&user2_key_pair).data,
+        &tampered.encode_to_vec(),
+        &proof
+    ));
+
+    // the proof is of the account's own leaf
+    assert!(!verify_sparse_merkle_proof(
+        &block.state_root,
+        &account_id(&user1_key_pair).data,
+        &user.encode_to_vec(),
+        &proof
+    ));
+
+    // the karma rewards round winner's account is proved against the round's block
+    ServerService::process_karma_rewards().await.unwrap();
+    let mut stats = None;
+    for _ in 0..100 {
+        let resp = api_client
+            .get_blockchain_data(GetBlockchainDataRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .stats
+            .unwrap();
+        if resp.karma_rewards_count != 0 {
+            stats = Some(resp);
+            break;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    assert_eq!(stats.unwrap().tip_height, 4);
+
+    let resp = api_client
+        .get_account_proof(GetAccountProofRequest {
+            account_id: Some(account_id(&user1_key_pair)),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let winner = resp.user.unwrap();
+    let rewards_block = resp.block.unwrap();
+    let proof = resp.proof.unwrap();
+    assert_eq!(rewards_block.height, 4);
+    assert_eq!(
+        rewards_block.karma_rewards.unwrap().winners,
+        vec![account_id(&user1_key_pair)]
+    );
+    assert_ne!(rewards_block.state_root, block.state_root);
+    assert!(verify_sparse_merkle_proof(
+        &rewards_block.state_root,
+        &account_id(&user1_key_pair).data,
+        &winner.encode_to_vec(),
+        &proof
+    ));
+
+    finalize_test().await;
+}
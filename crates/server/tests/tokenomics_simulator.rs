@@ -0,0 +1,79 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use base::genesis_config_service::{GenesisConfigService, ONE_KC_IN_KCENTS};
+use server::{StepActivity, TokenomicsSimulator};
+
+/// Test that the simulator accounts for all minted funds and reports allocations depletion
+#[tokio::test(flavor = "multi_thread")]
+async fn tokenomics_simulator_test() {
+    let mut genesis_data = GenesisConfigService::get_genesis_data().await.unwrap();
+    genesis_data.tx_fee_subsidies_alloc = 20;
+    genesis_data.block_reward_last_block = 5;
+    genesis_data.karma_reward_alloc = 1;
+    genesis_data.karma_reward_amount = ONE_KC_IN_KCENTS * ONE_KC_IN_KCENTS / 4;
+    genesis_data.karma_reward_top_n_users = 1;
+    // karma rewards are paid every step
+    genesis_data.karma_rewards_period_hours = 60;
+
+    let steps = StepActivity::parse_csv("signups,payments,referrals\n2,4,1\n\n# idle\n0,0,0\n")
+        .unwrap()
+        .repeat(5);
+    assert_eq!(steps.len(), 10);
+    assert!(StepActivity::parse_csv("2,4").is_err());
+
+    let report = TokenomicsSimulator::new(genesis_data.clone(), 3600, 1)
+        .unwrap()
+        .run(&steps)
+        .unwrap();
+    assert_eq!(report.records.len(), 10);
+
+    let last = report.records.last().unwrap();
+    // idle steps don't produce blocks
+    assert_eq!(last.height, 5);
+    assert_eq!(last.users, 10);
+    assert_eq!(
+        last.block_rewards_amount,
+        5 * genesis_data.block_reward_amount
+    );
+    assert_eq!(
+        last.minted_amount,
+        last.signup_rewards_amount
+            + last.referral_rewards_amount
+            + last.block_rewards_amount
+            + last.fee_subsidies_amount
+    );
+    assert_eq!(
+        last.circulation,
+        last.minted_amount + last.karma_rewards_amount
+    );
+    assert_eq!(last.karma_rewards_remaining, 0);
+
+    // payments are no longer subsidised once the phase 1 subsidies are used
+    assert!(last.fee_subsidies_amount < last.fees_amount);
+
+    let switches: Vec<_> = report
+        .phase_switches
+        .iter()
+        .map(|s| (s.name.as_str(), s.from, s.to))
+        .collect();
+    assert!(switches.contains(&("fee_subsidy_phase", 1, 2)));
+    assert!(switches.contains(&("karma_reward", genesis_data.karma_reward_amount, 0)));
+    assert!(switches.contains(&("block_reward", genesis_data.block_reward_amount, 0)));
+
+    let mut csv = vec![];
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), 11);
+    assert!(csv.starts_with("step,elapsed_secs,height"));
+
+    let mut json = vec![];
+    report.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["records"].as_array().unwrap().len(), 10);
+    assert_eq!(
+        json["phase_switches"].as_array().unwrap().len(),
+        report.phase_switches.len()
+    );
+}
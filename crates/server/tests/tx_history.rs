@@ -0,0 +1,256 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{
+    GetTransactionsRequest, GetTransactionsResponse, TransactionDirection, TransactionsFilter,
+};
+use base::karma_coin::karma_coin_core_types::TransactionType::NewUserV2;
+use base::karma_coin::karma_coin_core_types::{
+    AccountId, KeyPair, MobileNumber, SignedTransaction,
+};
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{
+    create_payment_tx, create_user, finalize_test, init_test, resign_tx, submit_and_wait,
+};
+use prost::Message;
+use tonic::transport::Channel;
+use tonic::Code;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to create a payment tx with an appreciation
+async fn create_appreciation_tx(
+    key_pair: &KeyPair,
+    to_number: &MobileNumber,
+    nonce: u64,
+    char_trait_id: u32,
+) -> SignedTransaction {
+    let mut tx = create_payment_tx(key_pair, to_number, 10, nonce).await;
+    let mut body = tx.get_body().unwrap();
+    let mut payment = body.get_payment_transaction_v1().unwrap();
+    payment.char_trait_id = char_trait_id;
+    body.transaction_data.as_mut().unwrap().transaction_data = payment.encode_to_vec();
+    resign_tx(&mut tx, &body, key_pair);
+    tx
+}
+
+// helper function to get a page of an account's txs
+async fn get_page(
+    api_client: &mut ApiServiceClient<Channel>,
+    account_id: &[u8],
+    limit: u32,
+    cursor: Vec<u8>,
+    filter: TransactionsFilter,
+) -> GetTransactionsResponse {
+    api_client
+        .get_transactions(GetTransactionsRequest {
+            account_id: Some(AccountId {
+                data: account_id.to_vec(),
+            }),
+            limit,
+            cursor,
+            filter: Some(filter),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+// helper function to get the hashes of the txs in a page
+fn hashes(resp: &GetTransactionsResponse) -> Vec<Vec<u8>> {
+    resp.transactions
+        .iter()
+        .map(|tx| {
+            tx.transaction
+                .as_ref()
+                .unwrap()
+                .get_hash()
+                .unwrap()
+                .to_vec()
+        })
+        .collect()
+}
+
+/// Test paging through an account's transactions with filters
+#[tokio::test(flavor = "multi_thread")]
+async fn tx_history_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, user1_number, signup_tx_hash) =
+        create_user("avive".into(), "+972549805381".into())
+            .await
+            .unwrap();
+    let signup_tx_hash = signup_tx_hash.to_vec();
+    let user1_account_id = user1_key_pair.public_key.as_ref().unwrap().key.clone();
+
+    let (user2_key_pair, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    // each tx is executed in its own block
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let payment1_hash = tx.get_hash().unwrap().to_vec();
+    submit_and_wait(&mut api_client, &tx).await;
+    let tx = create_appreciation_tx(&user1_key_pair, &user2_number, 3, 40).await;
+    let appreciation_hash = tx.get_hash().unwrap().to_vec();
+    submit_and_wait(&mut api_client, &tx).await;
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 4).await;
+    let payment2_timestamp = tx.get_body().unwrap().timestamp;
+    let payment2_hash = tx.get_hash().unwrap().to_vec();
+    submit_and_wait(&mut api_client, &tx).await;
+    let tx = create_payment_tx(&user2_key_pair, &user1_number, 10, 2).await;
+    let received_hash = tx.get_hash().unwrap().to_vec();
+    submit_and_wait(&mut api_client, &tx).await;
+
+    let all_hashes = vec![
+        received_hash.clone(),
+        payment2_hash.clone(),
+        appreciation_hash.clone(),
+        payment1_hash.clone(),
+        signup_tx_hash.clone(),
+    ];
+
+    // page through all txs newest first
+    let mut paged_hashes = vec![];
+    let mut cursor = vec![];
+    loop {
+        let resp = get_page(
+            &mut api_client,
+            &user1_account_id,
+            2,
+            cursor,
+            TransactionsFilter::default(),
+        )
+        .await;
+        assert!(resp.transactions.len() <= 2);
+        assert_eq!(
+            resp.tx_events.as_ref().unwrap().events.len(),
+            resp.transactions.len()
+        );
+        paged_hashes.append(&mut hashes(&resp));
+        if resp.next_cursor.is_empty() {
+            break;
+        }
+        cursor = resp.next_cursor;
+    }
+    assert_eq!(paged_hashes, all_hashes);
+
+    // a single page with all txs has no next page
+    let resp = get_page(
+        &mut api_client,
+        &user1_account_id,
+        0,
+        vec![],
+        TransactionsFilter::default(),
+    )
+    .await;
+    assert_eq!(hashes(&resp), all_hashes);
+    assert!(resp.next_cursor.is_empty());
+
+    // direction filters
+    let sent = TransactionsFilter {
+        direction: TransactionDirection::Sent as i32,
+        ..Default::default()
+    };
+    let resp = get_page(&mut api_client, &user1_account_id, 0, vec![], sent).await;
+    assert_eq!(hashes(&resp), all_hashes[1..].to_vec());
+
+    let received = TransactionsFilter {
+        direction: TransactionDirection::Received as i32,
+        ..Default::default()
+    };
+    let resp = get_page(&mut api_client, &user1_account_id, 0, vec![], received).await;
+    assert_eq!(hashes(&resp), vec![received_hash.clone()]);
+
+    // tx type filter
+    let signups = TransactionsFilter {
+        transaction_types: vec![NewUserV2 as i32],
+        ..Default::default()
+    };
+    let resp = get_page(&mut api_client, &user1_account_id, 0, vec![], signups).await;
+    assert_eq!(hashes(&resp), vec![signup_tx_hash]);
+
+    // char trait and community filters
+    let trait_filter = TransactionsFilter {
+        char_trait_id: 40,
+        ..Default::default()
+    };
+    let resp = get_page(&mut api_client, &user1_account_id, 0, vec![], trait_filter).await;
+    assert_eq!(hashes(&resp), vec![appreciation_hash]);
+
+    let community_filter = TransactionsFilter {
+        community_id: 1,
+        ..Default::default()
+    };
+    let resp = get_page(
+        &mut api_client,
+        &user1_account_id,
+        0,
+        vec![],
+        community_filter,
+    )
+    .await;
+    assert!(resp.transactions.is_empty());
+    assert!(resp.next_cursor.is_empty());
+
+    // time range filter with a page size smaller than the matching txs
+    let time_filter = TransactionsFilter {
+        from_time: payment2_timestamp,
+        ..Default::default()
+    };
+    let resp = get_page(
+        &mut api_client,
+        &user1_account_id,
+        1,
+        vec![],
+        time_filter.clone(),
+    )
+    .await;
+    assert_eq!(hashes(&resp), vec![received_hash]);
+    let resp = get_page(
+        &mut api_client,
+        &user1_account_id,
+        1,
+        resp.next_cursor,
+        time_filter,
+    )
+    .await;
+    assert_eq!(hashes(&resp), vec![payment2_hash]);
+
+    // a cursor of another account is rejected
+    let err = api_client
+        .get_transactions(GetTransactionsRequest {
+            account_id: Some(AccountId {
+                data: user1_account_id.clone(),
+            }),
+            limit: 1,
+            cursor: vec![1, 2, 3],
+            filter: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    finalize_test().await;
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason::{
+    InsufficientBalance, Unspecified,
+};
+use base::karma_coin::karma_coin_api::{
+    GetTransactionRequest, WaitForTransactionRequest, WaitForTransactionResponse,
+};
+use base::karma_coin::karma_coin_core_types::TransactionStatus;
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_payment_tx, create_user, finalize_test, init_test, submit};
+use tonic::transport::Channel;
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+// helper function to wait for a tx for up to timeout_ms
+async fn wait(
+    api_client: &mut ApiServiceClient<Channel>,
+    tx_hash: &[u8],
+    timeout_ms: u64,
+) -> WaitForTransactionResponse {
+    api_client
+        .wait_for_transaction(WaitForTransactionRequest {
+            tx_hash: tx_hash.to_vec(),
+            timeout_ms,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+// helper function to get a tx status
+async fn get_status(api_client: &mut ApiServiceClient<Channel>, tx_hash: &[u8]) -> i32 {
+    api_client
+        .get_transaction(GetTransactionRequest {
+            tx_hash: tx_hash.to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .transaction
+        .unwrap()
+        .status
+}
+
+/// Test transactions status tracking from submission to inclusion or rejection
+#[tokio::test(flavor = "multi_thread")]
+async fn tx_status_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let (user1_key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    let (_, user2_number, _) = create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    // unknown tx
+    let resp = wait(&mut api_client, &[1, 2, 3], 100).await;
+    assert!(resp.timed_out);
+    assert!(resp.transaction.is_none());
+
+    // a future nonce tx waits in the pool
+    let future_tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 3).await;
+    let future_tx_hash = future_tx.get_hash().unwrap().to_vec();
+    assert_eq!(submit(&mut api_client, &future_tx).await, Unspecified);
+    assert_eq!(
+        get_status(&mut api_client, &future_tx_hash).await,
+        TransactionStatus::Submitted as i32
+    );
+    let resp = wait(&mut api_client, &future_tx_hash, 300).await;
+    assert!(resp.timed_out);
+    assert_eq!(
+        resp.transaction.unwrap().status,
+        TransactionStatus::Submitted as i32
+    );
+
+    // the future tx is executed after the next tx in the same block
+    let tx = create_payment_tx(&user1_key_pair, &user2_number, 10, 2).await;
+    let tx_hash = tx.get_hash().unwrap().to_vec();
+    assert_eq!(submit(&mut api_client, &tx).await, Unspecified);
+    let resp = wait(&mut api_client, &future_tx_hash, 5000).await;
+    assert!(!resp.timed_out);
+    assert_eq!(
+        resp.transaction.unwrap().status,
+        TransactionStatus::OnChain as i32
+    );
+    assert!(!resp.tx_events.unwrap().events.is_empty());
+    assert_eq!(
+        get_status(&mut api_client, &tx_hash).await,
+        TransactionStatus::OnChain as i32
+    );
+
+    // a rejected tx has a final status with the rejection reason
+    let rejected_tx = create_payment_tx(&user1_key_pair, &user2_number, u64::MAX, 4).await;
+    let rejected_tx_hash = rejected_tx.get_hash().unwrap().to_vec();
+    assert_eq!(
+        submit(&mut api_client, &rejected_tx).await,
+        InsufficientBalance
+    );
+    let resp = wait(&mut api_client, &rejected_tx_hash, 5000).await;
+    assert!(!resp.timed_out);
+    let tx = resp.transaction.unwrap();
+    assert_eq!(tx.status, TransactionStatus::Rejected as i32);
+    assert!(!tx.rejection_reason.is_empty());
+
+    finalize_test().await;
+}
@@ -0,0 +1,173 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::genesis_config_service::{
+    GenesisConfigService, VERIFIERS_ACCOUNTS_IDS, VERIFIERS_THRESHOLD_KEY,
+};
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason;
+use base::karma_coin::karma_coin_core_types::TransactionType::UpdateUserV1;
+use base::karma_coin::karma_coin_core_types::{
+    ExecutionInfo, ExecutionResult, KeyPair, TransactionData, UpdateUserTransactionV1,
+};
+use base::mobile_number::mobile_number_hash;
+use base::server_config_service::{
+    GetVerifierIdKeyPair, ServerConfigService, DEFAULT_GRPC_SERVER_PORT,
+};
+use common::{
+    create_evidence, create_new_user_tx, create_tx, finalize_test, get_user, init_test, submit,
+    submit_and_wait,
+};
+use prost::Message;
+use server::server_service::{ServerService, Startup};
+use std::collections::HashMap;
+use xactor::Service;
+
+/// Test that new user and mobile number update txs require evidence by the genesis verifiers
+/// threshold of distinct registered verifiers
+#[tokio::test(flavor = "multi_thread")]
+async fn verifier_quorum_test() {
+    init_test().await;
+
+    GenesisConfigService::set_u64(VERIFIERS_THRESHOLD_KEY.into(), 2)
+        .await
+        .unwrap();
+
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    // the node's verifier and another genesis verifier must both attest to a number
+    let verifier1 = ServerConfigService::from_registry()
+        .await
+        .unwrap()
+        .call(GetVerifierIdKeyPair)
+        .await
+        .unwrap()
+        .unwrap();
+    let verifier2 = KeyPair::new();
+    let verifiers_ids: HashMap<String, String> = HashMap::from([
+        (
+            "Verifier 1".to_string(),
+            hex::encode(&verifier1.public_key.as_ref().unwrap().key),
+        ),
+        (
+            "Verifier 2".to_string(),
+            hex::encode(&verifier2.public_key.as_ref().unwrap().key),
+        ),
+    ]);
+    GenesisConfigService::set_map(VERIFIERS_ACCOUNTS_IDS.into(), verifiers_ids)
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    use SubmitTransactionRejectReason::*;
+    let user_key_pair = KeyPair::new();
+    let number = "+972549805381";
+
+    // a single verifier evidence is rejected
+    let evidence = create_evidence(&[&verifier1], &user_key_pair, "avive", number);
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+
+    // evidence by the same verifier can't be counted twice
+    let evidence = create_evidence(&[&verifier1, &verifier1], &user_key_pair, "avive", number);
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+
+    // all evidence must attest to the same number
+    let mut evidence = create_evidence(&[&verifier1], &user_key_pair, "avive", number);
+    evidence.append(&mut create_evidence(
+        &[&verifier2],
+        &user_key_pair,
+        "avive",
+        "+972549805382",
+    ));
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+
+    // evidence by an unregistered verifier doesn't count
+    let evidence = create_evidence(
+        &[&verifier1, &KeyPair::new()],
+        &user_key_pair,
+        "avive",
+        number,
+    );
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+
+    // a quorum of verifiers
+    let evidence = create_evidence(&[&verifier1, &verifier2], &user_key_pair, "avive", number);
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    // a mobile number update requires a quorum of verifiers as well
+    let new_number = "+972549805383";
+    let update_number_tx = |verifiers: &[&KeyPair], nonce: u64| {
+        let evidence = create_evidence(verifiers, &user_key_pair, "avive", new_number);
+        let update_user_tx = UpdateUserTransactionV1 {
+            nickname: "avive".into(),
+            mobile_number: None,
+            user_verification_data: None,
+            additional_user_verification_data: vec![],
+            user_verification_data_ex: evidence,
+        };
+        create_tx(
+            &user_key_pair,
+            TransactionData {
+                transaction_data: update_user_tx.encode_to_vec(),
+                transaction_type: UpdateUserV1 as i32,
+            },
+            nonce,
+        )
+    };
+
+    let tx = update_number_tx(&[&verifier2], 2).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].info, ExecutionInfo::InvalidData as i32);
+
+    assert_eq!(
+        get_user(&mut api_client, &user_key_pair)
+            .await
+            .unwrap()
+            .mobile_number_hash,
+        mobile_number_hash(number).unwrap()
+    );
+
+    // the rejected update didn't use its nonce
+    let tx = update_number_tx(&[&verifier2, &verifier1], 2).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+    assert_eq!(
+        get_user(&mut api_client, &user_key_pair)
+            .await
+            .unwrap()
+            .mobile_number_hash,
+        mobile_number_hash(new_number).unwrap()
+    );
+
+    finalize_test().await;
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::genesis_config_service::{
+    GenesisConfigService, GOVERNANCE_ADMINS_ACCOUNTS_IDS, GOVERNANCE_THRESHOLD_KEY,
+    VERIFIERS_ACCOUNTS_IDS,
+};
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::GetGenesisDataRequest;
+use base::karma_coin::karma_coin_api::SubmitTransactionRejectReason::InvalidVerificationEvidence;
+use base::karma_coin::karma_coin_core_types::TransactionType::VerifierUpdateV1;
+use base::karma_coin::karma_coin_core_types::{
+    ExecutionResult, KeyPair, TransactionData, VerifierAction, VerifierUpdateProposal,
+    VerifierUpdateTransactionV1,
+};
+use base::server_config_service::{
+    GetVerifierIdKeyPair, ServerConfigService, DEFAULT_GRPC_SERVER_PORT,
+};
+use common::{
+    account_id, create_evidence, create_new_user_tx, create_tx, create_user, finalize_test,
+    init_test, submit, submit_and_wait,
+};
+use prost::Message;
+use server::server_service::{ServerService, Startup};
+use std::collections::HashMap;
+use xactor::Service;
+
+/// Test that verifiers are added, rotated and revoked by admin approved transactions and that
+/// evidence is only accepted from verifiers which are valid at the block height
+#[tokio::test(flavor = "multi_thread")]
+async fn verifier_registry_test() {
+    init_test().await;
+
+    let admin = KeyPair::new();
+    let admins_ids: HashMap<String, String> = HashMap::from([(
+        "Admin 1".to_string(),
+        hex::encode(&admin.public_key.as_ref().unwrap().key),
+    )]);
+    GenesisConfigService::set_map(GOVERNANCE_ADMINS_ACCOUNTS_IDS.into(), admins_ids)
+        .await
+        .unwrap();
+    GenesisConfigService::set_u64(GOVERNANCE_THRESHOLD_KEY.into(), 1)
+        .await
+        .unwrap();
+
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let genesis_hash = api_client
+        .get_genesis_data(GetGenesisDataRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .genesis_data
+        .unwrap()
+        .genesis_hash;
+
+    // the node's verifier is accepted when it is a genesis verifier
+    let node_verifier = ServerConfigService::from_registry()
+        .await
+        .unwrap()
+        .call(GetVerifierIdKeyPair)
+        .await
+        .unwrap()
+        .unwrap();
+    let verifiers_ids: HashMap<String, String> = HashMap::from([(
+        "Verifier 1".to_string(),
+        hex::encode(&node_verifier.public_key.as_ref().unwrap().key),
+    )]);
+    GenesisConfigService::set_map(VERIFIERS_ACCOUNTS_IDS.into(), verifiers_ids)
+        .await
+        .unwrap();
+
+    let (key_pair, _, _) = create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+    let update_verifier = |proposal: VerifierUpdateProposal, nonce: u64| {
+        let approvals = vec![proposal.approve(&genesis_hash, &admin.to_ed2559_keypair())];
+        let tx_data = TransactionData {
+            transaction_data: VerifierUpdateTransactionV1 {
+                proposal: Some(proposal),
+                approvals,
+            }
+            .encode_to_vec(),
+            transaction_type: VerifierUpdateV1 as i32,
+        };
+        create_tx(&key_pair, tx_data, nonce)
+    };
+
+    // evidence by an unregistered verifier is rejected
+    let verifier = KeyPair::new();
+    let user_key_pair = KeyPair::new();
+    let evidence = create_evidence(&[&verifier], &user_key_pair, "rachel", "+972549805382");
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+
+    // add the verifier
+    let tx = update_verifier(
+        VerifierUpdateProposal {
+            action: VerifierAction::Add as i32,
+            account_id: Some(account_id(&verifier)),
+            name: "Verifier 2".into(),
+            ..Default::default()
+        },
+        2,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    let user_key_pair = KeyPair::new();
+    let evidence = create_evidence(&[&verifier], &user_key_pair, "rachel", "+972549805382");
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    // a verifier can't be added twice. A failed tx doesn't use its nonce
+    let tx = update_verifier(
+        VerifierUpdateProposal {
+            action: VerifierAction::Add as i32,
+            account_id: Some(account_id(&verifier)),
+            name: "Verifier 2".into(),
+            ..Default::default()
+        },
+        3,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Invalid as i32);
+
+    // rotate the verifier key. Only the new key is accepted after the rotation
+    let new_verifier = KeyPair::new();
+    let tx = update_verifier(
+        VerifierUpdateProposal {
+            action: VerifierAction::Rotate as i32,
+            account_id: Some(account_id(&verifier)),
+            new_account_id: Some(account_id(&new_verifier)),
+            ..Default::default()
+        },
+        3,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    let user_key_pair = KeyPair::new();
+    let evidence = create_evidence(&[&verifier], &user_key_pair, "sarah", "+972549805383");
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    assert_eq!(
+        submit(&mut api_client, &tx).await,
+        InvalidVerificationEvidence
+    );
+    let user_key_pair = KeyPair::new();
+    let evidence = create_evidence(&[&new_verifier], &user_key_pair, "sarah", "+972549805383");
+    let tx = create_new_user_tx(&user_key_pair, evidence).await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    // revoke the node's verifier
+    let tx = update_verifier(
+        VerifierUpdateProposal {
+            action: VerifierAction::Revoke as i32,
+            account_id: Some(account_id(&node_verifier)),
+            ..Default::default()
+        },
+        4,
+    )
+    .await;
+    let events = submit_and_wait(&mut api_client, &tx).await;
+    assert_eq!(events[0].result, ExecutionResult::Executed as i32);
+
+    assert!(create_user("lea".into(), "+972549805384".into())
+        .await
+        .is_err());
+
+    finalize_test().await;
+}
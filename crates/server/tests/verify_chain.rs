@@ -0,0 +1,117 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+#[path = "common/mod.rs"]
+mod common;
+
+use base::block::verify_chain;
+use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
+use base::karma_coin::karma_coin_api::{GetBlocksRequest, VerifyChainRequest};
+use base::server_config_service::DEFAULT_GRPC_SERVER_PORT;
+use common::{create_user, finalize_test, init_test};
+
+/// tests in this file should be run sequentially and not in parallel
+use server::server_service::{ServerService, Startup};
+use xactor::Service;
+
+/// Test chain verification by the node and offline verification of exported blocks
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_chain_test() {
+    init_test().await;
+
+    // Start the server
+    let server = ServerService::from_registry().await.unwrap();
+    server.call(Startup {}).await.unwrap().unwrap();
+
+    // todo: figure out why grpc warmup is needed - without the delay we have random connection refused
+    // from api client
+    use tokio::time::{sleep, Duration};
+    sleep(Duration::from_millis(300)).await;
+
+    create_user("avive".into(), "+972549805381".into())
+        .await
+        .unwrap();
+
+    create_user("rachel".into(), "+972549805382".into())
+        .await
+        .unwrap();
+
+    let mut api_client =
+        ApiServiceClient::connect(format!("http://[::1]:{}", DEFAULT_GRPC_SERVER_PORT))
+            .await
+            .unwrap();
+
+    let resp = api_client
+        .verify_chain(VerifyChainRequest { from: 1, to: 2 })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(
+        resp.errors.is_empty(),
+        "unexpected errors: {:?}",
+        resp.errors
+    );
+    assert_eq!(resp.blocks_verified, 2);
+
+    // a range which doesn't start at genesis is anchored on the previous block
+    let resp = api_client
+        .verify_chain(VerifyChainRequest { from: 2, to: 2 })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(
+        resp.errors.is_empty(),
+        "unexpected errors: {:?}",
+        resp.errors
+    );
+    assert_eq!(resp.blocks_verified, 1);
+
+    // heights beyond the tip are not verified
+    let resp = api_client
+        .verify_chain(VerifyChainRequest { from: 1, to: 3 })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(
+        resp.errors.is_empty(),
+        "unexpected errors: {:?}",
+        resp.errors
+    );
+    assert_eq!(resp.blocks_verified, 2);
+
+    // too many blocks in one request
+    assert!(api_client
+        .verify_chain(VerifyChainRequest { from: 1, to: 2000 })
+        .await
+        .is_err());
+
+    // verify exported blocks offline
+    let mut blocks = api_client
+        .get_blocks(GetBlocksRequest {
+            from_block_height: 1,
+            to_block_height: 2,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .blocks;
+
+    assert!(verify_chain(None, &blocks).is_empty());
+
+    // a block which doesn't link to the previous block
+    blocks[1].prev_block_digest = vec![0; 32];
+    let errors = verify_chain(None, &blocks);
+    assert!(!errors.is_empty());
+
+    // a tampered block
+    blocks[1].prev_block_digest = blocks[0].digest.clone();
+    blocks[1].reward += 1;
+    let errors = verify_chain(None, &blocks);
+    assert_eq!(errors.len(), 1, "unexpected errors: {:?}", errors);
+
+    finalize_test().await;
+}